@@ -0,0 +1,240 @@
+//locating Active Directory domain controllers, from the DC SRV records and the LDAP rootDSE
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::cli::Options;
+use crate::dns;
+use crate::host::{HostResult, PortState};
+
+//the role recorded on hosts found to be domain controllers
+pub const DC_ROLE: &str = "domain-controller";
+
+const LDAP_PORT: u16 = 389;
+const SMB_PORT: u16 = 445;
+
+//how long to wait for the directory to answer
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+//largest rootDSE answer read, the four attributes asked for are well under this
+const MAX_RESPONSE: usize = 16384;
+
+//the rootDSE attributes asked for, readable without binding on every AD domain controller
+const ROOT_DSE_ATTRIBUTES: [&str; 4] = ["defaultNamingContext", "dnsHostName", "domainControllerFunctionality", "ldapServiceName"];
+
+//a domain controller listed in the SRV records of a domain
+pub struct Located {
+    pub domain: String,
+    pub name: String,
+    pub address: Ipv4Addr,
+}
+
+//function to ask the directory on port 389 what it is, marking the host a domain controller when it says so
+pub fn classify(host: &mut HostResult) {
+    let address = host.address;
+    let Some(port) = host.ports.iter_mut().find(|port| port.protocol == "tcp" && port.port == LDAP_PORT && port.state == PortState::Open) else {
+        return;
+    };
+    let Some(attributes) = root_dse(address) else {
+        return;
+    };
+    let value = |name: &str| attributes.iter().find(|(attribute, _)| attribute.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+
+    //plain LDAP servers answer too, only AD publishes a functional level
+    let mut parts = Vec::new();
+    if let Some(context) = value("defaultNamingContext") {
+        parts.push(format!("domain {}", naming_context_domain(context)));
+    }
+    if let Some(name) = value("dnsHostName") {
+        parts.push(format!("name {}", name));
+    }
+    let level = value("domainControllerFunctionality");
+    if let Some(level) = level {
+        parts.push(format!("functional level {}", functional_level(level)));
+    }
+    if !parts.is_empty() {
+        port.info = Some(format!("LDAP {}", parts.join(", ")));
+    }
+    if level.is_some() {
+        add_role(host);
+    }
+}
+
+//function to look up the domain controllers of the search domains and of the domains hosts were named in,
+//flagging every listed host that answered on LDAP or SMB
+pub fn locate(results: &mut [HostResult], options: &Options) -> Vec<Located> {
+    let Ok(resolver) = dns::Resolver::from_options(&options.resolvers, options.doh.as_deref()) else {
+        return Vec::new();
+    };
+
+    let mut domains = dns::search_domains();
+    for host in results.iter() {
+        if let Some((_, domain)) = host.hostname.as_deref().and_then(|name| name.trim_end_matches('.').split_once('.')) {
+            domains.push(domain.to_lowercase());
+        }
+    }
+    domains.sort();
+    domains.dedup();
+
+    let mut located = Vec::new();
+    for domain in domains {
+        let Ok(services) = resolver.service_lookup(&format!("_ldap._tcp.dc._msdcs.{}", domain)) else {
+            continue;
+        };
+        for (name, _) in services {
+            let Ok(Some(address)) = resolver.lookup(&name) else {
+                continue;
+            };
+            let Some(host) = results.iter_mut().find(|host| host.address == address) else {
+                continue;
+            };
+            let answers = host
+                .ports
+                .iter()
+                .any(|port| port.protocol == "tcp" && (port.port == LDAP_PORT || port.port == SMB_PORT) && port.state == PortState::Open);
+            if !answers {
+                continue;
+            }
+            add_role(host);
+            located.push(Located { domain: domain.clone(), name: name.trim_end_matches('.').to_string(), address });
+        }
+    }
+    located
+}
+
+fn add_role(host: &mut HostResult) {
+    if !host.roles.iter().any(|role| role == DC_ROLE) {
+        host.roles.push(DC_ROLE.to_string());
+    }
+}
+
+//function to send an anonymous base search of the rootDSE and collect the first value of each attribute
+fn root_dse(address: Ipv4Addr) -> Option<Vec<(String, String)>> {
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::from((address, LDAP_PORT)), READ_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(READ_TIMEOUT)).ok()?;
+    stream.write_all(&search_request()).ok()?;
+
+    //read until the first message, the SearchResultEntry, is complete
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let message = loop {
+        if let Some((0x30, message, _)) = ber_element(&response, 0) {
+            break message.to_vec();
+        }
+        let read = stream.read(&mut buffer).ok()?;
+        if read == 0 || response.len() > MAX_RESPONSE {
+            return None;
+        }
+        response.extend_from_slice(&buffer[..read]);
+    };
+
+    //messageID, then the SearchResultEntry with the object name and its attribute list
+    let (_, _, next) = ber_element(&message, 0)?;
+    let (tag, entry, _) = ber_element(&message, next)?;
+    if tag != 0x64 {
+        return None;
+    }
+    let (_, _, next) = ber_element(entry, 0)?;
+    let (_, list, _) = ber_element(entry, next)?;
+
+    let mut attributes = Vec::new();
+    let mut position = 0;
+    while let Some((_, attribute, next)) = ber_element(list, position) {
+        position = next;
+        let Some((_, name, after_name)) = ber_element(attribute, 0) else {
+            continue;
+        };
+        let Some((_, values, _)) = ber_element(attribute, after_name) else {
+            continue;
+        };
+        if let Some((_, value, _)) = ber_element(values, 0) {
+            attributes.push((String::from_utf8_lossy(name).to_string(), String::from_utf8_lossy(value).to_string()));
+        }
+    }
+    Some(attributes)
+}
+
+//an LDAPv3 SearchRequest for the rootDSE: base "", scope base, filter (objectClass=*)
+fn search_request() -> Vec<u8> {
+    let mut attributes = Vec::new();
+    for attribute in ROOT_DSE_ATTRIBUTES {
+        attributes.extend(ber(0x04, attribute.as_bytes()));
+    }
+
+    let mut search = Vec::new();
+    search.extend(ber(0x04, b""));
+    search.extend(ber(0x0a, &[0]));
+    search.extend(ber(0x0a, &[0]));
+    search.extend(ber(0x02, &[0]));
+    search.extend(ber(0x02, &[READ_TIMEOUT.as_secs() as u8]));
+    search.extend(ber(0x01, &[0]));
+    search.extend(ber(0x87, b"objectClass"));
+    search.extend(ber(0x30, &attributes));
+
+    let mut message = ber(0x02, &[1]);
+    message.extend(ber(0x63, &search));
+    ber(0x30, &message)
+}
+
+//function to encode one BER element, lengths over 127 use the two byte long form
+fn ber(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    if content.len() < 0x80 {
+        element.push(content.len() as u8);
+    }
+    else {
+        element.push(0x82);
+        element.extend((content.len() as u16).to_be_bytes());
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+//function to read the BER element at a position, returning its tag, content and where the next one starts
+fn ber_element(data: &[u8], position: usize) -> Option<(u8, &[u8], usize)> {
+    let tag = *data.get(position)?;
+    let first = *data.get(position + 1)? as usize;
+    let (length, start) = if first < 0x80 {
+        (first, position + 2)
+    }
+    else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = data.get(position + 2..position + 2 + count)?;
+        (bytes.iter().fold(0usize, |length, byte| length << 8 | *byte as usize), position + 2 + count)
+    };
+    let content = data.get(start..start.checked_add(length)?)?;
+    Some((tag, content, start + length))
+}
+
+//function to turn a naming context into a domain name, e.g. DC=corp,DC=local becomes corp.local
+fn naming_context_domain(context: &str) -> String {
+    context
+        .split(',')
+        .filter_map(|part| part.trim().split_once('='))
+        .filter(|(key, _)| key.eq_ignore_ascii_case("dc"))
+        .map(|(_, value)| value)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+//function to name the Windows Server version of a domain controller functional level
+fn functional_level(level: &str) -> String {
+    let name = match level {
+        "0" => "2000",
+        "1" => "2003 interim",
+        "2" => "2003",
+        "3" => "2008",
+        "4" => "2008 R2",
+        "5" => "2012",
+        "6" => "2012 R2",
+        "7" => "2016",
+        "10" => "2025",
+        _ => return level.to_string(),
+    };
+    format!("Windows Server {}", name)
+}
@@ -0,0 +1,540 @@
+//a read-only HTTP API on the stored scans for dashboards, served alongside watch mode (--api ADDRESS:PORT)
+//
+//  GET /scans[?range=KEY]                 the stored runs, oldest first, each with its id, range and counts,
+//                                         KEY as the store names the range, e.g. 10.0.0.0_24, or the range
+//                                         as it was given, 10.0.0.0%2F24
+//  GET /diff?from=ID&to=ID                what changed between two runs, the same changes --delta reports
+//  GET /history?host=IP[&since=DURATION]  the result of one address in every stored run it is in, the
+//                                         runs of watch mode keep the hosts that answered and those
+//                                         that stopped answering, not every address swept
+//  GET /healthz                           200 while the process answers, for a liveness check
+//  GET /readyz                            200 when ping can send (a raw socket or the ping group) and an
+//                                         interface has a network attached, 503 with what failed if not
+//
+//everything comes back as JSON, errors as {"error":"..."} with a 4xx or 5xx status
+//
+//the config file's [api-token NAME] sections are the bearer tokens a request has to send, as
+//Authorization: Bearer SECRET, when there are any, except the health checks. without one the API only
+//listens on a loopback address
+//
+//the API speaks plain HTTP, there is no TLS in the standard library, so the tokens and the scans would
+//cross the network in the clear. it only listens on a loopback address unless --api-insecure says the
+//network between it and its clients is trusted. to reach it from elsewhere, put a TLS reverse proxy on
+//the same machine in front of the loopback listener, which can also ask clients for a certificate:
+//
+//  [host_disco]                         stunnel, verify = 2 asks every client for a certificate
+//  accept = 0.0.0.0:8443                signed by CAfile
+//  connect = 127.0.0.1:8080
+//  cert = /etc/host_disco/api.pem
+//  key = /etc/host_disco/api.key
+//  CAfile = /etc/host_disco/clients.pem
+//  verify = 2
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::cli;
+use crate::config;
+use crate::delta::{self, Change};
+use crate::host::PortResult;
+use crate::json;
+use crate::local;
+use crate::metadata;
+use crate::probe::{self, Outcome};
+use crate::scope;
+use crate::store::{self, RunName, StoredScan};
+use crate::target::{self, Target};
+
+//how long a client gets to send its request
+const TIMEOUT: Duration = Duration::from_secs(5);
+//the request line and headers have to fit, there is no body to read
+const MAX_REQUEST: usize = 8192;
+//the most requests answered at once, more are turned away until one is done
+const MAX_CLIENTS: usize = 8;
+
+//what the threads answering requests share
+struct Server {
+    tokens: Vec<Token>,
+    index: Mutex<Index>,
+    clients: AtomicUsize,
+}
+
+//a bearer token from the config file
+struct Token {
+    secret: String,
+    //the addresses it may see, None for every one
+    scopes: Option<Vec<Target>>,
+    rate: Option<u32>,
+    //the minute the requests are being counted in, and how many it has had
+    usage: Mutex<(u64, u32)>,
+}
+
+//what is kept of a stored run to list it and tell which runs an address is in without reading it again
+struct Run {
+    id: String,
+    //every address in it and whether it was up
+    hosts: Vec<(Ipv4Addr, bool)>,
+}
+
+//the stored runs by start time and range, as their files are named, and by id. a stored run isn't
+//changed once written, so each is read the first time it is seen and forgotten once its file is gone
+#[derive(Default)]
+struct Index {
+    runs: BTreeMap<(RunName, String), Run>,
+    ids: HashMap<String, (RunName, String)>,
+}
+
+impl Token {
+    fn sees(&self, address: Ipv4Addr) -> bool {
+        self.scopes.as_ref().is_none_or(|targets| targets.iter().any(|target| target.contains(address)))
+    }
+
+    //function to count a request against the rate, false when the minute's are used up
+    fn admit(&self) -> bool {
+        let minute = store::now() / 60;
+        let mut usage = self.usage.lock().unwrap();
+        if usage.0 != minute {
+            *usage = (minute, 0);
+        }
+        usage.1 += 1;
+        self.rate.is_none_or(|rate| usage.1 <= rate)
+    }
+}
+
+impl Index {
+    //function to read the runs stored since the last look and forget those removed, the rest are known
+    //from the listing of the store alone
+    fn refresh(&mut self) -> Result<(), String> {
+        let mut present = HashSet::new();
+        for key in store::scan_keys()? {
+            for name in store::scan_runs(&key)? {
+                present.insert((name, key.clone()));
+            }
+        }
+        self.runs.retain(|run, _| present.contains(run));
+        self.ids.retain(|_, run| present.contains(run));
+        for (name, key) in present {
+            if self.runs.contains_key(&(name, key.clone())) {
+                continue;
+            }
+            //one removed since the listing, or that can't be read, is left out until it can
+            let Ok(scan) = store::load_scan(&key, name) else {
+                continue;
+            };
+            if !scan.id.is_empty() {
+                self.ids.insert(scan.id.clone(), (name, key.clone()));
+            }
+            let hosts = scan.hosts.iter().map(|host| (host.address, host.is_up())).collect();
+            self.runs.insert((name, key), Run { id: scan.id, hosts });
+        }
+        Ok(())
+    }
+}
+
+//function to listen on the address and answer each request on a thread of its own
+pub fn serve(address: &str, config_path: Option<&str>, insecure: bool) -> Result<(), String> {
+    let tokens = tokens(&config::load(config_path)?)?;
+    let loopback = address.parse::<SocketAddr>().is_ok_and(|address| address.ip().is_loopback());
+    if !loopback && !insecure {
+        return Err(format!(
+            "--api on {} would send the tokens and the stored scans over plain HTTP, listen on a loopback address behind a TLS reverse proxy (stunnel, nginx) or add --api-insecure on a network you trust",
+            address
+        ));
+    }
+    if tokens.is_empty() && !loopback {
+        return Err(format!(
+            "--api on {} would serve the stored scans to anyone who can reach it, listen on a loopback address or add an [api-token NAME] with a secret to the config file",
+            address
+        ));
+    }
+    let listener = TcpListener::bind(address).map_err(|error| format!("cannot listen on {}, {}", address, error))?;
+    if !loopback {
+        eprintln!("Warning: the API on {} is plain HTTP, its tokens and answers can be read on the network.", address);
+    }
+    println!("Serving the stored scans on http://{}/ (scans, diff and history).", address);
+    let server = Arc::new(Server { tokens, index: Mutex::new(Index::default()), clients: AtomicUsize::new(0) });
+    thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(Result::ok) {
+            if server.clients.fetch_add(1, Ordering::AcqRel) >= MAX_CLIENTS {
+                server.clients.fetch_sub(1, Ordering::AcqRel);
+                let _ = stream.set_write_timeout(Some(TIMEOUT));
+                let _ = respond(&mut stream, 503, &error_json("too many requests at once, try again shortly"));
+                continue;
+            }
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                if let Err(error) = answer(stream, &server) {
+                    eprintln!("API request failed, {}", error);
+                }
+                server.clients.fetch_sub(1, Ordering::AcqRel);
+            });
+        }
+    });
+    Ok(())
+}
+
+//function to read the [api-token NAME] sections, each has to have a secret
+fn tokens(config: &config::Config) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    for (_, section) in config.sections_of("api-token") {
+        let secret = section.require("secret")?;
+        if secret.is_empty() {
+            return Err(format!("[{}] has an empty secret", section.name));
+        }
+        let scopes = match section.get("scopes") {
+            Some(_) => {
+                let mut targets = Vec::new();
+                for name in section.list("scopes") {
+                    for block in scope::blocks(config, &name).map_err(|error| format!("[{}] scopes, {}", section.name, error))? {
+                        targets.push(target::expand(&block, None)?);
+                    }
+                }
+                Some(targets)
+            }
+            None => None,
+        };
+        let rate = match section.get("rate").map(str::parse::<u32>) {
+            Some(Ok(0)) | Some(Err(_)) => return Err(format!("[{}] rate expects the requests allowed a minute, e.g. 60", section.name)),
+            Some(Ok(rate)) => Some(rate),
+            None => None,
+        };
+        tokens.push(Token { secret: secret.to_string(), scopes, rate, usage: Mutex::new((0, 0)) });
+    }
+    Ok(tokens)
+}
+
+//function to find the token a request sends, compared in full so the time taken doesn't give away how much matched
+fn authorized<'a>(request: &str, tokens: &'a [Token]) -> Option<&'a Token> {
+    let sent = request.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("authorization").then(|| value.trim().strip_prefix("Bearer "))?
+    })?;
+    tokens.iter().find(|token| {
+        token.secret.len() == sent.len() && token.secret.bytes().zip(sent.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+    })
+}
+
+fn answer(mut stream: TcpStream, server: &Server) -> Result<(), String> {
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        match stream.read(&mut buffer).map_err(|error| format!("cannot read the request, {}", error))? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.lines().next().unwrap_or("").split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some(target)) => match authorized(&request, &server.tokens) {
+            //a liveness probe has no token to send, the checks give nothing of the scans away
+            None if !server.tokens.is_empty() && !matches!(target.split('?').next(), Some("/healthz" | "/readyz")) => (401, error_json("a token is needed, send it as Authorization: Bearer SECRET")),
+            Some(token) if !token.admit() => (429, error_json("too many requests, the token's rate is used up for this minute")),
+            token => route(target, token, server),
+        },
+        (Some(_), Some(_)) => (405, error_json("only GET is supported")),
+        _ => (400, error_json("not an HTTP request")),
+    };
+    respond(&mut stream, status, &body)
+}
+
+//function to send the response and its status
+fn respond(stream: &mut TcpStream, status: u16, body: &str) -> Result<(), String> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let challenge = if status == 401 { "WWW-Authenticate: Bearer\r\n" } else { "" };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        challenge,
+        body
+    );
+    stream.write_all(response.as_bytes()).map_err(|error| format!("cannot send the response, {}", error))
+}
+
+//function to answer one request, with its status
+fn route(target: &str, token: Option<&Token>, server: &Server) -> (u16, String) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let parameter = |name: &str| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('=')).map(decode);
+    let result = match path {
+        "/scans" => scans(parameter("range").map(|range| store::scan_key(&[&range])).as_deref(), token, server),
+        "/diff" => match (parameter("from"), parameter("to")) {
+            (Some(from), Some(to)) => diff(&from, &to, token, server),
+            _ => Err((400, "diff needs the ids of two scans, /diff?from=ID&to=ID".to_string())),
+        },
+        "/history" => match parameter("host").map(|host| host.parse::<Ipv4Addr>()) {
+            Some(Ok(address)) => history(address, parameter("since").as_deref(), token, server),
+            _ => Err((400, "history needs an IPv4 address, /history?host=IP".to_string())),
+        },
+        "/healthz" => Ok("{\"status\":\"ok\"}".to_string()),
+        "/readyz" => return ready(),
+        _ => Err((404, format!("no such endpoint {}, expected /scans, /diff, /history, /healthz or /readyz", path))),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err((status, error)) => (status, error_json(&error)),
+    }
+}
+
+//function to undo the escaping of a query value, %XX and + for a space, e.g. 10.0.0.0%2F24
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+//function to check the sweeps can run, ping able to send and a network to send on, with its status
+fn ready() -> (u16, String) {
+    let ping = match probe::ping("127.0.0.1") {
+        Outcome::Up { .. } => Ok("ping sends".to_string()),
+        Outcome::LocalError(error) => Err(format!("ping cannot send, {}", error)),
+        Outcome::Down(reason) => Err(format!("ping got no answer from 127.0.0.1, {}", reason.label())),
+    };
+    let interfaces = local::attached().map(|(networks, too_wide)| {
+        let mut names: Vec<String> = networks.iter().chain(&too_wide).map(|network| network.interface.clone()).collect();
+        names.dedup();
+        format!("networks attached to {}", names.join(", "))
+    });
+    let check = |result: &Result<String, String>| match result {
+        Ok(detail) => format!("{{\"ok\":true,\"detail\":{}}}", json::quote(detail)),
+        Err(error) => format!("{{\"ok\":false,\"detail\":{}}}", json::quote(error)),
+    };
+    let ready = ping.is_ok() && interfaces.is_ok();
+    let body = format!("{{\"ready\":{},\"checks\":{{\"ping\":{},\"interfaces\":{}}}}}", ready, check(&ping), check(&interfaces));
+    (if ready { 200 } else { 503 }, body)
+}
+
+fn error_json(error: &str) -> String {
+    format!("{{\"error\":{}}}", json::quote(error))
+}
+
+//the errors of the store are the server's, those of the request the client's
+fn stored<T>(result: Result<T, String>) -> Result<T, (u16, String)> {
+    result.map_err(|error| (500, error))
+}
+
+//function to list every stored run, or those of one range, from the index alone
+fn scans(range: Option<&str>, token: Option<&Token>, server: &Server) -> Result<String, (u16, String)> {
+    let index = index(server)?;
+    let mut runs = Vec::new();
+    for ((name, key), run) in index.runs.iter().filter(|((_, key), _)| range.is_none_or(|range| range == key)) {
+        let visible: Vec<bool> = run.hosts.iter().filter(|(address, _)| token.is_none_or(|token| token.sees(*address))).map(|(_, up)| *up).collect();
+        if visible.is_empty() && token.is_some_and(|token| token.scopes.is_some()) {
+            continue;
+        }
+        let up = visible.iter().filter(|up| **up).count();
+        runs.push(format!("{{{},\"hosts\":{},\"up\":{}}}", describe(key, &run.id, name.started_at), visible.len(), up));
+    }
+    Ok(format!("{{\"scans\":[{}]}}", runs.join(",")))
+}
+
+//function to compare two runs, usually of the same range, the later one against the earlier
+fn diff(from: &str, to: &str, token: Option<&Token>, server: &Server) -> Result<String, (u16, String)> {
+    let (from_key, from_name) = find(from, server)?;
+    let (to_key, to_name) = find(to, server)?;
+    //a run the token sees nothing of is one it can't know about
+    let Some(from) = load(&from_key, from_name, token)? else {
+        return Err((404, format!("no stored scan has the id {}", from)));
+    };
+    let Some(to) = load(&to_key, to_name, token)? else {
+        return Err((404, format!("no stored scan has the id {}", to)));
+    };
+    let (changes, unchanged) = delta::changes(&from.hosts, &to.hosts);
+    let ports = |ports: &[&PortResult]| -> String {
+        ports.iter().map(|port| json::quote(&format!("{}/{}", port.port, port.protocol))).collect::<Vec<String>>().join(",")
+    };
+    let changes: Vec<String> = changes
+        .iter()
+        .map(|change| match change {
+            Change::Up(host) => format!("{{\"address\":\"{}\",\"change\":\"up\",\"host\":{}}}", host.address, host.to_json()),
+            Change::Down(host) => format!(
+                "{{\"address\":\"{}\",\"change\":\"down\",\"reason\":{}}}",
+                host.address,
+                json::quote_option(host.reason().as_deref())
+            ),
+            Change::Changed { host, opened, closed, info } => format!(
+                "{{\"address\":\"{}\",\"change\":\"changed\",\"opened\":[{}],\"closed\":[{}],\"info\":[{}]}}",
+                host.address,
+                ports(opened),
+                ports(closed),
+                info.iter().map(|line| json::quote(line)).collect::<Vec<String>>().join(",")
+            ),
+        })
+        .collect();
+    Ok(format!(
+        "{{\"from\":{{{}}},\"to\":{{{}}},\"changes\":[{}],\"unchanged\":{}}}",
+        describe(&from_key, &from.id, from.started_at),
+        describe(&to_key, &to.id, to.started_at),
+        changes.join(","),
+        unchanged
+    ))
+}
+
+//function to follow one address through every stored run it is in, oldest first, reading only those runs
+fn history(address: Ipv4Addr, since: Option<&str>, token: Option<&Token>, server: &Server) -> Result<String, (u16, String)> {
+    let since = match since {
+        Some(since) => store::now().saturating_sub(cli::parse_duration(since, "since").map_err(|error| (400, error))?.as_secs()),
+        None => 0,
+    };
+    let wanted: Vec<(RunName, String)> = if token.is_none_or(|token| token.sees(address)) {
+        index(server)?
+            .runs
+            .range((RunName { started_at: since, sequence: 0 }, String::new())..)
+            .filter(|(_, run)| run.hosts.iter().any(|(host, _)| *host == address))
+            .map(|(run, _)| run.clone())
+            .collect()
+    }
+    else {
+        Vec::new()
+    };
+    let mut runs = Vec::new();
+    for (name, key) in wanted {
+        //a run removed since the index was brought up to date is just left out
+        let Ok(scan) = store::load_scan(&key, name) else {
+            continue;
+        };
+        if let Some(host) = scan.hosts.iter().find(|host| host.address == address) {
+            runs.push(format!("{{{},\"host\":{}}}", describe(&key, &scan.id, scan.started_at), host.to_json()));
+        }
+    }
+    if runs.is_empty() {
+        return Err((404, format!("{} isn't in any stored scan", address)));
+    }
+    Ok(format!("{{\"address\":\"{}\",\"history\":[{}]}}", address, runs.join(",")))
+}
+
+//function to read a stored run with only the hosts the token may see, None when it sees none of them
+fn load(key: &str, name: RunName, token: Option<&Token>) -> Result<Option<StoredScan>, (u16, String)> {
+    let mut scan = stored(store::load_scan(key, name))?;
+    if let Some(token) = token.filter(|token| token.scopes.is_some()) {
+        scan.hosts.retain(|host| token.sees(host.address));
+        if scan.hosts.is_empty() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(scan))
+}
+
+//function to find the range and name of a stored run by its id
+fn find(id: &str, server: &Server) -> Result<(String, RunName), (u16, String)> {
+    match index(server)?.ids.get(id) {
+        Some((name, key)) => Ok((key.clone(), *name)),
+        None => Err((404, format!("no stored scan has the id {}", id))),
+    }
+}
+
+//function to bring the index up to date, it is held until the caller is done with it
+fn index(server: &Server) -> Result<MutexGuard<'_, Index>, (u16, String)> {
+    let mut index = server.index.lock().unwrap();
+    stored(index.refresh())?;
+    Ok(index)
+}
+
+//function to give the members that say which run a result is from
+fn describe(key: &str, id: &str, started_at: u64) -> String {
+    format!("\"id\":{},\"range\":{},\"started\":{}", json::quote(id), json::quote(key), json::quote(&metadata::iso8601(started_at)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens_of(text: &str) -> Result<Vec<Token>, String> {
+        tokens(&config::parse(text).unwrap())
+    }
+
+    fn sent(header: &str) -> String {
+        format!("GET /scans HTTP/1.1\r\nHost: dashboard\r\n{}\r\n\r\n", header)
+    }
+
+    #[test]
+    fn tokens_need_a_secret() {
+        let tokens = tokens_of("[api-token grafana]\nsecret = s3cret\n[notify ops]\ntype = email\n").unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0].scopes.is_none() && tokens[0].rate.is_none());
+        assert_eq!(tokens_of("[api-token grafana]\nrate = 5\n").err().as_deref(), Some("[api-token grafana] is missing secret"));
+        assert_eq!(tokens_of("[api-token grafana]\nsecret =\n").err().as_deref(), Some("[api-token grafana] has an empty secret"));
+    }
+
+    #[test]
+    fn authorized_matches_the_whole_secret() {
+        let tokens = tokens_of("[api-token grafana]\nsecret = s3cret\n[api-token ci]\nsecret = other\n").unwrap();
+        let secret = |request: &str| authorized(request, &tokens).map(|token| token.secret.as_str());
+        assert_eq!(secret(&sent("Authorization: Bearer s3cret")), Some("s3cret"));
+        //the header name is case insensitive, the secret isn't
+        assert_eq!(secret(&sent("authorization:   Bearer other")), Some("other"));
+        for header in ["Authorization: Bearer S3CRET", "Authorization: Bearer s3cre", "Authorization: Bearer s3crets", "Authorization: Basic s3cret", "X-Token: s3cret"] {
+            assert_eq!(secret(&sent(header)), None, "{} was let in", header);
+        }
+        //the request line isn't a header
+        assert_eq!(secret("GET /authorization: Bearer s3cret HTTP/1.1\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn query_values_are_unescaped() {
+        assert_eq!(decode("10.0.0.0%2F24"), "10.0.0.0/24");
+        assert_eq!(decode("a+b%20c"), "a b c");
+        //an incomplete or bad escape is kept as it was
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%2"), "%2");
+        assert_eq!(decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn scopes_limit_the_addresses_a_token_sees() {
+        let config = "[scope office]\ninclude = 10.0.0.0/24, 10.0.1.5\n[api-token grafana]\nsecret = s3cret\nscopes = office\n";
+        let tokens = tokens_of(config).unwrap();
+        assert!(tokens[0].sees(Ipv4Addr::new(10, 0, 0, 7)) && tokens[0].sees(Ipv4Addr::new(10, 0, 1, 5)));
+        assert!(!tokens[0].sees(Ipv4Addr::new(10, 0, 1, 6)));
+        assert!(tokens_of("[api-token grafana]\nsecret = s3cret\nscopes = lab\n").err().unwrap().starts_with("[api-token grafana] scopes, "));
+    }
+
+    #[test]
+    fn rate_counts_the_requests_of_a_minute() {
+        let tokens = tokens_of("[api-token grafana]\nsecret = s3cret\nrate = 2\n[api-token ci]\nsecret = other\n").unwrap();
+        assert_eq!((0..3).map(|_| tokens[0].admit()).collect::<Vec<_>>(), [true, true, false]);
+        assert!((0..100).all(|_| tokens[1].admit()));
+        for rate in ["0", "-1", "lots"] {
+            let error = tokens_of(&format!("[api-token grafana]\nsecret = s3cret\nrate = {}\n", rate)).err();
+            assert_eq!(error.as_deref(), Some("[api-token grafana] rate expects the requests allowed a minute, e.g. 60"));
+        }
+    }
+}
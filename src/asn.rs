@@ -0,0 +1,106 @@
+//annotating public hosts with the network that announces them (--asn-data), from an offline IP to ASN
+//dataset so it works in air-gapped engagements, nothing is looked up over the network
+//
+//the datasets are read as tab separated lines, plain or gzip/zstd compressed, in either of two layouts:
+//
+//  1.0.0.0	1.0.0.255	13335	US	CLOUDFLARENET           iptoasn.com's ip2asn-v4.tsv (or -u32.tsv
+//                                                          with the addresses as numbers)
+//  8.8.8.0/24	15169	GOOGLE                              a prefix, the ASN and the owner
+//
+//ranges announced by nobody (AS0) are skipped
+
+use std::net::Ipv4Addr;
+use std::sync::OnceLock;
+
+use crate::compress;
+use crate::subnet;
+
+//one announced range, start and end included
+struct Entry {
+    start: u32,
+    end: u32,
+    asn: u32,
+    owner: String,
+}
+
+static TABLE: OnceLock<Vec<Entry>> = OnceLock::new();
+
+//function to read the dataset, the lookups after this use it, returning how many ranges it has
+pub fn load(path: &str) -> Result<usize, String> {
+    let contents = compress::read(path)?;
+    let text = String::from_utf8_lossy(&contents);
+    let mut table = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse(line) {
+            Some(entry) if entry.asn == 0 => {}
+            Some(entry) => table.push(entry),
+            None => return Err(format!("line {} of {} isn't an IP to ASN range", number + 1, path)),
+        }
+    }
+    if table.is_empty() {
+        return Err(format!("{} has no announced ranges", path));
+    }
+    table.sort_by_key(|entry| entry.start);
+    let count = table.len();
+    let _ = TABLE.set(table);
+    Ok(count)
+}
+
+fn parse(line: &str) -> Option<Entry> {
+    let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+    match fields.as_slice() {
+        [start, end, asn, _country, owner] => {
+            let (start, end) = (address(start)?, address(end)?);
+            if start > end {
+                return None;
+            }
+            Some(Entry { start, end, asn: number(asn)?, owner: owner.to_string() })
+        }
+        [prefix, asn, owner] => {
+            let (network, length) = prefix.split_once('/')?;
+            let length: u8 = length.parse().ok().filter(|length| *length <= 32)?;
+            let start = u32::from(network.parse::<Ipv4Addr>().ok()?) & subnet::mask(length);
+            Some(Entry { start, end: start | !subnet::mask(length), asn: number(asn)?, owner: owner.to_string() })
+        }
+        _ => None,
+    }
+}
+
+//an address as dotted quads or as the number the -u32 files use
+fn address(field: &str) -> Option<u32> {
+    field.parse::<Ipv4Addr>().map(u32::from).or_else(|_| field.parse()).ok()
+}
+
+//an ASN with or without the AS in front
+fn number(field: &str) -> Option<u32> {
+    field.trim_start_matches("AS").parse().ok()
+}
+
+//function to describe the network a public address is in, e.g. "AS15169 GOOGLE (8.8.8.0/24)",
+//None when no dataset was loaded, the address isn't public or nothing announces it
+pub fn lookup(address: Ipv4Addr) -> Option<String> {
+    let table = TABLE.get()?;
+    if subnet::classify(address, 32) != "public" {
+        return None;
+    }
+    let address = u32::from(address);
+    //the last range starting at or before the address, ranges don't overlap
+    let index = table.partition_point(|entry| entry.start <= address).checked_sub(1)?;
+    let entry = table.get(index).filter(|entry| address <= entry.end)?;
+    let owner = if entry.owner.is_empty() { String::new() } else { format!(" {}", entry.owner) };
+    Some(format!("AS{}{} ({})", entry.asn, owner, range(entry.start, entry.end)))
+}
+
+//function to write a range as a prefix when it is exactly one, e.g. 8.8.8.0/24, else as start-end
+fn range(start: u32, end: u32) -> String {
+    let size = u64::from(end - start) + 1;
+    if size.is_power_of_two() && u64::from(start) % size == 0 {
+        format!("{}/{}", Ipv4Addr::from(start), 32 - size.trailing_zeros())
+    }
+    else {
+        format!("{}-{}", Ipv4Addr::from(start), Ipv4Addr::from(end))
+    }
+}
@@ -0,0 +1,142 @@
+//the audit log, one line per run that sends or plans probes, who ran it, when, against what scope, with
+//which options and out of which interface, kept apart from the results in audit.jsonl next to the stored
+//scans and only ever appended to
+//
+//  [audit]
+//  path = /var/log/host_disco/audit.jsonl
+//  syslog = yes      also send every entry to the local syslog, on Unix
+//  required = yes    refuse to run when the entry can't be written or sent, a warning without
+//
+//without required a run that can't be audited, e.g. with an unwritable data directory, goes ahead
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+use crate::config;
+use crate::json;
+use crate::metadata;
+use crate::store;
+use crate::target::Target;
+
+//syslog priority of the entries, facility authpriv (10) and severity notice (5)
+const SYSLOG_PRIORITY: u8 = 10 * 8 + 5;
+
+//the socket syslog reads local messages from
+#[cfg(all(unix, not(target_os = "macos")))]
+const SYSLOG_SOCKET: &str = "/dev/log";
+#[cfg(target_os = "macos")]
+const SYSLOG_SOCKET: &str = "/var/run/syslog";
+
+//function to append an entry for a run to the audit log, and send it to syslog when configured, an
+//entry that can't be recorded is an error only when the [audit] section requires it
+pub fn record(config_path: Option<&str>, action: &str, targets: &[Target], arguments: &[String]) -> Result<(), String> {
+    let config = config::load(config_path)?;
+    let section = config.sections.iter().find(|section| section.name == "audit");
+    let enabled = |key: &str| section.and_then(|section| section.get(key)).is_some_and(|value| matches!(value, "yes" | "true" | "1"));
+    match append(section, action, targets, arguments, enabled("syslog")) {
+        Err(error) if !enabled("required") => {
+            eprintln!("\x1b[0;33mWarning:\x1b[0m this run isn't in the audit log, {}", error);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+//function to write the entry to the log file, and to syslog
+fn append(section: Option<&config::Section>, action: &str, targets: &[Target], arguments: &[String], syslog: bool) -> Result<(), String> {
+    let path = match section.and_then(|section| section.get("path")) {
+        Some(path) => PathBuf::from(path),
+        None => store::data_dir()?.join("audit.jsonl"),
+    };
+
+    let labels: Vec<&str> = targets.iter().map(|target| target.label.as_str()).collect();
+    let interfaces = interfaces(targets);
+    let arguments_json: Vec<String> = arguments.iter().map(|argument| json::quote(argument)).collect();
+    let time = metadata::iso8601(store::now());
+    let user = metadata::invoking_user();
+    let line = format!(
+        "{{\"time\":{},\"user\":{},\"action\":{},\"scope\":[{}],\"interfaces\":[{}],\"arguments\":[{}]}}\n",
+        json::quote(&time),
+        json::quote(&user),
+        json::quote(action),
+        labels.iter().map(|label| json::quote(label)).collect::<Vec<String>>().join(","),
+        interfaces.iter().map(|interface| json::quote(interface)).collect::<Vec<String>>().join(","),
+        arguments_json.join(",")
+    );
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|error| format!("cannot open {}, {}", path.display(), error))?;
+    file.write_all(line.as_bytes()).map_err(|error| format!("cannot write {}, {}", path.display(), error))?;
+
+    if syslog {
+        let message = format!(
+            "<{}>host_disco[{}]: user={} action={} scope={} interfaces={} arguments={}",
+            SYSLOG_PRIORITY,
+            std::process::id(),
+            user,
+            action,
+            labels.join(","),
+            interfaces.join(","),
+            arguments.join(" ")
+        );
+        send_syslog(&message)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn send_syslog(message: &str) -> Result<(), String> {
+    let socket = UnixDatagram::unbound().map_err(|error| format!("cannot reach syslog, {}", error))?;
+    socket.send_to(message.as_bytes(), SYSLOG_SOCKET).map_err(|error| format!("cannot reach syslog, {}", error))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_syslog(_message: &str) -> Result<(), String> {
+    Err("there is no syslog to send to on this system".to_string())
+}
+
+//function to work out which interfaces the probes to the targets leave by, from the kernel's routing table
+fn interfaces(targets: &[Target]) -> Vec<String> {
+    let routes = fs::read_to_string("/proc/net/route").unwrap_or_default();
+    //Iface Destination Gateway Flags RefCnt Use Metric Mask ..., addresses in little endian hex
+    let table: Vec<(&str, u32, u32)> = routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let hex = |field: &str| u32::from_str_radix(field, 16).ok().map(|value| u32::from_be_bytes(value.to_ne_bytes()));
+            Some((*fields.first()?, hex(fields.get(1)?)?, hex(fields.get(7)?)?))
+        })
+        .collect();
+
+    let mut interfaces: Vec<String> = Vec::new();
+    for target in targets {
+        let network = u32::from(target.network);
+        let interface = if target.network.is_loopback() {
+            Some("lo")
+        }
+        else {
+            //the most specific route covering the target
+            table
+                .iter()
+                .filter(|(_, destination, mask)| network & mask == *destination)
+                .max_by_key(|(_, _, mask)| mask.count_ones())
+                .map(|(interface, _, _)| *interface)
+        };
+        let interface = interface.unwrap_or("unknown").to_string();
+        if !interfaces.contains(&interface) {
+            interfaces.push(interface);
+        }
+    }
+    interfaces
+}
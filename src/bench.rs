@@ -0,0 +1,98 @@
+//measuring how fast and how reliably each probe backend works on this machine
+
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::probe::{self, Outcome};
+use crate::stats;
+
+const TCP_TIMEOUT: Duration = Duration::from_secs(1);
+
+//what one backend achieved
+struct BenchResult {
+    answered: usize,
+    elapsed: Duration,
+    rtts: Vec<f64>,
+}
+
+//function to benchmark every backend against a target that is known to be up
+pub fn run(target: &str, count: usize, port: u16, workers: usize) -> Result<(), String> {
+    let address: Ipv4Addr = target.parse().map_err(|_| format!("Invalid benchmark target {}.", target))?;
+    println!("Benchmarking {} probes per backend against {} with {} workers.", count, address, workers);
+    println!();
+    println!("{:<12} {:>10} {:>10} {:>10} {:>10}", "BACKEND", "PROBES/S", "ANSWERED", "P50", "P95");
+
+    let ping = measure(count, workers, || match probe::ping(&address.to_string()) {
+        Outcome::Up { rtt, .. } => Some(rtt.unwrap_or(0.0)),
+        _ => None,
+    });
+    print_row("ping", count, &ping);
+
+    //the system ping is the only ICMP implementation there is, raw sockets aren't implemented
+    println!("{:<12} {:>10}", "raw-icmp", "not available");
+
+    //a refused connection is still an answer from the host, only timeouts count as misses
+    let tcp = measure(count, workers, || {
+        let started = Instant::now();
+        match TcpStream::connect_timeout(&SocketAddr::from((address, port)), TCP_TIMEOUT) {
+            Ok(_) => Some(started.elapsed().as_secs_f64() * 1000.0),
+            Err(error) if error.kind() == std::io::ErrorKind::ConnectionRefused => Some(started.elapsed().as_secs_f64() * 1000.0),
+            Err(_) => None,
+        }
+    });
+    print_row(&format!("tcp/{}", port), count, &tcp);
+
+    Ok(())
+}
+
+//function to run a probe count times on a pool of workers, the probe returns the RTT when answered
+fn measure(count: usize, workers: usize, probe: impl Fn() -> Option<f64> + Sync) -> BenchResult {
+    let next = AtomicUsize::new(0);
+    let rtts = Mutex::new(Vec::new());
+    let started = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.min(count) {
+            scope.spawn(|| {
+                while next.fetch_add(1, Ordering::Relaxed) < count {
+                    if let Some(rtt) = probe() {
+                        rtts.lock().unwrap().push(rtt);
+                    }
+                }
+            });
+        }
+    });
+
+    let rtts = rtts.into_inner().unwrap();
+    BenchResult {
+        answered: rtts.len(),
+        elapsed: started.elapsed(),
+        rtts,
+    }
+}
+
+fn print_row(backend: &str, count: usize, result: &BenchResult) {
+    let mut sorted = result.rtts.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let (p50, p95) = if sorted.is_empty() {
+        ("-".to_string(), "-".to_string())
+    }
+    else {
+        (
+            format!("{:.2}ms", stats::percentile(&sorted, 50.0)),
+            format!("{:.2}ms", stats::percentile(&sorted, 95.0)),
+        )
+    };
+
+    println!(
+        "{:<12} {:>10.1} {:>9.1}% {:>10} {:>10}",
+        backend,
+        count as f64 / result.elapsed.as_secs_f64().max(f64::EPSILON),
+        result.answered as f64 * 100.0 / count as f64,
+        p50,
+        p95
+    );
+}
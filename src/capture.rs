@@ -0,0 +1,76 @@
+//recording the probe and reply packets of a sweep to a pcap file with tcpdump, so a disputed result
+//can be checked in Wireshark and the capture attached to a report
+//
+//the probes come from ping, curl and friends rather than our own sockets, so tcpdump watches every
+//interface for traffic to and from the target ranges while the sweep runs
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::Ipv4Addr;
+use std::process::{Child, Command, Stdio};
+
+
+pub struct Capture {
+    child: Child,
+}
+
+impl Capture {
+    //function to record the traffic to and from the target ranges to the pcap file given
+    pub fn start(path: &str, blocks: &[(Ipv4Addr, u8)]) -> Result<Capture, String> {
+        //the target ranges as few net primitives as possible, e.g. "net 10.0.0.0/24 or net 10.0.1.7/32"
+        let filter = blocks
+            .iter()
+            .map(|(network, prefix)| format!("net {}/{}", network, prefix))
+            .collect::<Vec<String>>()
+            .join(" or ");
+        Capture::spawn(Command::new("tcpdump").args(["-i", "any", "-n", "-U", "-w", path]).arg(filter).stdout(Stdio::null()))
+    }
+
+    //function to capture the frames matching a tcpdump filter on one interface, e.g. the LLDP announcements,
+    //into a file already open, tcpdump writes it to stdout so it never opens a path as root
+    pub fn listen(file: File, interface: &str, filter: &str) -> Result<Capture, String> {
+        Capture::spawn(Command::new("tcpdump").args(["-i", interface, "-n", "-U", "-w", "-"]).arg(filter).stdout(file))
+    }
+
+    //function to start tcpdump and wait until it is listening, so the first probes are not missed
+    fn spawn(command: &mut Command) -> Result<Capture, String> {
+        let mut child = command
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("cannot run tcpdump, {}", error))?;
+
+        //tcpdump says "listening on any, ..." once packets are being captured, or why it can't
+        let mut stderr = BufReader::new(child.stderr.take().ok_or("cannot read tcpdump's output")?);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match stderr.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    let _ = child.wait();
+                    return Err("tcpdump exited before it started capturing".to_string());
+                }
+                Ok(_) if line.contains("listening on") => break,
+                //e.g. "tcpdump: any: You don't have permission to capture on that device", warnings aren't fatal
+                Ok(_) if line.starts_with("tcpdump: ") && !line.contains("WARNING") => {
+                    let _ = child.wait();
+                    return Err(line.trim_start_matches("tcpdump: ").trim().to_string());
+                }
+                Ok(_) => {}
+            }
+        }
+        //the rest of what tcpdump prints is the packet count when it stops
+        child.stderr = Some(stderr.into_inner());
+        Ok(Capture { child })
+    }
+
+    //function to stop tcpdump the way Ctrl-C would, so it flushes the file, returning the packet count
+    pub fn stop(self) -> Result<Option<u64>, String> {
+        let _ = Command::new("kill").args(["-INT", &self.child.id().to_string()]).status();
+        let output = self.child.wait_with_output().map_err(|error| format!("tcpdump failed, {}", error))?;
+
+        //"12 packets captured"
+        Ok(String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find_map(|line| line.strip_suffix(" packets captured")?.parse().ok()))
+    }
+}
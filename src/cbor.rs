@@ -0,0 +1,339 @@
+//a compact binary form of the results (-o cbor) for scans too large for JSON, CBOR (RFC 8949) with
+//the hosts as arrays so the field names are written once rather than per host, and the addresses as
+//4 bytes; host_disco convert turns a file back into the json format
+//
+//the schema, in CDDL (RFC 8610):
+//
+//  results = [
+//      format: "host_disco",
+//      version: 1,
+//      scan: { * tstr => any },    ; the "scan" object of the json format
+//      fields: [+ tstr],           ; the names of the host fields, in order
+//      hosts: [* host],
+//  ]
+//  host = [+ any]                  ; the values of the fields as in the json format, except
+//                                  ; "address" which is bstr .size 4
+//
+//whole numbers are integers and others float64, definite lengths only, no tags
+
+use std::net::Ipv4Addr;
+
+use crate::compress;
+use crate::host::HostResult;
+use crate::json::{self, Value};
+use crate::metadata::ScanMetadata;
+
+const FORMAT: &str = "host_disco";
+const VERSION: u64 = 1;
+
+//major types, the top 3 bits of the initial byte
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+//a hostile file can't nest deeper than this and run the decoder out of stack
+const MAX_DEPTH: usize = 32;
+
+//function to encode the results
+pub fn results(metadata: &ScanMetadata, results: &[HostResult]) -> Result<Vec<u8>, String> {
+    let scan = json::parse(&metadata.to_json()).map_err(|error| format!("cannot encode the scan metadata, {}", error))?;
+    let mut fields: Vec<String> = Vec::new();
+    let mut hosts = Vec::new();
+    for host in results {
+        let Value::Object(entries) = json::parse(&host.to_json()).map_err(|error| format!("cannot encode {}, {}", host.address, error))? else {
+            return Err(format!("cannot encode {}", host.address));
+        };
+        //every host has the same fields, the first one names them
+        if fields.is_empty() {
+            fields = entries.iter().map(|(name, _)| name.clone()).collect();
+        }
+        hosts.push(entries.into_iter().map(|(_, value)| value).collect::<Vec<Value>>());
+    }
+
+    let mut out = Vec::new();
+    header(&mut out, ARRAY, 5);
+    text(&mut out, FORMAT);
+    header(&mut out, UNSIGNED, VERSION);
+    encode(&mut out, &scan);
+    header(&mut out, ARRAY, fields.len() as u64);
+    for field in &fields {
+        text(&mut out, field);
+    }
+    header(&mut out, ARRAY, hosts.len() as u64);
+    for values in &hosts {
+        header(&mut out, ARRAY, values.len() as u64);
+        for (field, value) in fields.iter().zip(values) {
+            match value.as_str().and_then(|address| address.parse::<Ipv4Addr>().ok()) {
+                Some(address) if field == "address" => {
+                    header(&mut out, BYTES, 4);
+                    out.extend_from_slice(&address.octets());
+                }
+                _ => encode(&mut out, value),
+            }
+        }
+    }
+    Ok(out)
+}
+
+//function to transcode a cbor results file to the json format, to the output file or stdout
+pub fn convert(path: &str, output_file: Option<&str>, compress: Option<&str>) -> Result<(), String> {
+    let contents = compress::read(path)?;
+    let mut decoder = Decoder { bytes: &contents, pos: 0 };
+    let document = decoder.value(0).map_err(|error| format!("{} isn't a host_disco cbor file, {}", path, error))?;
+    if decoder.pos != contents.len() {
+        return Err(format!("{} has unexpected data at byte {}", path, decoder.pos));
+    }
+
+    let (scan, fields, hosts) = match &document {
+        Value::Array(parts) => match parts.as_slice() {
+            [Value::String(format), version, scan, Value::Array(fields), Value::Array(hosts)] if format == FORMAT => {
+                if version.as_u64() != Some(VERSION) {
+                    return Err(format!("{} is version {} of the cbor format, this build reads version {}", path, json::stringify(version), VERSION));
+                }
+                (scan, fields, hosts)
+            }
+            _ => return Err(format!("{} isn't a host_disco cbor file", path)),
+        },
+        _ => return Err(format!("{} isn't a host_disco cbor file", path)),
+    };
+    let fields: Vec<&str> = fields.iter().map(|field| field.as_str().ok_or_else(|| format!("{} has a field name that isn't text", path))).collect::<Result<_, _>>()?;
+
+    let mut lines = Vec::new();
+    for (index, host) in hosts.iter().enumerate() {
+        let Some(values) = host.as_array().filter(|values| values.len() == fields.len()) else {
+            return Err(format!("host {} of {} doesn't have the {} fields", index + 1, path, fields.len()));
+        };
+        let entries = fields.iter().zip(values).map(|(field, value)| (field.to_string(), value.clone())).collect();
+        lines.push(json::stringify(&Value::Object(entries)));
+    }
+    //the same layout as -o json writes
+    let document = format!("{{\"scan\":{},\n\"hosts\":[\n{}\n]}}\n", json::stringify(scan), lines.join(",\n"));
+
+    match output_file {
+        Some(output_file) => {
+            compress::write(output_file, document.as_bytes(), compress, None)?;
+            eprintln!("{} hosts of {} written to {} as json.", hosts.len(), path, output_file);
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+//function to write an initial byte and its argument in the fewest bytes
+fn header(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+fn text(out: &mut Vec<u8>, value: &str) {
+    header(out, TEXT, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn encode(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(number) if number.fract() == 0.0 && *number >= 0.0 && *number < u64::MAX as f64 => header(out, UNSIGNED, *number as u64),
+        //-1 - n, so -1 is 0
+        Value::Number(number) if number.fract() == 0.0 && *number < 0.0 && *number > i64::MIN as f64 => {
+            header(out, NEGATIVE, (-1 - *number as i64) as u64)
+        }
+        Value::Number(number) => {
+            out.push(0xfb);
+            out.extend_from_slice(&number.to_be_bytes());
+        }
+        Value::String(value) => text(out, value),
+        Value::Array(values) => {
+            header(out, ARRAY, values.len() as u64);
+            for value in values {
+                encode(out, value);
+            }
+        }
+        Value::Object(entries) => {
+            header(out, MAP, entries.len() as u64);
+            for (key, value) in entries {
+                text(out, key);
+                encode(out, value);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, length: u64) -> Result<&[u8], String> {
+        let end = usize::try_from(length).ok().and_then(|length| self.pos.checked_add(length)).filter(|end| *end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(format!("it ends early at byte {}", self.pos));
+        };
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    //function to read an initial byte, returning the major type, the additional information and its argument
+    fn header(&mut self) -> Result<(u8, u8, u64), String> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let argument = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().unwrap_or_default()) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().unwrap_or_default()) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap_or_default()),
+            _ => return Err(format!("indefinite lengths aren't used, found one at byte {}", self.pos - 1)),
+        };
+        Ok((major, info, argument))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("it nests too deep at byte {}", self.pos));
+        }
+        let start = self.pos;
+        let (major, info, argument) = self.header()?;
+        match major {
+            UNSIGNED => Ok(Value::Number(argument as f64)),
+            NEGATIVE => Ok(Value::Number(-1.0 - argument as f64)),
+            //the only byte strings are addresses
+            BYTES => match self.take(argument)? {
+                [a, b, c, d] => Ok(Value::String(Ipv4Addr::new(*a, *b, *c, *d).to_string())),
+                _ => Err(format!("the byte string at byte {} isn't an address", start)),
+            },
+            TEXT => {
+                let bytes = self.take(argument)?;
+                String::from_utf8(bytes.to_vec()).map(Value::String).map_err(|_| format!("the text at byte {} isn't UTF-8", start))
+            }
+            //the lengths aren't trusted for allocating, each item takes at least a byte
+            ARRAY => {
+                let mut values = Vec::new();
+                for _ in 0..argument {
+                    values.push(self.value(depth + 1)?);
+                }
+                Ok(Value::Array(values))
+            }
+            MAP => {
+                let mut entries = Vec::new();
+                for _ in 0..argument {
+                    let key_at = self.pos;
+                    let Value::String(key) = self.value(depth + 1)? else {
+                        return Err(format!("the map key at byte {} isn't text", key_at));
+                    };
+                    entries.push((key, self.value(depth + 1)?));
+                }
+                Ok(Value::Object(entries))
+            }
+            TAG => Err(format!("tags aren't used, found one at byte {}", start)),
+            SIMPLE => match (info, argument) {
+                (20, _) => Ok(Value::Bool(false)),
+                (21, _) => Ok(Value::Bool(true)),
+                (22 | 23, _) => Ok(Value::Null),
+                (26, bits) => Ok(Value::Number(f32::from_bits(bits as u32) as f64)),
+                (27, bits) => Ok(Value::Number(f64::from_bits(bits))),
+                _ => Err(format!("unexpected simple value at byte {}", start)),
+            },
+            _ => unreachable!("the major type is 3 bits"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Result<Value, String> {
+        let mut decoder = Decoder { bytes, pos: 0 };
+        let value = decoder.value(0)?;
+        assert_eq!(decoder.pos, bytes.len(), "bytes left over");
+        Ok(value)
+    }
+
+    fn round_trip(value: &Value) -> Value {
+        let mut out = Vec::new();
+        encode(&mut out, value);
+        decode(&out).unwrap()
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let value = json::parse(
+            r#"{"address":"10.0.0.1","up":true,"reason":null,"rtt_ms":1.25,"ttl":64,"offset":-3,"big":4294967296,
+                "ports":[{"port":22,"protocol":"tcp"},{"port":53,"protocol":"udp"}],"name":"café","empty":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(round_trip(&value), value);
+    }
+
+    #[test]
+    fn headers_use_the_fewest_bytes() {
+        let encoded = |value: f64| {
+            let mut out = Vec::new();
+            encode(&mut out, &Value::Number(value));
+            out
+        };
+        assert_eq!(encoded(23.0), [0x17]);
+        assert_eq!(encoded(24.0), [0x18, 24]);
+        assert_eq!(encoded(256.0), [0x19, 1, 0]);
+        assert_eq!(encoded(65536.0), [0x1a, 0, 1, 0, 0]);
+        assert_eq!(encoded(-1.0), [0x20]);
+        assert_eq!(encoded(0.5)[0], 0xfb);
+    }
+
+    #[test]
+    fn addresses_are_four_byte_strings() {
+        assert_eq!(decode(&[0x44, 10, 0, 0, 1]).unwrap(), Value::String("10.0.0.1".to_string()));
+        assert!(decode(&[0x43, 10, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| {
+            let mut bytes = vec![0x81; depth];
+            bytes.push(0x00);
+            bytes
+        };
+        assert!(decode(&nested(MAX_DEPTH)).is_ok());
+        assert!(decode(&nested(MAX_DEPTH + 1)).unwrap_err().contains("nests too deep"));
+        assert!(decode(&nested(100_000)).is_err());
+    }
+
+    #[test]
+    fn truncated_and_unsupported_input_is_refused() {
+        //an array claiming more items than there are bytes, a huge text length, an indefinite length and a tag
+        assert!(decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]).is_err());
+        assert!(decode(&[0x7b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(decode(&[0x9f, 0x00, 0xff]).is_err());
+        assert!(decode(&[0xc1, 0x00]).is_err());
+        assert!(decode(&[0x19, 0x01]).is_err());
+        assert!(decode(&[]).is_err());
+        //a map key has to be text
+        assert!(decode(&[0xa1, 0x01, 0x02]).is_err());
+        assert!(decode(&[0x62, 0xff, 0xfe]).is_err());
+    }
+}
@@ -0,0 +1,1107 @@
+//command line parsing, everything is optional so the tool still works as an interactive prompt
+
+use crate::cloud;
+use crate::compress;
+use crate::encrypt::{self, Encryption};
+use crate::identify;
+use crate::ipv6;
+use crate::latency;
+use crate::lldp;
+use crate::oui;
+use crate::output;
+use crate::pattern::Pattern;
+use crate::ports;
+use crate::silence;
+use crate::sshkeys;
+use crate::store;
+use crate::target;
+use crate::vlan;
+use crate::wait;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: host_disco [OPTIONS] [TARGET]...
+       host_disco net <IP/CIDR> [--split <PREFIX>]
+       host_disco completions <bash|zsh|fish|powershell>
+       host_disco bench [IP] [--count <N>] [--port <PORT>] [--concurrency <N>]
+       host_disco silence <IP|IP/CIDR> <DURATION> [REASON] | list | clear <IP|IP/CIDR>
+       host_disco history <TARGET>... [--since <DURATION>] [--output-file <FILE> [--compress <METHOD>]]
+       host_disco update-oui <FILE|URL>
+       host_disco convert <FILE> [--output-file <FILE> [--compress <METHOD>]]
+       host_disco wait <IP|HOSTNAME> [--timeout <DURATION>] [--reboot [--ports <LIST>]]
+       host_disco ipv6 <INTERFACE> [--global] [--prefix <PREFIX>]... [--macs-from <FILE>]
+       host_disco merge <FILE>... [-o <FORMAT>] [--output-file <FILE> [--compress <METHOD>]]
+
+Sweeps every address in TARGET with ping and reports which hosts are up. TARGET
+is an IP/CIDR range, a single IP or hostname, or - to read targets from stdin
+(one per line). Overlapping targets are reported and every address is probed once.
+When no target is given the tool prompts for one. While a scan runs in a terminal,
+press p to pause sending probes, r to resume and s for an interim summary. Every
+run and benchmark is appended to an audit log first (audit.jsonl with the stored
+scans, or the path, syslog and required settings of the [audit] section of the config
+file), a run that can't be logged only warns unless the section sets required = yes.
+
+Subcommands:
+    net                    print network, broadcast, masks and host counts of a range and
+                           split it into smaller prefixes (the next prefix down by default)
+    completions            print a shell completion script
+    bench                  measure probe throughput and answer rate of each backend against a
+                           host known to be up (127.0.0.1, 200 probes, TCP port 22 by default)
+    silence                keep watch mode from notifying about an address or range for a while
+                           (e.g. silence 192.168.1.20 2h patching), changes are still recorded,
+                           [maintenance NAME] sections of the config file add recurring windows
+    history                export the stored runs of the targets (as given to the scan, e.g. from
+                           watch mode) as CSV, one row per host per run with its status and RTT
+    update-oui             replace the embedded MAC vendor table with the MA-L assignments of an
+                           IEEE oui.csv (https://standards-oui.ieee.org/oui/oui.csv), file or URL
+    convert                turn a results file written with -o cbor into the json format
+    wait                   probe a host every second until it answers (up to 5m by default), exiting
+                           0 once it does and 2 when the timeout passes, e.g. after rebooting it,
+                           --reboot times a reboot instead, waiting for the host to go down and then
+                           reporting how long ping and each port (SSH by default) took to come back
+    ipv6                   find the IPv6 hosts on an interface's link by pinging the all-nodes and
+                           all-routers groups (ff02::1 and ff02::2), listing their link-local
+                           addresses, MACs and vendors, --global also probes the global addresses
+                           made of the interface's /64 prefixes and each host's interface id and
+                           the EUI-64 SLAAC addresses of the MACs in the neighbour tables, --prefix
+                           derives them into another prefix too (e.g. 2001:db8:1:2::/64) and
+                           --macs-from takes more MACs from the json results of an IPv4 sweep
+    merge                  combine json results files, the shards of a sweep (--shards, saying which
+                           are missing) or partial and repeated scans, into one set of results (json
+                           unless -o gives another format), a host in several files gets the result
+                           of the scan that finished last and the id of that scan as its source
+
+Options:
+    --targets-file <FILE>  also scan the targets listed in FILE, one per line, repeatable
+    --scope <NAME>         also scan a [scope NAME] of the config file, its include list minus its
+                           exclude list (addresses, ranges or other scopes), repeatable
+    --name-pattern <PATTERN>
+                           also scan the hosts a naming convention gives, {01..20} standing for the
+                           numbers padded as written and {web,db} for each of a list, e.g.
+                           'web{01..20}.corp.local', names that don't resolve are skipped, repeatable
+    --local                sweep the networks attached to every interface (up to /16) at once, the
+                           interfaces taking turns, and report the live hosts per interface
+    --docker-networks      add the subnets of the Docker bridge networks as targets, listed by the
+                           Docker API on /var/run/docker.sock (run on the Docker host)
+    --targets-from <SOURCE>
+                           also scan the private addresses of a cloud network, aws:VPC_ID,
+                           gcp:PROJECT[/NETWORK] or azure:GROUP[/VNET], listed with the aws, gcloud
+                           or az CLI and its credentials, and report inventoried hosts that didn't
+                           answer, or the nodes of a Kubernetes cluster, k8s:[CONTEXT] (kubectl),
+                           reporting which probes each node answered, or the machines this one
+                           knows, known-hosts:[FILE] (~/.ssh/known_hosts) or hosts:[FILE]
+                           (/etc/hosts), reporting which no longer answer, or the clients of a DHCP
+                           server, leases:[FILE] (ISC dhcpd, dnsmasq or Kea, found in the usual
+                           places), flagging the leases of clients that don't answer as stale,
+                           repeatable
+    --vlan <ID[:ADDRESS/PREFIX]>
+                           also sweep the subnet of an 802.1Q VLAN on the --trunk interface, through
+                           its tagged subinterface (e.g. eth0.30), created and given the address to
+                           probe from when missing and removed afterwards (needs root), repeatable
+    --trunk <INTERFACE>    the trunk interface carrying the --vlan VLANs
+    --snmp-arp <DEVICE>    read the ARP and MAC address tables of a router or switch over SNMPv2c
+                           (community from the [snmp] section of the config file, default public)
+                           and report hosts it has seen that didn't answer, repeatable
+    --ports <LIST>         TCP connect probe these ports on live hosts, by number or service
+                           name (e.g. 22,80,8000-8010 or ssh,http,https,rdp)
+    --tcp-ping <LIST>      also look for hosts that don't answer ICMP by TCP connect to these ports,
+                           a connection or a reset means the host is there (e.g. 22,80,443)
+    --top-ports <N>        also probe the N most common ports (up to 100)
+    --udp-ports <LIST>     UDP probe these ports on live hosts, DNS, NTP, SNMP and NetBIOS get
+                           payloads those services answer (e.g. dns,ntp,snmp,netbios-ns)
+    --profile <NAME>       probe and identify the services of a profile on live hosts, repeatable:
+                           windows (SMB dialect and names, RDP security, VNC version),
+                           printers (IPP make and model on 631, PJL model on 9100),
+                           ot (Modbus device id, EtherNet/IP ListIdentity, BACnet Who-Is and
+                           ReadProperty, read-only requests sent one at a time, 250ms apart),
+                           ad (Active Directory, flags domain controllers from the LDAP rootDSE
+                           and the _ldap._tcp.dc._msdcs SRV records of the search domains),
+                           infra (DNS and NTP servers, tagged with the dns-server and ntp-server
+                           roles),
+                           web (HTTP status, server and page title on 80, 443, 8000, 8080 and 8443,
+                           fetched with curl)
+    --collect-ssh-keys     probe port 22 and record the SSH server version and host key fingerprints
+                           of live hosts (needs ssh-keyscan), --delta reports keys that changed
+    --http-proxy <URL>     send the web profile's requests through this proxy (e.g. http://proxy:3128),
+                           curl's http_proxy and https_proxy variables are honoured without it
+    --http-header <NAME: VALUE>
+                           add this header to the web profile's requests, repeatable
+    --user-agent <STRING>  user agent of the web profile's requests (default host_disco/VERSION)
+    --asn-data <FILE>      annotate live public hosts with the ASN and owner of their prefix from an
+                           offline dataset (iptoasn.com's ip2asn-v4.tsv, or PREFIX, ASN and OWNER
+                           tab separated lines), nothing is looked up over the network
+    --enrich-after         name, port scan and identify the live hosts after the sweep rather than
+                           alongside it, so the sweep runs at full speed (the live output shows them
+                           unnamed, and --vendor-filter and --hostname-filter only apply to the report)
+    --spill <FILE>         write the addresses that don't answer to this file as JSON lines as the sweep
+                           goes, keeping only the live hosts in memory (on its own from 1048576 addresses,
+                           under spill/ in the data directory)
+    --shards <N>           split the addresses of the targets into N equal slices and, with --shard K,
+    --shard <K>            sweep only the Kth (from 1), e.g. on K of N machines given the same targets,
+                           and combine their json results with host_disco merge
+    --vendor-filter <LIST> only report live hosts whose MAC vendor contains one of these names
+                           (e.g. raspberry,apple), in the live output, summary and output formats
+    --vendor-exclude <LIST>
+                           leave out live hosts whose MAC vendor contains one of these names
+    --hostname-filter <REGEX>
+                           only report live hosts whose reverse DNS name matches (e.g. '^db-'),
+                           case insensitive, with . [ ] ( | ) ^ $ * + ? {n,m} \\d \\w and \\s
+    -o, --output <FORMAT>  render the results as table, json, csv or xml, or cbor (compact binary, for
+                           very large scans, written to --output-file), the open ports of live hosts
+                           as a matrix (hosts by ports), live hosts as nmap (Nmap XML, for
+                           Metasploit's db_import), masscan-json (masscan -oJ) or zmap (an address
+                           per line), or as nagios, icinga (Icinga 2) or zabbix (import file)
+                           monitoring configuration
+    --monitoring-name <TEMPLATE>
+                           host name for the monitoring formats, {name} (hostname, else address),
+                           {hostname}, {ip}, {ip_dashed} and {vendor} are filled in (default {name})
+    --monitoring-group <NAME>
+                           host group for the monitoring formats (default discovered)
+    --output-file <FILE>   write the rendered results to a file instead of the terminal
+    --compress <METHOD>    compress the --output-file, --pcap and --export-dot files with gzip or zstd
+                           (needs the command), compressed files given back to --replay, convert or
+                           --targets-file are decompressed as they are read
+    --encrypt-to <RECIPIENT>
+                           encrypt the --output-file, --pcap and --export-dot files to a recipient, an
+                           age key (age1..., ssh-ed25519 ...) with age or a key in the gpg keyring
+                           (e.g. an email address) with gpg, repeatable
+    --encrypt-passphrase   encrypt those files with a passphrase instead (gpg, AES256), taken from
+                           HOST_DISCO_PASSPHRASE or asked for before the scan starts
+    --sign-with <KEY>      write a detached signature next to the --output-file, --pcap and --export-dot
+                           files, with an SSH private key file (ssh-keygen -Y sign, FILE.sig) or a
+                           key in the gpg keyring (FILE.asc), so recipients can check the results
+    --compare              scan two ranges (e.g. a primary and a DR site) and list the live hosts
+                           found in both, matched by MAC address or hostname, and those found in
+                           only one of them
+    --paths <LIST>         ping every address out of each of these interfaces (e.g. eth0,wlan0,tun0)
+                           and list the round trip times side by side, slow and failing paths marked
+    --count                print only the number of live hosts (after any filters)
+    --silent               print nothing, the exit status says whether enough hosts were up
+    --min-up <N>           exit with status 1 when fewer than N hosts are up (default 1 with
+                           --count and --silent)
+    --lldp <INTERFACE>     listen for LLDP and CDP announcements on the interface during the sweep
+                           and report the switches and routers it is connected to, their ports,
+                           VLAN and management address (needs tcpdump and the rights to capture)
+    --lldp-time <DURATION> listen for at least this long, switches announce every 30s or 60s
+                           (default 60s)
+    --pcap <FILE>          record the probe and reply packets to a pcap file for Wireshark (needs
+                           tcpdump and the rights to capture, usually root)
+    --dry-run              print the addresses that would be probed, in order, with the probe methods
+                           and timing, without sending anything
+    --export-plan <FILE>   do a dry run and write the plan to a file for approval, signed with the key
+                           in the [plan] section of the config file, which both plan options need
+    --execute-plan <FILE>  run an exported plan exactly, its options, targets and addresses in order,
+                           refusing one that was changed since (only --config may be given with it)
+    --replay <FILE>        answer the probes from a fixture of ping output or a pcap (e.g. one taken
+                           with --pcap) instead of sending anything, for demos and checking output
+                           formats, sweeps the addresses in it when no target is given
+    --export-dot <FILE>    write a Graphviz DOT map of routers and discovered hosts
+    --netbox               create or update a NetBox IP address for every live host, with its name,
+                           MAC and vendor, using the [netbox] section of the config file
+    --progress-json        write JSON progress records to stderr while scanning
+    --progress-file <FILE> write the JSON progress records to a file or named pipe instead
+    --skip-self            don't probe this machine's own addresses, otherwise they are marked self
+    --allow-public         allow sweeping public ranges (single public hosts only get a warning)
+    --whois                look up who owns each public target range (one whois query per range, not
+                           per host) and put its netname, organisation and abuse contact in the
+                           report header
+    --by-hops              group the live hosts by their hop distance, estimated from the reply TTL,
+                           and sort the output formats the same way
+    --aggregate            summarise live and free addresses as minimal CIDR blocks
+    --dns-sweep            only do reverse DNS lookups across the range, no probes are sent
+    --resolver <IP[:PORT]> use this DNS server for all lookups, repeat to rotate across several
+    --doh <URL>            resolve names over DNS-over-HTTPS (e.g. https://1.1.1.1/dns-query)
+    --concurrency <N>      probe N addresses at once instead of sizing the pool from the
+                           open file limit and available memory and scaling up automatically
+    --ramp <DURATION>      slow start, begin with one worker and work up to the full count over
+                           DURATION (e.g. 30s), holding while timeouts climb, for fragile links
+    --priority             probe the addresses most likely to be in use first, those up in earlier
+                           scans, then .1 and .254, then the low and high ends of each subnet
+    --retries <N>          retry probes that fail on the scanning host up to N times (default 3)
+    --cache <AGE>          reuse results of hosts found up within AGE (e.g. 30s, 10m, 2h) instead
+                           of probing them again, stale and down hosts are probed as usual
+    --no-cache             look up every name and MAC afresh instead of using the name cache, which
+                           keeps PTR names for a day and MAC addresses for an hour
+    --watch <INTERVAL>     keep sweeping every INTERVAL (e.g. 5m), reporting hosts that are new, come
+                           back up or go down and sending them to the notifiers in the config file
+    --watch-scopes         watch every [scope NAME] of the config file that has a watch interval
+                           (watch = 5m) at once, each with its own interval and, when the scope sets
+                           them, ports, tcp_ping, udp_ports, profiles, watch_profile and the notify
+                           sections it sends to, instead of --watch and targets
+    --api <ADDRESS:PORT>   in watch mode, answer HTTP requests for the stored scans as JSON, GET /scans,
+                           /diff?from=ID&to=ID (what changed between two scans) and
+                           /history?host=IP[&since=DURATION] (e.g. 127.0.0.1:8080), with a bearer token
+                           when the config file has [api-token NAME] sections, loopback only without,
+                           and /healthz and /readyz for liveness and readiness checks. it is plain HTTP
+                           and listens on loopback only, put a TLS reverse proxy in front to reach it
+    --api-insecure         let --api listen on a non-loopback address without TLS, tokens and answers
+                           cross the network in the clear
+    --keep-scans <N|AGE>   in watch mode, keep the latest N stored scans of each watched range (default
+                           50), or those younger than AGE (e.g. 30d), the latest is always kept
+    --service              run watch mode as a systemd service (Type=notify), signalling readiness and
+                           status, reloading the config file on SIGHUP and stopping cleanly on SIGTERM
+    --notify-desktop       raise a desktop notification when the sweep finishes and, in watch mode, for
+                           every change (notify-send on Linux, osascript on macOS, a toast on Windows)
+    --bell                 ring the terminal bell for every live host, in watch mode only when a device
+                           never seen before turns up
+    --down-after <N>       in watch mode, only report a host down after N missed cycles in a row
+    --up-after <N>         in watch mode, only report a host up after N answered cycles in a row
+    --sparklines           in watch mode, list the up hosts every cycle with a sparkline of their
+                           last 30 round trip times and the latest one
+    --warn-rtt <MS>        flag up hosts slower than MS (e.g. 50ms) as warning, in watch mode a
+                           change of latency level is an event of its own
+    --crit-rtt <MS>        flag up hosts slower than MS (e.g. 200ms) as critical
+    --watch-profile <NAME> take down_after, up_after, warn_rtt and crit_rtt from the [watch NAME]
+                           section of the config file instead of [watch] (down_after and up_after
+                           default to 1, the flags above override)
+    --config <FILE>        read settings from FILE instead of ~/.config/host_disco/config
+    --delta                compare against the last stored scan of the same targets, hosts that were
+                           up are checked first and the report lists only what changed
+    -h, --help             print this help";
+
+//names of the subcommands, anything else in first position is a target
+pub const SUBCOMMANDS: [&str; 10] = ["net", "completions", "bench", "silence", "history", "update-oui", "convert", "wait", "merge", "ipv6"];
+
+//subcommands that do something other than a sweep
+pub enum Subcommand {
+    Net { cidr: String, split: Option<u8> },
+    Completions { shell: String },
+    Bench { target: String, count: usize, port: u16, workers: usize },
+    Silence { scope: String, duration: Duration, reason: String },
+    SilenceList,
+    SilenceClear { scope: String },
+    History { targets: Vec<String>, since: Option<Duration>, output_file: Option<String>, compress: Option<String> },
+    UpdateOui { source: String },
+    Convert { path: String, output_file: Option<String>, compress: Option<String> },
+    Wait { target: String, timeout: Duration, reboot: bool, ports: Vec<u16> },
+    Merge { paths: Vec<String>, format: String, output_file: Option<String>, compress: Option<String> },
+    Ipv6 { interface: String, global: bool, prefixes: Vec<u128>, macs_from: Option<String> },
+}
+
+//all the options the user can set on the command line
+pub struct Options {
+    pub subcommand: Option<Subcommand>,
+    pub targets: Vec<String>,
+    pub target_files: Vec<String>,
+    pub scopes: Vec<String>,
+    pub name_patterns: Vec<String>,
+    pub local: bool,
+    pub docker_networks: bool,
+    pub targets_from: Vec<String>,
+    pub vlans: Vec<(u16, Option<(Ipv4Addr, u8)>)>,
+    pub trunk: Option<String>,
+    pub snmp_arp: Vec<String>,
+    pub ports: Vec<u16>,
+    pub udp_ports: Vec<u16>,
+    pub tcp_ping: Vec<u16>,
+    pub profiles: Vec<String>,
+    pub collect_ssh_keys: bool,
+    pub http_proxy: Option<String>,
+    pub http_headers: Vec<String>,
+    pub user_agent: Option<String>,
+    pub asn_data: Option<String>,
+    pub enrich_after: bool,
+    pub spill: Option<String>,
+    pub shards: Option<u32>,
+    pub shard: Option<u32>,
+    pub vendor_filter: Vec<String>,
+    pub vendor_exclude: Vec<String>,
+    pub hostname_filter: Option<Pattern>,
+    pub output: Option<String>,
+    pub output_file: Option<String>,
+    pub compress: Option<String>,
+    pub encrypt_to: Vec<String>,
+    pub encrypt_passphrase: bool,
+    pub encryption: Option<Encryption>,
+    pub sign_with: Option<String>,
+    pub notify_desktop: bool,
+    pub bell: bool,
+    pub compare: bool,
+    pub paths: Vec<String>,
+    pub count: bool,
+    pub silent: bool,
+    pub min_up: Option<usize>,
+    pub monitoring_name: String,
+    pub monitoring_group: String,
+    pub pcap: Option<String>,
+    pub lldp: Option<String>,
+    pub lldp_time: Duration,
+    pub dry_run: bool,
+    pub export_plan: Option<String>,
+    pub execute_plan: Option<String>,
+    pub replay: Option<String>,
+    pub export_dot: Option<String>,
+    pub netbox: bool,
+    pub progress_json: bool,
+    pub progress_file: Option<String>,
+    pub priority: bool,
+    pub retries: u32,
+    pub concurrency: Option<usize>,
+    pub ramp: Option<Duration>,
+    pub cache: Option<Duration>,
+    pub no_cache: bool,
+    pub delta: bool,
+    pub watch: Option<Duration>,
+    pub watch_profile: Option<String>,
+    pub service: bool,
+    pub watch_scopes: bool,
+    pub api: Option<String>,
+    pub api_insecure: bool,
+    pub keep_scans: Option<store::Keep>,
+    pub sparklines: bool,
+    pub down_after: Option<u32>,
+    pub up_after: Option<u32>,
+    pub warn_rtt: Option<f64>,
+    pub crit_rtt: Option<f64>,
+    pub config: Option<String>,
+    pub skip_self: bool,
+    pub allow_public: bool,
+    pub whois: bool,
+    pub by_hops: bool,
+    pub aggregate: bool,
+    pub dns_sweep: bool,
+    pub resolvers: Vec<String>,
+    pub doh: Option<String>,
+    pub help: bool,
+}
+
+//function to turn the raw arguments (without the program name) into options
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    if let Some(name) = args.first() {
+        if let Some(subcommand) = parse_subcommand(name, &args[1..])? {
+            let mut options = parse_args(&[])?;
+            options.subcommand = Some(subcommand);
+            return Ok(options);
+        }
+    }
+
+    let mut options = Options {
+        subcommand: None,
+        targets: Vec::new(),
+        target_files: Vec::new(),
+        scopes: Vec::new(),
+        name_patterns: Vec::new(),
+        local: false,
+        docker_networks: false,
+        targets_from: Vec::new(),
+        vlans: Vec::new(),
+        trunk: None,
+        snmp_arp: Vec::new(),
+        ports: Vec::new(),
+        udp_ports: Vec::new(),
+        tcp_ping: Vec::new(),
+        profiles: Vec::new(),
+        collect_ssh_keys: false,
+        http_proxy: None,
+        http_headers: Vec::new(),
+        user_agent: None,
+        asn_data: None,
+        enrich_after: false,
+        spill: None,
+        shards: None,
+        shard: None,
+        vendor_filter: Vec::new(),
+        vendor_exclude: Vec::new(),
+        hostname_filter: None,
+        output: None,
+        output_file: None,
+        compress: None,
+        encrypt_to: Vec::new(),
+        encrypt_passphrase: false,
+        encryption: None,
+        sign_with: None,
+        notify_desktop: false,
+        bell: false,
+        compare: false,
+        paths: Vec::new(),
+        count: false,
+        silent: false,
+        min_up: None,
+        monitoring_name: "{name}".to_string(),
+        monitoring_group: "discovered".to_string(),
+        pcap: None,
+        lldp: None,
+        lldp_time: lldp::DEFAULT_TIME,
+        dry_run: false,
+        export_plan: None,
+        execute_plan: None,
+        replay: None,
+        export_dot: None,
+        netbox: false,
+        progress_json: false,
+        progress_file: None,
+        priority: false,
+        retries: 3,
+        concurrency: None,
+        ramp: None,
+        cache: None,
+        no_cache: false,
+        delta: false,
+        watch: None,
+        watch_profile: None,
+        service: false,
+        watch_scopes: false,
+        api: None,
+        api_insecure: false,
+        keep_scans: None,
+        sparklines: false,
+        down_after: None,
+        up_after: None,
+        warn_rtt: None,
+        crit_rtt: None,
+        config: None,
+        skip_self: false,
+        allow_public: false,
+        whois: false,
+        by_hops: false,
+        aggregate: false,
+        dns_sweep: false,
+        resolvers: Vec::new(),
+        doh: None,
+        help: false,
+    };
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+            "-h" | "--help" => options.help = true,
+            "--targets-file" => options.target_files.push(next_value(args, &mut i, arg)?),
+            "--scope" => options.scopes.push(next_value(args, &mut i, arg)?),
+            "--name-pattern" => {
+                let pattern = next_value(args, &mut i, arg)?;
+                target::expand_pattern(&pattern)?;
+                options.name_patterns.push(pattern);
+            }
+            "--ports" => options.ports.extend(ports::parse_port_list(&next_value(args, &mut i, arg)?)?),
+            "--top-ports" => options.ports.extend(ports::top_ports(parse_number(&next_value(args, &mut i, arg)?, arg)?)?),
+            "--tcp-ping" => options.tcp_ping.extend(ports::parse_port_list(&next_value(args, &mut i, arg)?)?),
+            "--udp-ports" => options.udp_ports.extend(ports::parse_port_list(&next_value(args, &mut i, arg)?)?),
+            "--profile" => {
+                let profile = next_value(args, &mut i, arg)?;
+                if !identify::PROFILES.contains(&profile.as_str()) {
+                    return Err(format!("Unknown profile {}, expected one of {}.", profile, identify::PROFILES.join(", ")));
+                }
+                options.ports.extend(identify::profile_tcp_ports(&profile));
+                options.udp_ports.extend(identify::profile_udp_ports(&profile));
+                options.profiles.push(profile);
+            }
+            "--collect-ssh-keys" => {
+                options.collect_ssh_keys = true;
+                options.ports.push(sshkeys::SSH_PORT);
+            }
+            "--http-proxy" => options.http_proxy = Some(next_value(args, &mut i, arg)?),
+            "--http-header" => {
+                let header = next_value(args, &mut i, arg)?;
+                if !header.contains(':') {
+                    return Err(format!("--http-header expects NAME: VALUE, got {}", header));
+                }
+                options.http_headers.push(header);
+            }
+            "--user-agent" => options.user_agent = Some(next_value(args, &mut i, arg)?),
+            "--asn-data" => options.asn_data = Some(next_value(args, &mut i, arg)?),
+            "--enrich-after" => options.enrich_after = true,
+            "--spill" => options.spill = Some(next_value(args, &mut i, arg)?),
+            "--shards" => options.shards = Some(parse_number(&next_value(args, &mut i, arg)?, arg)?),
+            "--shard" => options.shard = Some(parse_number(&next_value(args, &mut i, arg)?, arg)?),
+            "--targets-from" => options.targets_from.push(next_value(args, &mut i, arg)?),
+            "--local" => options.local = true,
+            "--docker-networks" => options.docker_networks = true,
+            "--vlan" => options.vlans.push(vlan::parse(&next_value(args, &mut i, arg)?)?),
+            "--trunk" => options.trunk = Some(next_value(args, &mut i, arg)?),
+            "--snmp-arp" => options.snmp_arp.push(next_value(args, &mut i, arg)?),
+            "--vendor-filter" => options.vendor_filter.extend(parse_names(&next_value(args, &mut i, arg)?, arg)?),
+            "--vendor-exclude" => options.vendor_exclude.extend(parse_names(&next_value(args, &mut i, arg)?, arg)?),
+            "--hostname-filter" => options.hostname_filter = Some(Pattern::new(&next_value(args, &mut i, arg)?)?),
+            "-o" | "--output" => {
+                let format = next_value(args, &mut i, arg)?;
+                if !output::FORMATS.contains(&format.as_str()) {
+                    return Err(format!("Unknown output format {}, expected one of {}.", format, output::FORMATS.join(", ")));
+                }
+                options.output = Some(format);
+            }
+            "--output-file" => options.output_file = Some(next_value(args, &mut i, arg)?),
+            "--compress" => options.compress = Some(parse_compress(&next_value(args, &mut i, arg)?)?),
+            "--encrypt-to" => options.encrypt_to.push(next_value(args, &mut i, arg)?),
+            "--encrypt-passphrase" => options.encrypt_passphrase = true,
+            "--sign-with" => options.sign_with = Some(next_value(args, &mut i, arg)?),
+            "--notify-desktop" => options.notify_desktop = true,
+            "--bell" => options.bell = true,
+            "--compare" => options.compare = true,
+            "--paths" => options.paths = next_value(args, &mut i, arg)?.split(',').map(|interface| interface.trim().to_string()).filter(|interface| !interface.is_empty()).collect(),
+            "--count" => options.count = true,
+            "--silent" => options.silent = true,
+            "--min-up" => options.min_up = Some(parse_number(&next_value(args, &mut i, arg)?, arg)?),
+            "--monitoring-name" => options.monitoring_name = next_value(args, &mut i, arg)?,
+            "--monitoring-group" => options.monitoring_group = next_value(args, &mut i, arg)?,
+            "--dry-run" => options.dry_run = true,
+            "--export-plan" => {
+                options.export_plan = Some(next_value(args, &mut i, arg)?);
+                options.dry_run = true;
+            }
+            "--execute-plan" => options.execute_plan = Some(next_value(args, &mut i, arg)?),
+            "--replay" => options.replay = Some(next_value(args, &mut i, arg)?),
+            "--pcap" => options.pcap = Some(next_value(args, &mut i, arg)?),
+            "--lldp" => options.lldp = Some(next_value(args, &mut i, arg)?),
+            "--lldp-time" => options.lldp_time = parse_duration(&next_value(args, &mut i, arg)?, arg)?,
+            "--export-dot" => options.export_dot = Some(next_value(args, &mut i, arg)?),
+            "--netbox" => options.netbox = true,
+            "--concurrency" => match parse_number(&next_value(args, &mut i, arg)?, arg)? {
+                0 => return Err("--concurrency must be at least 1".to_string()),
+                workers => options.concurrency = Some(workers),
+            },
+            "--ramp" => match parse_duration(&next_value(args, &mut i, arg)?, arg)? {
+                duration if duration.is_zero() => return Err("--ramp needs a duration above zero".to_string()),
+                duration => options.ramp = Some(duration),
+            },
+            "--priority" => options.priority = true,
+            "--retries" => options.retries = parse_number(&next_value(args, &mut i, arg)?, arg)?,
+            "--cache" => options.cache = Some(parse_duration(&next_value(args, &mut i, arg)?, arg)?),
+            "--no-cache" => options.no_cache = true,
+            "--delta" => options.delta = true,
+            "--watch" => match parse_duration(&next_value(args, &mut i, arg)?, arg)? {
+                interval if interval.is_zero() => return Err("--watch needs an interval above zero".to_string()),
+                interval => options.watch = Some(interval),
+            },
+            "--sparklines" => options.sparklines = true,
+            "--service" if cfg!(target_os = "linux") => options.service = true,
+            "--service" => return Err("--service runs under systemd, which is only on Linux".to_string()),
+            "--watch-scopes" => options.watch_scopes = true,
+            "--api" => {
+                let address = next_value(args, &mut i, arg)?;
+                if address.parse::<SocketAddr>().is_err() {
+                    return Err(format!("--api expects ADDRESS:PORT, e.g. 127.0.0.1:8080, got {}", address));
+                }
+                options.api = Some(address);
+            }
+            "--api-insecure" => options.api_insecure = true,
+            "--keep-scans" => options.keep_scans = Some(parse_keep(&next_value(args, &mut i, arg)?, arg)?),
+            "--watch-profile" => options.watch_profile = Some(next_value(args, &mut i, arg)?),
+            "--down-after" => options.down_after = Some(parse_cycles(&next_value(args, &mut i, arg)?, arg)?),
+            "--warn-rtt" => options.warn_rtt = Some(latency::parse_ms(&next_value(args, &mut i, arg)?, arg)?),
+            "--crit-rtt" => options.crit_rtt = Some(latency::parse_ms(&next_value(args, &mut i, arg)?, arg)?),
+            "--up-after" => options.up_after = Some(parse_cycles(&next_value(args, &mut i, arg)?, arg)?),
+            "--config" => options.config = Some(next_value(args, &mut i, arg)?),
+            "--skip-self" => options.skip_self = true,
+            "--allow-public" => options.allow_public = true,
+            "--whois" => options.whois = true,
+            "--by-hops" => options.by_hops = true,
+            "--aggregate" => options.aggregate = true,
+            "--dns-sweep" => options.dns_sweep = true,
+            "--resolver" => options.resolvers.push(next_value(args, &mut i, arg)?),
+            "--doh" => options.doh = Some(next_value(args, &mut i, arg)?),
+            "--progress-json" => options.progress_json = true,
+            "--progress-file" => {
+                options.progress_json = true;
+                options.progress_file = Some(next_value(args, &mut i, arg)?);
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("Unknown option {}", arg)),
+            _ => options.targets.push(arg.to_string()),
+        }
+        i += 1;
+    }
+
+    //every node of an imported cluster gets its kubelet probed
+    if options.targets_from.iter().any(|source| source.starts_with("k8s:")) {
+        options.ports.push(cloud::KUBELET_PORT);
+    }
+
+    //--ports and --top-ports can overlap
+    options.ports.sort_unstable();
+    options.ports.dedup();
+    options.udp_ports.sort_unstable();
+    options.udp_ports.dedup();
+
+    //--watch-scopes is watch mode too, with the targets and intervals of the scopes in the config file
+    let watching = options.watch.is_some() || options.watch_scopes;
+    if options.watch_scopes
+        && (options.watch.is_some()
+            || !options.targets.is_empty()
+            || !options.target_files.is_empty()
+            || !options.scopes.is_empty()
+            || !options.name_patterns.is_empty()
+            || !options.targets_from.is_empty()
+            || options.local
+            || options.docker_networks
+            || options.trunk.is_some())
+    {
+        return Err("--watch-scopes takes its targets and intervals from the config file, not from --watch or targets".to_string());
+    }
+
+    //the live count is the whole point of these, there is nothing to count in watch mode or a DNS sweep
+    if (options.count || options.silent || options.min_up.is_some()) && (watching || options.dns_sweep) {
+        return Err("--count, --silent and --min-up don't apply to --watch or --dns-sweep".to_string());
+    }
+
+    //watch mode runs its own sweeps and a DNS sweep sends no probes to capture
+    if options.pcap.is_some() && (watching || options.dns_sweep) {
+        return Err("--pcap doesn't apply to --watch or --dns-sweep".to_string());
+    }
+
+    if options.service && !watching {
+        return Err("--service needs --watch or --watch-scopes".to_string());
+    }
+    //a vendor filter would quietly drop every host the embedded sample of vendors doesn't know
+    if !options.vendor_filter.is_empty() {
+        oui::require_registry("--vendor-filter")?;
+    }
+    if !options.vendor_exclude.is_empty() {
+        oui::require_registry("--vendor-exclude")?;
+    }
+
+    if options.api.is_some() && !watching {
+        return Err("--api needs --watch or --watch-scopes".to_string());
+    }
+    if options.api_insecure && options.api.is_none() {
+        return Err("--api-insecure needs --api".to_string());
+    }
+    if options.keep_scans.is_some() && !watching {
+        return Err("--keep-scans needs --watch or --watch-scopes".to_string());
+    }
+
+    //watch mode and a DNS sweep have no probe plan, and a plan has nothing to count
+    if options.dry_run && (watching || options.dns_sweep || options.count || options.silent) {
+        return Err("--dry-run doesn't apply to --watch, --dns-sweep, --count or --silent".to_string());
+    }
+
+    //an approved plan is run as it was approved, and one that reuses cached results isn't the plan that runs
+    if options.execute_plan.is_some() && args.len() != if options.config.is_some() { 4 } else { 2 } {
+        return Err("--execute-plan can only be combined with --config".to_string());
+    }
+    if options.export_plan.is_some() && options.cache.is_some() {
+        return Err("--export-plan doesn't apply to --cache, cached hosts would not be in the plan".to_string());
+    }
+
+    if !options.vlans.is_empty() && options.trunk.is_none() {
+        return Err("--vlan needs the --trunk interface the VLANs are on".to_string());
+    }
+    if !options.vlans.is_empty() && (options.dns_sweep || options.replay.is_some()) {
+        return Err("--vlan doesn't apply to --dns-sweep or --replay".to_string());
+    }
+    if !options.snmp_arp.is_empty() && (watching || options.dns_sweep) {
+        return Err("--snmp-arp doesn't apply to --watch or --dns-sweep".to_string());
+    }
+    if options.lldp.is_some() && (watching || options.dns_sweep || options.replay.is_some()) {
+        return Err("--lldp doesn't apply to --watch, --dns-sweep or --replay".to_string());
+    }
+    //a replay sends nothing, there is no traffic to capture and no change to watch for,
+    //and reusing cached results would mix real ones in
+    if options.replay.is_some() && (options.pcap.is_some() || options.cache.is_some() || watching || options.dns_sweep || !options.snmp_arp.is_empty()) {
+        return Err("--replay doesn't apply to --pcap, --cache, --watch, --dns-sweep or --snmp-arp".to_string());
+    }
+    if options.compress.is_some() && options.output_file.is_none() && options.pcap.is_none() && options.export_dot.is_none() {
+        return Err("--compress needs a file to compress, give --output-file, --pcap or --export-dot".to_string());
+    }
+    if (options.encrypt_passphrase || !options.encrypt_to.is_empty()) && options.output_file.is_none() && options.pcap.is_none() && options.export_dot.is_none() {
+        return Err("--encrypt-to and --encrypt-passphrase need a file to encrypt, give --output-file, --pcap or --export-dot".to_string());
+    }
+    if options.sign_with.is_some() && options.output_file.is_none() && options.pcap.is_none() && options.export_dot.is_none() {
+        return Err("--sign-with needs a file to sign, give --output-file, --pcap or --export-dot".to_string());
+    }
+    if options.encrypt_passphrase && !options.encrypt_to.is_empty() {
+        return Err("--encrypt-passphrase and --encrypt-to can't both be given".to_string());
+    }
+    if !options.paths.is_empty() && options.paths.len() < 2 {
+        return Err("--paths needs two or more interfaces to compare, e.g. eth0,wlan0".to_string());
+    }
+    if !options.paths.is_empty() && (watching || options.dns_sweep || options.compare || options.count || options.silent || options.output.is_some() || options.dry_run) {
+        return Err("--paths doesn't apply to --watch, --dns-sweep, --compare, --count, --silent, --output or --dry-run".to_string());
+    }
+    //watch mode reports changes as each sweep ends, there is no after to enrich in
+    if options.enrich_after && (watching || options.dns_sweep) {
+        return Err("--enrich-after doesn't apply to --watch or --dns-sweep".to_string());
+    }
+    //every shard has to cut the same targets the same way, watch mode and a comparison need all of them
+    match (options.shard, options.shards) {
+        (None, None) => {}
+        (Some(shard), Some(shards)) if (1..=shards).contains(&shard) => {}
+        (Some(_), Some(shards)) => return Err(format!("--shard has to be from 1 to {}", shards)),
+        _ => return Err("--shard and --shards go together".to_string()),
+    }
+    if options.shards.is_some() && (watching || options.dns_sweep || options.compare || !options.paths.is_empty()) {
+        return Err("--shards doesn't apply to --watch, --dns-sweep, --compare or --paths".to_string());
+    }
+    //the infrastructure marks down hosts as seen after the sweep, they have to stay in memory for it
+    if options.spill.is_some() && (watching || options.dns_sweep || !options.snmp_arp.is_empty()) {
+        return Err("--spill doesn't apply to --watch, --dns-sweep or --snmp-arp".to_string());
+    }
+    //the owners go in the report of a single sweep, a replay sends nothing at all
+    if options.whois && (watching || options.dns_sweep || options.replay.is_some()) {
+        return Err("--whois doesn't apply to --watch, --dns-sweep or --replay".to_string());
+    }
+    if options.output.as_deref() == Some("cbor") && options.output_file.is_none() {
+        return Err("the cbor format is binary, give --output-file to write it to".to_string());
+    }
+    if options.output.as_deref() == Some("matrix") && options.ports.is_empty() && options.udp_ports.is_empty() {
+        return Err("the matrix format needs ports to show, give --ports, --top-ports or --udp-ports".to_string());
+    }
+
+    //a file on its own gets the JSON format
+    if options.output_file.is_some() && options.output.is_none() {
+        options.output = Some("json".to_string());
+    }
+
+    //the passphrase is asked for now rather than after a long scan
+    if options.encrypt_passphrase {
+        options.encryption = Some(encrypt::passphrase()?);
+    }
+    else if !options.encrypt_to.is_empty() {
+        options.encryption = Some(encrypt::recipients(&options.encrypt_to)?);
+    }
+
+    Ok(options)
+}
+
+//function to parse the arguments of a subcommand, None when the name isn't a subcommand
+fn parse_subcommand(name: &str, args: &[String]) -> Result<Option<Subcommand>, String> {
+    if !SUBCOMMANDS.contains(&name) {
+        return Ok(None);
+    }
+
+    let mut positional = Vec::new();
+    let mut split = None;
+    let mut count = 200;
+    let mut port = 22;
+    let mut workers = 16;
+    let mut since = None;
+    let mut output_file = None;
+    let mut compress = None;
+    let mut timeout = wait::DEFAULT_TIMEOUT;
+    let mut reboot = false;
+    let mut wait_ports = None;
+    let mut format = "json".to_string();
+    let mut global = false;
+    let mut prefixes = Vec::new();
+    let mut macs_from = None;
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+            "--split" if name == "net" => split = Some(parse_number(next_value(args, &mut i, arg)?.trim_start_matches('/'), arg)?),
+            "--count" if name == "bench" => count = parse_number(&next_value(args, &mut i, arg)?, arg)?,
+            "--port" if name == "bench" => port = parse_number(&next_value(args, &mut i, arg)?, arg)?,
+            "--since" if name == "history" => since = Some(parse_duration(&next_value(args, &mut i, arg)?, arg)?),
+            "--output-file" if name == "history" || name == "convert" || name == "merge" => output_file = Some(next_value(args, &mut i, arg)?),
+            "--compress" if name == "history" || name == "convert" || name == "merge" => compress = Some(parse_compress(&next_value(args, &mut i, arg)?)?),
+            "-o" | "--output" if name == "merge" => {
+                format = next_value(args, &mut i, arg)?;
+                if !output::FORMATS.contains(&format.as_str()) {
+                    return Err(format!("Unknown output format {}, expected one of {}.", format, output::FORMATS.join(", ")));
+                }
+            }
+            "--timeout" if name == "wait" => timeout = parse_duration(&next_value(args, &mut i, arg)?, arg)?,
+            "--reboot" if name == "wait" => reboot = true,
+            "--global" if name == "ipv6" => global = true,
+            "--prefix" if name == "ipv6" => prefixes.push(ipv6::parse_prefix(&next_value(args, &mut i, arg)?)?),
+            "--macs-from" if name == "ipv6" => macs_from = Some(next_value(args, &mut i, arg)?),
+            "--ports" if name == "wait" => wait_ports = Some(ports::parse_port_list(&next_value(args, &mut i, arg)?)?),
+            "--concurrency" if name == "bench" => workers = parse_number::<usize>(&next_value(args, &mut i, arg)?, arg)?.max(1),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {} for {}", arg, name)),
+            _ => positional.push(arg.to_string()),
+        }
+        i += 1;
+    }
+
+    if compress.is_some() && output_file.is_none() {
+        return Err(format!("--compress needs --output-file for {}", name));
+    }
+
+    match name {
+        "net" => match positional.as_slice() {
+            [cidr] => Ok(Some(Subcommand::Net { cidr: cidr.clone(), split })),
+            _ => Err("net expects exactly one IP/CIDR".to_string()),
+        },
+        "completions" => match positional.as_slice() {
+            [shell] => Ok(Some(Subcommand::Completions { shell: shell.clone() })),
+            _ => Err("completions expects a shell name".to_string()),
+        },
+        "bench" => match positional.as_slice() {
+            [] => Ok(Some(Subcommand::Bench { target: "127.0.0.1".to_string(), count, port, workers })),
+            [target] => Ok(Some(Subcommand::Bench { target: target.clone(), count, port, workers })),
+            _ => Err("bench expects at most one IP".to_string()),
+        },
+        "silence" => match positional.as_slice() {
+            [list] if list == "list" => Ok(Some(Subcommand::SilenceList)),
+            [clear, scope] if clear == "clear" => Ok(Some(Subcommand::SilenceClear { scope: scope.clone() })),
+            [scope, duration, reason @ ..] => {
+                silence::validate_scope(scope)?;
+                Ok(Some(Subcommand::Silence {
+                    scope: scope.clone(),
+                    duration: parse_duration(duration, "silence")?,
+                    reason: reason.join(" "),
+                }))
+            }
+            _ => Err("silence expects an IP or range and a duration, list or clear".to_string()),
+        },
+        "history" if positional.is_empty() => Err("history expects the targets whose runs to export".to_string()),
+        "history" => Ok(Some(Subcommand::History { targets: positional, since, output_file, compress })),
+        "update-oui" => match positional.as_slice() {
+            [source] => Ok(Some(Subcommand::UpdateOui { source: source.clone() })),
+            _ => Err("update-oui expects an IEEE oui.csv file or URL".to_string()),
+        },
+        "convert" => match positional.as_slice() {
+            [path] => Ok(Some(Subcommand::Convert { path: path.clone(), output_file, compress })),
+            _ => Err("convert expects a results file written with -o cbor".to_string()),
+        },
+        "wait" => match positional.as_slice() {
+            [_] if wait_ports.is_some() && !reboot => Err("--ports only applies to wait --reboot".to_string()),
+            [target] => {
+                let ports = wait_ports.unwrap_or_else(|| wait::DEFAULT_REBOOT_PORTS.to_vec());
+                Ok(Some(Subcommand::Wait { target: target.clone(), timeout, reboot, ports }))
+            }
+            _ => Err("wait expects one IP or hostname".to_string()),
+        },
+        "merge" if positional.is_empty() => Err("merge expects the json results of the scans".to_string()),
+        "merge" => Ok(Some(Subcommand::Merge { paths: positional, format, output_file, compress })),
+        "ipv6" => match positional.as_slice() {
+            [_] if macs_from.is_some() && !global && prefixes.is_empty() => Err("--macs-from needs --global or --prefix to derive addresses into".to_string()),
+            [interface] => Ok(Some(Subcommand::Ipv6 { interface: interface.clone(), global, prefixes, macs_from })),
+            _ => Err("ipv6 expects the interface whose link to search".to_string()),
+        },
+        _ => Ok(None),
+    }
+}
+
+//function to take the value that follows a flag, moving the index past it
+fn next_value(args: &[String], i: &mut usize, flag: &str) -> Result<String, String> {
+    *i += 1;
+    args.get(*i).cloned().ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+//function to check a --compress method
+fn parse_compress(value: &str) -> Result<String, String> {
+    if !compress::METHODS.contains(&value) {
+        return Err(format!("Unknown compression {}, expected one of {}.", value, compress::METHODS.join(", ")));
+    }
+    Ok(value.to_string())
+}
+
+//function to parse a flag value as a number, naming the flag if it isn't one
+fn parse_number<T: std::str::FromStr>(value: &str, flag: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid number {} for {}", value, flag))
+}
+
+//function to split a comma separated list of names into lowercase items, matching is case insensitive
+fn parse_names(value: &str, flag: &str) -> Result<Vec<String>, String> {
+    let names: Vec<String> = value.split(',').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()).collect();
+    if names.is_empty() {
+        return Err(format!("{} needs at least one name", flag));
+    }
+    Ok(names)
+}
+
+//function to parse a count of watch cycles, at least one
+pub fn parse_cycles(value: &str, flag: &str) -> Result<u32, String> {
+    match parse_number(value, flag)? {
+        0 => Err(format!("{} needs at least 1 cycle", flag)),
+        cycles => Ok(cycles),
+    }
+}
+
+//function to parse what to keep of the stored scans, a number of runs or an age with its unit
+fn parse_keep(value: &str, flag: &str) -> Result<store::Keep, String> {
+    if !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return parse_duration(value, flag).map(store::Keep::Age);
+    }
+    match parse_number(value, flag)? {
+        0 => Err(format!("{} needs to keep at least 1 run", flag)),
+        runs => Ok(store::Keep::Runs(runs)),
+    }
+}
+
+//function to parse a duration like 90s, 10m, 2h or 1d, a bare number is seconds
+pub fn parse_duration(value: &str, flag: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let invalid = || format!("Invalid duration {} for {}, expected e.g. 30s, 10m or 2h", value, flag);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let number: u64 = parse_number(number, flag)?;
+    number.checked_mul(seconds).map(Duration::from_secs).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn accepted(line: &str) -> Options {
+        parse_args(&args(line)).unwrap_or_else(|error| panic!("{} was refused, {}", line, error))
+    }
+
+    fn refused(line: &str) -> String {
+        match parse_args(&args(line)) {
+            Ok(_) => panic!("{} was accepted", line),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn count_and_silent_need_a_single_sweep() {
+        accepted("--count --min-up 3 10.0.0.0/24");
+        assert_eq!(refused("--count --watch 10s 10.0.0.0/24"), "--count, --silent and --min-up don't apply to --watch or --dns-sweep");
+        assert_eq!(refused("--min-up 3 --dns-sweep 10.0.0.0/24"), "--count, --silent and --min-up don't apply to --watch or --dns-sweep");
+    }
+
+    #[test]
+    fn pcap_needs_a_sweep_that_sends_probes() {
+        accepted("--pcap out.pcap 10.0.0.0/24");
+        assert_eq!(refused("--pcap out.pcap --watch 1m 10.0.0.0/24"), "--pcap doesn't apply to --watch or --dns-sweep");
+    }
+
+    #[test]
+    fn replay_sends_nothing_to_capture_or_cache() {
+        accepted("--replay old.json");
+        for line in ["--replay old.json --pcap out.pcap", "--replay old.json --cache 1h", "--replay old.json --snmp-arp 10.0.0.1"] {
+            assert_eq!(refused(line), "--replay doesn't apply to --pcap, --cache, --watch, --dns-sweep or --snmp-arp");
+        }
+    }
+
+    #[test]
+    fn dry_run_needs_a_probe_plan() {
+        accepted("--dry-run 10.0.0.0/24");
+        assert_eq!(refused("--dry-run --count 10.0.0.0/24"), "--dry-run doesn't apply to --watch, --dns-sweep, --count or --silent");
+    }
+
+    #[test]
+    fn plans_run_as_they_were_approved() {
+        accepted("--execute-plan plan.txt");
+        accepted("--config host_disco.conf --execute-plan plan.txt");
+        assert_eq!(refused("--execute-plan plan.txt 10.0.0.0/24"), "--execute-plan can only be combined with --config");
+        assert!(accepted("--export-plan plan.txt 10.0.0.0/24").dry_run);
+        assert_eq!(refused("--export-plan plan.txt --cache 1h 10.0.0.0/24"), "--export-plan doesn't apply to --cache, cached hosts would not be in the plan");
+    }
+
+    #[test]
+    fn api_insecure_needs_the_api() {
+        assert!(accepted("--watch 1m --api 0.0.0.0:8080 --api-insecure 10.0.0.0/24").api_insecure);
+        assert_eq!(refused("--watch 1m --api-insecure 10.0.0.0/24"), "--api-insecure needs --api");
+    }
+
+    #[test]
+    fn service_needs_watch_mode_on_linux() {
+        if cfg!(target_os = "linux") {
+            assert!(accepted("--watch 1m --service 10.0.0.0/24").service);
+            assert_eq!(refused("--service 10.0.0.0/24"), "--service needs --watch or --watch-scopes");
+        }
+        else {
+            assert_eq!(refused("--watch 1m --service 10.0.0.0/24"), "--service runs under systemd, which is only on Linux");
+        }
+    }
+
+    #[test]
+    fn snmp_arp_needs_a_single_sweep() {
+        accepted("--snmp-arp 10.0.0.1 10.0.0.0/24");
+        assert_eq!(refused("--snmp-arp 10.0.0.1 --watch 1m 10.0.0.0/24"), "--snmp-arp doesn't apply to --watch or --dns-sweep");
+    }
+
+    #[test]
+    fn lldp_needs_a_live_sweep() {
+        accepted("--lldp eth0 10.0.0.0/24");
+        assert_eq!(refused("--lldp eth0 --replay old.json"), "--lldp doesn't apply to --watch, --dns-sweep or --replay");
+    }
+
+    #[test]
+    fn vlans_need_their_trunk() {
+        assert_eq!(accepted("--trunk eth0 --vlan 30:10.30.0.250/24 10.30.0.0/24").vlans.len(), 1);
+        assert_eq!(refused("--vlan 30 10.30.0.0/24"), "--vlan needs the --trunk interface the VLANs are on");
+        assert_eq!(refused("--trunk eth0 --vlan 30 --dns-sweep 10.30.0.0/24"), "--vlan doesn't apply to --dns-sweep or --replay");
+    }
+
+    #[test]
+    fn cbor_needs_a_file() {
+        accepted("-o cbor --output-file hosts.cbor 10.0.0.0/24");
+        assert_eq!(refused("-o cbor 10.0.0.0/24"), "the cbor format is binary, give --output-file to write it to");
+    }
+
+    #[test]
+    fn compress_needs_a_file() {
+        accepted("--compress gzip --output-file hosts.json.gz 10.0.0.0/24");
+        assert_eq!(refused("--compress gzip 10.0.0.0/24"), "--compress needs a file to compress, give --output-file, --pcap or --export-dot");
+    }
+
+    #[test]
+    fn encryption_needs_a_file_and_one_kind_of_key() {
+        accepted("--encrypt-to age1key --output-file hosts.json.age 10.0.0.0/24");
+        assert_eq!(refused("--encrypt-passphrase 10.0.0.0/24"), "--encrypt-to and --encrypt-passphrase need a file to encrypt, give --output-file, --pcap or --export-dot");
+        assert_eq!(refused("--encrypt-passphrase --encrypt-to age1key --pcap out.pcap 10.0.0.0/24"), "--encrypt-passphrase and --encrypt-to can't both be given");
+    }
+
+    #[test]
+    fn sign_with_needs_a_file() {
+        accepted("--sign-with key.pem --export-dot map.dot 10.0.0.0/24");
+        assert_eq!(refused("--sign-with key.pem 10.0.0.0/24"), "--sign-with needs a file to sign, give --output-file, --pcap or --export-dot");
+    }
+
+    #[test]
+    fn paths_need_two_interfaces_and_a_single_sweep() {
+        assert_eq!(accepted("--paths eth0,wlan0 10.0.0.0/24").paths, ["eth0", "wlan0"]);
+        assert_eq!(refused("--paths eth0, 10.0.0.0/24"), "--paths needs two or more interfaces to compare, e.g. eth0,wlan0");
+        assert_eq!(refused("--paths eth0,wlan0 --compare 10.0.0.0/24"), "--paths doesn't apply to --watch, --dns-sweep, --compare, --count, --silent, --output or --dry-run");
+    }
+
+    #[test]
+    fn whois_needs_a_live_sweep() {
+        accepted("--whois 10.0.0.0/24");
+        assert_eq!(refused("--whois --replay old.json"), "--whois doesn't apply to --watch, --dns-sweep or --replay");
+    }
+
+    #[test]
+    fn enrich_after_needs_a_single_sweep() {
+        accepted("--enrich-after 10.0.0.0/24");
+        assert_eq!(refused("--enrich-after --watch 1m 10.0.0.0/24"), "--enrich-after doesn't apply to --watch or --dns-sweep");
+    }
+
+    #[test]
+    fn spill_needs_a_single_sweep() {
+        accepted("--spill down.txt 10.0.0.0/8");
+        assert_eq!(refused("--spill down.txt --watch 1m 10.0.0.0/8"), "--spill doesn't apply to --watch, --dns-sweep or --snmp-arp");
+    }
+
+    #[test]
+    fn shard_and_shards_go_together() {
+        let options = accepted("--shards 4 --shard 4 10.0.0.0/8");
+        assert_eq!((options.shard, options.shards), (Some(4), Some(4)));
+        assert_eq!(refused("--shard 1 10.0.0.0/8"), "--shard and --shards go together");
+        assert_eq!(refused("--shards 2 10.0.0.0/8"), "--shard and --shards go together");
+        assert_eq!(refused("--shards 2 --shard 3 10.0.0.0/8"), "--shard has to be from 1 to 2");
+        assert_eq!(refused("--shards 2 --shard 0 10.0.0.0/8"), "--shard has to be from 1 to 2");
+        assert_eq!(refused("--shards 2 --shard 1 --compare 10.0.0.0/8"), "--shards doesn't apply to --watch, --dns-sweep, --compare or --paths");
+    }
+
+    #[test]
+    fn watch_scopes_take_their_targets_from_the_config() {
+        accepted("--watch-scopes");
+        for line in ["--watch-scopes 10.0.0.0/24", "--watch-scopes --watch 1m", "--watch-scopes --local"] {
+            assert_eq!(refused(line), "--watch-scopes takes its targets and intervals from the config file, not from --watch or targets");
+        }
+    }
+
+    #[test]
+    fn api_and_keep_scans_need_watch_mode() {
+        accepted("--watch-scopes --api 127.0.0.1:8080 --keep-scans 10");
+        assert_eq!(refused("--api 127.0.0.1:8080 10.0.0.0/24"), "--api needs --watch or --watch-scopes");
+        assert_eq!(refused("--keep-scans 10 10.0.0.0/24"), "--keep-scans needs --watch or --watch-scopes");
+        assert!(refused("--watch 1m --api localhost 10.0.0.0/24").starts_with("--api expects ADDRESS:PORT"));
+    }
+
+    #[test]
+    fn keep_scans_takes_a_count_or_an_age() {
+        assert!(matches!(parse_keep("50", "--keep-scans"), Ok(store::Keep::Runs(50))));
+        assert!(matches!(parse_keep("7d", "--keep-scans"), Ok(store::Keep::Age(age)) if age == Duration::from_secs(7 * 24 * 60 * 60)));
+        assert!(parse_keep("0", "--keep-scans").is_err());
+        assert!(parse_keep("7w", "--keep-scans").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//importing targets from cloud inventories (--targets-from), the private addresses of a VPC or network
+//as listed by the provider's CLI with whatever credentials it already has, and checking the inventory
+//against what answered the sweep
+//
+//  aws:vpc-0abc123                 every network interface in the VPC (aws ec2 describe-network-interfaces)
+//  gcp:my-project[/network]        every instance in the project, optionally one network (gcloud)
+//  azure:resource-group[/vnet]     every NIC in the resource group, optionally one virtual network (az)
+//  k8s:[context]                   the internal addresses of the cluster's nodes, from the kubeconfig's
+//                                  current or the named context (kubectl), with the kubelet port probed
+//
+//known_hosts and hosts files are imported the same way (known-hosts:[FILE] and hosts:[FILE]), see
+//hostfiles.rs, and so are the leases of a DHCP server (leases:[FILE]), see leases.rs
+
+use std::net::Ipv4Addr;
+use std::process::Command;
+
+use crate::host::{HostResult, PortState};
+use crate::hostfiles;
+use crate::json::{self, Value};
+use crate::leases;
+
+//the addresses one import found, each with the name the provider knows it by
+pub struct Inventory {
+    pub source: String,
+    pub entries: Vec<(Ipv4Addr, String)>,
+    //what else the source said about its networks, e.g. the pod CIDR of each node
+    pub notes: Vec<String>,
+}
+
+//the kubelet's API, probed on every node of an imported cluster
+pub const KUBELET_PORT: u16 = 10250;
+
+//function to run an import, e.g. aws:vpc-0abc123
+pub fn import(source: &str) -> Result<Inventory, String> {
+    let (provider, scope) = source
+        .split_once(':')
+        .filter(|(provider, scope)| !scope.is_empty() || ["k8s", "known-hosts", "hosts", "leases"].contains(provider))
+        .ok_or_else(|| {
+            format!(
+                "{} should be aws:VPC, gcp:PROJECT[/NETWORK], azure:GROUP[/VNET], k8s:[CONTEXT], known-hosts:[FILE], hosts:[FILE] or leases:[FILE]",
+                source
+            )
+        })?;
+    let mut notes = Vec::new();
+    let mut entries = match provider {
+        "aws" => aws(scope)?,
+        "gcp" => gcp(scope)?,
+        "azure" => azure(scope)?,
+        "k8s" => kubernetes(scope, &mut notes)?,
+        "known-hosts" => hostfiles::known_hosts(scope, &mut notes)?,
+        "hosts" => hostfiles::hosts(scope)?,
+        "leases" => leases::import(scope, &mut notes)?,
+        _ => return Err(format!("unknown source {}, expected aws, gcp, azure, k8s, known-hosts, hosts or leases", provider)),
+    };
+    entries.sort();
+    entries.dedup_by(|a, b| a.0 == b.0);
+    if entries.is_empty() {
+        return Err(format!("{} has no IPv4 addresses to sweep", source));
+    }
+    Ok(Inventory { source: source.to_string(), entries, notes })
+}
+
+//function to run a provider CLI that prints JSON
+fn run_json(program: &str, args: &[&str]) -> Result<Value, String> {
+    let output = Command::new(program).args(args).output().map_err(|error| format!("cannot run {}, {}", program, error))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed, {}", program, stderr.lines().last().unwrap_or("").trim()));
+    }
+    json::parse(&String::from_utf8_lossy(&output.stdout)).map_err(|error| format!("unexpected output from {}, {}", program, error))
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str).filter(|text| !text.is_empty())
+}
+
+fn items<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).unwrap_or_default()
+}
+
+//{"NetworkInterfaces": [{"NetworkInterfaceId": "eni-1", "Description": "...", "Attachment": {"InstanceId": "i-1"},
+//  "PrivateIpAddresses": [{"PrivateIpAddress": "10.0.1.5"}]}]}
+fn aws(vpc: &str) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let filter = format!("Name=vpc-id,Values={}", vpc);
+    let listing = run_json("aws", &["ec2", "describe-network-interfaces", "--filters", &filter, "--output", "json"])?;
+    let mut entries = Vec::new();
+    for interface in items(&listing, "NetworkInterfaces") {
+        let name = interface
+            .get("Attachment")
+            .and_then(|attachment| text(attachment, "InstanceId"))
+            .or_else(|| text(interface, "Description"))
+            .or_else(|| text(interface, "NetworkInterfaceId"))
+            .unwrap_or("-");
+        for address in items(interface, "PrivateIpAddresses") {
+            if let Some(address) = text(address, "PrivateIpAddress").and_then(|address| address.parse().ok()) {
+                entries.push((address, name.to_string()));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+//[{"name": "web-1", "networkInterfaces": [{"network": ".../global/networks/default", "networkIP": "10.128.0.2"}]}]
+fn gcp(scope: &str) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let (project, network) = scope.split_once('/').map_or((scope, None), |(project, network)| (project, Some(network)));
+    let listing = run_json("gcloud", &["compute", "instances", "list", "--project", project, "--format", "json"])?;
+    let mut entries = Vec::new();
+    for instance in listing.as_array().unwrap_or_default() {
+        let name = text(instance, "name").unwrap_or("-");
+        for interface in items(instance, "networkInterfaces") {
+            let on_network = network.is_none_or(|network| text(interface, "network").is_some_and(|url| url.rsplit('/').next() == Some(network)));
+            if let Some(address) = text(interface, "networkIP").and_then(|address| address.parse().ok()).filter(|_| on_network) {
+                entries.push((address, name.to_string()));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+//[{"name": "web-1-nic", "virtualMachine": {"id": ".../virtualMachines/web-1"},
+//  "ipConfigurations": [{"privateIPAddress": "10.1.0.4", "subnet": {"id": ".../virtualNetworks/prod/subnets/app"}}]}]
+fn azure(scope: &str) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let (group, vnet) = scope.split_once('/').map_or((scope, None), |(group, vnet)| (group, Some(vnet)));
+    let listing = run_json("az", &["network", "nic", "list", "--resource-group", group, "--output", "json"])?;
+    let mut entries = Vec::new();
+    for nic in listing.as_array().unwrap_or_default() {
+        let name = nic
+            .get("virtualMachine")
+            .and_then(|machine| text(machine, "id"))
+            .and_then(|id| id.rsplit('/').next())
+            .or_else(|| text(nic, "name"))
+            .unwrap_or("-");
+        for configuration in items(nic, "ipConfigurations") {
+            let subnet = configuration.get("subnet").and_then(|subnet| text(subnet, "id")).unwrap_or("");
+            let on_vnet = vnet.is_none_or(|vnet| subnet.contains(&format!("/virtualNetworks/{}/", vnet)));
+            if let Some(address) = text(configuration, "privateIPAddress").and_then(|address| address.parse().ok()).filter(|_| on_vnet) {
+                entries.push((address, name.to_string()));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+//{"items": [{"metadata": {"name": "node-1"}, "spec": {"podCIDRs": ["10.244.1.0/24"]},
+//  "status": {"addresses": [{"type": "InternalIP", "address": "192.168.10.11"}]}}]}
+fn kubernetes(context: &str, notes: &mut Vec<String>) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let mut args = vec!["get", "nodes", "--output", "json"];
+    if !context.is_empty() {
+        args.extend(["--context", context]);
+    }
+    let listing = run_json("kubectl", &args)?;
+    let mut entries = Vec::new();
+    for node in items(&listing, "items") {
+        let name = node.get("metadata").and_then(|metadata| text(metadata, "name")).unwrap_or("-");
+        let addresses = node.get("status").map(|status| items(status, "addresses")).unwrap_or_default();
+        for address in addresses.iter().filter(|address| text(address, "type") == Some("InternalIP")) {
+            if let Some(address) = text(address, "address").and_then(|address| address.parse().ok()) {
+                entries.push((address, name.to_string()));
+            }
+        }
+        //podCIDR alone on clusters older than dual-stack
+        let spec = node.get("spec");
+        let mut pod_cidrs: Vec<&str> = spec.map(|spec| items(spec, "podCIDRs")).unwrap_or_default().iter().filter_map(Value::as_str).collect();
+        if pod_cidrs.is_empty() {
+            pod_cidrs.extend(spec.and_then(|spec| text(spec, "podCIDR")));
+        }
+        if !pod_cidrs.is_empty() {
+            notes.push(format!("{} runs pods on {}", name, pod_cidrs.join(", ")));
+        }
+    }
+    Ok(entries)
+}
+
+//function to print how the inventory compares with the sweep, what answered and what didn't,
+//cluster nodes get a line each with the probes they answered
+pub fn print_reconciliation(inventory: &Inventory, results: &[HostResult]) {
+    if inventory.source.starts_with("k8s:") {
+        let context = inventory.source.trim_start_matches("k8s:");
+        println!("Nodes of {}:", if context.is_empty() { "the current Kubernetes context" } else { context });
+        for (address, name) in &inventory.entries {
+            let Some(host) = results.iter().find(|host| host.address == *address) else {
+                continue;
+            };
+            let mut answers = vec![if host.is_up() { "icmp answered".to_string() } else { format!("icmp {}", host.status()) }];
+            answers.extend(host.ports.iter().map(|port| {
+                let state = if port.state == PortState::Open { "open" } else { "closed" };
+                format!("{}/{} {}", port.port, port.protocol, state)
+            }));
+            println!("    {:<24} {:<15}  {}", name, address, answers.join(", "));
+        }
+        for note in &inventory.notes {
+            println!("    {}", note);
+        }
+        return;
+    }
+
+    let answered = |address: &Ipv4Addr| results.iter().any(|host| host.address == *address && host.is_up());
+    let silent: Vec<String> = inventory
+        .entries
+        .iter()
+        .filter(|(address, _)| !answered(address))
+        .map(|(address, name)| format!("{} ({})", address, name))
+        .collect();
+    println!(
+        "{}: {} of {} inventoried addresses answered.",
+        inventory.source,
+        inventory.entries.len() - silent.len(),
+        inventory.entries.len()
+    );
+    //a lease whose client doesn't answer is likely stale, the client gone before it ended
+    if !silent.is_empty() && inventory.source.starts_with("leases:") {
+        println!("    \x1b[0;33mStale leases, no answer from\x1b[0m {}", silent.join(", "));
+    }
+    else if !silent.is_empty() {
+        println!("    \x1b[0;33mNo answer from\x1b[0m {}", silent.join(", "));
+    }
+    for note in &inventory.notes {
+        println!("    {}", note);
+    }
+}
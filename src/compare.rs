@@ -0,0 +1,86 @@
+//comparing the live hosts of two ranges side by side, e.g. a primary site against its DR copy
+//
+//hosts are paired by MAC address first, then by the first label of their name, so db01.prod.example
+//and db01.dr.example are the same host at two sites
+
+use crate::host::HostResult;
+use crate::target::Target;
+
+//function to print which live hosts of one range have a counterpart in the other and which don't
+pub fn print_comparison(first: &Target, second: &Target, results: &[HostResult]) {
+    let live_in = |range: &Target| -> Vec<&HostResult> {
+        results
+            .iter()
+            .filter(|host| host.is_up() && range.contains(host.address))
+            .collect()
+    };
+    let (left, right) = (live_in(first), live_in(second));
+
+    let mut paired = vec![false; right.len()];
+    let mut pairs = Vec::new();
+    let mut only_left = Vec::new();
+    for host in &left {
+        let counterpart = (0..right.len())
+            .find(|index| !paired[*index] && same_mac(host, right[*index]))
+            .or_else(|| (0..right.len()).find(|index| !paired[*index] && same_name(host, right[*index])));
+        match counterpart {
+            Some(index) => {
+                paired[index] = true;
+                pairs.push((*host, right[index]));
+            }
+            None => only_left.push(*host),
+        }
+    }
+    let only_right: Vec<&HostResult> = right.iter().zip(&paired).filter(|(_, paired)| !**paired).map(|(host, _)| *host).collect();
+
+    println!("Comparing {} ({} up) with {} ({} up):", first.label, left.len(), second.label, right.len());
+    println!();
+    println!("In both:");
+    if pairs.is_empty() {
+        println!("    none");
+    }
+    for (left, right) in &pairs {
+        println!("    {:<28} {:<15} = {:<15} {}", name(left), left.address, right.address, name(right));
+    }
+    for (heading, hosts) in [(&first.label, &only_left), (&second.label, &only_right)] {
+        println!();
+        println!("\x1b[0;33mOnly in {}:\x1b[0m", heading);
+        if hosts.is_empty() {
+            println!("    none");
+        }
+        for host in hosts.iter() {
+            let unidentified = if host.hostname.is_none() && host.mac.is_none() { "  (no name or MAC to match on)" } else { "" };
+            println!("    {:<28} {}{}", name(host), host.address, unidentified);
+        }
+    }
+    println!();
+    println!(
+        "{} hosts in both, {} only in {}, {} only in {}.",
+        pairs.len(),
+        only_left.len(),
+        first.label,
+        only_right.len(),
+        second.label
+    );
+}
+
+fn same_mac(a: &HostResult, b: &HostResult) -> bool {
+    a.mac.is_some() && a.mac == b.mac
+}
+
+fn same_name(a: &HostResult, b: &HostResult) -> bool {
+    match (short_name(a), short_name(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+//function to get the first label of a host's name in lowercase, e.g. "db01" for DB01.prod.example
+fn short_name(host: &HostResult) -> Option<String> {
+    let hostname = host.hostname.as_deref()?;
+    Some(hostname.split('.').next().unwrap_or(hostname).to_lowercase())
+}
+
+fn name(host: &HostResult) -> String {
+    host.hostname.clone().or_else(|| host.mac.clone()).unwrap_or_else(|| "-".to_string())
+}
@@ -0,0 +1,140 @@
+//shell completion scripts, generated from the usage text so they never drift from the real flags
+
+use crate::cli;
+
+//one flag from the usage text
+struct Flag {
+    names: Vec<String>,
+    takes_value: bool,
+    description: String,
+}
+
+//function to print the completion script for a shell
+pub fn print_completions(shell: &str) -> Result<(), String> {
+    let flags = usage_flags();
+    let script = match shell {
+        "bash" => bash(&flags),
+        "zsh" => zsh(&flags),
+        "fish" => fish(&flags),
+        "powershell" => powershell(&flags),
+        _ => return Err(format!("Unsupported shell {}, expected bash, zsh, fish or powershell.", shell)),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+//function to pull the flags out of the Options section of the usage text
+fn usage_flags() -> Vec<Flag> {
+    let options = cli::USAGE.split("Options:").nth(1).unwrap_or("");
+    let mut flags = Vec::new();
+
+    let lines: Vec<&str> = options.lines().map(str::trim).collect();
+    for (index, line) in lines.iter().enumerate() {
+        if !line.starts_with('-') {
+            continue;
+        }
+
+        //the spec is every word up to the description, e.g. "-h, --help" or "--retries <N>"
+        let mut names = Vec::new();
+        let mut takes_value = false;
+        let mut words = line.split_whitespace().peekable();
+        while let Some(word) = words.peek() {
+            if word.starts_with('-') {
+                names.push(word.trim_end_matches(',').to_string());
+            }
+            else if word.starts_with('<') {
+                takes_value = true;
+            }
+            else {
+                break;
+            }
+            words.next();
+        }
+
+        //long specs put the description on the following line
+        let mut description = words.collect::<Vec<_>>().join(" ");
+        if description.is_empty() {
+            description = lines.get(index + 1).filter(|next| !next.starts_with('-')).unwrap_or(&"").to_string();
+        }
+        flags.push(Flag { names, takes_value, description });
+    }
+
+    flags
+}
+
+fn all_names(flags: &[Flag]) -> String {
+    flags.iter().flat_map(|flag| flag.names.iter().cloned()).collect::<Vec<_>>().join(" ")
+}
+
+fn bash(flags: &[Flag]) -> String {
+    format!(
+        r#"_host_disco() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{subcommands} {flags}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -W "{flags}" -- "$cur"))
+    fi
+}}
+complete -o default -F _host_disco host_disco
+"#,
+        subcommands = cli::SUBCOMMANDS.join(" "),
+        flags = all_names(flags)
+    )
+}
+
+fn zsh(flags: &[Flag]) -> String {
+    let mut script = String::from("#compdef host_disco\n\n_arguments \\\n");
+    for flag in flags {
+        let description = flag.description.replace('\'', "'\\''").replace('[', "(").replace(']', ")");
+        let value = if flag.takes_value { ":value:_files" } else { "" };
+        for name in &flag.names {
+            script.push_str(&format!("    '{}[{}]{}' \\\n", name, description, value));
+        }
+    }
+    script.push_str(&format!("    '1:command or target:({})' \\\n", cli::SUBCOMMANDS.join(" ")));
+    script.push_str("    '*:target:_files'\n");
+    script
+}
+
+fn fish(flags: &[Flag]) -> String {
+    let mut script = String::new();
+    for subcommand in cli::SUBCOMMANDS {
+        script.push_str(&format!("complete -c host_disco -n '__fish_use_subcommand' -a {}\n", subcommand));
+    }
+    for flag in flags {
+        let mut line = String::from("complete -c host_disco");
+        for name in &flag.names {
+            match name.strip_prefix("--") {
+                Some(long) => line.push_str(&format!(" -l {}", long)),
+                None => line.push_str(&format!(" -s {}", name.trim_start_matches('-'))),
+            }
+        }
+        if flag.takes_value {
+            line.push_str(" -r");
+        }
+        line.push_str(&format!(" -d '{}'\n", flag.description.replace('\'', "\\'")));
+        script.push_str(&line);
+    }
+    script
+}
+
+fn powershell(flags: &[Flag]) -> String {
+    let words: Vec<String> = cli::SUBCOMMANDS
+        .iter()
+        .map(|subcommand| subcommand.to_string())
+        .chain(flags.iter().flat_map(|flag| flag.names.iter().cloned()))
+        .map(|word| format!("'{}'", word))
+        .collect();
+
+    format!(
+        r#"Register-ArgumentCompleter -Native -CommandName host_disco -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    @({words}) | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        words = words.join(", ")
+    )
+}
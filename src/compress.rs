@@ -0,0 +1,98 @@
+//compressing the files a scan leaves behind (--compress), results, captures and maps, with the gzip or
+//zstd command so multi-million host scans don't take gigabytes, and reading compressed files back in
+//wherever a file is read, told apart from plain ones by their magic bytes
+//
+//a file that is also encrypted is compressed first, encrypted data doesn't compress
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::encrypt::{self, Encryption};
+
+pub const METHODS: [&str; 2] = ["gzip", "zstd"];
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+//function to write the contents to a file, compressed and encrypted when asked to
+pub fn write(path: &str, contents: &[u8], method: Option<&str>, encryption: Option<&Encryption>) -> Result<(), String> {
+    let compressed = match method {
+        Some(method) => pipe(&mut command(method, false)?, method, contents)?,
+        None => contents.to_vec(),
+    };
+    let sealed = match encryption {
+        Some(encryption) => encrypt::seal(&compressed, encryption)?,
+        None => compressed,
+    };
+    fs::write(path, sealed).map_err(|error| format!("cannot write {}, {}", path, error))
+}
+
+//function to compress and encrypt a file something else wrote, e.g. tcpdump's capture, leaving it under the same name
+pub fn file(path: &str, method: Option<&str>, encryption: Option<&Encryption>) -> Result<(), String> {
+    let contents = fs::read(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let replacement = format!("{}.sealing", path);
+    if let Err(error) = write(&replacement, &contents, method, encryption) {
+        let _ = fs::remove_file(&replacement);
+        return Err(error);
+    }
+    fs::rename(&replacement, path).map_err(|error| format!("cannot replace {}, {}", path, error))
+}
+
+//function to read a file, decompressing it first when it is gzip or zstd
+pub fn read(path: &str) -> Result<Vec<u8>, String> {
+    let contents = fs::read(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let method = if contents.starts_with(&GZIP_MAGIC) {
+        "gzip"
+    }
+    else if contents.starts_with(&ZSTD_MAGIC) {
+        "zstd"
+    }
+    else {
+        return Ok(contents);
+    };
+    let original = File::open(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let output = command(method, true)?.stdin(original).stdout(Stdio::piped()).stderr(Stdio::piped()).output();
+    finish(output, method).map_err(|error| format!("cannot decompress {}, {}", path, error))
+}
+
+//function to run a filter command over the input, returning what it printed
+//the input is fed from a thread of its own so a full output pipe can't stall it
+pub fn pipe(command: &mut Command, name: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("cannot run {}, {}", name, error))?;
+    let stdin = child.stdin.take();
+    thread::scope(|scope| {
+        //a filter that exits early closes its stdin, its exit status says why
+        scope.spawn(move || stdin.map(|mut stdin| stdin.write_all(input)));
+        finish(child.wait_with_output(), name)
+    })
+}
+
+fn command(method: &str, decompress: bool) -> Result<Command, String> {
+    let mut command = match method {
+        "gzip" => Command::new("gzip"),
+        //-T0 compresses on every core
+        "zstd" => {
+            let mut command = Command::new("zstd");
+            command.args(["-q", "-T0"]);
+            command
+        }
+        _ => return Err(format!("Unknown compression {}, expected one of {}.", method, METHODS.join(", "))),
+    };
+    command.arg(if decompress { "-dc" } else { "-c" });
+    Ok(command)
+}
+
+fn finish(output: std::io::Result<std::process::Output>, name: &str) -> Result<Vec<u8>, String> {
+    let output = output.map_err(|error| format!("cannot run {}, {}", name, error))?;
+    if !output.status.success() {
+        return Err(format!("{} failed, {}", name, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,404 @@
+//working out how many probes can run at once without exhausting the scanning host
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::net::Ipv4Addr;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::host::HostResult;
+use crate::probe::{DownReason, Outcome};
+
+//never run more workers than this, even on a very roomy machine
+const HARD_CEILING: usize = 256;
+
+//workers an automatic scan starts with before scaling up
+const START_WORKERS: usize = 8;
+
+//file descriptors one worker can hold at once (ping's pipes plus a port probe socket)
+const FDS_PER_WORKER: u64 = 8;
+
+//memory budget per worker, mostly the forked ping process
+const BYTES_PER_WORKER: u64 = 4 * 1024 * 1024;
+
+//descriptors kept back for the rest of the program (stdio, output files, resolver sockets)
+const RESERVED_FDS: u64 = 32;
+
+//probes a ramp looks at before comparing the timeout share with the one it started with
+const RAMP_WINDOW: usize = 20;
+
+//how far the timeout share may climb above the starting one before the ramp holds
+const RAMP_TIMEOUT_RISE: f64 = 0.25;
+
+//run of answers, then of timeouts, then of answers again that looks like a remote ICMP rate limiter
+//emptying and refilling its bucket
+const RATE_LIMIT_BURST: usize = 8;
+
+//admin-prohibited replies from one router, for as many different targets, among the later half of the recent
+//probes that are taken as a rate limiter answering for the hosts, when the answers fell off as they came in.
+//a router in front of a firewalled segment sends them for every filtered host from the start, so they
+//alone say nothing
+const RATE_LIMIT_PROHIBITED: usize = 3;
+
+//pause before every probe once throttled, doubled each time rate limiting shows up again
+const THROTTLE_PACE: Duration = Duration::from_millis(100);
+const MAX_THROTTLE_PACE: Duration = Duration::from_secs(1);
+
+//the system limits the ceiling was derived from, so they can be shown to the user
+pub struct Limits {
+    pub open_files: Option<u64>,
+    pub available_memory: Option<u64>,
+    pub ceiling: usize,
+}
+
+//function to read the soft open file limit and available memory and derive a safe worker ceiling
+pub fn detect_limits() -> Limits {
+    let open_files = fs::read_to_string("/proc/self/limits").ok().and_then(|limits| {
+        limits
+            .lines()
+            .find(|line| line.starts_with("Max open files"))
+            .and_then(|line| line.split_whitespace().nth(3))
+            .and_then(|soft| soft.parse::<u64>().ok())
+    });
+    let available_memory = fs::read_to_string("/proc/meminfo").ok().and_then(|meminfo| {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with("MemAvailable:"))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|kb| kb.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    });
+
+    let mut ceiling = HARD_CEILING as u64;
+    if let Some(open_files) = open_files {
+        ceiling = ceiling.min(open_files.saturating_sub(RESERVED_FDS) / FDS_PER_WORKER);
+    }
+    if let Some(available_memory) = available_memory {
+        ceiling = ceiling.min(available_memory / BYTES_PER_WORKER);
+    }
+
+    Limits {
+        open_files,
+        available_memory,
+        ceiling: ceiling.max(1) as usize,
+    }
+}
+
+//what a finished probe tells the controller
+pub enum Signal {
+    Answered,
+    Timeout(Ipv4Addr),
+    //the target and the router that said it is administratively prohibited
+    Prohibited { target: Ipv4Addr, from: String },
+    LocalError,
+}
+
+impl Signal {
+    //function to classify a finished probe, local errors (even ones a retry got past) mean the scanning host is struggling
+    pub fn of(host: &HostResult, retries: u32) -> Signal {
+        match &host.outcome {
+            _ if retries > 0 => Signal::LocalError,
+            Outcome::LocalError(_) => Signal::LocalError,
+            Outcome::Down(DownReason::Timeout) => Signal::Timeout(host.address),
+            Outcome::Down(DownReason::Prohibited { from }) => Signal::Prohibited { target: host.address, from: from.clone() },
+            _ => Signal::Answered,
+        }
+    }
+}
+
+//a change worth telling the user about
+pub enum Notice {
+    BackedOff(usize),
+    RampHeld(usize),
+    RampResumed(usize),
+    Throttled { workers: usize, pace: Duration },
+}
+
+//decides how many workers may probe at the moment, growing while probes succeed and halving on local errors
+pub struct Controller {
+    active: AtomicUsize,
+    ceiling: usize,
+    automatic: bool,
+    //clean completions since the worker count last changed
+    clean_streak: Mutex<usize>,
+    //set while the user has paused the scan, no worker picks up a new address
+    paused: Arc<AtomicBool>,
+    //the slow start from --ramp, None once it reached the full worker count
+    ramp: Mutex<Option<Ramp>>,
+    //the latest outcomes, oldest first, for spotting remote rate limiting
+    recent: Mutex<VecDeque<Signal>>,
+    //milliseconds each worker waits before a probe, zero until rate limiting was seen
+    pace_ms: AtomicU64,
+    //addresses that timed out in a rate limited burst, worth probing again once the scan is through
+    suspects: Mutex<Vec<Ipv4Addr>>,
+}
+
+//a slow start, the worker count grows from one to the full count over the ramp duration
+//the clock stops while timeouts run well above where they were at the start, fragile links get a breather
+struct Ramp {
+    duration: Duration,
+    //ramp time used so far, only counted while not holding
+    progress: Duration,
+    last_tick: Instant,
+    window_probes: usize,
+    window_timeouts: usize,
+    //the timeout share of the first window, some addresses of most ranges never answer
+    baseline: Option<f64>,
+    held: bool,
+}
+
+impl Controller {
+    //function to create a controller, a fixed count from --concurrency never changes once any ramp is over
+    //with a ramp the count starts at one and works up to the fixed count, or the ceiling when automatic
+    pub fn new(fixed: Option<usize>, ramp: Option<Duration>, limits: &Limits) -> Controller {
+        let (start, ceiling, automatic) = match fixed {
+            Some(workers) => (workers.max(1), workers.max(1), false),
+            None => (START_WORKERS.min(limits.ceiling), limits.ceiling, true),
+        };
+        let ramp = ramp.map(|duration| Ramp {
+            duration,
+            progress: Duration::ZERO,
+            last_tick: Instant::now(),
+            window_probes: 0,
+            window_timeouts: 0,
+            baseline: None,
+            held: false,
+        });
+        Controller {
+            active: AtomicUsize::new(if ramp.is_some() { 1 } else { start }),
+            ceiling,
+            automatic,
+            clean_streak: Mutex::new(0),
+            paused: Arc::new(AtomicBool::new(false)),
+            ramp: Mutex::new(ramp),
+            recent: Mutex::new(VecDeque::new()),
+            pace_ms: AtomicU64::new(0),
+            suspects: Mutex::new(Vec::new()),
+        }
+    }
+
+    //the largest number of workers that might ever be allowed to run
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    //how many workers may probe right now
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    //the switch that pauses the scan, shared with whatever reads the pause key
+    pub fn pause_switch(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.paused)
+    }
+
+    //how long a worker waits before each probe
+    pub fn pace(&self) -> Duration {
+        Duration::from_millis(self.pace_ms.load(Ordering::Relaxed))
+    }
+
+    //function to hand over the addresses that timed out while rate limited, emptying the list
+    pub fn take_suspects(&self) -> Vec<Ipv4Addr> {
+        std::mem::take(&mut *self.suspects.lock().unwrap())
+    }
+
+    //function to feed back each finished probe, returns what changed when the user should hear about it
+    pub fn record(&self, signal: Signal) -> Option<Notice> {
+        if let Some(notice) = self.watch_rate_limit(&signal) {
+            return Some(notice);
+        }
+        let local_error = matches!(signal, Signal::LocalError);
+
+        let mut ramp = self.ramp.lock().unwrap();
+        if let Some(state) = ramp.as_mut() {
+            let notice = self.ramp_step(state, local_error, matches!(signal, Signal::Timeout(_)));
+            if state.progress >= state.duration && !state.held {
+                *ramp = None;
+            }
+            return notice;
+        }
+        drop(ramp);
+        if !self.automatic {
+            return None;
+        }
+
+        let mut clean_streak = self.clean_streak.lock().unwrap();
+        let active = self.active();
+        if local_error {
+            *clean_streak = 0;
+            let reduced = (active / 2).max(1);
+            self.active.store(reduced, Ordering::Relaxed);
+            return (reduced != active).then_some(Notice::BackedOff(reduced));
+        }
+
+        //double once every active worker has finished a couple of probes without trouble, never again once throttled
+        *clean_streak += 1;
+        if *clean_streak >= active * 2 && active < self.ceiling && self.pace().is_zero() {
+            *clean_streak = 0;
+            self.active.store((active * 2).min(self.ceiling), Ordering::Relaxed);
+        }
+        None
+    }
+
+    //function to spot remote ICMP rate limiting, halving the workers and pacing the probes when it shows up
+    fn watch_rate_limit(&self, signal: &Signal) -> Option<Notice> {
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(match signal {
+            Signal::Answered => Signal::Answered,
+            Signal::Timeout(address) => Signal::Timeout(*address),
+            Signal::Prohibited { target, from } => Signal::Prohibited { target: *target, from: from.clone() },
+            //the scanning host's own trouble says nothing about the remote end
+            Signal::LocalError => return None,
+        });
+        if recent.len() > RATE_LIMIT_BURST * 3 {
+            recent.pop_front();
+        }
+        if recent.len() < RATE_LIMIT_BURST * 3 {
+            return None;
+        }
+
+        let answered = |signal: &Signal| matches!(signal, Signal::Answered);
+        let timed_out = |signal: &Signal| matches!(signal, Signal::Timeout(_));
+        let refilled = recent.range(..RATE_LIMIT_BURST).all(answered)
+            && recent.range(RATE_LIMIT_BURST..RATE_LIMIT_BURST * 2).all(timed_out)
+            && recent.range(RATE_LIMIT_BURST * 2..).all(answered);
+        if !refilled && !prohibited_by_limiter(&recent) {
+            return None;
+        }
+
+        //the timeouts of the burst may well be hosts that are up, they get another go at the end
+        self.suspects.lock().unwrap().extend(recent.drain(..).filter_map(|signal| match signal {
+            Signal::Timeout(address) => Some(address),
+            _ => None,
+        }));
+        let workers = (self.active() / 2).max(1);
+        self.active.store(workers, Ordering::Relaxed);
+        let pace = (self.pace() * 2).clamp(THROTTLE_PACE, MAX_THROTTLE_PACE);
+        self.pace_ms.store(pace.as_millis() as u64, Ordering::Relaxed);
+        Some(Notice::Throttled { workers, pace })
+    }
+
+    //function to move a ramp along, local errors halve the count and the ramp carries on from there
+    fn ramp_step(&self, ramp: &mut Ramp, local_error: bool, timed_out: bool) -> Option<Notice> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(ramp.last_tick);
+        ramp.last_tick = now;
+        let active = self.active();
+        if local_error {
+            let reduced = (active / 2).max(1);
+            ramp.progress = ramp.duration.mul_f64((reduced - 1) as f64 / (self.ceiling - 1).max(1) as f64);
+            self.active.store(reduced, Ordering::Relaxed);
+            return (reduced != active).then_some(Notice::BackedOff(reduced));
+        }
+
+        ramp.window_probes += 1;
+        ramp.window_timeouts += usize::from(timed_out);
+        let mut notice = None;
+        if ramp.window_probes >= RAMP_WINDOW {
+            let share = ramp.window_timeouts as f64 / ramp.window_probes as f64;
+            let baseline = *ramp.baseline.get_or_insert(share);
+            let held = share > baseline + RAMP_TIMEOUT_RISE;
+            if held != ramp.held {
+                ramp.held = held;
+                notice = Some(if held { Notice::RampHeld(active) } else { Notice::RampResumed(active) });
+            }
+            ramp.window_probes = 0;
+            ramp.window_timeouts = 0;
+        }
+        //a throttled scan stays where rate limiting left it
+        if ramp.held || !self.pace().is_zero() {
+            return notice;
+        }
+
+        ramp.progress = (ramp.progress + elapsed).min(ramp.duration);
+        let fraction = ramp.progress.as_secs_f64() / ramp.duration.as_secs_f64();
+        let allowed = 1 + ((self.ceiling - 1) as f64 * fraction) as usize;
+        if allowed > active {
+            self.active.store(allowed, Ordering::Relaxed);
+        }
+        notice
+    }
+}
+
+//function to tell a rate limiter answering for the hosts from a firewall that prohibits them, one router
+//prohibits different targets in the later half of the recent probes while the answers of the earlier
+//half fell to no more than half
+fn prohibited_by_limiter(recent: &VecDeque<Signal>) -> bool {
+    let half = recent.len() / 2;
+    let answers = |range: Range<usize>| recent.range(range).filter(|signal| matches!(signal, Signal::Answered)).count();
+    let (earlier, later) = (answers(0..half), answers(half..recent.len()));
+    if earlier < RATE_LIMIT_PROHIBITED || later * 2 > earlier {
+        return false;
+    }
+    let mut targets: HashMap<&str, HashSet<Ipv4Addr>> = HashMap::new();
+    for signal in recent.range(half..) {
+        if let Signal::Prohibited { target, from } = signal {
+            targets.entry(from.as_str()).or_default().insert(*target);
+        }
+    }
+    targets.values().any(|targets| targets.len() >= RATE_LIMIT_PROHIBITED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eight_workers() -> Controller {
+        Controller::new(Some(8), None, &Limits { open_files: None, available_memory: None, ceiling: 8 })
+    }
+
+    fn prohibited(last: u8) -> Signal {
+        Signal::Prohibited { target: Ipv4Addr::new(10, 0, 0, last), from: "10.0.0.254".to_string() }
+    }
+
+    #[test]
+    fn a_firewalled_subnet_is_not_taken_for_a_rate_limiter() {
+        //the router in front prohibits every host of the segment from the first probe on
+        let controller = eight_workers();
+        for last in 0..200u8 {
+            assert!(controller.record(prohibited(last)).is_none());
+        }
+        assert_eq!(controller.pace(), Duration::ZERO);
+        assert_eq!(controller.active(), 8);
+
+        //or every other host, the ones it lets through answering all along
+        let controller = eight_workers();
+        for last in 0..200u8 {
+            let signal = if last % 2 == 0 { prohibited(last) } else { Signal::Answered };
+            assert!(controller.record(signal).is_none());
+        }
+        assert_eq!(controller.pace(), Duration::ZERO);
+        assert_eq!(controller.active(), 8);
+    }
+
+    #[test]
+    fn prohibited_replies_as_the_answers_fall_off_throttle() {
+        let controller = eight_workers();
+        for _ in 0..12 {
+            assert!(controller.record(Signal::Answered).is_none());
+        }
+        let mut notice = None;
+        for last in 0..12u8 {
+            notice = notice.or(controller.record(prohibited(last)));
+        }
+        assert!(matches!(notice, Some(Notice::Throttled { workers: 4, .. })));
+        assert_eq!(controller.pace(), THROTTLE_PACE);
+    }
+
+    #[test]
+    fn one_target_prohibited_again_and_again_is_not_a_limiter() {
+        let controller = eight_workers();
+        for _ in 0..12 {
+            controller.record(Signal::Answered);
+        }
+        for _ in 0..12 {
+            assert!(controller.record(prohibited(1)).is_none());
+        }
+    }
+}
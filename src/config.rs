@@ -0,0 +1,98 @@
+//the config file, INI style sections of key = value lines, # starts a comment
+//
+//  [notify ops-mail]
+//  type = email
+//  server = smtps://mail.example.com:465
+//  to = ops@example.com, admin@example.com
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub struct Section {
+    pub name: String,
+    pub values: Vec<(String, String)>,
+}
+
+pub struct Config {
+    pub sections: Vec<Section>,
+}
+
+impl Section {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    //function to get a key that has to be set, naming the section if it isn't
+    pub fn require(&self, key: &str) -> Result<&str, String> {
+        self.get(key).ok_or_else(|| format!("[{}] is missing {}", self.name, key))
+    }
+
+    //function to split a comma separated value into its trimmed items
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .map(|value| value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Config {
+    //function to list the sections whose name starts with a kind, e.g. every [notify NAME]
+    pub fn sections_of<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = (&'a str, &'a Section)> {
+        self.sections.iter().filter_map(move |section| {
+            let (section_kind, name) = section.name.split_once(' ').unwrap_or((section.name.as_str(), ""));
+            (section_kind == kind).then_some((name.trim(), section))
+        })
+    }
+}
+
+//function to find the default config file, $XDG_CONFIG_HOME/host_disco/config or ~/.config/host_disco/config
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("host_disco/config"));
+    }
+    env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".config/host_disco/config"))
+}
+
+//function to load the given config file, or the default one which may be missing
+pub fn load(path: Option<&str>) -> Result<Config, String> {
+    let (path, required) = match path {
+        Some(path) => (PathBuf::from(path), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config { sections: Vec::new() }),
+        },
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if !required && error.kind() == std::io::ErrorKind::NotFound => return Ok(Config { sections: Vec::new() }),
+        Err(error) => return Err(format!("cannot read {}, {}", path.display(), error)),
+    };
+    parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+pub fn parse(contents: &str) -> Result<Config, String> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            sections.push(Section {
+                name: name.split_whitespace().collect::<Vec<_>>().join(" "),
+                values: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}, expected key = value", number + 1));
+        };
+        let Some(section) = sections.last_mut() else {
+            return Err(format!("line {}, {} is outside of a [section]", number + 1, key.trim()));
+        };
+        section.values.push((key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(Config { sections })
+}
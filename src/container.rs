@@ -0,0 +1,119 @@
+//noticing when the scan runs inside a container, where a bridge or NAT network hides the host's
+//networks, and listing the Docker bridge networks so they can be swept from the host (--docker-networks)
+
+use std::env;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use crate::curl;
+use crate::json;
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+//what the container looks like from inside
+pub struct Container {
+    //docker, podman, kubernetes, lxc or container when it can't be told
+    pub runtime: &'static str,
+    //the only interface with routes, its network and the default gateway, when the container has its own network
+    pub bridge: Option<(String, Ipv4Addr, u8, Option<Ipv4Addr>)>,
+}
+
+impl Container {
+    //function to explain what the container's networking means for the results, nothing to say when it
+    //shares the host's network
+    pub fn warning(&self) -> Option<String> {
+        let (interface, network, prefix, gateway) = self.bridge.as_ref()?;
+        Some(format!(
+            "Running in a {} container on its own network ({} on {}/{}{}), ARP and ICMP results reflect the container \
+             network and hosts are seen through NAT. Run it with host networking (e.g. docker run --network host \
+             --cap-add NET_RAW) to sweep the host's networks.",
+            self.runtime,
+            interface,
+            network,
+            prefix,
+            gateway.map(|gateway| format!(" via {}", gateway)).unwrap_or_default()
+        ))
+    }
+}
+
+//function to work out whether this is a container, None on a host or VM
+pub fn detect() -> Option<Container> {
+    let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+    let runtime = if env::var_os("KUBERNETES_SERVICE_HOST").is_some() || cgroup.contains("kubepods") {
+        "kubernetes"
+    }
+    else if Path::new("/.dockerenv").exists() || cgroup.contains("docker") {
+        "docker"
+    }
+    else if Path::new("/run/.containerenv").exists() || cgroup.contains("libpod") {
+        "podman"
+    }
+    else if cgroup.contains("lxc") || env::var("container").is_ok_and(|container| container == "lxc") {
+        "lxc"
+    }
+    else if env::var_os("container").is_some() {
+        "container"
+    }
+    else {
+        return None;
+    };
+    Some(Container { runtime, bridge: bridge() })
+}
+
+//function to find the container's own network, one interface carrying every route is a bridge or NAT
+//network, with host networking the host's interfaces and docker0 show up too
+fn bridge() -> Option<(String, Ipv4Addr, u8, Option<Ipv4Addr>)> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    //Iface Destination Gateway Flags RefCnt Use Metric Mask ..., addresses in the kernel's byte order
+    let entries: Vec<(String, Ipv4Addr, Ipv4Addr, u32)> = routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let address = |field: &str| u32::from_str_radix(field, 16).ok().map(|value| Ipv4Addr::from(value.to_ne_bytes()));
+            let mask = u32::from(address(fields.get(7)?)?);
+            Some((fields.first()?.to_string(), address(fields.get(1)?)?, address(fields.get(2)?)?, mask))
+        })
+        .collect();
+
+    let interface = entries.first()?.0.clone();
+    if entries.iter().any(|(name, ..)| *name != interface) {
+        return None;
+    }
+    let gateway = entries.iter().find(|(_, destination, ..)| destination.is_unspecified()).map(|(_, _, gateway, _)| *gateway);
+    let (_, network, _, mask) = entries.iter().find(|(_, destination, ..)| !destination.is_unspecified())?;
+    Some((interface, *network, mask.count_ones() as u8, gateway))
+}
+
+//function to list the IPv4 subnets of the Docker bridge networks, asking the Docker API on its socket
+pub fn docker_networks() -> Result<Vec<(String, String)>, String> {
+    let body = curl::run(&format!(
+        "url = \"http://docker/networks\"\nunix-socket = {}\nfail\n",
+        curl::quote(DOCKER_SOCKET)
+    ))
+    .map_err(|error| format!("cannot list the Docker networks on {}, {}", DOCKER_SOCKET, error))?;
+    let networks = json::parse(&String::from_utf8_lossy(&body)).map_err(|error| format!("unexpected answer from Docker, {}", error))?;
+
+    //[{"Name": "bridge", "Driver": "bridge", "IPAM": {"Config": [{"Subnet": "172.17.0.0/16"}]}}, ...]
+    let mut subnets = Vec::new();
+    for network in networks.as_array().unwrap_or_default() {
+        if network.get("Driver").and_then(json::Value::as_str) != Some("bridge") {
+            continue;
+        }
+        let name = network.get("Name").and_then(json::Value::as_str).unwrap_or("?");
+        let configs = network.get("IPAM").and_then(|ipam| ipam.get("Config")).and_then(json::Value::as_array).unwrap_or_default();
+        for subnet in configs.iter().filter_map(|config| config.get("Subnet")?.as_str()) {
+            //IPv6 subnets can't be swept
+            if let Some((address, prefix)) = subnet.split_once('/') {
+                if address.parse::<Ipv4Addr>().is_ok() && prefix.parse::<u8>().is_ok_and(|prefix| prefix <= 32) {
+                    subnets.push((name.to_string(), subnet.to_string()));
+                }
+            }
+        }
+    }
+    if subnets.is_empty() {
+        return Err("Docker has no IPv4 bridge networks".to_string());
+    }
+    Ok(subnets)
+}
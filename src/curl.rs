@@ -0,0 +1,47 @@
+//running curl for the HTTP and SMTP requests, options go over stdin so secrets never show up in ps
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::scratch::Scratch;
+
+//function to run curl with a config file on stdin, returning what it printed or its error message
+pub fn run(curl_config: &str) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--max-time", "30", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("cannot run curl, {}", error))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(curl_config.as_bytes()).map_err(|error| format!("cannot talk to curl, {}", error))?;
+    }
+    let output = child.wait_with_output().map_err(|error| format!("curl failed, {}", error))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    }
+    else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+//function to run curl with the payload in a private scratch file, as stdin is taken by the options
+//payload_option is the curl option that reads it, upload-file or data-binary
+pub fn run_with_payload(curl_config: &str, payload_option: &str, payload: &[u8]) -> Result<Vec<u8>, String> {
+    //the message or body can carry secrets, the directory goes with everything in it once curl is done
+    let scratch = Scratch::create()?;
+    let path = scratch.write("payload", payload)?;
+    let file = match payload_option {
+        "data-binary" => format!("@{}", path.display()),
+        _ => path.display().to_string(),
+    };
+    let curl_config = format!("{}{} = {}\n", curl_config, payload_option, quote(&file));
+
+    run(&curl_config)
+}
+
+//function to quote a value for a curl config file
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,130 @@
+//comparing a scan against the previous stored run of the same range
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use crate::host::{HostResult, PortResult, PortState};
+
+//what became of one address between two scans, hosts that stayed down aren't a change
+pub enum Change<'a> {
+    Up(&'a HostResult),
+    Down(&'a HostResult),
+    //an up host whose ports opened or closed, or whose identification changed
+    Changed { host: &'a HostResult, opened: Vec<&'a PortResult>, closed: Vec<&'a PortResult>, info: Vec<String> },
+}
+
+//function to find what changed between two scans of a range, with the number of up hosts that didn't
+pub fn changes<'a>(previous: &'a [HostResult], current: &'a [HostResult]) -> (Vec<Change<'a>>, usize) {
+    let before: HashMap<Ipv4Addr, &HostResult> = previous.iter().map(|host| (host.address, host)).collect();
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for host in current {
+        let was_up = before.get(&host.address).is_some_and(|host| host.is_up());
+        match (was_up, host.is_up()) {
+            (false, true) => changes.push(Change::Up(host)),
+            (true, false) => changes.push(Change::Down(host)),
+            (true, true) => {
+                let old = before[&host.address];
+                let opened = newly_open(host, old);
+                let closed = newly_open(old, host);
+                let info = changed_info(host, old);
+                if opened.is_empty() && closed.is_empty() && info.is_empty() {
+                    unchanged += 1;
+                }
+                else {
+                    changes.push(Change::Changed { host, opened, closed, info });
+                }
+            }
+            (false, false) => {}
+        }
+    }
+    (changes, unchanged)
+}
+
+//function to print what changed since the previous scan, unchanged hosts are only counted
+pub fn print_changes(previous: &[HostResult], current: &[HostResult], age_secs: u64) {
+    println!("Changes since the scan {} ago:", format_age(age_secs));
+    let (changes, unchanged) = changes(previous, current);
+    for change in &changes {
+        match change {
+            Change::Up(host) => println!("  \x1b[0;32m+ {}\x1b[0m is now up{}", host.address, open_list(host)),
+            Change::Down(host) => println!(
+                "  \x1b[31m- {}\x1b[0m is no longer up ({})",
+                host.address,
+                host.reason().unwrap_or_default()
+            ),
+            Change::Changed { host, opened, closed, info } => {
+                println!("  \x1b[0;33m~ {}\x1b[0m", host.address);
+                if !opened.is_empty() {
+                    println!("      opened{}", port_list(opened.iter().copied()));
+                }
+                if !closed.is_empty() {
+                    println!("      closed{}", port_list(closed.iter().copied()));
+                }
+                for line in info {
+                    println!("      {}", line);
+                }
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        println!("  nothing changed");
+    }
+    println!("{} changed, {} still up and unchanged.", changes.len(), unchanged);
+}
+
+//function to list the open ports of a host, prefixed with ": "
+fn open_list(host: &HostResult) -> String {
+    port_list(host.ports.iter().filter(|port| port.state == PortState::Open))
+}
+
+//function to list ports open on one result that the other result probed and found not open
+fn newly_open<'a>(host: &'a HostResult, other: &HostResult) -> Vec<&'a PortResult> {
+    host.ports
+        .iter()
+        .filter(|port| {
+            port.state == PortState::Open
+                && other
+                    .ports
+                    .iter()
+                    .any(|old| old.port == port.port && old.protocol == port.protocol && old.state != PortState::Open)
+        })
+        .collect()
+}
+
+//function to list ports whose identification changed, e.g. a new SSH host key after a reinstall
+fn changed_info(host: &HostResult, old: &HostResult) -> Vec<String> {
+    host.ports
+        .iter()
+        .filter_map(|port| {
+            let before = old.ports.iter().find(|old| old.port == port.port && old.protocol == port.protocol)?;
+            match (&before.info, &port.info) {
+                (Some(before), Some(now)) if before != now => {
+                    Some(format!("{}/{} changed from [{}] to [{}]", port.port, port.protocol, before, now))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn port_list<'a>(ports: impl Iterator<Item = &'a PortResult>) -> String {
+    let ports: Vec<String> = ports.map(|port| format!("{}/{}", port.port, port.protocol)).collect();
+    if ports.is_empty() {
+        String::new()
+    }
+    else {
+        format!(": {}", ports.join(", "))
+    }
+}
+
+//function to show an age in seconds in the largest sensible unit
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        0..=119 => format!("{}s", secs),
+        120..=7199 => format!("{}m", secs / 60),
+        7200..=172_799 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
@@ -0,0 +1,408 @@
+//a small DNS client over UDP or DNS-over-HTTPS, so lookups don't depend on what the system resolver supports
+
+use std::cell::Cell;
+use std::fs;
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_SRV: u16 = 33;
+
+const CLASS_IN: u16 = 1;
+
+//how long to wait for each server before trying the next one
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct Resolver {
+    servers: Vec<SocketAddr>,
+    //which server the next query starts with, so load is rotated across them
+    next_server: Cell<usize>,
+    //when set every query is sent to this DNS-over-HTTPS endpoint instead of the servers
+    doh_url: Option<String>,
+}
+
+//the parts of an answer record we care about
+pub struct Record {
+    pub rtype: u16,
+    pub data: RecordData,
+}
+
+pub enum RecordData {
+    Address(Ipv4Addr),
+    Name(String),
+    //an SRV record, the port and host a service is offered on
+    Service { port: u16, target: String },
+    Other,
+}
+
+impl Resolver {
+    //function to build a resolver from the --doh or --resolver values, or the system nameservers when there are none
+    pub fn from_options(resolvers: &[String], doh_url: Option<&str>) -> Result<Resolver, String> {
+        if let Some(url) = doh_url {
+            if !url.starts_with("https://") {
+                return Err(format!("Invalid DoH URL {}, expected an https:// URL", url));
+            }
+            let mut resolver = Resolver::new(Vec::new());
+            resolver.doh_url = Some(url.to_string());
+            return Ok(resolver);
+        }
+        if resolvers.is_empty() {
+            return Resolver::system();
+        }
+
+        let mut servers = Vec::new();
+        for resolver in resolvers {
+            servers.push(parse_server(resolver)?);
+        }
+        Ok(Resolver::new(servers))
+    }
+
+    fn new(servers: Vec<SocketAddr>) -> Resolver {
+        Resolver {
+            servers,
+            next_server: Cell::new(0),
+            doh_url: None,
+        }
+    }
+
+    //function to build a resolver from the nameservers in /etc/resolv.conf
+    pub fn system() -> Result<Resolver, String> {
+        let resolv_conf = fs::read_to_string("/etc/resolv.conf").map_err(|error| format!("cannot read /etc/resolv.conf, {}", error))?;
+        let servers: Vec<SocketAddr> = resolv_conf
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|server| server.trim().parse::<Ipv4Addr>().ok())
+            .map(|server| SocketAddr::from((server, 53)))
+            .collect();
+
+        if servers.is_empty() {
+            return Err("no IPv4 nameservers in /etc/resolv.conf".to_string());
+        }
+        Ok(Resolver::new(servers))
+    }
+
+    //function to find the PTR name of an address, None when it has no PTR record
+    pub fn reverse_lookup(&self, address: Ipv4Addr) -> Result<Option<String>, String> {
+        let octets = address.octets();
+        let name = format!("{}.{}.{}.{}.in-addr.arpa", octets[3], octets[2], octets[1], octets[0]);
+
+        let records = self.query(&name, TYPE_PTR)?;
+        Ok(records.into_iter().find_map(|record| match record.data {
+            RecordData::Name(name) if record.rtype == TYPE_PTR => Some(name),
+            _ => None,
+        }))
+    }
+
+    //function to find the first IPv4 address of a hostname
+    pub fn lookup(&self, hostname: &str) -> Result<Option<Ipv4Addr>, String> {
+        let records = self.query(hostname, TYPE_A)?;
+        Ok(records.into_iter().find_map(|record| match record.data {
+            RecordData::Address(address) => Some(address),
+            _ => None,
+        }))
+    }
+
+    //function to find the hosts and ports an SRV name points at, e.g. _ldap._tcp.dc._msdcs.corp.local
+    pub fn service_lookup(&self, name: &str) -> Result<Vec<(String, u16)>, String> {
+        let records = self.query(name, TYPE_SRV)?;
+        Ok(records
+            .into_iter()
+            .filter_map(|record| match record.data {
+                RecordData::Service { port, target } => Some((target, port)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    //function to send a query to each server in turn until one answers, starting one server further along each time
+    pub fn query(&self, name: &str, qtype: u16) -> Result<Vec<Record>, String> {
+        if let Some(url) = &self.doh_url {
+            return query_doh(url, name, qtype);
+        }
+
+        let start = self.next_server.get();
+        self.next_server.set((start + 1) % self.servers.len());
+
+        let mut last_error = String::new();
+        for offset in 0..self.servers.len() {
+            let server = &self.servers[(start + offset) % self.servers.len()];
+            match query_server(*server, name, qtype) {
+                Ok(records) => return Ok(records),
+                Err(error) => last_error = format!("{} ({})", error, server),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+//function to read the search domains of the system resolver from /etc/resolv.conf
+pub fn search_domains() -> Vec<String> {
+    let resolv_conf = fs::read_to_string("/etc/resolv.conf").unwrap_or_default();
+    resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("search").or_else(|| line.trim().strip_prefix("domain")))
+        .flat_map(|domains| domains.split_whitespace())
+        .map(|domain| domain.trim_end_matches('.').to_string())
+        .collect()
+}
+
+//function to parse a resolver given as ip or ip:port
+fn parse_server(resolver: &str) -> Result<SocketAddr, String> {
+    if let Ok(address) = resolver.parse::<Ipv4Addr>() {
+        return Ok(SocketAddr::from((address, 53)));
+    }
+    resolver.parse::<SocketAddr>().map_err(|_| format!("Invalid resolver {}, expected IP or IP:PORT", resolver))
+}
+
+//function to send one query over UDP and wait for the matching response
+fn query_server(server: SocketAddr, name: &str, qtype: u16) -> Result<Vec<Record>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|error| error.to_string())?;
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    socket.connect(server).map_err(|error| error.to_string())?;
+
+    let id = query_id();
+    socket.send(&build_query(id, name, qtype)).map_err(|error| error.to_string())?;
+
+    let mut buf = [0u8; 1500];
+    loop {
+        let len = socket.recv(&mut buf).map_err(|_| "no response".to_string())?;
+        //ignore stray responses to earlier queries
+        if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            return parse_response(&buf[..len]);
+        }
+    }
+}
+
+//function to send one query as an RFC 8484 POST through curl, which takes care of the TLS
+fn query_doh(url: &str, name: &str, qtype: u16) -> Result<Vec<Record>, String> {
+    let mut curl = Command::new("curl")
+        .arg("--silent")
+        .arg("--fail")
+        .arg("--max-time").arg(TIMEOUT.as_secs().to_string())
+        .arg("--header").arg("content-type: application/dns-message")
+        .arg("--header").arg("accept: application/dns-message")
+        .arg("--data-binary").arg("@-")
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("cannot run curl for DoH, {}", error))?;
+
+    //the id is zero for DoH so responses stay cacheable
+    let query = build_query(0, name, qtype);
+    if let Some(mut stdin) = curl.stdin.take() {
+        stdin.write_all(&query).map_err(|error| error.to_string())?;
+    }
+
+    let curl_out = curl.wait_with_output().map_err(|error| error.to_string())?;
+    if !curl_out.status.success() {
+        return Err(format!("DoH request to {} failed", url));
+    }
+    parse_response(&curl_out.stdout)
+}
+
+//function to pick a query id, the low bits of the clock are unpredictable enough for a scanner
+fn query_id() -> u16 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0);
+    (nanos ^ (nanos >> 16)) as u16
+}
+
+//function to build a standard recursive query for one name
+pub fn build_query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(32 + name.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&[0x01, 0x00]); //recursion desired
+    packet.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]); //one question, no other records
+
+    for label in name.trim_end_matches('.').split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet
+}
+
+//function to pull the answer records out of a response
+pub fn parse_response(packet: &[u8]) -> Result<Vec<Record>, String> {
+    if packet.len() < 12 {
+        return Err("response too short".to_string());
+    }
+
+    //NXDOMAIN just means there is nothing there, any other error code is a real failure
+    let rcode = packet[3] & 0x0f;
+    if rcode == 3 {
+        return Ok(Vec::new());
+    }
+    if rcode != 0 {
+        return Err(format!("server returned error code {}", rcode));
+    }
+
+    let question_count = u16::from_be_bytes([packet[4], packet[5]]);
+    let answer_count = u16::from_be_bytes([packet[6], packet[7]]);
+
+    //skip past the questions to get to the answers
+    let mut pos = 12;
+    for _ in 0..question_count {
+        let (_, next) = read_name(packet, pos)?;
+        pos = next + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..answer_count {
+        let (_, next) = read_name(packet, pos)?;
+        pos = next;
+        if pos + 10 > packet.len() {
+            return Err("truncated answer".to_string());
+        }
+        let rtype = u16::from_be_bytes([packet[pos], packet[pos + 1]]);
+        let data_len = u16::from_be_bytes([packet[pos + 8], packet[pos + 9]]) as usize;
+        let data_start = pos + 10;
+        if data_start + data_len > packet.len() {
+            return Err("truncated answer".to_string());
+        }
+
+        let data = match rtype {
+            TYPE_A if data_len == 4 => {
+                let bytes = &packet[data_start..data_start + 4];
+                RecordData::Address(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))
+            }
+            TYPE_PTR => RecordData::Name(read_name(packet, data_start)?.0),
+            //priority and weight come first, they don't matter for finding the hosts
+            TYPE_SRV if data_len > 6 => RecordData::Service {
+                port: u16::from_be_bytes([packet[data_start + 4], packet[data_start + 5]]),
+                target: read_name(packet, data_start + 6)?.0,
+            },
+            _ => RecordData::Other,
+        };
+        records.push(Record { rtype, data });
+        pos = data_start + data_len;
+    }
+
+    Ok(records)
+}
+
+//function to read a possibly compressed name, returning it and the position just after it
+pub fn read_name(packet: &[u8], start: usize) -> Result<(String, usize), String> {
+    let mut labels = Vec::new();
+    let mut pos = start;
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(pos).ok_or("name runs past the end of the packet")? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+
+        //a compression pointer continues the name somewhere earlier in the packet
+        if len & 0xc0 == 0xc0 {
+            let low = *packet.get(pos + 1).ok_or("name runs past the end of the packet")? as usize;
+            end.get_or_insert(pos + 2);
+            pos = ((len & 0x3f) << 8) | low;
+            jumps += 1;
+            if jumps > 32 {
+                return Err("name compression loop".to_string());
+            }
+            continue;
+        }
+
+        let label = packet.get(pos + 1..pos + 1 + len).ok_or("name runs past the end of the packet")?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+
+    Ok((labels.join("."), end.unwrap_or(pos)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a response to build_query's question, with the answers appended
+    fn response(name: &str, qtype: u16, answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut packet = build_query(7, name, qtype);
+        packet[2] |= 0x80;
+        packet[7] = answers.len() as u8;
+        for (rtype, data) in answers {
+            //a pointer to the name of the question at byte 12
+            packet.extend_from_slice(&[0xc0, 12]);
+            packet.extend_from_slice(&rtype.to_be_bytes());
+            packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0, 60]);
+            packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            packet.extend_from_slice(data);
+        }
+        packet
+    }
+
+    #[test]
+    fn answers_are_read() {
+        let packet = response("web.lan", TYPE_A, &[(TYPE_A, &[10, 0, 0, 5]), (TYPE_PTR, &[0xc0, 12]), (16, b"\x03txt")]);
+        let records = parse_response(&packet).unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(matches!(records[0].data, RecordData::Address(address) if address == Ipv4Addr::new(10, 0, 0, 5)));
+        assert!(matches!(&records[1].data, RecordData::Name(name) if name == "web.lan"));
+        assert!(matches!(records[2].data, RecordData::Other));
+    }
+
+    #[test]
+    fn service_records_give_port_and_target() {
+        let packet = response("_ssh._tcp.lan", TYPE_SRV, &[(TYPE_SRV, &[0, 1, 0, 0, 0, 22, 0xc0, 12])]);
+        let records = parse_response(&packet).unwrap();
+        assert!(matches!(&records[0].data, RecordData::Service { port: 22, target } if target == "_ssh._tcp.lan"));
+    }
+
+    #[test]
+    fn error_codes_are_told_apart() {
+        let mut packet = response("gone.lan", TYPE_A, &[]);
+        packet[3] |= 3;
+        assert!(parse_response(&packet).unwrap().is_empty());
+        packet[3] = (packet[3] & 0xf0) | 2;
+        assert!(parse_response(&packet).is_err());
+    }
+
+    #[test]
+    fn truncated_responses_are_refused() {
+        let packet = response("web.lan", TYPE_A, &[(TYPE_A, &[10, 0, 0, 5])]);
+        for length in 0..packet.len() {
+            assert!(parse_response(&packet[..length]).is_err(), "truncated to {} bytes", length);
+        }
+        //more answers claimed than there are
+        let mut packet = packet;
+        packet[7] = 2;
+        assert!(parse_response(&packet).is_err());
+        //a record length past the end
+        let mut packet = response("web.lan", TYPE_A, &[(TYPE_A, &[10, 0, 0, 5])]);
+        let last = packet.len() - 5;
+        packet[last] = 0xff;
+        assert!(parse_response(&packet).is_err());
+    }
+
+    #[test]
+    fn names_that_loop_or_point_past_the_end_are_refused() {
+        let mut packet = vec![0u8; 12];
+        packet.extend_from_slice(&[0xc0, 12]);
+        assert!(read_name(&packet, 12).unwrap_err().contains("loop"));
+        let packet = [0xc0, 0xff];
+        assert!(read_name(&packet, 0).is_err());
+        let packet = [5, b'a', b'b'];
+        assert!(read_name(&packet, 0).is_err());
+    }
+
+    #[test]
+    fn compressed_names_end_after_the_first_pointer() {
+        let mut packet = vec![3, b'l', b'a', b'n', 0];
+        packet.extend_from_slice(&[3, b'w', b'e', b'b', 0xc0, 0]);
+        assert_eq!(read_name(&packet, 5).unwrap(), ("web.lan".to_string(), 11));
+    }
+}
@@ -0,0 +1,103 @@
+//encrypting the files a scan leaves behind, as discovery results are sensitive and get copied between
+//machines and attached to tickets: to a passphrase (--encrypt-passphrase, gpg's symmetric AES256) or to
+//recipients' keys (--encrypt-to), age recipients (age1..., ssh-ed25519 ..., ssh-rsa ...) with age and
+//anything else as a key in the gpg keyring
+//
+//the files are plain gpg or age files, decrypted with gpg -d or age -d -i KEY
+
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::process::Command;
+
+use crate::compress;
+use crate::keys;
+
+//the passphrase is taken from here before asking on the terminal, for scripts and cron
+const PASSPHRASE_VARIABLE: &str = "HOST_DISCO_PASSPHRASE";
+
+#[derive(Clone)]
+pub enum Encryption {
+    Passphrase(String),
+    Age(Vec<String>),
+    Gpg(Vec<String>),
+}
+
+//function to tell age recipients from gpg ones, refusing a mix as a file has one or the other
+pub fn recipients(recipients: &[String]) -> Result<Encryption, String> {
+    let is_age = |recipient: &String| ["age1", "ssh-ed25519 ", "ssh-rsa "].iter().any(|prefix| recipient.starts_with(prefix));
+    if recipients.iter().all(is_age) {
+        Ok(Encryption::Age(recipients.to_vec()))
+    }
+    else if !recipients.iter().any(is_age) {
+        Ok(Encryption::Gpg(recipients.to_vec()))
+    }
+    else {
+        Err("--encrypt-to takes age or gpg recipients, not both".to_string())
+    }
+}
+
+//function to get the passphrase from the environment, else to ask for it twice on the terminal
+pub fn passphrase() -> Result<Encryption, String> {
+    if let Some(passphrase) = env::var(PASSPHRASE_VARIABLE).ok().filter(|passphrase| !passphrase.is_empty()) {
+        return Ok(Encryption::Passphrase(passphrase));
+    }
+    let first = ask("Passphrase for the result files: ")?;
+    if first.is_empty() {
+        return Err("the passphrase is empty".to_string());
+    }
+    if ask("Passphrase again: ")? != first {
+        return Err("the passphrases don't match".to_string());
+    }
+    Ok(Encryption::Passphrase(first))
+}
+
+//function to read a line from the terminal without echoing it
+fn ask(prompt: &str) -> Result<String, String> {
+    let no_terminal = |_| format!("--encrypt-passphrase needs a terminal to ask on, or the passphrase in {}", PASSPHRASE_VARIABLE);
+    let mut tty = File::options().read(true).write(true).open("/dev/tty").map_err(no_terminal)?;
+    let _ = write!(tty, "{}", prompt);
+    let _ = tty.flush();
+    let saved = keys::stty(&["-g"]);
+    keys::stty(&["-echo"]);
+    let mut line = String::new();
+    let read = BufReader::new(&tty).read_line(&mut line);
+    if let Some(saved) = saved {
+        keys::stty(&[saved.trim()]);
+    }
+    let _ = writeln!(tty);
+    read.map_err(|error| format!("cannot read the passphrase, {}", error))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+//function to encrypt the contents
+pub fn seal(contents: &[u8], encryption: &Encryption) -> Result<Vec<u8>, String> {
+    match encryption {
+        //gpg reads the passphrase line from stdin ahead of the data
+        Encryption::Passphrase(passphrase) => {
+            let mut input = format!("{}\n", passphrase).into_bytes();
+            input.extend_from_slice(contents);
+            let mut gpg = Command::new("gpg");
+            gpg.args(["--batch", "--yes", "--quiet", "--pinentry-mode", "loopback", "--passphrase-fd", "0"]);
+            gpg.args(["--symmetric", "--cipher-algo", "AES256", "--output", "-"]);
+            compress::pipe(&mut gpg, "gpg", &input)
+        }
+        Encryption::Age(recipients) => {
+            let mut age = Command::new("age");
+            for recipient in recipients {
+                age.args(["--recipient", recipient]);
+            }
+            compress::pipe(&mut age, "age", contents)
+        }
+        //the keys are the user's own choice, so they are used whether or not the keyring trusts them
+        Encryption::Gpg(recipients) => {
+            let mut gpg = Command::new("gpg");
+            gpg.args(["--batch", "--yes", "--quiet", "--trust-model", "always", "--encrypt"]);
+            for recipient in recipients {
+                gpg.args(["--recipient", recipient]);
+            }
+            gpg.args(["--output", "-"]);
+            compress::pipe(&mut gpg, "gpg", contents)
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//what else can be learned about a live host without probing it, its name, MAC address and vendor,
+//and for public addresses the network announcing them
+
+use std::collections::HashSet;
+use std::fs;
+use std::net::Ipv4Addr;
+
+use crate::asn;
+use crate::cli::Options;
+use crate::dns;
+use crate::host::HostResult;
+use crate::names;
+use crate::oui;
+use crate::store;
+
+//role given to the scanning machine's own addresses
+pub const SELF_ROLE: &str = "self";
+
+//function to fill in the name, MAC, vendor and ASN of a live host, from the name cache when it has them
+pub fn enrich(host: &mut HostResult, options: &Options) {
+    let now = store::now();
+    match names::hostname(host.address, now).filter(|_| !options.no_cache) {
+        Some(hostname) => host.hostname = hostname,
+        //each worker gets its own resolver, a failed lookup just leaves the name empty and isn't cached
+        None => {
+            if let Ok(resolver) = dns::Resolver::from_options(&options.resolvers, options.doh.as_deref()) {
+                if let Ok(hostname) = resolver.reverse_lookup(host.address) {
+                    names::remember_hostname(host.address, hostname.clone(), now);
+                    host.hostname = hostname;
+                }
+            }
+        }
+    }
+
+    //the neighbour table forgets quiet hosts after a while, the cache still knows their MAC
+    host.mac = match mac_address(host.address) {
+        Some(mac) => {
+            names::remember_mac(host.address, mac.clone(), now);
+            Some(mac)
+        }
+        None if !options.no_cache => names::mac(host.address, now),
+        None => None,
+    };
+    host.vendor = host.mac.as_deref().and_then(oui::vendor).map(str::to_string);
+    host.asn = asn::lookup(host.address);
+}
+
+//function to look an address up in the kernel's neighbour table, only hosts on a local segment have one
+pub fn mac_address(address: Ipv4Addr) -> Option<String> {
+    let table = fs::read_to_string("/proc/net/arp").ok()?;
+    let address = address.to_string();
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        //flags 0x0 is an entry that never resolved
+        match fields.as_slice() {
+            [ip, _, flags, mac, ..] if *ip == address && *flags != "0x0" && *mac != "00:00:00:00:00:00" => Some(mac.to_uppercase()),
+            _ => None,
+        }
+    })
+}
+
+//function to list this machine's own interface addresses, the /32 LOCAL entries of the kernel's routing trie
+pub fn own_addresses() -> HashSet<Ipv4Addr> {
+    let Ok(trie) = fs::read_to_string("/proc/net/fib_trie") else {
+        return HashSet::new();
+    };
+    let mut own = HashSet::new();
+    let mut last = None;
+    for line in trie.lines() {
+        let line = line.trim();
+        if let Some(address) = line.strip_prefix("|-- ").or_else(|| line.strip_prefix("+-- ")) {
+            last = address.split('/').next().and_then(|address| address.parse::<Ipv4Addr>().ok());
+        }
+        else if line == "/32 host LOCAL" {
+            own.extend(last);
+        }
+    }
+    own
+}
+
+//function to give one of this machine's own addresses the self role
+pub fn mark_self(host: &mut HostResult, own: &HashSet<Ipv4Addr>) {
+    if own.contains(&host.address) && !host.roles.iter().any(|role| role == SELF_ROLE) {
+        host.roles.push(SELF_ROLE.to_string());
+    }
+}
@@ -0,0 +1,80 @@
+//exports of the scan results to files other tools can consume
+
+use std::process::{Command, Stdio};
+
+use crate::compress;
+use crate::encrypt::Encryption;
+use crate::host::HostResult;
+use crate::target::Target;
+
+//function to write a Graphviz DOT map of the scanner, the routers on the way and every up host
+pub fn write_dot(path: &str, targets: &[Target], results: &[HostResult], compress: Option<&str>, encryption: Option<&Encryption>) -> Result<(), String> {
+    let mut dot = String::new();
+    dot.push_str("digraph network {\n");
+    dot.push_str("    rankdir=LR;\n");
+    dot.push_str("    \"scanner\" [shape=doublecircle];\n");
+
+    for target in targets {
+        let target_ups: Vec<String> = results
+            .iter()
+            .filter(|host| host.is_up() && target.contains(host.address))
+            .map(|host| host.address.to_string())
+            .collect();
+
+        //trace the path to the first up host, the routers in front of it are shared by the whole subnet
+        let hops = match target_ups.first() {
+            Some(ip) => trace_hops(ip),
+            None => Vec::new(),
+        };
+
+        //chain scanner -> router -> ... -> subnet
+        let mut previous = "scanner".to_string();
+        for hop in &hops {
+            dot.push_str(&format!("    \"{}\" [shape=diamond, label=\"router\\n{}\"];\n", hop, hop));
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", previous, hop));
+            previous = hop.clone();
+        }
+
+        //single hosts hang straight off the last router, ranges get a node of their own
+        if target.prefix < 32 {
+            dot.push_str(&format!("    \"{}\" [shape=folder];\n", target.label));
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", previous, target.label));
+            previous = target.label.clone();
+        }
+
+        for ip in target_ups {
+            dot.push_str(&format!("    \"{}\" [shape=box];\n", ip));
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", previous, ip));
+        }
+    }
+    dot.push_str("}\n");
+
+    compress::write(path, dot.as_bytes(), compress, encryption)
+}
+
+//function to list the routers between the scanner and a host using the system traceroute,
+//an empty list means the host is on-link or traceroute isn't available
+fn trace_hops(ip: &str) -> Vec<String> {
+    let trace_out = match Command::new("traceroute")
+        .arg("-n")              //don't resolve hop names
+        .arg("-q").arg("1")     //one probe per hop is enough for a map
+        .arg("-w").arg("1")     //wait at most a second for each hop
+        .arg(ip)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+
+    //hop lines look like " 1  192.168.1.1  0.512 ms", unanswered hops are " 2  *"
+    let trace_stdout = String::from_utf8_lossy(&trace_out.stdout);
+    trace_stdout
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter(|hop| *hop != "*" && *hop != ip)
+        .map(|hop| hop.to_string())
+        .collect()
+}
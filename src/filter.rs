@@ -0,0 +1,56 @@
+//narrowing the reported hosts down to the ones being hunted for, every address is still probed and stored
+
+use crate::cli::Options;
+use crate::host::HostResult;
+use crate::pattern::Pattern;
+
+pub struct Filter<'a> {
+    //lowercase parts of vendor names, a host has to match one of these and none of the excluded ones
+    vendors: Vec<String>,
+    excluded_vendors: Vec<String>,
+    //the reverse DNS name has to match this, hosts without a name never do
+    hostname: Option<&'a Pattern>,
+}
+
+impl Filter<'_> {
+    pub fn from_options(options: &Options) -> Filter<'_> {
+        Filter {
+            vendors: options.vendor_filter.clone(),
+            excluded_vendors: options.vendor_exclude.clone(),
+            hostname: options.hostname_filter.as_ref(),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.vendors.is_empty() || !self.excluded_vendors.is_empty() || self.hostname.is_some()
+    }
+
+    //function to test whether a host should be reported, only live hosts have a vendor or name so a set filter hides the rest
+    pub fn matches(&self, host: &HostResult) -> bool {
+        if !self.is_set() {
+            return true;
+        }
+        if !host.is_up() {
+            return false;
+        }
+        let vendor = host.vendor.as_deref().unwrap_or("").to_lowercase();
+        let mentions = |names: &[String]| names.iter().any(|name| vendor.contains(name.as_str()));
+        let named = self.hostname.is_none_or(|pattern| host.hostname.as_deref().is_some_and(|name| pattern.is_match(name)));
+        (self.vendors.is_empty() || mentions(&self.vendors)) && !mentions(&self.excluded_vendors) && named
+    }
+
+    //function to describe the filter for the summary, e.g. "vendor raspberry or apple, hostname ^db-"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.vendors.is_empty() {
+            parts.push(format!("vendor {}", self.vendors.join(" or ")));
+        }
+        if !self.excluded_vendors.is_empty() {
+            parts.push(format!("not vendor {}", self.excluded_vendors.join(" or ")));
+        }
+        if let Some(pattern) = self.hostname {
+            parts.push(format!("hostname {}", pattern.source()));
+        }
+        parts.join(", ")
+    }
+}
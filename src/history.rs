@@ -0,0 +1,52 @@
+//exporting the stored runs of a range as time series CSV, one row per host per run, for pandas or a spreadsheet
+
+use crate::compress;
+use crate::metadata;
+use crate::output::csv_field;
+use crate::store;
+use crate::target;
+
+//function to write every stored run of the targets since a point in time as CSV, to a file or the terminal
+pub fn export(targets: &[String], since: Option<u64>, output_file: Option<&str>, compress: Option<&str>) -> Result<(), String> {
+    let mut labels = Vec::new();
+    for entry in targets {
+        labels.push(target::expand(entry, None)?.label);
+    }
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    let scan_key = store::scan_key(&labels);
+
+    let runs: Vec<store::RunName> = store::scan_runs(&scan_key)?
+        .into_iter()
+        .filter(|run| since.is_none_or(|since| run.started_at >= since))
+        .collect();
+    if runs.is_empty() {
+        return Err(format!("No stored runs of {} to export", labels.join(", ")));
+    }
+
+    //epoch seconds next to the ISO time, spreadsheets like the one and pandas the other
+    let mut csv = String::from("time,epoch,address,status,reason,rtt_ms,hostname\n");
+    for run in &runs {
+        let scan = store::load_scan(&scan_key, *run)?;
+        for host in &scan.hosts {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                metadata::iso8601(scan.started_at),
+                scan.started_at,
+                host.address,
+                host.status(),
+                csv_field(&host.reason().unwrap_or_default()),
+                host.rtt().map(|rtt| format!("{:.3}", rtt)).unwrap_or_default(),
+                csv_field(host.hostname.as_deref().unwrap_or(""))
+            ));
+        }
+    }
+
+    match output_file {
+        Some(path) => {
+            compress::write(path, csv.as_bytes(), compress, None)?;
+            eprintln!("History of {} runs written to {}.", runs.len(), path);
+        }
+        None => print!("{}", csv),
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//the result model every output format is rendered from
+
+use std::net::Ipv4Addr;
+
+use crate::json::{self, Value};
+use crate::oui;
+use crate::probe::{DownReason, Outcome};
+
+//everything learned about one address during a scan
+#[derive(Clone)]
+pub struct HostResult {
+    pub address: Ipv4Addr,
+    pub outcome: Outcome,
+    //the PTR name, MAC address and MAC vendor of live hosts, when they can be found
+    pub hostname: Option<String>,
+    pub mac: Option<String>,
+    pub vendor: Option<String>,
+    //infrastructure roles identification found, e.g. "domain-controller"
+    pub roles: Vec<String>,
+    pub ports: Vec<PortResult>,
+    //how the host was found, "icmp", "arp" and "tcp" (a connection or reset on a --tcp-ping port)
+    pub found_by: Vec<&'static str>,
+    //the routers and switches whose tables have the host when it didn't answer, e.g. "10.0.0.1, sw1 Gi1/0/12"
+    pub seen_by: Option<String>,
+    //the ASN, owner and prefix of a public address from the --asn-data dataset, e.g. "AS15169 GOOGLE (8.8.8.0/24)"
+    pub asn: Option<String>,
+    //the id of the scan the result was taken from, when host_disco merge combined several
+    pub source: Option<String>,
+}
+
+//one probed port on a host
+#[derive(Clone)]
+pub struct PortResult {
+    pub port: u16,
+    pub protocol: &'static str,
+    pub state: PortState,
+    pub service: Option<String>,
+    //whatever identification learned about the service, e.g. "SMB 3.1.1, name FILESRV01"
+    pub info: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PortState {
+    Open,
+    Closed,
+    Filtered,
+    //no answer to a UDP probe, the port may be open or a firewall may be dropping it
+    OpenFiltered,
+}
+
+//the ways a host can be found
+pub const FOUND_BY: [&str; 3] = ["icmp", "arp", "tcp"];
+
+impl HostResult {
+    pub fn is_up(&self) -> bool {
+        matches!(self.outcome, Outcome::Up { .. })
+    }
+
+    pub fn rtt(&self) -> Option<f64> {
+        match self.outcome {
+            Outcome::Up { rtt, .. } => rtt,
+            _ => None,
+        }
+    }
+
+    pub fn ttl(&self) -> Option<u8> {
+        match self.outcome {
+            Outcome::Up { ttl, .. } => ttl,
+            _ => None,
+        }
+    }
+
+    //function to estimate how many routers are between us and the host from the reply TTL, systems start
+    //at 64 (Linux, macOS), 128 (Windows) or 255 (network gear) and every hop takes one off
+    pub fn hops(&self) -> Option<u8> {
+        let ttl = self.ttl()?;
+        let initial = [64, 128, 255].into_iter().find(|initial| ttl <= *initial)?;
+        Some(initial - ttl)
+    }
+
+    //function to tell whether the host answered from a randomized MAC, None when its MAC isn't known
+    pub fn randomized_mac(&self) -> Option<bool> {
+        self.mac.as_deref().map(oui::is_randomized)
+    }
+
+    //function to describe anything odd about the replies, e.g. "2 duplicate replies" or "reply from 10.0.0.9"
+    pub fn anomalies(&self) -> Vec<String> {
+        let Outcome::Up { duplicates, replied_from, .. } = self.outcome else {
+            return Vec::new();
+        };
+        let mut anomalies = Vec::new();
+        match duplicates {
+            0 => {}
+            1 => anomalies.push("1 duplicate reply".to_string()),
+            count => anomalies.push(format!("{} duplicate replies", count)),
+        }
+        if let Some(source) = replied_from {
+            anomalies.push(format!("reply from {}", source));
+        }
+        anomalies
+    }
+
+    //function to name the host status for the output formats
+    pub fn status(&self) -> &'static str {
+        match self.outcome {
+            Outcome::Up { .. } => "up",
+            Outcome::Down(_) => "down",
+            Outcome::LocalError(_) => "error",
+        }
+    }
+
+    //function to give the reason a host isn't up, None for up hosts
+    pub fn reason(&self) -> Option<String> {
+        match &self.outcome {
+            Outcome::Up { .. } => None,
+            Outcome::Down(reason) => Some(reason.label().to_string()),
+            Outcome::LocalError(error) => Some(error.clone()),
+        }
+    }
+
+    //function to write the host as one JSON object, the shape every stored and exported result uses
+    pub fn to_json(&self) -> String {
+        let ports: Vec<String> = self
+            .ports
+            .iter()
+            .map(|port| {
+                format!(
+                    "{{\"port\":{},\"protocol\":\"{}\",\"state\":\"{}\",\"service\":{},\"info\":{}}}",
+                    port.port,
+                    port.protocol,
+                    port.state.label(),
+                    json::quote_option(port.service.as_deref()),
+                    json::quote_option(port.info.as_deref())
+                )
+            })
+            .collect();
+        format!(
+            "{{\"address\":\"{}\",\"status\":\"{}\",\"reason\":{},\"rtt_ms\":{},\"hostname\":{},\"mac\":{},\"vendor\":{},\"mac_randomized\":{},\"roles\":[{}],\"ttl\":{},\"hops\":{},\"duplicates\":{},\"replied_from\":{},\"found_by\":[{}],\"seen_by\":{},\"asn\":{},\"source\":{},\"ports\":[{}]}}",
+            self.address,
+            self.status(),
+            json::quote_option(self.reason().as_deref()),
+            self.rtt().map(|rtt| format!("{:.3}", rtt)).unwrap_or_else(|| "null".to_string()),
+            json::quote_option(self.hostname.as_deref()),
+            json::quote_option(self.mac.as_deref()),
+            json::quote_option(self.vendor.as_deref()),
+            self.randomized_mac().map(|randomized| randomized.to_string()).unwrap_or_else(|| "null".to_string()),
+            self.roles.iter().map(|role| json::quote(role)).collect::<Vec<_>>().join(","),
+            self.ttl().map(|ttl| ttl.to_string()).unwrap_or_else(|| "null".to_string()),
+            self.hops().map(|hops| hops.to_string()).unwrap_or_else(|| "null".to_string()),
+            match self.outcome {
+                Outcome::Up { duplicates, .. } => duplicates,
+                _ => 0,
+            },
+            match self.outcome {
+                Outcome::Up { replied_from: Some(source), .. } => format!("\"{}\"", source),
+                _ => "null".to_string(),
+            },
+            self.found_by.iter().map(|method| json::quote(method)).collect::<Vec<_>>().join(","),
+            json::quote_option(self.seen_by.as_deref()),
+            json::quote_option(self.asn.as_deref()),
+            json::quote_option(self.source.as_deref()),
+            ports.join(",")
+        )
+    }
+
+    //function to read a host back from the object to_json writes, None if it doesn't look like one
+    pub fn from_json(value: &Value) -> Option<HostResult> {
+        let address = value.get("address")?.as_str()?.parse().ok()?;
+        let reason = value.get("reason").and_then(Value::as_str).unwrap_or("").to_string();
+        let outcome = match value.get("status")?.as_str()? {
+            "up" => Outcome::Up {
+                rtt: value.get("rtt_ms").and_then(Value::as_f64),
+                ttl: value.get("ttl").and_then(Value::as_u64).and_then(|ttl| u8::try_from(ttl).ok()),
+                duplicates: value.get("duplicates").and_then(Value::as_u64).unwrap_or(0) as u32,
+                replied_from: value.get("replied_from").and_then(Value::as_str).and_then(|source| source.parse().ok()),
+            },
+            //the router that answered isn't kept in the output, only the kind of answer
+            "down" => Outcome::Down(match reason.as_str() {
+                "unreachable" => DownReason::Unreachable { from: "unknown".to_string() },
+                "admin-prohibited" => DownReason::Prohibited { from: "unknown".to_string() },
+                "send error" => DownReason::SendError(reason),
+                _ => DownReason::Timeout,
+            }),
+            _ => Outcome::LocalError(reason),
+        };
+
+        let mut ports = Vec::new();
+        for port in value.get("ports").and_then(Value::as_array).unwrap_or(&[]) {
+            ports.push(PortResult {
+                port: u16::try_from(port.get("port")?.as_u64()?).ok()?,
+                protocol: if port.get("protocol")?.as_str()? == "udp" { "udp" } else { "tcp" },
+                state: PortState::from_label(port.get("state")?.as_str()?)?,
+                service: port.get("service").and_then(Value::as_str).map(str::to_string),
+                info: port.get("info").and_then(Value::as_str).map(str::to_string),
+            });
+        }
+        let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        Some(HostResult {
+            address,
+            outcome,
+            hostname: text("hostname"),
+            mac: text("mac"),
+            vendor: text("vendor"),
+            roles: value
+                .get("roles")
+                .and_then(Value::as_array)
+                .unwrap_or(&[])
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            ports,
+            found_by: value
+                .get("found_by")
+                .and_then(Value::as_array)
+                .unwrap_or(&[])
+                .iter()
+                .filter_map(|method| FOUND_BY.iter().find(|known| Some(**known) == method.as_str()).copied())
+                .collect(),
+            seen_by: text("seen_by"),
+            asn: text("asn"),
+            source: text("source"),
+        })
+    }
+}
+
+impl PortState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::OpenFiltered => "open|filtered",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<PortState> {
+        match label {
+            "open" => Some(PortState::Open),
+            "closed" => Some(PortState::Closed),
+            "filtered" => Some(PortState::Filtered),
+            "open|filtered" => Some(PortState::OpenFiltered),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//importing targets from the files that record the machines this one knows (--targets-from), so a sweep
+//answers which of the machines ever connected to are still alive
+//
+//  known-hosts:[FILE]              the hosts of an SSH known_hosts file (~/.ssh/known_hosts by default),
+//                                  names looked up with the system resolver, hashed entries
+//                                  (HashKnownHosts yes) can't be read back and are only counted
+//  hosts:[FILE]                    the IPv4 addresses of a hosts file (/etc/hosts by default), each with
+//                                  its first name
+//
+//loopback, unspecified and broadcast addresses are left out, they aren't machines to sweep
+
+use std::env;
+use std::fs;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+
+//function to read the hosts of a known_hosts file, each with the name it was connected to by
+//e.g. "web01.corp.local,10.0.0.5 ssh-ed25519 AAAA..." or "[git.corp.local]:2222 ssh-rsa AAAA..."
+pub fn known_hosts(path: &str, notes: &mut Vec<String>) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let path = if path.is_empty() { home_file(".ssh/known_hosts")? } else { path.to_string() };
+    let contents = fs::read_to_string(&path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let mut entries = Vec::new();
+    let (mut hashed, mut unresolved) = (0, Vec::new());
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut words = line.split_whitespace();
+        //@cert-authority lines name a key for many hosts by pattern, @revoked ones a key not to trust
+        let hosts = match words.next() {
+            Some(marker) if marker.starts_with('@') => continue,
+            Some(hosts) => hosts,
+            None => continue,
+        };
+        let mut names = Vec::new();
+        let mut addresses = Vec::new();
+        for host in hosts.split(',') {
+            if host.starts_with("|1|") {
+                hashed += 1;
+                continue;
+            }
+            //a non-standard port is written [host]:port, patterns and negations aren't hosts
+            let host = host.strip_prefix('[').and_then(|host| host.split(']').next()).unwrap_or(host);
+            if host.contains(['*', '?', '!']) {
+                continue;
+            }
+            match host.parse::<Ipv4Addr>() {
+                Ok(address) => addresses.push(address),
+                Err(_) if host.contains(':') => {}
+                Err(_) => names.push(host),
+            }
+        }
+        //a line that has the address already needs no lookup, its name is the label
+        if !addresses.is_empty() {
+            let name = names.first().map(|name| name.to_string());
+            entries.extend(addresses.into_iter().map(|address| (address, name.clone().unwrap_or_else(|| address.to_string()))));
+            continue;
+        }
+        for name in names {
+            match resolve(name) {
+                Some(address) => entries.push((address, name.to_string())),
+                None => unresolved.push(name.to_string()),
+            }
+        }
+    }
+    entries.retain(|(address, _)| sweepable(*address));
+    if hashed > 0 {
+        notes.push(format!("{} hashed entries of {} can't be read back, only hosts written in the clear were swept", hashed, path));
+    }
+    if !unresolved.is_empty() {
+        notes.push(format!("{} names of {} don't resolve any more: {}", unresolved.len(), path, unresolved.join(", ")));
+    }
+    Ok(entries)
+}
+
+//function to read the IPv4 addresses of a hosts file with their first name, e.g. "10.0.0.5 web01.corp.local web01"
+pub fn hosts(path: &str) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let path = if path.is_empty() { "/etc/hosts" } else { path };
+    let contents = fs::read_to_string(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let mut entries = Vec::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let (Some(address), Some(name)) = (words.next().and_then(|address| address.parse::<Ipv4Addr>().ok()), words.next()) else {
+            continue;
+        };
+        if sweepable(address) {
+            entries.push((address, name.to_string()));
+        }
+    }
+    Ok(entries)
+}
+
+fn sweepable(address: Ipv4Addr) -> bool {
+    !address.is_loopback() && !address.is_unspecified() && !address.is_broadcast()
+}
+
+fn resolve(name: &str) -> Option<Ipv4Addr> {
+    (name, 22).to_socket_addrs().ok()?.find_map(|address| match address {
+        SocketAddr::V4(v4) => Some(*v4.ip()),
+        SocketAddr::V6(_) => None,
+    })
+}
+
+fn home_file(name: &str) -> Result<String, String> {
+    env::var_os("HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(|home| PathBuf::from(home).join(name).to_string_lossy().into_owned())
+        .ok_or_else(|| format!("HOME isn't set, give the path of the file, e.g. known-hosts:/home/me/{}", name))
+}
@@ -0,0 +1,795 @@
+//lightweight identification of services on open ports, grouped into opt-in probe profiles
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use crate::host::{HostResult, PortState};
+
+//how long to wait for a service to answer an identification request
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+//pause before every request of the OT profile, plant controllers can be fragile and shouldn't see bursts
+const OT_REQUEST_DELAY: Duration = Duration::from_millis(250);
+
+//the probe profiles --profile accepts
+pub const PROFILES: [&str; 6] = ["windows", "printers", "ot", "ad", "infra", "web"];
+
+//the roles the infra profile tags, for hosts whose service on the UDP port identified itself
+const INFRA_ROLES: [(u16, &str); 2] = [(53, "dns-server"), (123, "ntp-server")];
+
+//function to list the TCP ports a profile needs probed
+pub fn profile_tcp_ports(profile: &str) -> &'static [u16] {
+    match profile {
+        "windows" => &[445, 3389, 5900],
+        "printers" => &[631, 9100],
+        "ot" => &[502, 44818],
+        "ad" => &[88, 389, 445],
+        "web" => &crate::web::HTTP_PORTS,
+        _ => &[],
+    }
+}
+
+//function to list the UDP ports a profile needs probed
+pub fn profile_udp_ports(profile: &str) -> &'static [u16] {
+    match profile {
+        "ot" => &[47808],
+        "infra" => &[53, 123],
+        _ => &[],
+    }
+}
+
+//function to fill in the info of every open port a selected profile knows how to identify
+pub fn identify(host: &mut HostResult, profiles: &[String]) {
+    let address = host.address;
+    for port in host.ports.iter_mut().filter(|port| port.state == PortState::Open) {
+        let info = profiles.iter().find_map(|profile| match (profile.as_str(), port.protocol, port.port) {
+            ("windows", "tcp", 445) => identify_smb(address),
+            ("windows", "tcp", 3389) => identify_rdp(address),
+            ("windows", "tcp", 5900) => identify_vnc(address),
+            ("printers", "tcp", 631) => identify_ipp(address),
+            ("printers", "tcp", 9100) => identify_pjl(address),
+            ("ot", "tcp", 502) => identify_modbus(address),
+            ("ot", "tcp", 44818) => identify_enip(address),
+            ("ot", "udp", 47808) => identify_bacnet(address),
+            ("ad", "tcp", 445) => identify_smb(address),
+            ("infra", "udp", 53) => identify_dns(address),
+            ("infra", "udp", 123) => identify_ntp(address),
+            _ => None,
+        });
+        if info.is_some() {
+            port.info = info;
+        }
+    }
+
+    if profiles.iter().any(|profile| profile == "infra") {
+        for (port, role) in INFRA_ROLES {
+            let answered = host.ports.iter().any(|result| result.protocol == "udp" && result.port == port && result.info.is_some());
+            if answered && !host.roles.iter().any(|existing| existing == role) {
+                host.roles.push(role.to_string());
+            }
+        }
+    }
+}
+
+fn connect(address: Ipv4Addr, port: u16) -> Option<TcpStream> {
+    let stream = TcpStream::connect_timeout(&SocketAddr::from((address, port)), READ_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(READ_TIMEOUT)).ok()?;
+    Some(stream)
+}
+
+//VNC servers greet with their protocol version, e.g. "RFB 003.008\n"
+fn identify_vnc(address: Ipv4Addr) -> Option<String> {
+    let mut stream = connect(address, 5900)?;
+    let mut greeting = [0u8; 12];
+    stream.read_exact(&mut greeting).ok()?;
+
+    let greeting = String::from_utf8_lossy(&greeting);
+    let version = greeting.strip_prefix("RFB ")?.trim();
+    let (major, minor) = version.split_once('.')?;
+    Some(format!("RFB {}.{}", major.trim_start_matches('0'), minor.trim_start_matches('0')))
+}
+
+//RDP answers an X.224 connection request with the security protocol it picked from the ones offered
+fn identify_rdp(address: Ipv4Addr) -> Option<String> {
+    let mut stream = connect(address, 3389)?;
+
+    //TPKT header, X.224 connection request, RDP negotiation request offering TLS, CredSSP and CredSSP with early auth
+    let request = [
+        0x03, 0x00, 0x00, 0x13, 0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x0b, 0x00, 0x00, 0x00,
+    ];
+    stream.write_all(&request).ok()?;
+
+    let mut response = [0u8; 19];
+    let len = stream.read(&mut response).ok()?;
+    if len < 11 || response[0] != 0x03 || response[5] != 0xd0 {
+        return None;
+    }
+    //old servers ignore the negotiation request and only speak standard RDP security
+    if len < 19 {
+        return Some("RDP security: standard RDP".to_string());
+    }
+
+    let value = u32::from_le_bytes([response[15], response[16], response[17], response[18]]);
+    match response[11] {
+        0x02 => {
+            let protocol = match value {
+                0 => "standard RDP".to_string(),
+                1 => "TLS".to_string(),
+                2 => "CredSSP (NLA)".to_string(),
+                8 => "CredSSP with early user auth (NLA)".to_string(),
+                other => format!("unknown protocol {}", other),
+            };
+            Some(format!("RDP security: {}", protocol))
+        }
+        0x03 => Some(format!("RDP negotiation refused (code {})", value)),
+        _ => None,
+    }
+}
+
+//IPP Get-Printer-Attributes for the make and model, the same request every driverless print client sends
+fn identify_ipp(address: Ipv4Addr) -> Option<String> {
+    let mut stream = connect(address, 631)?;
+
+    let mut ipp = vec![0x02, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x01]; //IPP 2.0, Get-Printer-Attributes, request 1
+    ipp.push(0x01); //operation attributes
+    ipp_attribute(&mut ipp, 0x47, "attributes-charset", "utf-8");
+    ipp_attribute(&mut ipp, 0x48, "attributes-natural-language", "en");
+    ipp_attribute(&mut ipp, 0x45, "printer-uri", &format!("ipp://{}:631/ipp/print", address));
+    ipp_attribute(&mut ipp, 0x44, "requested-attributes", "printer-make-and-model");
+    ipp_attribute(&mut ipp, 0x44, "", "printer-name");
+    ipp.push(0x03); //end of attributes
+
+    let request = format!(
+        "POST /ipp/print HTTP/1.1\r\nHost: {}:631\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        address,
+        ipp.len()
+    );
+    stream.write_all(request.as_bytes()).ok()?;
+    stream.write_all(&ipp).ok()?;
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    let body = http_body(&response)?;
+    let attributes = ipp_attributes(&body);
+
+    let model = attributes.iter().find(|(name, _)| name == "printer-make-and-model").map(|(_, value)| value);
+    let name = attributes.iter().find(|(name, _)| name == "printer-name").map(|(_, value)| value);
+    match (model, name) {
+        (Some(model), Some(name)) => Some(format!("IPP printer {}, name {}", model, name)),
+        (Some(model), None) => Some(format!("IPP printer {}", model)),
+        (None, Some(name)) => Some(format!("IPP printer, name {}", name)),
+        (None, None) => None,
+    }
+}
+
+fn ipp_attribute(ipp: &mut Vec<u8>, tag: u8, name: &str, value: &str) {
+    ipp.push(tag);
+    ipp.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    ipp.extend_from_slice(name.as_bytes());
+    ipp.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    ipp.extend_from_slice(value.as_bytes());
+}
+
+//function to read the (name, value) pairs of the text attributes in an IPP response
+fn ipp_attributes(body: &[u8]) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut pos = 8;
+    let mut name = String::new();
+
+    while let Some(&tag) = body.get(pos) {
+        pos += 1;
+        //delimiter tags start a new attribute group, 0x03 ends them all
+        if tag == 0x03 {
+            break;
+        }
+        if tag < 0x10 {
+            continue;
+        }
+
+        let Some(name_len) = body.get(pos..pos + 2).map(|len| u16::from_be_bytes([len[0], len[1]]) as usize) else {
+            break;
+        };
+        pos += 2;
+        //an empty name is another value of the previous attribute
+        if name_len > 0 {
+            name = String::from_utf8_lossy(body.get(pos..pos + name_len).unwrap_or_default()).to_string();
+        }
+        pos += name_len;
+
+        let Some(value_len) = body.get(pos..pos + 2).map(|len| u16::from_be_bytes([len[0], len[1]]) as usize) else {
+            break;
+        };
+        pos += 2;
+        let Some(value) = body.get(pos..pos + value_len) else {
+            break;
+        };
+        pos += value_len;
+
+        //text, name, keyword and uri values are all plain strings
+        if (0x41..=0x49).contains(&tag) {
+            attributes.push((name.clone(), String::from_utf8_lossy(value).to_string()));
+        }
+    }
+    attributes
+}
+
+//function to get the body of an HTTP response, undoing chunked transfer encoding
+fn http_body(response: &[u8]) -> Option<Vec<u8>> {
+    let header_end = response.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let headers = String::from_utf8_lossy(&response[..header_end]).to_ascii_lowercase();
+    let body = &response[header_end + 4..];
+    if !headers.contains("transfer-encoding: chunked") {
+        return Some(body.to_vec());
+    }
+
+    let mut decoded = Vec::new();
+    let mut pos = 0;
+    loop {
+        let line_end = pos + body.get(pos..)?.windows(2).position(|window| window == b"\r\n")?;
+        let size_line = String::from_utf8_lossy(&body[pos..line_end]);
+        let size = usize::from_str_radix(size_line.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            return Some(decoded);
+        }
+        //a size near usize::MAX from a hostile server would wrap around instead of running off the end
+        let chunk_start = line_end + 2;
+        let chunk_end = chunk_start.checked_add(size)?;
+        decoded.extend_from_slice(body.get(chunk_start..chunk_end)?);
+        pos = chunk_end.checked_add(2)?;
+    }
+}
+
+//raw port printers answer the PJL INFO ID query with their model name
+fn identify_pjl(address: Ipv4Addr) -> Option<String> {
+    let mut stream = connect(address, 9100)?;
+    stream.write_all(b"\x1b%-12345X@PJL INFO ID\r\n\x1b%-12345X\r\n").ok()?;
+
+    let mut buf = [0u8; 512];
+    let len = stream.read(&mut buf).ok()?;
+    let reply = String::from_utf8_lossy(&buf[..len]);
+
+    //the reply echoes the command, the model is on the next non-empty line, usually quoted
+    let model = reply
+        .lines()
+        .skip_while(|line| !line.contains("INFO ID"))
+        .skip(1)
+        .map(|line| line.trim().trim_matches('"').trim_matches('\x0c'))
+        .find(|line| !line.is_empty())?;
+    Some(format!("PJL printer {}", model))
+}
+
+//a DNS response to the version.bind query, whether it resolves for us and how it answered
+fn identify_dns(address: Ipv4Addr) -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(SocketAddr::from((address, 53))).ok()?;
+    socket.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+    let query = crate::udp::payload(53);
+    socket.send(&query).ok()?;
+    let mut buf = [0u8; 1500];
+    let len = socket.recv(&mut buf).ok()?;
+
+    //same id, QR set, the recursion available bit and the response code are in the flags
+    if len < 12 || buf[0..2] != query[0..2] || buf[2] & 0x80 == 0 {
+        return None;
+    }
+    let recursion = if buf[3] & 0x80 != 0 { "recursive" } else { "non-recursive" };
+    let rcode = match buf[3] & 0x0f {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        code => format!("rcode {}", code),
+    };
+    Some(format!("DNS {}, {}", recursion, rcode))
+}
+
+//an NTP server answers a mode 3 client request in mode 4 with its stratum and reference
+fn identify_ntp(address: Ipv4Addr) -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(SocketAddr::from((address, 123))).ok()?;
+    socket.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+    socket.send(&crate::udp::payload(123)).ok()?;
+    let mut buf = [0u8; 1500];
+    let len = socket.recv(&mut buf).ok()?;
+    if len < 48 || buf[0] & 0x07 != 4 {
+        return None;
+    }
+
+    //stratum 1 servers name their clock in ASCII, the others give the address of their upstream
+    let stratum = buf[1];
+    let reference = &buf[12..16];
+    let info = match stratum {
+        0 | 16.. => "NTP unsynchronised".to_string(),
+        1 => format!("NTP stratum 1, reference {}", String::from_utf8_lossy(reference).trim_end_matches('\0')),
+        _ => format!(
+            "NTP stratum {}, reference {}",
+            stratum,
+            Ipv4Addr::new(reference[0], reference[1], reference[2], reference[3])
+        ),
+    };
+    Some(info)
+}
+
+//the OT identification below only ever sends read-only requests defined for exactly this purpose
+//(Modbus Read Device Identification, EtherNet/IP ListIdentity, BACnet Who-Is and ReadProperty),
+//one at a time with a pause before each, never writes or control commands
+
+//Modbus function 43/14 Read Device Identification, basic objects (vendor, product code, revision)
+fn identify_modbus(address: Ipv4Addr) -> Option<String> {
+    thread::sleep(OT_REQUEST_DELAY);
+    let mut stream = connect(address, 502)?;
+
+    //MBAP header (transaction 1, protocol 0, length 5, unit 1) then the request
+    let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x01, 0x2b, 0x0e, 0x01, 0x00];
+    stream.write_all(&request).ok()?;
+
+    let mut response = [0u8; 260];
+    let len = stream.read(&mut response).ok()?;
+    let response = &response[..len];
+    if len < 9 {
+        return None;
+    }
+    //an exception reply still proves a Modbus device is there
+    if response[7] == 0xab {
+        return Some("Modbus device (no device identification)".to_string());
+    }
+    if response[7] != 0x2b || len < 14 {
+        return None;
+    }
+
+    let mut objects = Vec::new();
+    let mut pos = 14;
+    for _ in 0..response[13] {
+        let (Some(&id), Some(&object_len)) = (response.get(pos), response.get(pos + 1)) else {
+            break;
+        };
+        let Some(value) = response.get(pos + 2..pos + 2 + object_len as usize) else {
+            break;
+        };
+        objects.push((id, String::from_utf8_lossy(value).trim().to_string()));
+        pos += 2 + object_len as usize;
+    }
+
+    let object = |id: u8| objects.iter().find(|(object_id, _)| *object_id == id).map(|(_, value)| value.as_str());
+    match (object(0), object(1), object(2)) {
+        (Some(vendor), Some(product), Some(revision)) => Some(format!("Modbus {} {} rev {}", vendor, product, revision)),
+        (Some(vendor), Some(product), None) => Some(format!("Modbus {} {}", vendor, product)),
+        (Some(vendor), None, _) => Some(format!("Modbus {}", vendor)),
+        _ => Some("Modbus device".to_string()),
+    }
+}
+
+//EtherNet/IP ListIdentity, the same broadcast-safe request Rockwell's own tools use to browse a network
+fn identify_enip(address: Ipv4Addr) -> Option<String> {
+    thread::sleep(OT_REQUEST_DELAY);
+    let mut stream = connect(address, 44818)?;
+
+    //encapsulation header with command 0x63 and no data
+    let mut request = [0u8; 24];
+    request[0] = 0x63;
+    stream.write_all(&request).ok()?;
+
+    let mut response = [0u8; 600];
+    let len = stream.read(&mut response).ok()?;
+    let response = &response[..len];
+    if len < 24 + 2 + 4 + 33 || response[0] != 0x63 {
+        return None;
+    }
+
+    //the identity item starts after the header, item count, item type and item length
+    let item = &response[30..];
+    let vendor = u16::from_le_bytes([item[18], item[19]]);
+    let product_code = u16::from_le_bytes([item[22], item[23]]);
+    let (major, minor) = (item[24], item[25]);
+    let name_len = item[32] as usize;
+    let name = item.get(33..33 + name_len).map(|name| String::from_utf8_lossy(name).to_string()).unwrap_or_default();
+    Some(format!("EtherNet/IP {} (vendor {}, product {}, rev {}.{})", name, vendor, product_code, major, minor))
+}
+
+//BACnet unicast Who-Is for the device instance and vendor, then ReadProperty for the vendor and model names
+fn identify_bacnet(address: Ipv4Addr) -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(SocketAddr::from((address, 47808))).ok()?;
+    socket.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+
+    thread::sleep(OT_REQUEST_DELAY);
+    socket.send(&crate::udp::payload(47808)).ok()?;
+    let mut buf = [0u8; 1500];
+    let len = socket.recv(&mut buf).ok()?;
+    let apdu = bacnet_apdu(&buf[..len])?;
+
+    //I-Am: unconfirmed request, service 0, then the device object identifier
+    if apdu.len() < 7 || apdu[0] != 0x10 || apdu[1] != 0x00 || apdu[2] != 0xc4 {
+        return Some("BACnet device".to_string());
+    }
+    let object_id = u32::from_be_bytes([apdu[3], apdu[4], apdu[5], apdu[6]]);
+    let instance = object_id & 0x3f_ffff;
+
+    let mut info = format!("BACnet device {}", instance);
+    for (invoke_id, property) in [(1u8, 121u8), (2, 70)] {
+        thread::sleep(OT_REQUEST_DELAY);
+        let mut request = vec![0x81, 0x0a, 0x00, 0x11, 0x01, 0x04, 0x00, 0x05, invoke_id, 0x0c, 0x0c];
+        request.extend_from_slice(&object_id.to_be_bytes());
+        request.extend_from_slice(&[0x19, property]);
+        if socket.send(&request).is_err() {
+            break;
+        }
+        let Ok(len) = socket.recv(&mut buf) else {
+            break;
+        };
+        if let Some(value) = bacnet_apdu(&buf[..len]).and_then(bacnet_read_property_string) {
+            info.push_str(&format!(", {}", value));
+        }
+    }
+    Some(info)
+}
+
+//function to step over the BVLC and NPDU headers of a BACnet/IP packet to get to the APDU
+fn bacnet_apdu(packet: &[u8]) -> Option<&[u8]> {
+    if packet.len() < 6 || packet[0] != 0x81 || packet[4] != 0x01 {
+        return None;
+    }
+    let control = packet[5];
+    let mut pos = 6;
+    //destination network, address length, address
+    if control & 0x20 != 0 {
+        pos += 3 + *packet.get(pos + 2)? as usize;
+    }
+    //source network, address length, address
+    if control & 0x08 != 0 {
+        pos += 3 + *packet.get(pos + 2)? as usize;
+    }
+    //hop count follows a destination
+    if control & 0x20 != 0 {
+        pos += 1;
+    }
+    packet.get(pos..)
+}
+
+//function to pull the character string value out of a ReadProperty ComplexACK
+fn bacnet_read_property_string(apdu: &[u8]) -> Option<String> {
+    if apdu.len() < 3 || apdu[0] != 0x30 || apdu[2] != 0x0c {
+        return None;
+    }
+    let open = apdu.iter().position(|&byte| byte == 0x3e)?;
+    let tag = *apdu.get(open + 1)?;
+    if tag >> 4 != 7 {
+        return None;
+    }
+    //lengths over 4 are in the next byte
+    let (len, start) = match tag & 0x07 {
+        5 => (*apdu.get(open + 2)? as usize, open + 3),
+        len => (len as usize, open + 2),
+    };
+    //the first byte of the string is its character set, 0 is UTF-8
+    let value = apdu.get(start + 1..start + len)?;
+    Some(String::from_utf8_lossy(value).to_string())
+}
+
+//SMB2 negotiate for the dialect, then an NTLM session setup whose challenge carries the host names
+fn identify_smb(address: Ipv4Addr) -> Option<String> {
+    let mut stream = connect(address, 445)?;
+
+    let negotiate = smb_message(smb2_header(0, 0), smb2_negotiate_body());
+    stream.write_all(&negotiate).ok()?;
+    let response = read_smb_message(&mut stream)?;
+    if response.len() < 70 || &response[0..4] != b"\xfeSMB" {
+        return None;
+    }
+    let dialect = u16::from_le_bytes([response[68], response[69]]);
+    let mut info = format!("SMB {}", dialect_name(dialect));
+
+    //the NTLM challenge comes back even without credentials and names the machine
+    let session_setup = smb_message(smb2_header(1, 1), smb2_session_setup_body());
+    if stream.write_all(&session_setup).is_ok() {
+        if let Some(response) = read_smb_message(&mut stream) {
+            let names = ntlm_target_names(&response);
+            if let Some(netbios) = names.netbios_name {
+                info.push_str(&format!(", name {}", netbios));
+            }
+            if let Some(domain) = names.netbios_domain {
+                info.push_str(&format!(", domain {}", domain));
+            }
+            if let Some(dns) = names.dns_name {
+                info.push_str(&format!(", dns {}", dns));
+            }
+        }
+    }
+
+    Some(info)
+}
+
+fn dialect_name(dialect: u16) -> String {
+    match dialect {
+        0x0202 => "2.0.2".to_string(),
+        0x0210 => "2.1".to_string(),
+        0x0300 => "3.0".to_string(),
+        0x0302 => "3.0.2".to_string(),
+        0x0311 => "3.1.1".to_string(),
+        other => format!("dialect 0x{:04x}", other),
+    }
+}
+
+//function to wrap an SMB2 message in the 4 byte direct TCP transport header
+fn smb_message(header: Vec<u8>, body: Vec<u8>) -> Vec<u8> {
+    let len = header.len() + body.len();
+    let mut message = vec![0, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+    message.extend(header);
+    message.extend(body);
+    message
+}
+
+fn read_smb_message(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut transport = [0u8; 4];
+    stream.read_exact(&mut transport).ok()?;
+    let len = ((transport[1] as usize) << 16) | ((transport[2] as usize) << 8) | transport[3] as usize;
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message).ok()?;
+    Some(message)
+}
+
+fn smb2_header(command: u16, message_id: u64) -> Vec<u8> {
+    let mut header = Vec::with_capacity(64);
+    header.extend_from_slice(b"\xfeSMB");
+    header.extend_from_slice(&64u16.to_le_bytes()); //structure size
+    header.extend_from_slice(&[0; 2]); //credit charge
+    header.extend_from_slice(&[0; 4]); //status
+    header.extend_from_slice(&command.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); //credits requested
+    header.extend_from_slice(&[0; 4]); //flags
+    header.extend_from_slice(&[0; 4]); //next command
+    header.extend_from_slice(&message_id.to_le_bytes());
+    header.extend_from_slice(&[0; 4]); //process id
+    header.extend_from_slice(&[0; 4]); //tree id
+    header.extend_from_slice(&[0; 8]); //session id
+    header.extend_from_slice(&[0; 16]); //signature
+    header
+}
+
+//negotiate request offering every SMB2/3 dialect, 3.1.1 needs a preauth integrity context to be accepted
+fn smb2_negotiate_body() -> Vec<u8> {
+    let dialects: [u16; 5] = [0x0202, 0x0210, 0x0300, 0x0302, 0x0311];
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&36u16.to_le_bytes()); //structure size
+    body.extend_from_slice(&(dialects.len() as u16).to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes()); //signing enabled
+    body.extend_from_slice(&[0; 2]); //reserved
+    body.extend_from_slice(&[0; 4]); //capabilities
+    body.extend_from_slice(b"host_disco\0\0\0\0\0\0"); //client guid
+
+    //the context offset is from the start of the SMB2 header and has to be 8 byte aligned
+    let dialects_end = 64 + 36 + dialects.len() * 2;
+    let context_offset = dialects_end.div_ceil(8) * 8;
+    body.extend_from_slice(&(context_offset as u32).to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes()); //one negotiate context
+    body.extend_from_slice(&[0; 2]); //reserved
+    for dialect in dialects {
+        body.extend_from_slice(&dialect.to_le_bytes());
+    }
+    body.resize(context_offset - 64, 0);
+
+    //SMB2_PREAUTH_INTEGRITY_CAPABILITIES with SHA-512 and a 32 byte salt
+    body.extend_from_slice(&1u16.to_le_bytes()); //context type
+    body.extend_from_slice(&38u16.to_le_bytes()); //data length
+    body.extend_from_slice(&[0; 4]); //reserved
+    body.extend_from_slice(&1u16.to_le_bytes()); //hash algorithm count
+    body.extend_from_slice(&32u16.to_le_bytes()); //salt length
+    body.extend_from_slice(&1u16.to_le_bytes()); //SHA-512
+    body.extend_from_slice(&[0x5a; 32]); //salt
+    body
+}
+
+//session setup carrying a bare NTLMSSP negotiate message
+fn smb2_session_setup_body() -> Vec<u8> {
+    let mut ntlm = Vec::new();
+    ntlm.extend_from_slice(b"NTLMSSP\0");
+    ntlm.extend_from_slice(&1u32.to_le_bytes()); //negotiate message
+    ntlm.extend_from_slice(&0xe008_8297u32.to_le_bytes()); //unicode, request target, NTLM, always sign, target info, 128/56 bit
+    ntlm.extend_from_slice(&[0; 16]); //empty domain and workstation
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&25u16.to_le_bytes()); //structure size
+    body.push(0); //flags
+    body.push(1); //signing enabled
+    body.extend_from_slice(&[0; 4]); //capabilities
+    body.extend_from_slice(&[0; 4]); //channel
+    body.extend_from_slice(&((64 + 24) as u16).to_le_bytes()); //security buffer offset
+    body.extend_from_slice(&(ntlm.len() as u16).to_le_bytes());
+    body.extend_from_slice(&[0; 8]); //previous session id
+    body.extend(ntlm);
+    body
+}
+
+#[derive(Default)]
+struct NtlmNames {
+    netbios_name: Option<String>,
+    netbios_domain: Option<String>,
+    dns_name: Option<String>,
+}
+
+//function to find the NTLM challenge in a response and read the names out of its target info
+fn ntlm_target_names(response: &[u8]) -> NtlmNames {
+    let mut names = NtlmNames::default();
+    let Some(start) = response.windows(8).position(|window| window == b"NTLMSSP\0") else {
+        return names;
+    };
+    let challenge = &response[start..];
+    if challenge.len() < 48 || challenge[8] != 2 {
+        return names;
+    }
+
+    let info_len = u16::from_le_bytes([challenge[40], challenge[41]]) as usize;
+    let info_offset = u32::from_le_bytes([challenge[44], challenge[45], challenge[46], challenge[47]]) as usize;
+    let Some(target_info) = challenge.get(info_offset..info_offset + info_len) else {
+        return names;
+    };
+
+    //target info is a list of (id, length, UTF-16 value) pairs ending with id 0
+    let mut pos = 0;
+    while pos + 4 <= target_info.len() {
+        let id = u16::from_le_bytes([target_info[pos], target_info[pos + 1]]);
+        let len = u16::from_le_bytes([target_info[pos + 2], target_info[pos + 3]]) as usize;
+        if id == 0 || pos + 4 + len > target_info.len() {
+            break;
+        }
+        let value = utf16_le(&target_info[pos + 4..pos + 4 + len]);
+        match id {
+            1 => names.netbios_name = Some(value),
+            2 => names.netbios_domain = Some(value),
+            3 => names.dns_name = Some(value),
+            _ => {}
+        }
+        pos += 4 + len;
+    }
+    names
+}
+
+fn utf16_le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipp_attribute(body: &mut Vec<u8>, tag: u8, name: &str, value: &[u8]) {
+        body.push(tag);
+        body.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        body.extend_from_slice(name.as_bytes());
+        body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        body.extend_from_slice(value);
+    }
+
+    #[test]
+    fn ipp_attributes_keep_the_text_values() {
+        let mut body = vec![2, 0, 0, 0, 0, 0, 0, 1, 0x01];
+        ipp_attribute(&mut body, 0x47, "attributes-charset", b"utf-8");
+        body.push(0x04);
+        ipp_attribute(&mut body, 0x41, "printer-make-and-model", b"HP LaserJet");
+        ipp_attribute(&mut body, 0x21, "printer-state", &[0, 0, 0, 3]);
+        ipp_attribute(&mut body, 0x45, "printer-uri-supported", b"ipp://a/");
+        //an empty name is another value of the attribute before
+        ipp_attribute(&mut body, 0x45, "", b"ipps://a/");
+        body.push(0x03);
+        ipp_attribute(&mut body, 0x41, "after-the-end", b"ignored");
+        assert_eq!(
+            ipp_attributes(&body),
+            [
+                ("attributes-charset", "utf-8"),
+                ("printer-make-and-model", "HP LaserJet"),
+                ("printer-uri-supported", "ipp://a/"),
+                ("printer-uri-supported", "ipps://a/"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn ipp_attributes_stop_at_truncation() {
+        let mut body = vec![2, 0, 0, 0, 0, 0, 0, 1, 0x04];
+        ipp_attribute(&mut body, 0x41, "printer-info", b"Office");
+        for length in 0..body.len() {
+            assert!(ipp_attributes(&body[..length]).is_empty(), "truncated to {} bytes", length);
+        }
+        //a name longer than what is left
+        assert!(ipp_attributes(&[2, 0, 0, 0, 0, 0, 0, 1, 0x41, 0xff, 0xff, b'a']).is_empty());
+    }
+
+    #[test]
+    fn http_body_follows_the_headers() {
+        assert_eq!(http_body(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi").unwrap(), b"hi");
+        assert!(http_body(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n").is_none());
+    }
+
+    #[test]
+    fn http_body_undoes_chunking() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4;name=x\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        assert_eq!(http_body(response).unwrap(), b"Wikipedia");
+    }
+
+    #[test]
+    fn http_body_refuses_bad_chunks() {
+        let chunked = |chunks: &str| http_body(format!("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}", chunks).as_bytes());
+        assert!(chunked("4\r\nWi").is_none());
+        assert!(chunked("zz\r\nWiki\r\n0\r\n\r\n").is_none());
+        assert!(chunked("4\r\nWiki\r\n").is_none());
+        //sizes that would wrap around the end of memory
+        assert!(chunked("ffffffffffffffff\r\nWiki\r\n0\r\n\r\n").is_none());
+        assert!(chunked("fffffffffffffff0\r\nWiki\r\n0\r\n\r\n").is_none());
+    }
+
+    //an NTLM challenge message with the target info pairs at the end
+    fn ntlm_challenge(pairs: &[(u16, &str)]) -> Vec<u8> {
+        let mut info = Vec::new();
+        for (id, value) in pairs {
+            let value: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            info.extend_from_slice(&id.to_le_bytes());
+            info.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            info.extend_from_slice(&value);
+        }
+        info.extend_from_slice(&[0, 0, 0, 0]);
+        let mut challenge = b"NTLMSSP\0".to_vec();
+        challenge.extend_from_slice(&2u32.to_le_bytes());
+        challenge.resize(40, 0);
+        challenge.extend_from_slice(&(info.len() as u16).to_le_bytes());
+        challenge.extend_from_slice(&(info.len() as u16).to_le_bytes());
+        challenge.extend_from_slice(&48u32.to_le_bytes());
+        challenge.extend_from_slice(&info);
+        challenge
+    }
+
+    #[test]
+    fn ntlm_target_names_are_read_from_the_challenge() {
+        let mut response = b"SMB2 header and such".to_vec();
+        response.extend(ntlm_challenge(&[(2, "CORP"), (1, "FILES01"), (7, "ignored"), (3, "files01.corp.local")]));
+        let names = ntlm_target_names(&response);
+        assert_eq!(names.netbios_name.as_deref(), Some("FILES01"));
+        assert_eq!(names.netbios_domain.as_deref(), Some("CORP"));
+        assert_eq!(names.dns_name.as_deref(), Some("files01.corp.local"));
+    }
+
+    #[test]
+    fn ntlm_target_names_stay_in_bounds() {
+        let challenge = ntlm_challenge(&[(1, "FILES01")]);
+        for length in 0..challenge.len() {
+            assert!(ntlm_target_names(&challenge[..length]).netbios_name.is_none(), "truncated to {} bytes", length);
+        }
+        //an offset far past the end
+        let mut challenge = challenge;
+        challenge[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ntlm_target_names(&challenge).netbios_name.is_none());
+        //a pair claiming more than there is
+        let mut challenge = ntlm_challenge(&[(1, "FILES01")]);
+        challenge[50] = 0xff;
+        assert!(ntlm_target_names(&challenge).netbios_name.is_none());
+    }
+
+    #[test]
+    fn bacnet_apdu_skips_the_headers() {
+        let apdu = [0x30, 0x01, 0x0c];
+        let mut packet = vec![0x81, 0x0a, 0x00, 0x09, 0x01, 0x00];
+        packet.extend_from_slice(&apdu);
+        assert_eq!(bacnet_apdu(&packet).unwrap(), apdu);
+        //a destination of network 5 and a 2 byte address, a source of network 7 and a 1 byte address, then the hop count
+        let mut routed = vec![0x81, 0x0a, 0x00, 0x00, 0x01, 0x28, 0, 5, 2, 0xaa, 0xbb, 0, 7, 1, 0xcc, 255];
+        routed.extend_from_slice(&apdu);
+        assert_eq!(bacnet_apdu(&routed).unwrap(), apdu);
+    }
+
+    #[test]
+    fn bacnet_apdu_refuses_other_packets() {
+        assert!(bacnet_apdu(&[0x81, 0x0a, 0x00]).is_none());
+        assert!(bacnet_apdu(&[0x82, 0x0a, 0x00, 0x06, 0x01, 0x00]).is_none());
+        assert!(bacnet_apdu(&[0x81, 0x0a, 0x00, 0x06, 0x02, 0x00]).is_none());
+        //a destination address running past the end
+        assert!(bacnet_apdu(&[0x81, 0x0a, 0x00, 0x00, 0x01, 0x20, 0, 5, 9, 0xaa]).is_none());
+        assert!(bacnet_apdu(&[0x81, 0x0a, 0x00, 0x00, 0x01, 0x20, 0]).is_none());
+    }
+}
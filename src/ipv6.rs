@@ -0,0 +1,289 @@
+//IPv6 discovery of the hosts on a link (host_disco ipv6 INTERFACE), which a sweep can't do as a /64 is
+//far too big to walk, but every IPv6 host answers an echo request to the all-nodes group ff02::1 and
+//every router one to the all-routers group ff02::2, from its link-local address
+//
+//the MAC addresses come from the kernel's neighbour table (ip -6 neigh), which the replies fill in, and
+//--global then probes the addresses made of the interface's global prefixes and each host's interface
+//id, which finds hosts with EUI-64 or stable addresses but not those with only temporary ones
+//
+//hosts that use SLAAC with EUI-64 interface ids have addresses that follow from their MAC: the MAC with
+//ff:fe in the middle and the universal/local bit flipped, e.g. 00:11:22:33:44:55 is ::211:22ff:fe33:4455.
+//--prefix gives prefixes to derive those into besides the interface's own, from the MACs of the IPv6
+//and IPv4 neighbour tables and, with --macs-from, of the live hosts of an earlier sweep's json results
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv6Addr;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::compress;
+use crate::host::HostResult;
+use crate::json::{self, Value};
+use crate::oui;
+
+//the groups pinged and whether the hosts answering are routers
+const GROUPS: [(&str, bool); 2] = [("ff02::1", false), ("ff02::2", true)];
+//echo requests sent to each group, and how many seconds to wait for the last replies
+const REQUESTS: &str = "3";
+const DEADLINE: &str = "4";
+//derived global addresses probed at once
+const WORKERS: usize = 32;
+
+//a host that answered on the link
+struct Neighbour {
+    address: Ipv6Addr,
+    mac: Option<String>,
+    router: bool,
+    own: bool,
+}
+
+//one of the interface's own addresses
+struct Own {
+    address: Ipv6Addr,
+    prefix: u8,
+    global: bool,
+}
+
+//function to find the hosts on the interface's link and print them, with --global and --prefix the global
+//addresses derived from them too
+pub fn discover(interface: &str, global: bool, prefixes: &[u128], macs_from: Option<&str>) -> Result<(), String> {
+    let own = own_addresses(interface)?;
+    if own.is_empty() {
+        return Err(format!("{} has no IPv6 address, IPv6 is disabled on it or it is down", interface));
+    }
+
+    println!("Pinging the all-nodes and all-routers groups on {}.", interface);
+    let mut neighbours: Vec<Neighbour> = Vec::new();
+    for (group, router) in GROUPS {
+        for address in ping_group(group, interface)? {
+            match neighbours.iter_mut().find(|neighbour| neighbour.address == address) {
+                Some(neighbour) => neighbour.router |= router,
+                None => neighbours.push(Neighbour {
+                    address,
+                    mac: None,
+                    router,
+                    own: own.iter().any(|own| own.address == address),
+                }),
+            }
+        }
+    }
+    //the replies put the hosts in the neighbour table, which also knows routers from their advertisements
+    let table = neighbour_table(interface);
+    for neighbour in &mut neighbours {
+        if let Some((mac, router)) = table.get(&neighbour.address) {
+            neighbour.mac = Some(mac.clone());
+            neighbour.router |= router;
+        }
+    }
+    neighbours.sort_by_key(|neighbour| neighbour.address);
+
+    if neighbours.is_empty() {
+        println!("Nothing on {} answered.", interface);
+    }
+    else {
+        print_neighbours(interface, &neighbours);
+    }
+
+    if global || !prefixes.is_empty() {
+        let mut prefixes = prefixes.to_vec();
+        if global {
+            prefixes.extend(own.iter().filter(|own| own.global && own.prefix <= 64).map(|own| u128::from(own.address) & !0 << 64));
+        }
+        let mut macs: Vec<String> = table.into_values().map(|(mac, _)| mac).collect();
+        macs.extend(arp_macs(interface));
+        if let Some(path) = macs_from {
+            macs.extend(result_macs(path)?);
+        }
+        probe_derived(interface, &prefixes, &neighbours, &macs);
+    }
+    Ok(())
+}
+
+//function to list the hosts that answered with their MAC, vendor and whether they are routers
+fn print_neighbours(interface: &str, neighbours: &[Neighbour]) {
+    println!();
+    for neighbour in neighbours {
+        let mut notes = Vec::new();
+        notes.extend(neighbour.mac.as_deref().and_then(oui::vendor));
+        if neighbour.router {
+            notes.push("router");
+        }
+        if neighbour.own {
+            notes.push("this machine");
+        }
+        println!("{:<28} {:<18} {}", neighbour.address, neighbour.mac.as_deref().unwrap_or("-"), notes.join(", "));
+    }
+    let routers = neighbours.iter().filter(|neighbour| neighbour.router).count();
+    println!("\n{} hosts answered on {}, {} of them routers.", neighbours.len(), interface, routers);
+}
+
+//function to probe the addresses the prefixes make with the link-local hosts' interface ids and the
+//EUI-64 interface ids of the MACs
+fn probe_derived(interface: &str, prefixes: &[u128], neighbours: &[Neighbour], macs: &[String]) {
+    if prefixes.is_empty() {
+        println!("\n{} has no global /64 prefix to derive addresses from, give one with --prefix.", interface);
+        return;
+    }
+    //each interface id with where it came from, e.g. "fe80::1" or "EUI-64 of 00:11:22:33:44:55"
+    let mut ids: Vec<(u128, String)> = Vec::new();
+    for neighbour in neighbours.iter().filter(|neighbour| !neighbour.own && neighbour.address.is_unicast_link_local()) {
+        ids.push((u128::from(neighbour.address) & !(!0 << 64), neighbour.address.to_string()));
+    }
+    for mac in macs {
+        if let Some(id) = eui64(mac).filter(|id| !ids.iter().any(|(known, _)| known == id)) {
+            ids.push((id, format!("EUI-64 of {}", mac)));
+        }
+    }
+    let mut candidates: Vec<(Ipv6Addr, &str)> = Vec::new();
+    for (id, origin) in &ids {
+        for prefix in prefixes {
+            let candidate = Ipv6Addr::from(prefix | id);
+            if !candidates.iter().any(|(known, _)| *known == candidate) {
+                candidates.push((candidate, origin));
+            }
+        }
+    }
+
+    println!("\nProbing {} global addresses derived from {} prefixes and {} interface ids.", candidates.len(), prefixes.len(), ids.len());
+    let mut answered = 0;
+    for chunk in candidates.chunks(WORKERS) {
+        let replies: Vec<bool> = thread::scope(|scope| {
+            let probes: Vec<_> = chunk.iter().map(|(candidate, _)| scope.spawn(move || ping_one(*candidate))).collect();
+            probes.into_iter().map(|probe| probe.join().unwrap_or(false)).collect()
+        });
+        for ((candidate, origin), up) in chunk.iter().zip(replies) {
+            if up {
+                answered += 1;
+                println!("{} is \x1b[0;32mup\x1b[0m ({}).", candidate, origin);
+            }
+        }
+    }
+    println!("{} of {} answered.", answered, candidates.len());
+}
+
+//function to ping a multicast group out of the interface, returning the addresses the replies came from
+fn ping_group(group: &str, interface: &str) -> Result<Vec<Ipv6Addr>, String> {
+    //ping exits non-zero when nothing answered, which is an empty link rather than an error
+    let output = Command::new("ping")
+        .args(["-6", "-n", "-c", REQUESTS, "-w", DEADLINE, "-I", interface, group])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|error| format!("cannot run ping, {}", error))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !stderr.trim().is_empty() {
+        return Err(format!("ping of {} on {} failed, {}", group, interface, stderr.trim()));
+    }
+    //e.g. "64 bytes from fe80::1%eth0: icmp_seq=1 ttl=64 time=0.41 ms (DUP!)"
+    let mut addresses = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((_, rest)) = line.split_once(" bytes from ") else {
+            continue;
+        };
+        let source = rest.split(": ").next().unwrap_or("");
+        if let Ok(address) = source.split('%').next().unwrap_or("").parse::<Ipv6Addr>() {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+//function to turn a MAC into its modified EUI-64 interface id, None for one that isn't a MAC
+fn eui64(mac: &str) -> Option<u128> {
+    let octets: Vec<u8> = mac.split([':', '-']).map(|octet| u8::from_str_radix(octet, 16)).collect::<Result<_, _>>().ok()?;
+    let [a, b, c, d, e, f] = octets[..] else {
+        return None;
+    };
+    Some(u128::from(u64::from_be_bytes([a ^ 0x02, b, c, 0xff, 0xfe, d, e, f])))
+}
+
+//function to parse a --prefix, e.g. 2001:db8:1:2::/64, into its network bits
+pub fn parse_prefix(value: &str) -> Result<u128, String> {
+    let invalid = || format!("Invalid IPv6 prefix {}, expected one like 2001:db8::/64", value);
+    let (network, length) = value.split_once('/').ok_or_else(invalid)?;
+    let network: Ipv6Addr = network.parse().map_err(|_| invalid())?;
+    match length.parse::<u8>() {
+        //SLAAC addresses are a /64 and an interface id, a shorter prefix is taken as its first /64
+        Ok(length) if length <= 64 => Ok(u128::from(network) & !0 << 64),
+        _ => Err(format!("IPv6 prefix {} has to be a /64 or shorter for SLAAC addresses", value)),
+    }
+}
+
+fn ping_one(address: Ipv6Addr) -> bool {
+    Command::new("ping")
+        .args(["-6", "-n", "-c", "1", "-W", "1", &address.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+//function to read the interface's entries of the kernel's neighbour table, the MAC and router flag by address
+//e.g. "fe80::1 lladdr 00:11:22:33:44:55 router REACHABLE"
+fn neighbour_table(interface: &str) -> HashMap<Ipv6Addr, (String, bool)> {
+    let Ok(output) = Command::new("ip").args(["-6", "neigh", "show", "dev", interface]).output() else {
+        return HashMap::new();
+    };
+    let mut table = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some(address) = words.first().and_then(|address| address.parse::<Ipv6Addr>().ok()) else {
+            continue;
+        };
+        if let Some(mac) = words.iter().position(|word| *word == "lladdr").and_then(|index| words.get(index + 1)) {
+            table.insert(address, (mac.to_uppercase(), words.contains(&"router")));
+        }
+    }
+    table
+}
+
+//function to read the MACs of the interface's entries of the kernel's IPv4 neighbour table, dual stacked
+//hosts that only spoke IPv4 so far are in there
+fn arp_macs(interface: &str) -> Vec<String> {
+    let Ok(table) = fs::read_to_string("/proc/net/arp") else {
+        return Vec::new();
+    };
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [_, _, flags, mac, _, device] if *device == interface && *flags != "0x0" && *mac != "00:00:00:00:00:00" => Some(mac.to_uppercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+//function to read the MACs of the live hosts in a json results file
+fn result_macs(path: &str) -> Result<Vec<String>, String> {
+    let contents = compress::read(path)?;
+    let document = json::parse(&String::from_utf8_lossy(&contents)).map_err(|error| format!("{} isn't a json results file, {}", path, error))?;
+    let hosts = document.get("hosts").and_then(Value::as_array).ok_or_else(|| format!("{} isn't a json results file", path))?;
+    Ok(hosts.iter().filter_map(HostResult::from_json).filter(HostResult::is_up).filter_map(|host| host.mac).collect())
+}
+
+//function to list the interface's own addresses, e.g. "2: eth0    inet6 2001:db8::5/64 scope global dynamic ..."
+fn own_addresses(interface: &str) -> Result<Vec<Own>, String> {
+    let output = Command::new("ip")
+        .args(["-6", "-o", "addr", "show", "dev", interface])
+        .output()
+        .map_err(|error| format!("cannot run ip, {}", error))?;
+    if !output.status.success() {
+        return Err(format!("cannot list the addresses of {}, {}", interface, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let mut own = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((address, prefix)) = words.iter().position(|word| *word == "inet6").and_then(|index| words.get(index + 1)?.split_once('/')) else {
+            continue;
+        };
+        let (Ok(address), Ok(prefix)) = (address.parse(), prefix.parse()) else {
+            continue;
+        };
+        let global = words.windows(2).any(|pair| pair == ["scope", "global"]);
+        own.push(Own { address, prefix, global });
+    }
+    Ok(own)
+}
@@ -0,0 +1,320 @@
+//a small JSON reader and writer, enough for the files this tool writes itself
+
+//a parsed JSON value, objects keep their key order
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    //function to look up a key of an object, None for missing keys and non-objects
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_f64().filter(|value| *value >= 0.0).map(|value| value as u64)
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+//function to parse a complete JSON document
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.bytes.len() {
+        return Err(format!("unexpected data at byte {}", parser.pos));
+    }
+    Ok(value)
+}
+
+//function to quote a string for JSON
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+//function to write a value back out as compact JSON, whole numbers without a fraction
+pub fn stringify(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => format!("{}", *value as i64),
+        //JSON has no NaN or infinity
+        Value::Number(value) if !value.is_finite() => "null".to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => quote(value),
+        Value::Array(values) => format!("[{}]", values.iter().map(stringify).collect::<Vec<_>>().join(",")),
+        Value::Object(entries) => {
+            format!("{{{}}}", entries.iter().map(|(key, value)| format!("{}:{}", quote(key), stringify(value))).collect::<Vec<_>>().join(","))
+        }
+    }
+}
+
+//function to quote an optional string, None becomes null
+pub fn quote_option(value: Option<&str>) -> String {
+    value.map(quote).unwrap_or_else(|| "null".to_string())
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, expected: &str) -> String {
+        format!("expected {} at byte {}", expected, self.pos)
+    }
+
+    fn expect_literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        }
+        else {
+            Err(self.error(literal))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b't') => self.expect_literal("true", Value::Bool(true)),
+            Some(b'f') => self.expect_literal("false", Value::Bool(false)),
+            Some(b'n') => self.expect_literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b'"') {
+                return Err(self.error("a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b':') {
+                return Err(self.error("':'"));
+            }
+            self.pos += 1;
+            entries.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(entries));
+                }
+                _ => return Err(self.error("',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self.pos < self.bytes.len() && matches!(self.bytes[self.pos], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| self.error("a number"))?;
+        text.parse().map(Value::Number).map_err(|_| self.error("a number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                return Err(self.error("closing quote"));
+            };
+            match byte {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                b'\\' => {
+                    let escape = *self.bytes.get(self.pos + 1).ok_or_else(|| self.error("an escape"))?;
+                    self.pos += 2;
+                    match escape {
+                        b'"' => value.push('"'),
+                        b'\\' => value.push('\\'),
+                        b'/' => value.push('/'),
+                        b'b' => value.push('\u{8}'),
+                        b'f' => value.push('\u{c}'),
+                        b'n' => value.push('\n'),
+                        b'r' => value.push('\r'),
+                        b't' => value.push('\t'),
+                        b'u' => value.push(self.unicode_escape()?),
+                        _ => return Err(self.error("a valid escape")),
+                    }
+                }
+                _ => {
+                    //copy a whole UTF-8 sequence at once
+                    let len = match byte {
+                        0xf0..=0xff => 4,
+                        0xe0..=0xef => 3,
+                        0xc0..=0xdf => 2,
+                        _ => 1,
+                    };
+                    let chunk = self.bytes.get(self.pos..self.pos + len).ok_or_else(|| self.error("valid UTF-8"))?;
+                    value.push_str(std::str::from_utf8(chunk).map_err(|_| self.error("valid UTF-8"))?);
+                    self.pos += len;
+                }
+            }
+        }
+    }
+
+    //function to read the four hex digits of a \u escape, joining surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if (0xd800..0xdc00).contains(&high) && self.bytes[self.pos..].starts_with(b"\\u") {
+            self.pos += 2;
+            let low = self.hex4()?;
+            let code = 0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+            return Ok(char::from_u32(code).unwrap_or('\u{fffd}'));
+        }
+        Ok(char::from_u32(high).unwrap_or('\u{fffd}'))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).ok_or_else(|| self.error("four hex digits"))?;
+        let digits = std::str::from_utf8(digits).map_err(|_| self.error("four hex digits"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("four hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_are_read_in_order() {
+        let value = parse(" {\"b\": [1, -2.5, 3e2], \"a\": {\"t\": true, \"f\": false, \"n\": null}, \"s\": \"x\"} ").unwrap();
+        assert_eq!(value.get("b"), Some(&Value::Array(vec![Value::Number(1.0), Value::Number(-2.5), Value::Number(300.0)])));
+        assert_eq!(value.get("a").and_then(|a| a.get("n")), Some(&Value::Null));
+        assert_eq!(value.get("s").and_then(Value::as_str), Some("x"));
+        assert_eq!(value.get("missing"), None);
+        let Value::Object(entries) = value
+        else {
+            panic!("not an object");
+        };
+        let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["b", "a", "s"]);
+    }
+
+    #[test]
+    fn escapes_are_undone() {
+        assert_eq!(parse(r#""a\"b\\c\/d\n\t\u00e9""#).unwrap(), Value::String("a\"b\\c/d\n\té".to_string()));
+        //a surrogate pair is one character
+        assert_eq!(parse(r#""\ud83d\ude00""#).unwrap(), Value::String("\u{1f600}".to_string()));
+        assert_eq!(parse("\"grüße\"").unwrap(), Value::String("grüße".to_string()));
+    }
+
+    #[test]
+    fn broken_documents_are_refused() {
+        for text in ["", "{", "[1,]", "[1 2]", "{\"a\" 1}", "{a: 1}", "\"open", "\"\\x\"", "\"\\u12\"", "tru", "nul", "+1", "1 2", "[] x"] {
+            assert!(parse(text).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn values_are_written_back_compactly() {
+        let text = "{\"n\":[1,-2.5,null,true],\"s\":\"a\\\"b\\n\\u0001\"}";
+        assert_eq!(stringify(&parse(text).unwrap()), text);
+        assert_eq!(stringify(&Value::Number(f64::NAN)), "null");
+        assert_eq!(quote_option(None), "null");
+        assert_eq!(quote_option(Some("x")), "\"x\"");
+    }
+
+    #[test]
+    fn numbers_read_as_counts() {
+        assert_eq!(parse("42").unwrap().as_u64(), Some(42));
+        assert_eq!(parse("-1").unwrap().as_u64(), None);
+        assert_eq!(parse("\"42\"").unwrap().as_u64(), None);
+    }
+}
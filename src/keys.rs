@@ -0,0 +1,89 @@
+//single key commands while a scan runs in a terminal, p pauses probe dispatch, r resumes, s prints an interim summary
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+//the running counts an interim summary is made from, updated by the main thread as hosts finish
+pub struct Tally {
+    pub probed: AtomicUsize,
+    pub up: AtomicUsize,
+    pub retries: AtomicUsize,
+    total: usize,
+    started: Instant,
+}
+
+impl Tally {
+    pub fn new(total: usize) -> Tally {
+        Tally {
+            probed: AtomicUsize::new(0),
+            up: AtomicUsize::new(0),
+            retries: AtomicUsize::new(0),
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    //function to describe how far the scan got, e.g. "120 of 256 addresses probed, 14 up, 0 retries, 12s elapsed"
+    fn summary(&self) -> String {
+        format!(
+            "{} of {} addresses probed, {} up, {} retries, {}s elapsed",
+            self.probed.load(Ordering::Relaxed),
+            self.total,
+            self.up.load(Ordering::Relaxed),
+            self.retries.load(Ordering::Relaxed),
+            self.started.elapsed().as_secs()
+        )
+    }
+}
+
+//puts the terminal back the way it was when the scan is over
+pub struct Listener {
+    saved: String,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+//function to start reading keys, None when there is no terminal to read them from
+//the terminal is switched out of line mode so a key acts without enter, and back again when the listener is dropped
+pub fn listen(paused: Arc<AtomicBool>, tally: Arc<Tally>) -> Option<Listener> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+    let saved = stty(&["-g"])?;
+    stty(&["-icanon", "-echo", "min", "1"])?;
+    let listener = Listener { saved: saved.trim().to_string() };
+
+    let mut tty = File::open("/dev/tty").ok()?;
+    thread::spawn(move || {
+        let mut key = [0u8; 1];
+        while let Ok(1) = tty.read(&mut key) {
+            match key[0] {
+                b'p' if !paused.swap(true, Ordering::Relaxed) => {
+                    println!("\x1b[0;33mPaused\x1b[0m, probes already sent will finish, press r to resume.");
+                }
+                b'r' if paused.swap(false, Ordering::Relaxed) => println!("\x1b[0;32mResumed.\x1b[0m"),
+                b's' => {
+                    let state = if paused.load(Ordering::Relaxed) { ", paused" } else { "" };
+                    println!("\x1b[1mInterim summary:\x1b[0m {}{}.", tally.summary(), state);
+                }
+                _ => {}
+            }
+        }
+    });
+    Some(listener)
+}
+
+//function to run stty on the controlling terminal, returning what it printed
+pub fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(File::open("/dev/tty").ok()?).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
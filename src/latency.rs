@@ -0,0 +1,79 @@
+//round trip time thresholds, hosts that answer but too slowly are flagged as warning or critical
+//
+//  [watch]
+//  warn_rtt = 50ms
+//  crit_rtt = 200ms
+
+use crate::cli::Options;
+use crate::config::Config;
+use crate::monitor;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl Level {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Normal => "normal",
+            Level::Warning => "warning",
+            Level::Critical => "critical",
+        }
+    }
+
+    pub fn colour(&self) -> &'static str {
+        match self {
+            Level::Normal => "\x1b[0;32m",
+            Level::Warning => "\x1b[0;33m",
+            Level::Critical => "\x1b[31m",
+        }
+    }
+}
+
+//the warning and critical round trip times in milliseconds, either may be unset
+pub struct Thresholds {
+    pub warn: Option<f64>,
+    pub crit: Option<f64>,
+}
+
+impl Thresholds {
+    //function to take the thresholds from the watch profile section of the config file, the flags win
+    pub fn from_options(config: &Config, options: &Options) -> Result<Thresholds, String> {
+        let section = monitor::profile_section(config, options)?;
+        let threshold = |key: &str, flag: Option<f64>| -> Result<Option<f64>, String> {
+            match (flag, section.and_then(|section| section.get(key))) {
+                (Some(ms), _) => Ok(Some(ms)),
+                (None, Some(value)) => parse_ms(value, key).map(Some),
+                (None, None) => Ok(None),
+            }
+        };
+        Ok(Thresholds {
+            warn: threshold("warn_rtt", options.warn_rtt)?,
+            crit: threshold("crit_rtt", options.crit_rtt)?,
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.warn.is_some() || self.crit.is_some()
+    }
+
+    //function to rate a round trip time, anything over a threshold is at its level
+    pub fn level(&self, rtt: f64) -> Level {
+        match (self.warn, self.crit) {
+            (_, Some(crit)) if rtt > crit => Level::Critical,
+            (Some(warn), _) if rtt > warn => Level::Warning,
+            _ => Level::Normal,
+        }
+    }
+}
+
+//function to parse a round trip time like 50 or 50ms
+pub fn parse_ms(value: &str, name: &str) -> Result<f64, String> {
+    match value.trim().trim_end_matches("ms").trim().parse::<f64>() {
+        Ok(ms) if ms > 0.0 => Ok(ms),
+        _ => Err(format!("Invalid round trip time {} for {}, expected milliseconds like 50ms", value, name)),
+    }
+}
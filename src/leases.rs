@@ -0,0 +1,168 @@
+//importing targets from a DHCP server's lease file (--targets-from leases:[FILE]), the clients it has
+//handed addresses to, so a sweep tells the leases of clients still there from the stale ones
+//
+//the format is told from the contents, the file from the usual places when none is given:
+//
+//  ISC dhcpd   /var/lib/dhcp/dhcpd.leases        lease 10.0.0.5 { binding state active; ... }, the last
+//                                                block of an address is its current state
+//  dnsmasq     /var/lib/misc/dnsmasq.leases      EXPIRY MAC ADDRESS HOSTNAME CLIENT-ID, expiry 0 never ends
+//  Kea         /var/lib/kea/kea-leases4.csv      address,hwaddr,...,expire,...,hostname,state,... with a
+//                                                header, appended to so the last row of an address counts
+//
+//leases that have ended are left out and counted, they say nothing about a client that is still there
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use crate::metadata;
+use crate::store;
+
+const DEFAULT_PATHS: [&str; 3] = ["/var/lib/dhcp/dhcpd.leases", "/var/lib/misc/dnsmasq.leases", "/var/lib/kea/kea-leases4.csv"];
+
+//one lease as read, ends is None for one that never does
+struct Lease {
+    mac: Option<String>,
+    hostname: Option<String>,
+    ends: Option<u64>,
+    active: bool,
+}
+
+//function to read the leases that haven't ended, each client named by its hostname and MAC
+pub fn import(path: &str, notes: &mut Vec<String>) -> Result<Vec<(Ipv4Addr, String)>, String> {
+    let path = match path {
+        "" => DEFAULT_PATHS
+            .iter()
+            .find(|path| Path::new(path).exists())
+            .ok_or_else(|| format!("no lease file in {}, give its path with leases:FILE", DEFAULT_PATHS.join(", ")))?,
+        path => path,
+    };
+    let contents = fs::read_to_string(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    let leases = if contents.starts_with("address,") {
+        kea(&contents)
+    }
+    else if contents.lines().any(|line| line.trim_start().starts_with("lease ")) {
+        isc(&contents)
+    }
+    else {
+        dnsmasq(&contents)
+    };
+
+    let now = store::now();
+    let mut entries = Vec::new();
+    let mut ended = 0;
+    for (address, lease) in leases {
+        if !lease.active || lease.ends.is_some_and(|ends| ends <= now) {
+            ended += 1;
+            continue;
+        }
+        let name: Vec<&str> = lease.hostname.iter().chain(&lease.mac).map(String::as_str).collect();
+        entries.push((address, if name.is_empty() { "-".to_string() } else { name.join(", ") }));
+    }
+    if ended > 0 {
+        notes.push(format!("{} leases in {} have ended and weren't swept", ended, path));
+    }
+    Ok(entries)
+}
+
+//lease 10.0.0.5 {
+//  ends 4 2026/10/15 12:00:00;
+//  binding state active;
+//  hardware ethernet 00:11:22:33:44:55;
+//  client-hostname "web01";
+//}
+fn isc(contents: &str) -> BTreeMap<Ipv4Addr, Lease> {
+    let mut leases = BTreeMap::new();
+    let mut current: Option<(Ipv4Addr, Lease)> = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("lease ") {
+            current = rest
+                .trim_end_matches('{')
+                .trim()
+                .parse()
+                .ok()
+                .map(|address| (address, Lease { mac: None, hostname: None, ends: None, active: true }));
+            continue;
+        }
+        if line == "}" {
+            if let Some((address, lease)) = current.take() {
+                leases.insert(address, lease);
+            }
+            continue;
+        }
+        let Some((_, lease)) = current.as_mut() else {
+            continue;
+        };
+        let line = line.trim_end_matches(';');
+        if let Some(state) = line.strip_prefix("binding state ") {
+            lease.active = state == "active";
+        }
+        else if let Some(mac) = line.strip_prefix("hardware ethernet ") {
+            lease.mac = Some(mac.to_uppercase());
+        }
+        else if let Some(hostname) = line.strip_prefix("client-hostname ") {
+            lease.hostname = Some(hostname.trim_matches('"').to_string());
+        }
+        //ends WEEKDAY YYYY/MM/DD HH:MM:SS in UTC, or ends never
+        else if let Some(ends) = line.strip_prefix("ends ") {
+            lease.ends = match ends.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                [_, date, time] => metadata::parse_iso8601(&format!("{}T{}Z", date.replace('/', "-"), time)),
+                _ => None,
+            };
+        }
+    }
+    leases
+}
+
+//1760616000 00:11:22:33:44:55 10.0.0.5 web01 01:00:11:22:33:44:55
+fn dnsmasq(contents: &str) -> BTreeMap<Ipv4Addr, Lease> {
+    let mut leases = BTreeMap::new();
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [expiry, mac, address, hostname, ..] = fields.as_slice() else {
+            continue;
+        };
+        let (Ok(expiry), Ok(address)) = (expiry.parse::<u64>(), address.parse::<Ipv4Addr>()) else {
+            continue;
+        };
+        leases.insert(
+            address,
+            Lease {
+                mac: Some(mac.to_uppercase()),
+                hostname: (*hostname != "*").then(|| hostname.to_string()),
+                ends: (expiry != 0).then_some(expiry),
+                active: true,
+            },
+        );
+    }
+    leases
+}
+
+//address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context
+//10.0.0.5,00:11:22:33:44:55,,3600,1760616000,1,0,0,web01,0,
+fn kea(contents: &str) -> BTreeMap<Ipv4Addr, Lease> {
+    let mut lines = contents.lines();
+    let header: Vec<&str> = lines.next().unwrap_or("").split(',').collect();
+    let column = |name: &str| header.iter().position(|column| *column == name);
+    let (address, mac, expire, hostname, state) = (column("address"), column("hwaddr"), column("expire"), column("hostname"), column("state"));
+    let mut leases = BTreeMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let field = |index: Option<usize>| index.and_then(|index| fields.get(index)).map(|field| field.trim()).filter(|field| !field.is_empty());
+        let Some(address) = field(address).and_then(|address| address.parse::<Ipv4Addr>().ok()) else {
+            continue;
+        };
+        leases.insert(
+            address,
+            Lease {
+                mac: field(mac).map(str::to_uppercase),
+                hostname: field(hostname).map(str::to_string),
+                ends: field(expire).and_then(|expire| expire.parse().ok()),
+                //0 is a lease in use, 1 a declined address and 2 one reclaimed after it expired
+                active: field(state).unwrap_or("0") == "0",
+            },
+        );
+    }
+    leases
+}
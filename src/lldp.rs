@@ -0,0 +1,248 @@
+//listening for the LLDP and CDP announcements of the switches and routers on a directly connected
+//segment (--lldp), passively with tcpdump while the sweep runs, so the results say which device and
+//port the scanning machine is plugged into
+//
+//switches announce every 30s (LLDP) or 60s (CDP) by default, so the listening lasts at least
+//--lldp-time (default 60s) even when the sweep is done sooner
+
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::capture::Capture;
+use crate::host::HostResult;
+use crate::replay;
+use crate::scratch::Scratch;
+
+pub const DEFAULT_TIME: Duration = Duration::from_secs(60);
+
+//LLDP by ethertype, CDP by its multicast address as it has none
+const FILTER: &str = "ether proto 0x88cc or ether dst 01:00:0c:cc:cc:cc";
+const LINK_ETHERNET: u32 = 1;
+const ETHERTYPE_LLDP: u16 = 0x88cc;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+//LLC and SNAP header of CDP, Cisco's OUI and protocol id 0x2000
+const CDP_SNAP: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+
+//a switch or router that announced itself
+#[derive(PartialEq)]
+pub struct Neighbour {
+    pub protocol: &'static str,
+    //the system name or CDP device id, else the chassis id
+    pub name: String,
+    //the port on the neighbour the announcement left by, and its description
+    pub port: String,
+    pub port_description: Option<String>,
+    //the system description or CDP platform
+    pub platform: Option<String>,
+    //e.g. "bridge", "router"
+    pub capabilities: Vec<&'static str>,
+    pub management: Option<Ipv4Addr>,
+    pub vlan: Option<u16>,
+}
+
+pub struct Listener {
+    capture: Capture,
+    //the capture is written here, and goes with the directory once read
+    scratch: Scratch,
+    path: PathBuf,
+    interface: String,
+    until: Instant,
+}
+
+impl Listener {
+    //function to start listening on the interface for the announcements
+    pub fn start(interface: &str, time: Duration) -> Result<Listener, String> {
+        let scratch = Scratch::create()?;
+        let (path, file) = scratch.file("lldp.pcap")?;
+        let capture = Capture::listen(file, interface, FILTER)?;
+        Ok(Listener { capture, scratch, path, interface: interface.to_string(), until: Instant::now() + time })
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    //how much longer listening has to go on for
+    pub fn remaining(&self) -> Duration {
+        self.until.saturating_duration_since(Instant::now())
+    }
+
+    //function to wait out the listening time, stop and read the neighbours heard, once each
+    pub fn finish(self) -> Result<Vec<Neighbour>, String> {
+        std::thread::sleep(self.remaining());
+        let stopped = self.capture.stop();
+        let contents = fs::read(&self.path);
+        drop(self.scratch);
+        stopped?;
+        let contents = contents.map_err(|error| format!("cannot read the capture, {}", error))?;
+
+        let (link_type, frames) = replay::pcap_frames(&contents)?;
+        if link_type != LINK_ETHERNET {
+            return Err(format!("{} isn't an Ethernet interface", self.interface));
+        }
+        let mut neighbours: Vec<Neighbour> = Vec::new();
+        for (_, frame) in frames {
+            if let Some(neighbour) = parse_frame(frame) {
+                if !neighbours.contains(&neighbour) {
+                    neighbours.push(neighbour);
+                }
+            }
+        }
+        Ok(neighbours)
+    }
+}
+
+//function to give the host a neighbour manages from the role its capabilities suggest, a router before a switch
+pub fn mark_roles(neighbours: &[Neighbour], results: &mut [HostResult]) {
+    for neighbour in neighbours {
+        let Some(host) = neighbour.management.and_then(|address| results.iter_mut().find(|host| host.address == address)) else {
+            continue;
+        };
+        let role = match neighbour.capabilities.as_slice() {
+            capabilities if capabilities.contains(&"router") => "router",
+            capabilities if capabilities.contains(&"bridge") => "switch",
+            capabilities if capabilities.contains(&"access-point") => "access-point",
+            _ => continue,
+        };
+        if !host.roles.iter().any(|existing| existing == role) {
+            host.roles.push(role.to_string());
+        }
+    }
+}
+
+impl Neighbour {
+    //function to describe the neighbour on one line, e.g. "sw-core1 port Gi1/0/12 (uplink), bridge, router, VLAN 30, 10.0.0.2"
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("{} port {}", self.name, self.port)];
+        if let Some(description) = self.port_description.as_ref().filter(|description| **description != self.port) {
+            parts[0].push_str(&format!(" ({})", description));
+        }
+        parts.extend(self.capabilities.iter().map(|capability| capability.to_string()));
+        if let Some(vlan) = self.vlan {
+            parts.push(format!("VLAN {}", vlan));
+        }
+        if let Some(address) = self.management {
+            parts.push(address.to_string());
+        }
+        format!("{} via {}", parts.join(", "), self.protocol)
+    }
+}
+
+fn parse_frame(frame: &[u8]) -> Option<Neighbour> {
+    let field = |offset: usize| Some(u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]));
+    //a tagged frame has its ethertype four bytes on
+    let offset = if field(12)? == ETHERTYPE_VLAN { 16 } else { 12 };
+    if field(offset)? == ETHERTYPE_LLDP {
+        return parse_lldp(frame.get(offset + 2..)?);
+    }
+    //CDP is 802.3 with a length rather than an ethertype, then LLC/SNAP and a 4 byte header
+    if frame.get(offset + 2..offset + 10)? == CDP_SNAP {
+        return parse_cdp(frame.get(offset + 14..)?);
+    }
+    None
+}
+
+//LLDPDU, TLVs with a 7 bit type and 9 bit length, ended by type 0
+fn parse_lldp(mut data: &[u8]) -> Option<Neighbour> {
+    let mut neighbour = Neighbour {
+        protocol: "LLDP",
+        name: String::new(),
+        port: String::new(),
+        port_description: None,
+        platform: None,
+        capabilities: Vec::new(),
+        management: None,
+        vlan: None,
+    };
+    let mut chassis = String::new();
+    while data.len() >= 2 {
+        let header = u16::from_be_bytes([data[0], data[1]]);
+        let (kind, length) = (header >> 9, (header & 0x1ff) as usize);
+        let value = data.get(2..2 + length)?;
+        data = &data[2 + length..];
+        match (kind, value) {
+            (0, _) => break,
+            (1, [subtype, id @ ..]) => chassis = lldp_id(*subtype, id, 4),
+            (2, [subtype, id @ ..]) => neighbour.port = lldp_id(*subtype, id, 3),
+            (4, _) => neighbour.port_description = text(value),
+            (5, _) => neighbour.name = text(value).unwrap_or_default(),
+            (6, _) => neighbour.platform = text(value),
+            //system capabilities then enabled capabilities, what it does rather than what it could
+            (7, [_, _, high, low, ..]) => neighbour.capabilities = capabilities(u16::from_be_bytes([*high, *low]) as u32, &LLDP_CAPABILITIES),
+            //address string length, address family (1 is IPv4), address
+            (8, [5, 1, a, b, c, d, ..]) => neighbour.management = neighbour.management.or(Some(Ipv4Addr::new(*a, *b, *c, *d))),
+            //IEEE 802.1 port VLAN id
+            (127, [0x00, 0x80, 0xc2, 0x01, high, low, ..]) => neighbour.vlan = Some(u16::from_be_bytes([*high, *low])).filter(|vlan| *vlan > 0),
+            _ => {}
+        }
+    }
+    if neighbour.name.is_empty() {
+        neighbour.name = chassis;
+    }
+    (!neighbour.name.is_empty()).then_some(neighbour)
+}
+
+//a chassis or port id, shown as a MAC address when the subtype says it is one
+fn lldp_id(subtype: u8, id: &[u8], mac_subtype: u8) -> String {
+    if subtype == mac_subtype && id.len() == 6 {
+        id.iter().map(|octet| format!("{:02X}", octet)).collect::<Vec<String>>().join(":")
+    }
+    else {
+        String::from_utf8_lossy(id).trim_end_matches('\0').to_string()
+    }
+}
+
+//capability bits, lowest first
+const LLDP_CAPABILITIES: [(u32, &str); 6] =
+    [(0x04, "bridge"), (0x08, "access-point"), (0x10, "router"), (0x20, "phone"), (0x80, "station"), (0x100, "cvlan")];
+const CDP_CAPABILITIES: [(u32, &str); 5] = [(0x01, "router"), (0x02, "bridge"), (0x08, "bridge"), (0x10, "host"), (0x80, "phone")];
+
+fn capabilities(bits: u32, names: &[(u32, &'static str)]) -> Vec<&'static str> {
+    let mut found: Vec<&'static str> = Vec::new();
+    for (bit, name) in names {
+        if bits & bit != 0 && !found.contains(name) {
+            found.push(name);
+        }
+    }
+    found
+}
+
+fn text(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value).trim().trim_end_matches('\0').to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+//CDP TLVs, a 16 bit type and a 16 bit length that counts the 4 byte header
+fn parse_cdp(mut data: &[u8]) -> Option<Neighbour> {
+    let mut neighbour = Neighbour {
+        protocol: "CDP",
+        name: String::new(),
+        port: String::new(),
+        port_description: None,
+        platform: None,
+        capabilities: Vec::new(),
+        management: None,
+        vlan: None,
+    };
+    while data.len() >= 4 {
+        let kind = u16::from_be_bytes([data[0], data[1]]);
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let value = data.get(4..length.max(4))?;
+        data = &data[length.max(4)..];
+        match (kind, value) {
+            (0x01, _) => neighbour.name = text(value).unwrap_or_default(),
+            (0x03, _) => neighbour.port = text(value).unwrap_or_default(),
+            (0x04, [a, b, c, d]) => neighbour.capabilities = capabilities(u32::from_be_bytes([*a, *b, *c, *d]), &CDP_CAPABILITIES),
+            (0x06, _) => neighbour.platform = text(value),
+            (0x0a, [high, low]) => neighbour.vlan = Some(u16::from_be_bytes([*high, *low])),
+            //the first address: count, then NLPID protocol type 1, length 1, protocol 0xcc (IP), length 4
+            (0x02 | 0x16, [_, _, _, _, 1, 1, 0xcc, 0, 4, a, b, c, d, ..]) => {
+                neighbour.management = neighbour.management.or(Some(Ipv4Addr::new(*a, *b, *c, *d)));
+            }
+            _ => {}
+        }
+    }
+    (!neighbour.name.is_empty()).then_some(neighbour)
+}
@@ -0,0 +1,83 @@
+//the networks attached to this machine's interfaces (--local), swept side by side with their addresses
+//taken in turns, so every interface has probes out at once rather than one network after another,
+//and the live hosts reported per interface
+
+use std::fs;
+use std::net::Ipv4Addr;
+
+use crate::subnet;
+
+//attached networks wider than this are left out, a /8 on a lab interface would take hours
+const WIDEST_PREFIX: u8 = 16;
+
+//a network directly attached to an interface
+pub struct Attached {
+    pub interface: String,
+    pub network: Ipv4Addr,
+    pub prefix: u8,
+}
+
+impl Attached {
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & subnet::mask(self.prefix) == u32::from(self.network)
+    }
+
+    pub fn block(&self) -> String {
+        format!("{}/{}", self.network, self.prefix)
+    }
+}
+
+//function to list the attached networks, the routes without a gateway in the kernel's routing table,
+//returning those too wide to sweep apart
+pub fn attached() -> Result<(Vec<Attached>, Vec<Attached>), String> {
+    let routes = fs::read_to_string("/proc/net/route").map_err(|error| format!("cannot read the routing table, {}", error))?;
+    let mut networks: Vec<Attached> = Vec::new();
+    let mut too_wide = Vec::new();
+    //Iface Destination Gateway Flags RefCnt Use Metric Mask ..., addresses in little endian hex
+    for line in routes.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let hex = |index: usize| fields.get(index).and_then(|field| u32::from_str_radix(field, 16).ok()).map(|value| Ipv4Addr::from(value.to_ne_bytes()));
+        let (Some(interface), Some(network), Some(gateway), Some(mask)) = (fields.first(), hex(1), hex(2), hex(7)) else {
+            continue;
+        };
+        let prefix = u32::from(mask).count_ones() as u8;
+        //the default route, routes through a gateway and host routes aren't attached networks
+        if *interface == "lo" || network.is_unspecified() || !gateway.is_unspecified() || prefix >= 31 || network.is_loopback() {
+            continue;
+        }
+        let attached = Attached { interface: interface.to_string(), network, prefix };
+        if networks.iter().any(|known| known.interface == attached.interface && known.network == network && known.prefix == prefix) {
+            continue;
+        }
+        if prefix < WIDEST_PREFIX {
+            too_wide.push(attached);
+        }
+        else {
+            networks.push(attached);
+        }
+    }
+    if networks.is_empty() && too_wide.is_empty() {
+        return Err("no interface has an attached IPv4 network".to_string());
+    }
+    Ok((networks, too_wide))
+}
+
+//function to reorder the addresses so the interfaces take turns, each keeping its own order
+pub fn interleave(addresses: &mut Vec<Ipv4Addr>, networks: &[Attached]) {
+    //addresses outside every attached network are one more group, e.g. other targets on the command line
+    let mut groups: Vec<Vec<Ipv4Addr>> = vec![Vec::new(); networks.len() + 1];
+    for address in addresses.iter() {
+        let group = networks.iter().position(|network| network.contains(*address)).unwrap_or(networks.len());
+        groups[group].push(*address);
+    }
+    let longest = groups.iter().map(Vec::len).max().unwrap_or(0);
+    addresses.clear();
+    for index in 0..longest {
+        addresses.extend(groups.iter().filter_map(|group| group.get(index)));
+    }
+}
+
+//function to find the attached network an address is on, for grouping the report
+pub fn network_of(address: Ipv4Addr, networks: &[Attached]) -> Option<usize> {
+    networks.iter().position(|network| network.contains(address))
+}
@@ -4,159 +4,1443 @@ Host discovery tool created by David Burns. This script will accept an IP addres
 from the user, convert it from string to Ipv4Addr and int, then iterate through all possible addresses
 given the IP/CIDR combination. It prints every discovered host IP to the terminal.
 
-TODO:
-Add threading
+Addresses are probed on a pool of worker threads sized from the system limits (or --concurrency).
 */
 
-#![allow(unused_comparisons)]
+mod ad;
+mod api;
+mod asn;
+mod audit;
+mod bench;
+mod capture;
+mod cbor;
+mod cli;
+mod cloud;
+mod compare;
+mod compress;
+mod completions;
+mod concurrency;
+mod config;
+mod container;
+mod curl;
+mod delta;
+mod dns;
+mod encrypt;
+mod enrich;
+mod export;
+mod filter;
+mod history;
+mod host;
+mod hostfiles;
+mod identify;
+mod ipv6;
+mod json;
+mod keys;
+mod latency;
+mod leases;
+mod local;
+mod lldp;
+mod merge;
+mod metadata;
+mod monitor;
+mod monitoring;
+mod mqtt;
+mod names;
+mod netbox;
+mod notify;
+mod oui;
+mod output;
+mod paths;
+mod pattern;
+mod plan;
+mod ports;
+mod probe;
+mod progress;
+mod replay;
+mod scan;
+mod scope;
+mod scratch;
+mod service;
+mod sha256;
+mod sign;
+mod silence;
+mod snmp;
+mod spill;
+mod sshkeys;
+mod stats;
+mod store;
+mod subnet;
+mod target;
+mod udp;
+mod vlan;
+mod wait;
+mod web;
+mod whois;
 
-use std::process::{Command, Stdio};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::process;
 use std::str;
 use std::io::{self, Write};
-use std::net::{Ipv4Addr};
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-//function for validating the IP and CIDR provided by the user
-fn validate_ip_cidr(input: &str) -> Result<(String, u8), String> {
-    let parts: Vec<&str> = input.split('/').collect();
-    if parts.len() != 2 {
-        return Err("Invalid format, expected IP with CIDR prefix.".to_string());
-    }
+//--count and --silent keep the scan's own output off stdout
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+//println that stays quiet under --count and --silent
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
 
-    //trim the IP from the ip/cidr combo
-    let ip_address = parts[0].trim();
-    if !validate_ip_address(ip_address) {
-        return Err("Invalid IP address.".to_string());
+fn main() {
+    //parse the command line, falling back to the interactive prompt when no target is given
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
     }
 
-    //trim the cidr from the ip/cidr combo
-    let cidr_prefix: u8 = match parts[1].trim().parse() {
-        Ok(prefix) => prefix,
-        Err(_) => return Err("Invalid CIDR prefix.".to_string()),
+    //--execute-plan runs an exported plan exactly, with its options, targets and addresses in its order
+    let (options, planned) = match &options.execute_plan {
+        Some(path) => match plan::Plan::load(path, options.config.as_deref()).and_then(|plan| Ok((plan.options(options.config.clone())?, plan))) {
+            Ok((options, plan)) => (options, Some(plan)),
+            Err(error) => {
+                eprintln!("Cannot execute the plan, {}", error);
+                process::exit(1);
+            }
+        },
+        None => (options, None),
     };
+    QUIET.store(options.count || options.silent, Ordering::Relaxed);
 
-    //checks the CIDR notation to make sure it is within range
-    if cidr_prefix > 32 {
-        return Err("CIDR prefix must be a number between 0 - 32.".to_string());
+    //subcommands don't scan anything, run them and stop
+    if let Some(subcommand) = &options.subcommand {
+        let result = match subcommand {
+            cli::Subcommand::Net { cidr, split } => subnet::print_net_info(cidr, *split),
+            cli::Subcommand::Completions { shell } => completions::print_completions(shell),
+            cli::Subcommand::Bench { target, count, port, workers } => target::expand(target, None)
+                .and_then(|expanded| audit::record(options.config.as_deref(), "bench", &[expanded], &args))
+                .and_then(|_| bench::run(target, *count, *port, *workers)),
+            cli::Subcommand::Silence { scope, duration, reason } => silence::add(scope, *duration, reason),
+            cli::Subcommand::SilenceList => silence::list(),
+            cli::Subcommand::UpdateOui { source } => oui::update(source),
+            //a host that didn't answer in time isn't an error, it has an exit status of its own
+            cli::Subcommand::Wait { target, timeout, reboot, ports } => {
+                let waited = if *reboot { wait::reboot(target, *timeout, ports) } else { wait::run(target, *timeout) };
+                match waited {
+                    Ok(true) => Ok(()),
+                    Ok(false) => process::exit(2),
+                    Err(error) => Err(error),
+                }
+            }
+            cli::Subcommand::Convert { path, output_file, compress } => cbor::convert(path, output_file.as_deref(), compress.as_deref()),
+            cli::Subcommand::SilenceClear { scope } => silence::clear(scope),
+            cli::Subcommand::Ipv6 { interface, global, prefixes, macs_from } => ipv6::discover(interface, *global, prefixes, macs_from.as_deref()),
+            cli::Subcommand::Merge { paths, format, output_file, compress } => merge::run(paths, format, output_file.as_deref(), compress.as_deref(), &options),
+            cli::Subcommand::History { targets, since, output_file, compress } => {
+                let since = since.map(|since| store::now().saturating_sub(since.as_secs()));
+                history::export(targets, since, output_file.as_deref(), compress.as_deref())
+            }
+        };
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        return;
     }
 
-    Ok((ip_address.to_string(), cidr_prefix))
-}
+    clear_screen();
+    banner("Network Host Discovery");
+    say!();
+
+    if let Some(plan) = &planned {
+        say!(
+            "Executing the plan made by {} at {}, {} addresses in {}.",
+            plan.user,
+            metadata::iso8601(plan.created_at),
+            plan.addresses.len(),
+            plan.targets.join(", ")
+        );
+    }
 
-//function to validate the IP address length and octet values,
-fn validate_ip_address(ip_address: &str) -> bool {
-    let octets: Vec<&str> = ip_address.split('.').collect();
-    if octets.len() != 4 {
-        return false;
+    //inside a container with its own network the results are about that network, not the host's
+    if let Some(warning) = container::detect().and_then(|container| container.warning()) {
+        say!("\x1b[0;33mNote:\x1b[0m {}", warning);
     }
 
-    for octet in octets {
-        if let Ok(value) = octet.parse::<u8>() {
-            if value > 255 {
-                return false;
+    //--replay answers every probe from a fixture or pcap, without a target it sweeps the addresses in it
+    if let Some(path) = &options.replay {
+        match replay::start(path) {
+            Ok(count) => say!("Replaying the replies of {} addresses from {}, nothing is sent.", count, path),
+            Err(error) => {
+                eprintln!("Replay failed, {}", error);
+                process::exit(1);
             }
         }
+    }
+
+    //targets come from the command line, stdin ("-"), --targets-file and --scope, or the prompt when there are none
+    //each entry keeps whether it came from a list, bad lines in a list are skipped rather than fatal
+    let mut target_list: Vec<(String, bool)> = Vec::new();
+    for entry in &options.targets {
+        if entry == "-" {
+            target_list.extend(target::read_stdin_targets().into_iter().map(|line| (line, true)));
+        }
         else {
-            return false;
+            target_list.push((entry.clone(), false));
+        }
+    }
+    for path in &options.target_files {
+        match target::read_target_file(path) {
+            Ok(lines) => target_list.extend(lines.into_iter().map(|line| (line, true))),
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    //the names a pattern stands for are looked up like the lines of a list, those that don't resolve are skipped
+    for pattern in &options.name_patterns {
+        match target::expand_pattern(pattern) {
+            Ok(names) => {
+                say!("Name pattern {} stands for {} name{}.", pattern, names.len(), if names.len() == 1 { "" } else { "s" });
+                target_list.extend(names.into_iter().map(|name| (name, true)));
+            }
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    //--watch-scopes watches every scope with a watch interval, together they are the targets
+    if !options.scopes.is_empty() || options.watch_scopes {
+        let scopes = config::load(options.config.as_deref()).and_then(|config| {
+            let names = if options.watch_scopes { monitor::watched_scopes(&config)? } else { options.scopes.clone() };
+            names
+                .into_iter()
+                .map(|name| scope::blocks(&config, &name).map(|blocks| (name, blocks)))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        match scopes {
+            Ok(scopes) => {
+                for (name, blocks) in scopes {
+                    say!("Scope {} is {}.", name, blocks.join(", "));
+                    target_list.extend(blocks.into_iter().map(|block| (block, false)));
+                }
+            }
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    if options.docker_networks {
+        match container::docker_networks() {
+            Ok(subnets) => {
+                for (name, subnet) in subnets {
+                    say!("Docker network {} is {}.", name, subnet);
+                    target_list.push((subnet, false));
+                }
+            }
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    //--local sweeps the networks attached to every interface at once, and reports them per interface
+    let mut attached = Vec::new();
+    if options.local {
+        match local::attached() {
+            Ok((networks, too_wide)) => {
+                for network in &too_wide {
+                    say!("\x1b[0;33mNote:\x1b[0m {} on {} is wider than a /16, leaving it out.", network.block(), network.interface);
+                }
+                for network in &networks {
+                    say!("Interface {} is on {}.", network.interface, network.block());
+                    target_list.push((network.block(), false));
+                }
+                attached = networks;
+            }
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    //each VLAN on the trunk is swept through its tagged subinterface, set up here and taken down when the scan ends
+    let mut vlans = None;
+    if let Some(trunk) = &options.trunk {
+        let dry_run = options.dry_run || options.export_plan.is_some();
+        match vlan::Vlans::setup(trunk, &options.vlans, dry_run) {
+            Ok((setup, ready)) => {
+                for vlan in ready {
+                    let created = match (vlan.created, dry_run) {
+                        (true, true) => ", to be created for the scan",
+                        (true, false) => ", created for the scan",
+                        _ => "",
+                    };
+                    say!("VLAN {} is {} on {}{}.", vlan.id, vlan.block, vlan.interface, created);
+                    target_list.push((vlan.block, false));
+                }
+                vlans = Some(setup);
+                //Ctrl-C would kill the scan before it takes the subinterfaces down, it stops the sweep instead
+                if !dry_run {
+                    service::install_handlers();
+                }
+            }
+            Err(error) => {
+                eprintln!("Cannot set up the VLANs on {}, {}", trunk, error);
+                return;
+            }
+        }
+    }
+    //cloud inventories are swept as the blocks their addresses make up and checked against the results
+    let mut inventories = Vec::new();
+    for source in &options.targets_from {
+        match cloud::import(source) {
+            Ok(inventory) => {
+                let addresses: Vec<Ipv4Addr> = inventory.entries.iter().map(|(address, _)| *address).collect();
+                say!("{} lists {} addresses.", source, addresses.len());
+                let blocks = subnet::aggregate(&addresses);
+                target_list.extend(blocks.into_iter().map(|(network, prefix)| (format!("{}/{}", network, prefix), false)));
+                inventories.push(inventory);
+            }
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
+    if target_list.is_empty() && replay::active() {
+        let blocks = subnet::aggregate(&replay::addresses());
+        target_list.extend(blocks.into_iter().map(|(network, prefix)| (format!("{}/{}", network, prefix), false)));
+    }
+    if target_list.is_empty() {
+        let mut ip_cidr = String::new();
+        print!("Please enter an IP address with CIDR notation (e.g. 192.168.1.0/24): ");
+        io::stdout().flush().unwrap();
+        io::stdin().read_line(&mut ip_cidr).unwrap();
+        match ip_cidr.trim() {
+            "-" => target_list.extend(target::read_stdin_targets().into_iter().map(|line| (line, true))),
+            ip_cidr => target_list.push((ip_cidr.to_string(), false)),
         }
     }
 
-    true
-}
+    //custom resolvers are used for every lookup, otherwise forward lookups go through the system
+    let custom_resolver = if options.resolvers.is_empty() && options.doh.is_none() {
+        None
+    }
+    else {
+        match dns::Resolver::from_options(&options.resolvers, options.doh.as_deref()) {
+            Ok(resolver) => Some(resolver),
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        }
+    };
+
+    //validate and expand every target into the addresses it covers, timed for the performance summary
+    let run_started = Instant::now();
+    let mut targets = Vec::new();
+    for (entry, from_list) in &target_list {
+        match target::expand(entry, custom_resolver.as_ref()) {
+            Ok(target) => targets.push(target),
+            //a bad line in piped input is skipped rather than aborting the whole sweep
+            Err(error) if *from_list => eprintln!("Skipping {}, {}", entry, error),
+            Err(error) => {
+                eprintln!("Input validation failed, {}", error);
+                return;
+            }
+        }
+    }
 
+    //point out targets that overlap, the same target twice is only kept once
+    for overlap in target::overlaps(&targets) {
+        say!("\x1b[0;33mNote:\x1b[0m {}, shared addresses are probed once.", overlap);
+    }
+    let mut labels_seen = HashSet::new();
+    targets.retain(|target| labels_seen.insert(target.label.clone()));
+    let mut expansion = run_started.elapsed();
 
-fn main() {
-    clear_screen();
-    //get user input:
-    banner("Network Host Discovery");
-    println!("");
-    let mut ip_cidr = String::new();
-    
-    print!("Please enter an IP address with CIDR notation (e.g. 192.168.1.0/24): ");
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut ip_cidr).unwrap();
-    let ip_cidr = ip_cidr.trim();
-  
-    //Validate IP with CIDR prefix
-    let (_ip_address, _cidr_prefix) = match validate_ip_cidr(ip_cidr) {
-        Ok((ip, cidr)) => (ip, cidr),
+    //a comparison sets two separate ranges side by side
+    if options.compare && (targets.len() != 2 || !target::overlaps(&targets).is_empty()) {
+        eprintln!("Input validation failed, --compare needs exactly two ranges that don't overlap.");
+        return;
+    }
+
+    //every run is written to the audit log before anything is sent, with [audit] required a run that can't
+    //be audited doesn't run
+    let action = if options.dns_sweep {
+        "dns-sweep"
+    }
+    else if options.watch.is_some() || options.watch_scopes {
+        "watch"
+    }
+    else if options.dry_run {
+        "dry-run"
+    }
+    else if !options.paths.is_empty() {
+        "paths"
+    }
+    else if options.replay.is_some() {
+        "replay"
+    }
+    else if planned.is_some() {
+        "execute-plan"
+    }
+    else {
+        "scan"
+    };
+    if let Err(error) = audit::record(options.config.as_deref(), action, &targets, &args) {
+        eprintln!("Cannot write the audit log, {}", error);
+        fail(vlans);
+    }
+
+    //a DNS sweep replaces the ping sweep entirely
+    if options.dns_sweep {
+        dns_sweep(&targets, &options.resolvers, options.doh.as_deref());
+        return;
+    }
+
+    //say what kind of address space is about to be probed, and refuse to sweep public ranges unless allowed
+    for target in &targets {
+        let class = subnet::classify(target.network, target.prefix);
+        say!("Target {} is {} address space.", target.label, class);
+        if class != "public" {
+            continue;
+        }
+        if target.prefix < 32 && !options.allow_public {
+            eprintln!(
+                "\x1b[31mRefusing to sweep public range {}\x1b[0m, make sure you are authorised to scan it and rerun with --allow-public.",
+                target.label
+            );
+            return;
+        }
+        say!("\x1b[0;33mWarning:\x1b[0m {} is public address space, only probe hosts you own or are authorised to test.", target.label);
+    }
+
+    //--paths pings from each interface and compares, rather than sweeping once
+    if !options.paths.is_empty() {
+        if let Err(error) = paths::check(&options.paths) {
+            eprintln!("Cannot compare paths, {}", error);
+            fail(vlans);
+        }
+        let addresses = target::unique_addresses(&targets).to_vec();
+        paths::compare(&addresses, &options.paths, options.concurrency.unwrap_or(paths::DEFAULT_WORKERS));
+        return;
+    }
+
+    //the ASN dataset is read once up front, every live public host is looked up in it
+    if let Some(path) = &options.asn_data {
+        match asn::load(path) {
+            Ok(count) => say!("Loaded {} announced ranges from {}.", count, path),
+            Err(error) => {
+                eprintln!("Cannot load the ASN data, {}", error);
+                fail(vlans);
+            }
+        }
+    }
+
+    //watch mode keeps sweeping and reports changes instead of doing a single sweep, --api answers
+    //questions about the stored scans meanwhile
+    if let Some(address) = &options.api {
+        if let Err(error) = api::serve(address, options.config.as_deref(), options.api_insecure) {
+            eprintln!("{}", error);
+            fail(vlans);
+        }
+    }
+    if options.watch_scopes {
+        if let Err(error) = monitor::run_scopes(&options, &args) {
+            eprintln!("{}", error);
+            fail(vlans);
+        }
+        return;
+    }
+    if let Some(interval) = options.watch {
+        if let Err(error) = monitor::run(&targets, &options, &args, interval, None) {
+            eprintln!("{}", error);
+            fail(vlans);
+        }
+        return;
+    }
+
+    //hosts slower than the latency thresholds are flagged as they are found
+    let thresholds = match config::load(options.config.as_deref()).and_then(|config| latency::Thresholds::from_options(&config, &options)) {
+        Ok(thresholds) => thresholds,
         Err(error) => {
-            eprintln!("Input validation failed, {}.", error);
+            eprintln!("{}", error);
             return;
         }
     };
-    
-    //split the ip_cidr variable into two variables, one for IP, one for CIDR
-    let mut parts = ip_cidr.split("/");
-    let ip_address = parts.next().unwrap();
-    let cidr_not = parts.next().unwrap();
-    
-    //parse and convert the ip address from a string into an ipv4addr that can be used
-    let ip_addr_parse = ip_address.parse::<Ipv4Addr>().unwrap();
-    let cidr_not_parse = cidr_not.parse().unwrap();
-    let subnet_mask = !0u32.checked_shr(cidr_not_parse).unwrap_or(0);
 
-    //convert the ip address and subnet mask to a u32
-    let ip_address_u32 = u32::from(ip_addr_parse);
-    let subnet_mask_u32 = u32::from(subnet_mask);
+    //--vendor-filter, --vendor-exclude and --hostname-filter narrow what is reported, not what is probed
+    let filter = filter::Filter::from_options(&options);
+
+    //--shard K --shards N sweeps the Kth of N slices of the addresses
+    let shard = options.shard.zip(options.shards);
+
+    //set up the progress side channel if a wrapper asked for one
+    let mut progress = if options.progress_json {
+        match progress::ProgressReporter::new(options.progress_file.as_deref(), shard) {
+            Ok(reporter) => Some(reporter),
+            Err(error) => {
+                eprintln!("Progress reporting failed, {}", error);
+                return;
+            }
+        }
+    }
+    else {
+        None
+    };
+    //every address of every target is probed once, printing each one as it finishes
+    //a plan's addresses are already filtered and ordered, they are probed as they are
+    let listing_started = Instant::now();
+    let mut addresses = match &planned {
+        Some(plan) => target::Addresses::Listed(plan.addresses.clone()),
+        None => target::unique_addresses(&targets),
+    };
+    //the slice is cut before anything that depends on the machine reorders or drops addresses
+    if let (Some((index, count)), None) = (shard, &planned) {
+        let total = addresses.len();
+        addresses = addresses.shard(index, count);
+        say!("Shard {} of {}, sweeping {} of the {} addresses.", index, count, addresses.len(), total);
+    }
+    //the interfaces take turns so each has probes out from the start, the orderings below keep the turns
+    if attached.len() > 1 && planned.is_none() {
+        local::interleave(addresses.list(), &attached);
+    }
+
+    //the scanning machine's own addresses are marked as self, or not probed at all with --skip-self
+    let own = enrich::own_addresses();
+    if options.skip_self && planned.is_none() {
+        let skipped: Vec<String> = addresses.iter().filter(|address| own.contains(address)).map(|address| address.to_string()).collect();
+        if !skipped.is_empty() {
+            addresses.list().retain(|address| !own.contains(address));
+            say!("Skipping this machine's own addresses, {}.", skipped.join(", "));
+        }
+    }
+    let address_total = addresses.len();
+    expansion += listing_started.elapsed();
 
-    //create a vec to store all up ip addresses:
-    let mut up_ips = Vec::new();
+    //create a vec to store the result of every scanned address:
+    let mut results: Vec<host::HostResult> = Vec::new();
 
     //create variables for tracking total versus up ip scans:
     let mut total_count = 0;
     let mut up_count = 0;
-    
-    //iterate through all the possible IP addresses given the provided IP/CIDR, sending
-    //each possible address to the ping function above
-
-    println!("");
-    for i in 0..(1 << (32 - cidr_not_parse)) {
-        let address_u32 = ip_address_u32 & subnet_mask_u32 | i;
-        let address = Ipv4Addr::from(address_u32);
-        let address: &str = &address.to_string();
-
-    //start the process of pinging all the addresses
-    let ping_out = Command::new("ping")     //runs the ping command
-    .arg(address)                                  //provides the argument from the function as an argument to the ping command
-    .arg("-c 1")                                   //adds the -c 1 argument, telling the command to only run once (ping will run until interrupted by default)
-    .stdout(Stdio::piped())                   //captures the output of the ping command
-    .output()
-    .unwrap();
-
-    let ping_stdout = String::from_utf8(ping_out.stdout).unwrap();
-  
-    total_count += 1;
-
-    if ping_stdout.contains("1 received") {
-        up_count += 1;
-        println!("Ping successful, {} is \x1b[0;32mup\x1b[0m.", address);
-        up_ips.push(address.to_string());
+
+    //create a variable for tracking probes that had to be retried after errors on the scanning host:
+    let mut retry_count = 0;
+
+    //the time the workers spent on each phase, and how far into the sweep each live host was found
+    let mut probing = Duration::ZERO;
+    let mut enrichment = Duration::ZERO;
+    let mut found_at: Vec<Duration> = Vec::new();
+
+    //work out how many probes can safely run at once
+    let limits = concurrency::detect_limits();
+    let controller = concurrency::Controller::new(options.concurrency, options.ramp, &limits);
+    match options.concurrency {
+        Some(workers) if options.ramp.is_some() => {
+            say!("Probing with 1 worker, ramping up to {} over {}s.", workers, options.ramp.unwrap_or_default().as_secs())
+        }
+        Some(workers) => say!("Probing with {} workers.", workers),
+        None => say!(
+            "Probing with up to {} workers (open file limit {}, {} available), starting at {}.",
+            limits.ceiling,
+            limits.open_files.map(|limit| limit.to_string()).unwrap_or_else(|| "unknown".to_string()),
+            limits.available_memory.map(|bytes| format!("{} MB", bytes / 1024 / 1024)).unwrap_or_else(|| "unknown memory".to_string()),
+            controller.active()
+        ),
+    }
+
+    say!();
+
+    //every run is stored, a delta scan compares against the previous one and re-checks its up hosts first
+    let labels: Vec<&str> = targets.iter().map(|target| target.label.as_str()).collect();
+    //a shard is stored apart from whole sweeps of the same targets, it would make a poor baseline for them
+    let scan_key = match shard {
+        Some((index, count)) => format!("{},shard-{}-of-{}", store::scan_key(&labels), index, count),
+        None => store::scan_key(&labels),
+    };
+    let previous = if options.delta {
+        match store::load_latest_scan(&scan_key) {
+            Ok(Some(previous)) => {
+                let was_up: HashSet<Ipv4Addr> = previous.hosts.iter().filter(|host| host.is_up()).map(|host| host.address).collect();
+                if planned.is_none() {
+                    addresses.list().sort_by_key(|address| !was_up.contains(address));
+                }
+                say!("Comparing against the stored scan of {}, re-checking its {} up hosts first.", labels.join(", "), was_up.len());
+                Some(previous)
+            }
+            Ok(None) => {
+                say!("No stored scan of {} yet, this run becomes the baseline.", labels.join(", "));
+                None
+            }
+            Err(error) => {
+                eprintln!("Cannot load the previous scan, {}", error);
+                None
+            }
+        }
+    }
+    else {
+        None
+    };
+
+    //every run refreshes the cache of up hosts, --cache reuses the fresh ones instead of probing them
+    let mut cache = store::load_cache().unwrap_or_else(|error| {
+        eprintln!("Ignoring the result cache, {}", error);
+        HashMap::new()
+    });
+
+    //--priority probes the addresses most likely to be in use first so a big sweep has results within seconds
+    if options.priority && planned.is_none() {
+        let mut seen: HashSet<Ipv4Addr> = cache.keys().copied().collect();
+        if let Some(previous) = &previous {
+            seen.extend(previous.hosts.iter().filter(|host| host.is_up()).map(|host| host.address));
+        }
+        target::prioritise(addresses.list(), &seen);
+    }
+
+    let started_at = store::now();
+    let mut metadata = metadata::ScanMetadata::new(started_at, &args);
+    metadata.shard = shard;
+    if let Some(max_age) = options.cache {
+        addresses.list().retain(|address| {
+            let Some(entry) = cache.get(address) else {
+                return true;
+            };
+            match store::reuse(entry, max_age, started_at, &options.ports, &options.udp_ports) {
+                Some(mut host) => {
+                    enrich::mark_self(&mut host, &own);
+                    if filter.matches(&host) {
+                        say!(
+                            "Cached, {} is \x1b[0;32mup\x1b[0m{} (probed {}s ago).",
+                            address,
+                            identity_suffix(&host),
+                            started_at.saturating_sub(entry.probed_at)
+                        );
+                        for port in host.ports.iter().filter(|port| port.state == host::PortState::Open) {
+                            say!("    {}/{} is \x1b[0;32mopen\x1b[0m{}", port.port, port.protocol, service_suffix(port));
+                        }
+                    }
+                    results.push(host);
+                    total_count += 1;
+                    up_count += 1;
+                    false
+                }
+                None => true,
+            }
+        });
+        if !results.is_empty() {
+            say!("Reused {} cached hosts, probing the other {} addresses.", results.len(), addresses.len());
+        }
+    }
+    let cached_count = results.len();
+
+    //--dry-run stops at the plan, after the exclusions and ordering that decide what would be probed
+    if options.dry_run {
+        let plan = plan::Plan::new(&addresses.to_vec(), &labels, &args, &options, &controller);
+        plan.print();
+        if let Some(path) = &options.export_plan {
+            match plan.save(path, options.config.as_deref()) {
+                Ok(()) => println!("Plan signed and written to {}.", path),
+                Err(error) => {
+                    eprintln!("Cannot export the plan, {}", error);
+                    fail(vlans);
+                }
+            }
+        }
+        return;
+    }
+
+    //--whois asks the registries who owns each public range, once per range, for the report header
+    if options.whois {
+        for target in targets.iter().filter(|target| subnet::classify(target.network, target.prefix) == "public") {
+            match whois::lookup(&target.label, target.network) {
+                Ok(owner) => {
+                    say!("Whois {}", owner.describe());
+                    metadata.owners.push(owner);
+                }
+                Err(error) => eprintln!("Whois lookup of {} failed, {}", target.label, error),
+            }
+        }
+    }
+
+    //p, r and s pause, resume and sum up the scan when it runs in a terminal
+    let tally = Arc::new(keys::Tally::new(address_total));
+    tally.probed.store(total_count, Ordering::Relaxed);
+    tally.up.store(up_count, Ordering::Relaxed);
+    let key_listener = if QUIET.load(Ordering::Relaxed) {
+        None
+    }
+    else {
+        keys::listen(controller.pause_switch(), Arc::clone(&tally))
+    };
+    if key_listener.is_some() {
+        say!("Press p to pause, r to resume or s for an interim summary.");
+    }
+
+    //--pcap records the sweep's traffic, a capture that can't start stops the scan rather than leave no evidence
+    let capture = match &options.pcap {
+        Some(path) => match capture::Capture::start(path, &addresses.blocks()) {
+            Ok(capture) => Some(capture),
+            Err(error) => {
+                eprintln!("Cannot capture to {}, {}", path, error);
+                fail(vlans);
+            }
+        },
+        None => None,
+    };
+
+    //the neighbours announce themselves every 30 or 60 seconds, listening starts before the sweep
+    let listener = match &options.lldp {
+        Some(interface) => match lldp::Listener::start(interface, options.lldp_time) {
+            Ok(listener) => Some(listener),
+            Err(error) => {
+                eprintln!("Cannot listen for LLDP and CDP on {}, {}", interface, error);
+                fail(vlans);
+            }
+        },
+        None => None,
+    };
+
+    //a sweep this big keeps only its live hosts in memory, the addresses that don't answer go to a spill
+    //file the summary counts back from, as do those of a sweep given --spill
+    let mut spill = match &options.spill {
+        Some(path) => match spill::Spill::create(path) {
+            Ok(spill) => Some(spill),
+            Err(error) => {
+                eprintln!("Cannot spill to {}, {}", path, error);
+                fail(vlans);
+            }
+        },
+        None if addresses.len() >= spill::THRESHOLD && options.snmp_arp.is_empty() => match spill::Spill::create_default(&metadata.id) {
+            Ok(spill) => Some(spill),
+            Err(error) => {
+                eprintln!("Cannot spill, keeping every address in memory, {}", error);
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(spill) = &spill {
+        say!("Writing the addresses that don't answer to {}.", spill.path());
+    }
+    //a delta scan reports the hosts that went down, so those stay in memory
+    let keep: HashSet<Ipv4Addr> = previous.iter().flat_map(|previous| &previous.hosts).filter(|host| host.is_up()).map(|host| host.address).collect();
+    let mut spilling = true;
+
+    let sweep_started = Instant::now();
+    scan::run(&addresses, &options, &controller, |finished| {
+        let mut host = finished.host;
+        enrich::mark_self(&mut host, &own);
+        let address = host.address;
+        retry_count += finished.retries;
+        total_count += 1;
+        tally.probed.store(total_count, Ordering::Relaxed);
+        tally.retries.store(retry_count as usize, Ordering::Relaxed);
+        probing += finished.probing;
+        enrichment += finished.enrichment;
+
+        if host.is_up() {
+            up_count += 1;
+            tally.up.store(up_count, Ordering::Relaxed);
+            found_at.push(sweep_started.elapsed());
+        }
+        //hosts the filter leaves out are still counted and stored, just not printed
+        if filter.matches(&host) {
+            match &host.outcome {
+                probe::Outcome::Up { .. } if !host.found_by.contains(&"icmp") => {
+                    say!(
+                        "No ping reply, but {} is \x1b[0;32mup\x1b[0m{}, found by {}.",
+                        address,
+                        identity_suffix(&host),
+                        found_by_label(&host.found_by)
+                    );
+                }
+                probe::Outcome::Up { .. } => {
+                    say!(
+                        "Ping successful, {} is \x1b[0;32mup\x1b[0m{}{}{}.",
+                        address,
+                        identity_suffix(&host),
+                        latency_suffix(&host, &thresholds),
+                        anomaly_suffix(&host)
+                    );
+                }
+                probe::Outcome::Down(probe::DownReason::Timeout) => {
+                    say!("Ping unsuccessful, {} is \x1b[31mdown\x1b[0m (no reply).", address);
+                }
+                probe::Outcome::Down(probe::DownReason::Unreachable { from }) => {
+                    say!("Ping unsuccessful, {} is \x1b[31munreachable\x1b[0m (reported by {}).", address, from);
+                }
+                probe::Outcome::Down(probe::DownReason::Prohibited { from }) => {
+                    say!("Ping unsuccessful, {} is \x1b[35madmin-prohibited\x1b[0m (filtered by {}).", address, from);
+                }
+                probe::Outcome::Down(probe::DownReason::SendError(error)) => {
+                    say!("Ping unsuccessful, probe to {} was \x1b[0;33mnot sent\x1b[0m ({}).", address, error);
+                }
+                probe::Outcome::LocalError(error) => {
+                    say!("Ping failed locally, {} could not be probed \x1b[0;33m({})\x1b[0m.", address, error);
+                }
+            }
+            for port in host.ports.iter().filter(|port| port.state == host::PortState::Open) {
+                say!("    {}/{} is \x1b[0;32mopen\x1b[0m{}", port.port, port.protocol, service_suffix(port));
+            }
+            //--bell rings the terminal for every live host, for someone waiting on a device to turn up
+            if options.bell && host.is_up() && !QUIET.load(Ordering::Relaxed) {
+                print!("\x07");
+                let _ = io::stdout().flush();
+            }
+        }
+
+        //local errors mean the scanning host is struggling, bursts of timeouts may be the far end rate limiting
+        match controller.record(concurrency::Signal::of(&host, finished.retries)) {
+            Some(concurrency::Notice::BackedOff(workers)) => {
+                say!("\x1b[0;33mLocal errors, backing off to {} workers.\x1b[0m", workers);
+            }
+            Some(concurrency::Notice::RampHeld(workers)) => {
+                say!("\x1b[0;33mTimeouts rising, holding the ramp at {} workers.\x1b[0m", workers);
+            }
+            Some(concurrency::Notice::RampResumed(workers)) => {
+                say!("Timeouts back to normal, ramping on from {} workers.", workers);
+            }
+            Some(concurrency::Notice::Throttled { workers, pace }) => say!(
+                "\x1b[0;33mICMP rate limiting suspected, slowing to {} workers with {}ms between probes.\x1b[0m",
+                workers,
+                pace.as_millis()
+            ),
+            None => {}
+        }
+
+        match spill.as_mut() {
+            Some(spill) if spilling && !host.is_up() && !keep.contains(&address) => {
+                if let Err(error) = spill.push(&host) {
+                    eprintln!("Cannot spill any more, keeping the rest in memory, {}", error);
+                    spilling = false;
+                    results.push(host);
+                }
+            }
+            _ => results.push(host),
+        }
+        io::stdout().flush().unwrap();
+
+        if let Some(progress) = progress.as_mut() {
+            progress.update(total_count, address_total, up_count);
+        }
+    });
+    drop(key_listener);
+
+    //Ctrl-C during a sweep through VLAN subinterfaces stops it, they are taken down on the way out
+    if service::stop_requested() {
+        eprintln!("\nInterrupted after {} of {} addresses, taking down the VLAN subinterfaces.", total_count, address_total);
+        drop(listener);
+        fail(vlans);
+    }
+
+    //timeouts during rate limiting are likely false downs, look at them again at the slower pace
+    let suspects = controller.take_suspects();
+    let mut recovered: HashSet<Ipv4Addr> = HashSet::new();
+    if !suspects.is_empty() {
+        say!("\nRe-checking {} addresses that timed out while rate limited.", suspects.len());
+        let found = scan::recheck(&suspects, &mut results, &options, &controller);
+        for address in &found {
+            let Some(host) = results.iter().find(|host| host.address == *address && filter.matches(host)) else {
+                continue;
+            };
+            say!(
+                "Ping successful, {} is \x1b[0;32mup\x1b[0m{} after all.",
+                address,
+                identity_suffix(host)
+            );
+        }
+        up_count += found.len();
+        found_at.extend(found.iter().map(|_| sweep_started.elapsed()));
+        say!("{} of {} were up after all.", found.len(), suspects.len());
+        recovered.extend(found);
+    }
+    let sweep = sweep_started.elapsed();
+
+    //what is still buffered is written out before the summary reads the file back
+    let spilled = spill.and_then(|spill| {
+        let count = spill.count;
+        match spill.finish() {
+            Ok(path) => Some((path, count)),
+            Err(error) => {
+                eprintln!("Cannot spill, the summary leaves those addresses out, {}", error);
+                None
+            }
+        }
+    });
+
+    //--enrich-after names, port scans and identifies the live hosts only now the sweep is done,
+    //hosts reused from the result cache were enriched when they were probed
+    if options.enrich_after && !replay::active() {
+        let live: Vec<host::HostResult> = results[cached_count..].iter().filter(|host| host.is_up()).cloned().collect();
+        if !live.is_empty() {
+            say!("\nEnriching {} live hosts.", live.len());
+            let enrich_started = Instant::now();
+            scan::enrich_later(live, &options, &controller, |finished| {
+                enrichment += finished.enrichment;
+                if let Some(slot) = results.iter_mut().find(|host| host.address == finished.host.address) {
+                    *slot = finished.host;
+                }
+            });
+            say!("Enriched them in {:.2}s.", enrich_started.elapsed().as_secs_f64());
+        }
+    }
+
+    //the DC SRV records name domain controllers the rootDSE alone may not have given away
+    if options.profiles.iter().any(|profile| profile == "ad") {
+        for located in ad::locate(&mut results, &options) {
+            say!(
+                "\x1b[0;32mDomain controller {} ({}) listed for {}.\x1b[0m",
+                located.name, located.address, located.domain
+            );
+        }
+    }
+
+    //the routers and switches know hosts that don't answer probes, or sit where the probes don't reach
+    if !options.snmp_arp.is_empty() {
+        let config = config::load(options.config.as_deref());
+        let mut harvests = Vec::new();
+        for device in &options.snmp_arp {
+            match config.as_ref().map_err(String::clone).and_then(|config| snmp::harvest(device, config)) {
+                Ok(harvest) => {
+                    say!(
+                        "{} has {} ARP and {} MAC address table entries.",
+                        device,
+                        harvest.arp.len(),
+                        harvest.macs.len()
+                    );
+                    harvests.push(harvest);
+                }
+                Err(error) => eprintln!("SNMP harvest failed, {}", error),
+            }
+        }
+        let seen = snmp::merge(&harvests, &mut results);
+        if seen > 0 {
+            say!("{} addresses that didn't answer were seen by the infrastructure.", seen);
+        }
+    }
+
+    let mut neighbours = Vec::new();
+    if let Some(listener) = listener {
+        let interface = listener.interface().to_string();
+        if !listener.remaining().is_zero() {
+            say!("Listening for LLDP and CDP on {} for another {}s.", interface, listener.remaining().as_secs());
+        }
+        match listener.finish() {
+            Ok(heard) => {
+                lldp::mark_roles(&heard, &mut results);
+                neighbours = heard;
+            }
+            Err(error) => eprintln!("Listening for LLDP and CDP on {} failed, {}", interface, error),
+        }
+    }
+
+    if let (Some(capture), Some(path)) = (capture, &options.pcap) {
+        //tcpdump writes the capture as it goes, it is compressed and encrypted once it is complete
+        let stopped = capture.stop().and_then(|packets| match (&options.compress, &options.encryption) {
+            (None, None) => Ok(packets),
+            (method, encryption) => compress::file(path, method.as_deref(), encryption.as_ref()).map(|_| packets),
+        });
+        match stopped {
+            Ok(Some(packets)) => {
+                say!("Captured {} packets to {}.", packets, path);
+                sign_file(path, &options);
+            }
+            Ok(None) => {
+                say!("Captured the scan traffic to {}.", path);
+                sign_file(path, &options);
+            }
+            Err(error) => eprintln!("Capture to {} failed, {}", path, error),
+        }
+    }
+
+    //a replay isn't a real scan, it leaves the caches and the stored scans alone
+    let replaying = replay::active();
+    if !replaying {
+        if let Err(error) = store::update_cache(&mut cache, &results[cached_count..], started_at) {
+            eprintln!("Failed to update the result cache, {}", error);
+        }
+        if let Err(error) = names::save() {
+            eprintln!("Failed to update the name cache, {}", error);
+        }
+    }
+
+    //workers finish in any order, put the results back in address order
+    results.sort_by_key(|host| host.address);
+    metadata.finished_at = store::now();
+    //the results and the stored scan say where the addresses left out of them went
+    metadata.spilled = spilled.clone();
+    if !replaying {
+        if let Err(error) = store::save_scan(&scan_key, &metadata, &results) {
+            eprintln!("Failed to store the scan, {}", error);
+        }
+    }
+
+    if let Some(progress) = progress.as_mut() {
+        progress.finish(total_count, address_total, up_count);
+    }
+
+    //the report only covers the hosts that pass the filter, --by-hops puts the nearest first
+    let mut reported: Vec<host::HostResult> = results.iter().filter(|host| filter.matches(host)).cloned().collect();
+    if options.by_hops {
+        reported.sort_by_key(|host| host.hops().unwrap_or(u8::MAX));
+    }
+    else if attached.len() > 1 {
+        reported.sort_by_key(|host| local::network_of(host.address, &attached).unwrap_or(attached.len()));
+    }
+
+    say!();
+    banner("Results");
+    say!();
+    match (&options.output, &options.output_file) {
+        //the results go to a file in the chosen format
+        (Some(format), Some(path)) => match output::write_file(path, format, &metadata, &options, &reported) {
+            Ok(()) => {
+                say!("Results written to {} as {}.", path, format);
+                sign_file(path, &options);
+            }
+            Err(error) => eprintln!("Failed to write results, {}", error),
+        },
+        //the chosen format replaces the list of up addresses
+        (Some(format), None) => match output::render(format, &metadata, &options, &reported) {
+            Ok(document) if !QUIET.load(Ordering::Relaxed) => print!("{}", document),
+            Ok(_) => {}
+            Err(error) => eprintln!("{}", error),
+        },
+        //a delta scan reports what changed rather than every up host
+        (None, _) if QUIET.load(Ordering::Relaxed) => {}
+        (None, _) if options.compare => compare::print_comparison(&targets[0], &targets[1], &reported),
+        (None, _) if previous.is_some() => {
+            if let Some(previous) = &previous {
+                //a host that matched last time and is gone now is still a change worth reporting
+                let before: Vec<host::HostResult> = previous.hosts.iter().filter(|host| filter.matches(host)).cloned().collect();
+                let current: Vec<host::HostResult> = results
+                    .iter()
+                    .filter(|host| filter.matches(host) || before.iter().any(|old| old.address == host.address))
+                    .cloned()
+                    .collect();
+                delta::print_changes(&before, &current, started_at.saturating_sub(previous.started_at));
+            }
+        }
+        (None, _) => {
+            //print summary of all up ip addresses:
+            say!("The following IP addresses were up:");
+            let mut distance = None;
+            let mut network = None;
+            let by_interface = !options.by_hops && attached.len() > 1;
+            for host in reported.iter().filter(|host| host.is_up()) {
+                //grouped under a heading per hop distance, local hosts apart from those behind routers
+                if options.by_hops && distance != Some(host.hops()) {
+                    distance = Some(host.hops());
+                    say!("{}", hops_heading(host.hops()));
+                }
+                //or under the interface they were found on
+                else if by_interface && network != Some(local::network_of(host.address, &attached)) {
+                    network = Some(local::network_of(host.address, &attached));
+                    match network.flatten().map(|index| &attached[index]) {
+                        Some(attached) => say!("On {} ({}):", attached.interface, attached.block()),
+                        None => say!("Elsewhere:"),
+                    }
+                }
+                let open: Vec<String> = host
+                    .ports
+                    .iter()
+                    .filter(|port| port.state == host::PortState::Open)
+                    .map(|port| format!("{}/{}{}", port.port, port.protocol, service_suffix(port)))
+                    .collect();
+                let colour = host.rtt().map(|rtt| thresholds.level(rtt)).unwrap_or(latency::Level::Normal).colour();
+                let mut suffix = format!("{}{}{}", identity_suffix(host), latency_suffix(host, &thresholds), anomaly_suffix(host));
+                //with several targets each host is attributed to all of those it falls in, the interface heading says it already
+                if targets.len() > 1 && !by_interface {
+                    suffix.push_str(&format!(" in {}", target::scopes_of(host.address, &targets).join(", ")));
+                }
+                if open.is_empty() {
+                    say!("{}{}\x1b[0m{}", colour, host.address, suffix);
+                }
+                else {
+                    say!("{}{}\x1b[0m{}  {}", colour, host.address, suffix, open.join(", "));
+                }
+            }
+            let seen: Vec<&host::HostResult> = reported.iter().filter(|host| host.seen_by.is_some()).collect();
+            if !seen.is_empty() {
+                say!("Seen by the infrastructure, but didn't answer:");
+                for host in seen {
+                    say!("\x1b[0;33m{}\x1b[0m{}  seen by {}", host.address, identity_suffix(host), host.seen_by.as_deref().unwrap_or(""));
+                }
+            }
+        }
+    }
+
+    //print summary of up vs total ports:
+    say!();
+    match shard {
+        Some((index, count)) => say!("Scanned a total of {} IP addresses in shard {} of {}, of which {} were up.", total_count, index, count, up_count),
+        None => say!("Scanned a total of {} IP addresses, of which {} were up.", total_count, up_count),
+    }
+    if let Some((path, count)) = &spilled {
+        say!("The {} addresses that didn't answer during the sweep are in {}.", count, path);
+    }
+    //how the hosts were found, what answers ICMP, ARP and TCP says a lot about the segment's firewalls
+    let mut methods: Vec<(String, usize)> = Vec::new();
+    for host in results.iter().filter(|host| host.is_up() && !host.found_by.is_empty()) {
+        let label = found_by_label(&host.found_by);
+        match methods.iter_mut().find(|(known, _)| *known == label) {
+            Some((_, count)) => *count += 1,
+            None => methods.push((label, 1)),
+        }
+    }
+    if !methods.is_empty() {
+        methods.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let breakdown: Vec<String> = methods.iter().map(|(label, count)| format!("{} {}", count, label)).collect();
+        say!("Found by: {}.", breakdown.join(", "));
+    }
+    if attached.len() > 1 {
+        let per_interface: Vec<String> = attached
+            .iter()
+            .map(|network| {
+                let scanned: Vec<&host::HostResult> = results.iter().filter(|host| network.contains(host.address)).collect();
+                let up = scanned.iter().filter(|host| host.is_up()).count();
+                format!("{} {} of {}", network.interface, up, scanned.len())
+            })
+            .collect();
+        say!("Up per interface: {}.", per_interface.join(", "));
+    }
+    if filter.is_set() {
+        say!("{} of the up hosts matched {}.", reported.len(), filter.describe());
+    }
+    if thresholds.is_set() {
+        let levels: Vec<latency::Level> = results.iter().filter_map(|host| host.rtt()).map(|rtt| thresholds.level(rtt)).collect();
+        let warning = levels.iter().filter(|level| **level == latency::Level::Warning).count();
+        let critical = levels.iter().filter(|level| **level == latency::Level::Critical).count();
+        if warning + critical > 0 {
+            say!("{} up hosts were over the latency thresholds, {} warning and {} critical.", warning + critical, warning, critical);
+        }
+    }
+    //duplicate replies and replies from other addresses are flagged rather than counted twice
+    let odd: Vec<String> = reported
+        .iter()
+        .filter(|host| !host.anomalies().is_empty())
+        .map(|host| format!("{} ({})", host.address, host.anomalies().join(", ")))
+        .collect();
+    if !odd.is_empty() {
+        say!("\x1b[0;33m{} hosts sent odd replies:\x1b[0m {}.", odd.len(), odd.join("; "));
+    }
+    //a randomized MAC changes with the network or the connection, it doesn't identify the device for long
+    let randomized: Vec<String> = reported.iter().filter(|host| host.randomized_mac() == Some(true)).map(|host| host.address.to_string()).collect();
+    if !randomized.is_empty() {
+        say!(
+            "{} hosts answered from randomized MACs, they won't identify the same devices in later scans: {}.",
+            randomized.len(),
+            randomized.join(", ")
+        );
+    }
+    say!("Scan {} finished at {}.", metadata.id, metadata::iso8601(metadata.finished_at));
+    //the spilled addresses are counted back from the file a line at a time, never all in memory
+    let mut down_reasons: Vec<(&str, usize)> = Vec::new();
+    let mut error_count = 0;
+    let mut count_down = |host: &host::HostResult| match &host.outcome {
+        probe::Outcome::Down(reason) => match down_reasons.iter_mut().find(|(label, _)| *label == reason.label()) {
+            Some((_, count)) => *count += 1,
+            None => down_reasons.push((reason.label(), 1)),
+        },
+        probe::Outcome::LocalError(_) => error_count += 1,
+        probe::Outcome::Up { .. } => {}
+    };
+    results.iter().for_each(&mut count_down);
+    if let Some((path, _)) = &spilled {
+        if let Err(error) = spill::read(path, &recovered, |host| count_down(&host)) {
+            eprintln!("Cannot count the spilled addresses, {}", error);
+        }
+    }
+    if !down_reasons.is_empty() {
+        down_reasons.sort();
+        let breakdown: Vec<String> = down_reasons.iter().map(|(label, count)| format!("{} {}", count, label)).collect();
+        say!("Down addresses by reason: {}.", breakdown.join(", "));
     }
 
+    //print the live and free addresses as CIDR blocks, handy for firewall rules and scope documents
+    //the free blocks are the addresses probed cut around the live ones, never listing every address, so a
+    //shard or --skip-self doesn't call addresses free that this run never asked
+    if options.aggregate {
+        let live: Vec<Ipv4Addr> = results.iter().filter(|host| host.is_up()).map(|host| host.address).collect();
+
+        say!();
+        print_blocks("Live address blocks:", &subnet::aggregate(&live));
+        say!();
+        print_blocks("Free address blocks:", &addresses.blocks_without(&live));
+    }
+
+    //inventoried addresses that didn't answer may be stopped, firewalled or gone
+    if !inventories.is_empty() && !QUIET.load(Ordering::Relaxed) {
+        say!();
+        for inventory in &inventories {
+            cloud::print_reconciliation(inventory, &results);
+        }
+    }
+
+    //the switch port and VLAN the scanning machine is on, from what the neighbours announced
+    if let Some(interface) = &options.lldp {
+        say!();
+        if neighbours.is_empty() {
+            say!("No LLDP or CDP announcements were heard on {}.", interface);
+        }
+        else {
+            say!("Neighbours heard on {}:", interface);
+            for neighbour in &neighbours {
+                say!("    {}", neighbour.describe());
+            }
+        }
+    }
+
+    //print the round trip time distribution of all up ip addresses:
+    say!();
+    let rtts: Vec<f64> = results.iter().filter_map(|host| host.rtt()).collect();
+    if !QUIET.load(Ordering::Relaxed) {
+        stats::print_rtt_summary(&rtts);
+        say!();
+        stats::Performance {
+            duration: run_started.elapsed(),
+            expansion,
+            sweep,
+            //hosts reused from the result cache weren't probed this time, and only live ones are reused
+            probed: total_count - cached_count,
+            retries: retry_count,
+            timeouts: down_reasons.iter().find(|(label, _)| *label == probe::DownReason::Timeout.label()).map_or(0, |(_, count)| *count),
+            errors: error_count,
+            probing,
+            enrichment,
+            found_at,
+        }
+        .print();
+    }
+
+    //write the network map if one was requested
+    if let Some(path) = &options.export_dot {
+        match export::write_dot(path, &targets, &results, options.compress.as_deref(), options.encryption.as_ref()) {
+            Ok(()) => {
+                say!("\nNetwork map written to {}.", path);
+                sign_file(path, &options);
+            }
+            Err(error) => eprintln!("\nFailed to write network map, {}.", error),
+        }
+    }
+
+    //push the live hosts into the IPAM
+    if options.netbox {
+        match config::load(options.config.as_deref()).and_then(|config| netbox::export(&config, &targets, &results)) {
+            Ok(summary) => say!(
+                "\nNetBox updated, {} addresses created, {} updated, {} failed.",
+                summary.created, summary.updated, summary.failed
+            ),
+            Err(error) => eprintln!("\nNetBox export failed, {}.", error),
+        }
+    }
+
+    //a sweep left running in the background says when it is done
+    if options.notify_desktop {
+        let labels: Vec<&str> = targets.iter().map(|target| target.label.as_str()).collect();
+        let title = format!("host_disco: sweep of {} finished", labels.join(", "));
+        let text = format!("{} of {} addresses up, in {}s.", up_count, total_count, store::now().saturating_sub(started_at));
+        if let Err(error) = notify::desktop(&title, &text) {
+            eprintln!("Desktop notification failed, {}", error);
+        }
+    }
+
+    //the subinterfaces and addresses set up for the VLANs go before the exit status below skips it
+    drop(vlans);
+
+    //health checks only care whether enough hosts answered, the exit status says so
+    let live_count = reported.iter().filter(|host| host.is_up()).count();
+    if options.count {
+        println!("{}", live_count);
+    }
+    if options.count || options.silent || options.min_up.is_some() {
+        let min_up = options.min_up.unwrap_or(1);
+        if live_count < min_up {
+            say!("\nOnly {} hosts were up, fewer than the {} required.", live_count, min_up);
+            process::exit(1);
+        }
+    }
+}
+
+//function to exit with a failure once the VLANs are set up, process::exit skips the Drop that takes
+//their subinterfaces and addresses down again
+fn fail(vlans: Option<vlan::Vlans>) -> ! {
+    drop(vlans);
+    process::exit(1);
+}
+
+//function to name a live host in the output, e.g. " (fileserver.lab, B8:27:EB:12:34:56 Raspberry Pi)"
+fn identity_suffix(host: &host::HostResult) -> String {
+    let mut parts = Vec::new();
+    if let Some(hostname) = &host.hostname {
+        parts.push(hostname.clone());
+    }
+    match (&host.mac, &host.vendor) {
+        (Some(mac), Some(vendor)) => parts.push(format!("{} {}", mac, vendor)),
+        (Some(mac), None) if host.randomized_mac() == Some(true) => parts.push(format!("{} randomized", mac)),
+        (Some(mac), None) => parts.push(mac.clone()),
+        _ => {}
+    }
+    parts.extend(host.roles.iter().cloned());
+    parts.extend(host.asn.clone());
+    if parts.is_empty() {
+        String::new()
+    }
     else {
-        println!("Ping unsuccessful, {} is \x1b[31mdown\x1b[0m.", address);
+        format!(" ({})", parts.join(", "))
     }
-    io::stdout().flush().unwrap();
 }
 
-println!("");
-banner("Results");
-println!("");
-//print summary of all up ip addresses:
-println!("The following IP addresses were up:");
-for ip in up_ips{
-    println!("\x1b[0;32m{}\x1b[0m", ip);
+//function to flag a host over a latency threshold, e.g. " 63.20ms warning" in yellow
+fn latency_suffix(host: &host::HostResult, thresholds: &latency::Thresholds) -> String {
+    let Some(rtt) = host.rtt().filter(|_| thresholds.is_set()) else {
+        return String::new();
+    };
+    match thresholds.level(rtt) {
+        latency::Level::Normal => String::new(),
+        level => format!(" {}{:.2}ms {}\x1b[0m", level.colour(), rtt, level.label()),
+    }
 }
 
-//print summary of up vs total ports:
-println!("");
-println!("Scanned a total of {} IP addresses, of which {} were up.", total_count, up_count);
+//function to describe how a host was found, e.g. "ICMP only" or "ICMP and ARP"
+fn found_by_label(found_by: &[&str]) -> String {
+    let methods: Vec<String> = host::FOUND_BY
+        .iter()
+        .filter(|method| found_by.contains(method))
+        .map(|method| method.to_uppercase())
+        .collect();
+    match methods.as_slice() {
+        [method] => format!("{} only", method),
+        _ => methods.join(" and "),
+    }
+}
+
+//function to head a group of hosts the same distance away, e.g. "2 hops away:"
+fn hops_heading(hops: Option<u8>) -> String {
+    match hops {
+        Some(0) => "On the local segment:".to_string(),
+        Some(1) => "1 hop away:".to_string(),
+        Some(hops) => format!("{} hops away:", hops),
+        None => "Distance unknown:".to_string(),
+    }
+}
+
+//function to flag duplicate or foreign replies, e.g. " [2 duplicate replies]" in yellow
+fn anomaly_suffix(host: &host::HostResult) -> String {
+    let anomalies = host.anomalies();
+    if anomalies.is_empty() {
+        String::new()
+    }
+    else {
+        format!(" \x1b[0;33m[{}]\x1b[0m", anomalies.join(", "))
+    }
+}
+
+//function to add the guessed service name and any identification to a port, e.g. " (ssh)"
+fn service_suffix(port: &host::PortResult) -> String {
+    let mut suffix = port.service.as_ref().map(|service| format!(" ({})", service)).unwrap_or_default();
+    if let Some(info) = &port.info {
+        suffix.push_str(&format!(" [{}]", info));
+    }
+    suffix
+}
+
+//function to print a list of CIDR blocks under a heading
+fn print_blocks(heading: &str, blocks: &[(Ipv4Addr, u8)]) {
+    say!("{}", heading);
+    if blocks.is_empty() {
+        say!("    none");
+    }
+    for (network, prefix) in blocks {
+        say!("    {}/{}", network, prefix);
+    }
+}
+
+//function to sign a file the scan wrote with the --sign-with key, if one was given
+fn sign_file(path: &str, options: &cli::Options) {
+    let Some(key) = &options.sign_with else {
+        return;
+    };
+    match sign::sign(path, key) {
+        Ok(signature) => say!("Signed {} in {}, check it with: {}", path, signature, sign::verify_command(path, &signature)),
+        Err(error) => eprintln!("Cannot sign {}, {}", path, error),
+    }
+}
+
+//function to reverse resolve every address without sending a single probe to the targets
+fn dns_sweep(targets: &[target::Target], resolvers: &[String], doh_url: Option<&str>) {
+    let resolver = match dns::Resolver::from_options(resolvers, doh_url) {
+        Ok(resolver) => resolver,
+        Err(error) => {
+            eprintln!("DNS sweep failed, {}", error);
+            return;
+        }
+    };
+
+    //create a vec to store every address that has a name:
+    let mut named = Vec::new();
+    let mut total_count = 0;
+    let mut error_count = 0;
+
+    say!();
+    for target in targets {
+        for address in target.addresses() {
+            total_count += 1;
+            match resolver.reverse_lookup(address) {
+                Ok(Some(name)) => {
+                    say!("Lookup successful, {} is \x1b[0;36mnamed\x1b[0m {}.", address, name);
+                    named.push((address, name));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    error_count += 1;
+                    say!("Lookup failed for {} \x1b[0;33m({})\x1b[0m.", address, error);
+                }
+            }
+            io::stdout().flush().unwrap();
+        }
+    }
+
+    say!();
+    banner("Results");
+    say!();
+    say!("The following IP addresses have PTR records:");
+    for (address, name) in &named {
+        say!("\x1b[0;36m{}\x1b[0m {}", address, name);
+    }
+    say!();
+    say!("Looked up a total of {} IP addresses, of which {} were named.", total_count, named.len());
+    if error_count > 0 {
+        say!("{} lookups failed and may hide named hosts.", error_count);
+    }
+}
 
 //function to create a banner for each menu
 fn banner(ban_title: &str) {
     let h_border = "═";
     let v_border = "║";
-    let tl_corner = "╔";   
+    let tl_corner = "╔";
     let tr_corner = "╗";
     let bl_corner = "╚";
     let br_corner = "╝";
@@ -165,17 +1449,16 @@ fn banner(ban_title: &str) {
     let title_length = ban_title.len();
 
     //print the actual box:
-    println!("{}{}{}{}{}", tl_corner, h_border, h_border.repeat(title_length), h_border, tr_corner);
-    println!("{}{}{}{}{}", v_border, " ", ban_title, " ", v_border);
-    println!("{}{}{}{}{}", bl_corner, h_border, h_border.repeat(title_length), h_border, br_corner);
+    say!("{}{}{}{}{}", tl_corner, h_border, h_border.repeat(title_length), h_border, tr_corner);
+    say!("{} {} {}", v_border, ban_title, v_border);
+    say!("{}{}{}{}{}", bl_corner, h_border, h_border.repeat(title_length), h_border, br_corner);
 
     //future feature to justify and add color to the banner and text
 }
 
-fn clear_screen(){
-    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+fn clear_screen() {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
     }
-
+    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
 }
-
-
@@ -0,0 +1,198 @@
+//combining results files into one set of results (host_disco merge), the shards of a sweep (--shards) or
+//partial and repeated scans of overlapping ranges
+//
+//the merged results read as those of one sweep: the hosts of every file in address order, started when
+//the first scan started and finished when the last one did, with the command line of the last one less
+//the shard flags. a host in several files gets the result of the scan that finished last, and every host
+//keeps the id of the scan its result came from, the scans themselves are listed in the header
+
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+
+use crate::cbor;
+use crate::cli::Options;
+use crate::compress;
+use crate::host::HostResult;
+use crate::json::{self, Value};
+use crate::metadata::{self, ScanMetadata};
+use crate::output;
+use crate::whois::Owner;
+
+//a scan whose results were merged, and the file they were read from
+pub struct Source {
+    pub id: String,
+    pub finished_at: u64,
+    pub file: String,
+}
+
+impl Source {
+    //function to describe the scan for the report header, e.g. "7d270c0b-... finished 2026-10-16T03:01:58Z, a.json"
+    pub fn describe(&self) -> String {
+        format!("{} finished {}, {}", self.id, metadata::iso8601(self.finished_at), self.file)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"id\":{},\"finished\":{},\"file\":{}}}",
+            json::quote(&self.id),
+            json::quote(&metadata::iso8601(self.finished_at)),
+            json::quote(&self.file)
+        )
+    }
+
+    fn from_json(value: &Value) -> Option<Source> {
+        Some(Source {
+            id: value.get("id")?.as_str()?.to_string(),
+            finished_at: value.get("finished").and_then(Value::as_str).and_then(metadata::parse_iso8601).unwrap_or(0),
+            file: value.get("file").and_then(Value::as_str).unwrap_or("").to_string(),
+        })
+    }
+}
+
+//one results file as read
+struct Part {
+    path: String,
+    started_at: u64,
+    finished_at: u64,
+    user: String,
+    parameters: Vec<String>,
+    shard: Option<(u32, u32)>,
+    owners: Vec<Owner>,
+    //the scan itself, or those it was merged from when it is a merge already
+    sources: Vec<Source>,
+    hosts: Vec<HostResult>,
+}
+
+//function to merge the results files and write them in the given format, to a file or the terminal
+pub fn run(paths: &[String], format: &str, output_file: Option<&str>, compress: Option<&str>, options: &Options) -> Result<(), String> {
+    let mut parts = Vec::new();
+    for path in paths {
+        let part = read(path)?;
+        let id = &part.sources[0].id;
+        if let Some(other) = parts.iter().find(|other: &&Part| other.sources.iter().any(|source| source.id == *id)) {
+            return Err(format!("{} and {} have the results of the same scan", other.path, path));
+        }
+        parts.push(part);
+    }
+    check_shards(&parts)?;
+
+    //the scans are taken oldest first so the result of a later one replaces that of an earlier one
+    parts.sort_by_key(|part| part.finished_at);
+    let mut hosts: BTreeMap<Ipv4Addr, HostResult> = BTreeMap::new();
+    let mut conflicts = 0;
+    for part in &mut parts {
+        for host in part.hosts.drain(..) {
+            if hosts.insert(host.address, host).is_some() {
+                conflicts += 1;
+            }
+        }
+    }
+    if conflicts > 0 {
+        eprintln!("{} hosts are in more than one of the files, the result of the scan that finished last was kept.", conflicts);
+    }
+
+    let Some(last) = parts.last() else {
+        return Err("merge expects the json results of the scans".to_string());
+    };
+    let mut metadata = ScanMetadata::new(parts.iter().map(|part| part.started_at).min().unwrap_or(0), &without_shard(&last.parameters));
+    metadata.finished_at = last.finished_at;
+    metadata.user = last.user.clone();
+    for part in &mut parts {
+        for owner in part.owners.drain(..) {
+            if !metadata.owners.iter().any(|known| known.range == owner.range) {
+                metadata.owners.push(owner);
+            }
+        }
+        metadata.sources.append(&mut part.sources);
+    }
+
+    let results: Vec<HostResult> = hosts.into_values().collect();
+    match output_file {
+        Some(output_file) => {
+            let document = match format {
+                "cbor" => cbor::results(&metadata, &results)?,
+                _ => output::render(format, &metadata, options, &results)?.into_bytes(),
+            };
+            compress::write(output_file, &document, compress, None)?;
+            eprintln!("{} hosts of {} scans written to {} as {}.", results.len(), metadata.sources.len(), output_file, format);
+        }
+        None => print!("{}", output::render(format, &metadata, options, &results)?),
+    }
+    Ok(())
+}
+
+//function to read a json results file, each host marked with the scan it came from
+fn read(path: &str) -> Result<Part, String> {
+    let contents = compress::read(path)?;
+    let document = json::parse(&String::from_utf8_lossy(&contents)).map_err(|error| format!("{} isn't a json results file, {}", path, error))?;
+    let scan = document.get("scan").ok_or_else(|| format!("{} isn't a json results file", path))?;
+    let id = scan.get("id").and_then(Value::as_str).ok_or_else(|| format!("{} isn't a json results file", path))?;
+    let time = |key: &str| scan.get(key).and_then(Value::as_str).and_then(metadata::parse_iso8601).unwrap_or(0);
+    let list = |key: &str| scan.get(key).and_then(Value::as_array).unwrap_or(&[]);
+
+    let mut sources: Vec<Source> = list("sources").iter().filter_map(Source::from_json).collect();
+    if sources.is_empty() {
+        sources.push(Source { id: id.to_string(), finished_at: time("finished"), file: path.to_string() });
+    }
+    let mut hosts = Vec::new();
+    for (index, host) in document.get("hosts").and_then(Value::as_array).unwrap_or(&[]).iter().enumerate() {
+        let mut host = HostResult::from_json(host).ok_or_else(|| format!("host {} of {} isn't a host", index + 1, path))?;
+        host.source.get_or_insert_with(|| id.to_string());
+        hosts.push(host);
+    }
+    Ok(Part {
+        path: path.to_string(),
+        started_at: time("started"),
+        finished_at: time("finished"),
+        user: scan.get("user").and_then(Value::as_str).unwrap_or("unknown").to_string(),
+        parameters: list("parameters").iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        shard: scan
+            .get("shard")
+            .and_then(Value::as_str)
+            .and_then(|shard| shard.split_once('/'))
+            .and_then(|(shard, of)| Some((shard.parse().ok()?, of.parse().ok()?))),
+        owners: list("owners").iter().filter_map(Owner::from_json).collect(),
+        sources,
+        hosts,
+    })
+}
+
+//function to check that shards of a sweep fit together, when every file is one, and say which are missing
+fn check_shards(parts: &[Part]) -> Result<(), String> {
+    let shards: Vec<(&str, (u32, u32))> = parts.iter().filter_map(|part| Some((part.path.as_str(), part.shard?))).collect();
+    let Some((_, (_, count))) = shards.first().filter(|_| shards.len() == parts.len()) else {
+        return Ok(());
+    };
+    let mut seen = Vec::new();
+    for (path, (shard, of)) in &shards {
+        if of != count {
+            return Err(format!("{} is a shard of {}, the others are of {}", path, of, count));
+        }
+        if seen.contains(shard) {
+            return Err(format!("{} is shard {} again", path, shard));
+        }
+        seen.push(*shard);
+    }
+    let missing: Vec<String> = (1..=*count).filter(|shard| !seen.contains(shard)).map(|shard| shard.to_string()).collect();
+    match missing.as_slice() {
+        [] => {}
+        [shard] => eprintln!("Shard {} of {} is missing, the merged results don't cover the whole sweep.", shard, count),
+        _ => eprintln!("Shards {} of {} are missing, the merged results don't cover the whole sweep.", missing.join(", "), count),
+    }
+    Ok(())
+}
+
+//function to take --shard and --shards out of a command line, the merged results are of the whole sweep
+fn without_shard(parameters: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut words = parameters.iter();
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "--shard" | "--shards" => {
+                words.next();
+            }
+            _ => kept.push(word.clone()),
+        }
+    }
+    kept
+}
@@ -0,0 +1,203 @@
+//what identifies a scan run, stamped into every output format and the result store
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process::Command;
+
+use crate::json;
+use crate::merge::Source;
+use crate::whois::Owner;
+
+pub struct ScanMetadata {
+    pub id: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub user: String,
+    pub version: &'static str,
+    //the command line arguments exactly as given
+    pub parameters: Vec<String>,
+    //the owners of the public ranges swept, with --whois
+    pub owners: Vec<Owner>,
+    //which slice of the addresses this run swept and of how many, with --shard and --shards
+    pub shard: Option<(u32, u32)>,
+    //the scans host_disco merge combined, oldest first
+    pub sources: Vec<Source>,
+    //the spill file the addresses that didn't answer went to instead of the results, and how many
+    pub spilled: Option<(String, usize)>,
+}
+
+impl ScanMetadata {
+    //function to start the metadata of a new run, finished_at is filled in when the scan ends
+    pub fn new(started_at: u64, parameters: &[String]) -> ScanMetadata {
+        ScanMetadata {
+            id: uuid_v4(),
+            started_at,
+            finished_at: started_at,
+            user: invoking_user(),
+            version: env!("CARGO_PKG_VERSION"),
+            parameters: parameters.to_vec(),
+            owners: Vec::new(),
+            shard: None,
+            sources: Vec::new(),
+            spilled: None,
+        }
+    }
+
+    pub fn command_line(&self) -> String {
+        let mut words = vec!["host_disco".to_string()];
+        words.extend(self.parameters.iter().map(|word| shell_quote(word)));
+        words.join(" ")
+    }
+
+    //function to list the metadata as ordered name/value pairs for the flat formats
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("scan_id", self.id.clone()),
+            ("started", iso8601(self.started_at)),
+            ("finished", iso8601(self.finished_at)),
+            ("user", self.user.clone()),
+            ("version", self.version.to_string()),
+            ("command", self.command_line()),
+        ];
+        if let Some((shard, shards)) = self.shard {
+            fields.push(("shard", format!("{}/{}", shard, shards)));
+        }
+        fields.extend(self.owners.iter().map(|owner| ("owner", owner.describe())));
+        fields.extend(self.sources.iter().map(|source| ("source", source.describe())));
+        if let Some((path, count)) = &self.spilled {
+            fields.push(("spilled", format!("{} addresses that didn't answer, in {}", count, path)));
+        }
+        fields
+    }
+
+    pub fn to_json(&self) -> String {
+        let parameters: Vec<String> = self.parameters.iter().map(|word| json::quote(word)).collect();
+        format!(
+            "{{\"id\":{},\"started\":{},\"finished\":{},\"user\":{},\"version\":{},\"parameters\":[{}],\"shard\":{},\"owners\":[{}],\"sources\":[{}],\"spilled\":{}}}",
+            json::quote(&self.id),
+            json::quote(&iso8601(self.started_at)),
+            json::quote(&iso8601(self.finished_at)),
+            json::quote(&self.user),
+            json::quote(self.version),
+            parameters.join(","),
+            json::quote_option(self.shard.map(|(shard, shards)| format!("{}/{}", shard, shards)).as_deref()),
+            self.owners.iter().map(Owner::to_json).collect::<Vec<_>>().join(","),
+            self.sources.iter().map(Source::to_json).collect::<Vec<_>>().join(","),
+            match &self.spilled {
+                Some((path, count)) => format!("{{\"count\":{},\"file\":{}}}", count, json::quote(path)),
+                None => "null".to_string(),
+            }
+        )
+    }
+}
+
+//function to make a random (version 4) UUID from the kernel's random source
+fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    let read = File::open("/dev/urandom").and_then(|mut random| random.read_exact(&mut bytes));
+    if read.is_err() {
+        //no urandom, fall back to the clock and pid which is still unique enough to tell runs apart
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or(0);
+        bytes = (nanos ^ (u128::from(std::process::id()) << 64)).to_be_bytes();
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+//function to find out who ran the scan, preferring the user behind sudo
+pub fn invoking_user() -> String {
+    for variable in ["SUDO_USER", "USER", "LOGNAME"] {
+        if let Some(user) = env::var(variable).ok().filter(|user| !user.is_empty()) {
+            return user;
+        }
+    }
+    Command::new("id")
+        .arg("-un")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|user| user.trim().to_string())
+        .filter(|user| !user.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+//function to format seconds since the epoch as an ISO 8601 UTC timestamp
+pub fn iso8601(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    //civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+//function to read back a timestamp iso8601 wrote, None when it isn't one
+pub fn parse_iso8601(text: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    if text.len() != 20 || !text.ends_with('Z') {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    //days since 1970-01-01 from the civil date, the inverse of the algorithm above
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+//function to quote an argument for the reproducible command line when the shell would split it
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:,=@%+".contains(c)) {
+        word.to_string()
+    }
+    else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+//function to format seconds since the epoch as an RFC 5322 date for mail headers
+pub fn rfc5322(secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    //take the pieces back out of the ISO form rather than repeating the calendar maths
+    let iso = iso8601(secs);
+    let month: usize = iso[5..7].parse().unwrap_or(1);
+    format!(
+        "{}, {} {} {} {} +0000",
+        DAYS[(secs / 86_400 % 7) as usize],
+        &iso[8..10],
+        MONTHS[month - 1],
+        &iso[0..4],
+        &iso[11..19]
+    )
+}
@@ -0,0 +1,672 @@
+//watch mode, sweeping the targets again every interval and reporting hosts that change state, and known
+//addresses that answer from a different MAC or resolve to a different name, a replaced device or ARP spoofing.
+//randomized MACs aren't compared, they change with every network a phone or laptop joins
+//
+//--watch-scopes watches every [scope NAME] of the config file that has a watch interval from one process,
+//each on its own thread with its own interval, probes and notifiers, the flags giving the rest
+//
+//  [scope office]
+//  include = 192.168.1.0/24
+//  watch = 5m                       (the interval, scopes without one aren't watched)
+//  ports = ssh,http                 (optional, as are tcp_ping, udp_ports and profiles, in place of
+//  tcp_ping = 22,443                 --ports, --tcp-ping, --udp-ports and --profile)
+//  watch_profile = lte              (the debounce and latency thresholds of a [watch NAME] section)
+//  notify = ops-mail, team-chat     (the [notify NAME] sections it sends to, all of them by default)
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::{self, Options};
+use crate::concurrency;
+use crate::config;
+use crate::host::{HostResult, PortState};
+use crate::identify;
+use crate::latency::{Level, Thresholds};
+use crate::metadata::{self, ScanMetadata};
+use crate::names;
+use crate::notify;
+use crate::oui;
+use crate::ports;
+use crate::scan;
+use crate::scope;
+use crate::service;
+use crate::silence;
+use crate::stats;
+use crate::store;
+use crate::target::{self, Target};
+
+//cycles of round trip times kept per host for the sparklines
+const SPARKLINE_WINDOW: usize = 30;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EventKind {
+    //a host that was seen before came back up
+    Up,
+    Down,
+    //a host that has never been up while this range was watched
+    New,
+    //an up host whose round trip time crossed a threshold, or came back under them
+    Warning,
+    Critical,
+    Normal,
+    //an up host whose MAC or name isn't the one it had before
+    Changed,
+}
+
+//one state change found by a sweep
+pub struct Event {
+    pub kind: EventKind,
+    pub host: HostResult,
+    //what a changed host had before, e.g. "MAC 00:11:22:33:44:66 (Dell), was 00:11:22:33:44:55 (HP)"
+    pub change: Option<String>,
+}
+
+impl EventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventKind::Up => "up",
+            EventKind::Down => "down",
+            EventKind::New => "new",
+            EventKind::Warning => "warning",
+            EventKind::Critical => "critical",
+            EventKind::Normal => "normal",
+            EventKind::Changed => "changed",
+        }
+    }
+}
+
+impl Event {
+    //function to describe the host side of the event, open ports for live hosts and the reason for down ones
+    pub fn detail(&self) -> String {
+        if let Some(change) = &self.change {
+            return change.clone();
+        }
+        if !self.host.is_up() {
+            return self.host.reason().unwrap_or_default();
+        }
+        let open: Vec<String> = self
+            .host
+            .ports
+            .iter()
+            .filter(|port| port.state == PortState::Open)
+            .map(|port| format!("{}/{}", port.port, port.protocol))
+            .collect();
+        open.join(", ")
+    }
+
+    //function to give the event as one plain line, e.g. "NEW       10.0.0.5  22/tcp", latency events lead with the round trip time
+    pub fn line(&self) -> String {
+        let detail = match (self.kind, self.host.rtt()) {
+            (EventKind::Warning | EventKind::Critical | EventKind::Normal, Some(rtt)) => format!("{:.2}ms  {}", rtt, self.detail()),
+            _ => self.detail(),
+        };
+        format!("{:<8}  {:<15}  {}", self.kind.label().to_uppercase(), self.host.address, detail)
+            .trim_end()
+            .to_string()
+    }
+}
+
+//how many cycles in a row a host has to miss or answer before it is reported down or up,
+//so flapping hosts don't notify every cycle
+//
+//  [watch]
+//  down_after = 3
+//
+//  [watch lte]
+//  down_after = 5
+//  up_after = 2
+pub struct Debounce {
+    pub down_after: u32,
+    pub up_after: u32,
+}
+
+impl Debounce {
+    //function to take the debounce from the [watch] section or the one --watch-profile names, the flags win
+    pub fn from_options(config: &config::Config, options: &Options) -> Result<Debounce, String> {
+        let section = profile_section(config, options)?;
+        let cycles = |key: &str, flag: Option<u32>| -> Result<u32, String> {
+            match (flag, section.and_then(|section| section.get(key))) {
+                (Some(cycles), _) => Ok(cycles),
+                (None, Some(value)) => cli::parse_cycles(value, key),
+                (None, None) => Ok(1),
+            }
+        };
+        Ok(Debounce {
+            down_after: cycles("down_after", options.down_after)?,
+            up_after: cycles("up_after", options.up_after)?,
+        })
+    }
+}
+
+//function to find the [watch] section, or the [watch NAME] one --watch-profile picks, which has to exist
+pub fn profile_section<'a>(config: &'a config::Config, options: &Options) -> Result<Option<&'a config::Section>, String> {
+    match &options.watch_profile {
+        Some(profile) => config
+            .sections_of("watch")
+            .find(|(name, _)| name == profile)
+            .map(|(_, section)| Some(section))
+            .ok_or_else(|| format!("no [watch {}] section in the config file", profile)),
+        None => Ok(config.sections_of("watch").find(|(name, _)| name.is_empty()).map(|(_, section)| section)),
+    }
+}
+
+//function to count the events of each kind, e.g. "1 new, 2 down"
+pub fn summary(events: &[&Event]) -> String {
+    let counts: Vec<String> = [EventKind::New, EventKind::Up, EventKind::Down, EventKind::Warning, EventKind::Critical, EventKind::Normal, EventKind::Changed]
+        .iter()
+        .map(|kind| (kind, events.iter().filter(|event| event.kind == *kind).count()))
+        .filter(|(_, count)| *count > 0)
+        .map(|(kind, count)| format!("{} {}", count, kind.label()))
+        .collect();
+    counts.join(", ")
+}
+
+//the MAC and name an address was last seen with, each kept until a sweep learns another
+#[derive(Default)]
+struct Identity {
+    mac: Option<String>,
+    vendor: Option<String>,
+    hostname: Option<String>,
+}
+
+impl Identity {
+    //function to take what the sweep learned about an up host, a MAC or name it didn't learn this time
+    //(no ARP reply, a failed lookup) isn't a change, and describe what changed. a locally administered MAC
+    //on either side isn't compared, phones and laptops pick a new random one per network or connection
+    fn update(&mut self, host: &HostResult) -> Option<String> {
+        let mut changes = Vec::new();
+        if let Some(mac) = &host.mac {
+            if let Some(previous) = self
+                .mac
+                .as_ref()
+                .filter(|previous| !previous.eq_ignore_ascii_case(mac) && !oui::is_randomized(previous) && !oui::is_randomized(mac))
+            {
+                let vendor = |vendor: &Option<String>| vendor.as_ref().map(|vendor| format!(" ({})", vendor)).unwrap_or_default();
+                changes.push(format!("MAC {}{}, was {}{}", mac, vendor(&host.vendor), previous, vendor(&self.vendor)));
+            }
+            self.mac = Some(mac.clone());
+            self.vendor = host.vendor.clone();
+        }
+        if let Some(hostname) = &host.hostname {
+            if let Some(previous) = self.hostname.as_ref().filter(|previous| !previous.eq_ignore_ascii_case(hostname)) {
+                changes.push(format!("name {}, was {}", hostname, previous));
+            }
+            self.hostname = Some(hostname.clone());
+        }
+        (!changes.is_empty()).then(|| changes.join("; "))
+    }
+}
+
+//what watch mode takes from the config file, read again on SIGHUP in service mode
+struct Settings {
+    notifiers: Vec<notify::Notifier>,
+    debounce: Debounce,
+    windows: Vec<silence::Window>,
+    thresholds: Thresholds,
+}
+
+impl Settings {
+    //function to read the settings, a watched scope only keeps the notifiers its notify key names
+    fn load(options: &Options, name: Option<&str>) -> Result<Settings, String> {
+        let config = config::load(options.config.as_deref())?;
+        let mut notifiers = notify::from_config(&config)?;
+        let wanted = name.and_then(|name| scope_section(&config, name)).map(|section| section.list("notify")).unwrap_or_default();
+        if let Some(unknown) = wanted.iter().find(|wanted| !notifiers.iter().any(|notifier| notifier.name == **wanted)) {
+            return Err(format!("[scope {}] notifies {}, which has no [notify {}] section", name.unwrap_or(""), unknown, unknown));
+        }
+        if !wanted.is_empty() {
+            notifiers.retain(|notifier| wanted.contains(&notifier.name));
+        }
+        if options.notify_desktop {
+            notifiers.push(notify::desktop_notifier());
+        }
+        Ok(Settings {
+            notifiers,
+            debounce: Debounce::from_options(&config, options)?,
+            windows: silence::windows(&config)?,
+            thresholds: Thresholds::from_options(&config, options)?,
+        })
+    }
+}
+
+fn scope_section<'a>(config: &'a config::Config, name: &str) -> Option<&'a config::Section> {
+    config.sections_of("scope").find(|(scope, _)| *scope == name).map(|(_, section)| section)
+}
+
+//function to list the scopes of the config file that have a watch interval, there has to be one
+pub fn watched_scopes(config: &config::Config) -> Result<Vec<String>, String> {
+    let names: Vec<String> = config
+        .sections_of("scope")
+        .filter(|(_, section)| section.get("watch").is_some())
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if names.is_empty() {
+        return Err("no [scope NAME] of the config file has a watch interval, e.g. watch = 5m".to_string());
+    }
+    Ok(names)
+}
+
+//function to watch every scope with a watch interval at once, the command line is parsed again for each
+//and its probes replaced by those the scope sets, every scope stops on the same signal
+pub fn run_scopes(options: &Options, args: &[String]) -> Result<(), String> {
+    let config = config::load(options.config.as_deref())?;
+    let mut watched = Vec::new();
+    for name in watched_scopes(&config)? {
+        let Some(section) = scope_section(&config, &name) else {
+            continue;
+        };
+        let interval = cli::parse_duration(section.require("watch")?, &format!("[scope {}] watch", name))?;
+        if interval.is_zero() {
+            return Err(format!("[scope {}] watch needs an interval above zero", name));
+        }
+        let mut options = cli::parse_args(args)?;
+        scope_probes(section, &mut options)?;
+        let targets = scope::blocks(&config, &name)?
+            .iter()
+            .map(|block| target::expand(block, None))
+            .collect::<Result<Vec<Target>, String>>()?;
+        //a scope that names a notifier or profile that isn't there stops the start, not its thread
+        Settings::load(&options, Some(&name))?;
+        watched.push((name, interval, targets, options));
+    }
+    if options.service {
+        service::install_handlers();
+    }
+
+    thread::scope(|threads| {
+        for (name, interval, targets, options) in &watched {
+            threads.spawn(move || {
+                if let Err(error) = run(targets, options, args, *interval, Some(name)) {
+                    eprintln!("Stopped watching {}, {}", name, error);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+//function to take the probes a scope sets in place of the flags, e.g. ports = ssh,http
+fn scope_probes(section: &config::Section, options: &mut Options) -> Result<(), String> {
+    let port_list = |key: &str| -> Result<Option<Vec<u16>>, String> {
+        section.get(key).map(|list| ports::parse_port_list(list).map_err(|error| format!("[{}] {}, {}", section.name, key, error))).transpose()
+    };
+    if let Some(list) = port_list("ports")? {
+        options.ports = list;
+    }
+    if let Some(list) = port_list("tcp_ping")? {
+        options.tcp_ping = list;
+    }
+    if let Some(list) = port_list("udp_ports")? {
+        options.udp_ports = list;
+    }
+    if section.get("profiles").is_some() {
+        options.profiles.clear();
+        for profile in section.list("profiles") {
+            if !identify::PROFILES.contains(&profile.as_str()) {
+                return Err(format!("[{}] has unknown profile {}, expected one of {}", section.name, profile, identify::PROFILES.join(", ")));
+            }
+            options.ports.extend(identify::profile_tcp_ports(&profile));
+            options.udp_ports.extend(identify::profile_udp_ports(&profile));
+            options.profiles.push(profile);
+        }
+    }
+    if let Some(profile) = section.get("watch_profile") {
+        options.watch_profile = Some(profile.to_string());
+    }
+    options.ports.sort_unstable();
+    options.ports.dedup();
+    options.udp_ports.sort_unstable();
+    options.udp_ports.dedup();
+    Ok(())
+}
+
+//how many stored runs of a watched range are kept without --keep-scans
+const DEFAULT_KEEP_SCANS: store::Keep = store::Keep::Runs(50);
+
+//how often a service waiting for its next sweep looks for signals
+const SIGNAL_CHECK: Duration = Duration::from_millis(200);
+
+//function to sweep the targets every interval until interrupted, or stopped by a signal in service mode,
+//a scope watched by --watch-scopes is named in what is printed and sent
+pub fn run(targets: &[Target], options: &Options, args: &[String], interval: Duration, name: Option<&str>) -> Result<(), String> {
+    let mut settings = Settings::load(options, name)?;
+    if options.service && name.is_none() {
+        service::install_handlers();
+    }
+
+    let labels: Vec<&str> = targets.iter().map(|target| target.label.as_str()).collect();
+    let scope = match name {
+        Some(name) => format!("{} {}", name, labels.join(", ")),
+        None => labels.join(", "),
+    };
+    let prefix = name.map(|name| format!("{} ", name)).unwrap_or_default();
+    let scan_key = store::scan_key(&labels);
+    let addresses = target::unique_addresses(targets);
+
+    //the last stored scan is the starting state and the identities the hosts had, without one the first
+    //sweep only sets the baseline
+    let previous = store::load_latest_scan(&scan_key)?;
+    let mut baseline = previous.is_some();
+    let mut up: HashSet<Ipv4Addr> = previous
+        .iter()
+        .flat_map(|scan| scan.hosts.iter().filter(|host| host.is_up()).map(|host| host.address))
+        .collect();
+    let mut identities: HashMap<Ipv4Addr, Identity> = HashMap::new();
+    for host in previous.iter().flat_map(|scan| scan.hosts.iter().filter(|host| host.is_up())) {
+        identities.entry(host.address).or_default().update(host);
+    }
+    //every host that was ever up while the range was watched is known, only the others count as new
+    //devices, ranges watched before the list was kept start from the last run
+    let stored_known = store::load_known(&scan_key)?;
+    let mut known_written = stored_known.is_some();
+    let mut known = stored_known.unwrap_or_else(|| up.clone());
+    known.extend(up.iter().copied());
+    //which hosts answered in the last stored run, a run keeps only those that answer and those that
+    //stopped answering since, the rest of the range didn't answer in either
+    let mut answered = up.clone();
+    //the latest stored runs start off the round trip time history
+    let mut history: HashMap<Ipv4Addr, VecDeque<Option<f64>>> = HashMap::new();
+    if options.sparklines {
+        let runs = store::scan_runs(&scan_key)?;
+        for run in &runs[runs.len().saturating_sub(SPARKLINE_WINDOW)..] {
+            record_rtts(&mut history, &store::load_scan(&scan_key, *run)?.hosts);
+        }
+    }
+
+    let limits = concurrency::detect_limits();
+    let controller = concurrency::Controller::new(options.concurrency, options.ramp, &limits);
+    println!(
+        "Watching {} ({} addresses) every {}s, notifying {}.",
+        scope,
+        addresses.len(),
+        interval.as_secs(),
+        if settings.notifiers.is_empty() { "nobody".to_string() } else { notify::names(&settings.notifiers) }
+    );
+    print_debounce(&settings.debounce);
+    service::notify("READY=1");
+    let mut reloads = service::reloads();
+
+    //the latency level of every up host, hosts start out normal
+    let mut levels: HashMap<Ipv4Addr, Level> = HashMap::new();
+
+    //consecutive cycles each address has answered (positive) or missed (negative) since it last changed
+    let mut streaks: HashMap<Ipv4Addr, i64> = HashMap::new();
+
+    for cycle in 1.. {
+        let Settings { notifiers, debounce, windows, thresholds } = &settings;
+        let start = Instant::now();
+        let mut metadata = ScanMetadata::new(store::now(), args);
+        let mut results = Vec::new();
+        scan::run(&addresses, options, &controller, |finished| {
+            controller.record(concurrency::Signal::of(&finished.host, finished.retries));
+            results.push(finished.host);
+        });
+        //a sweep cut short by a signal would start the next run off with hosts missing, it isn't stored
+        if service::stop_requested() {
+            service::notify("STOPPING=1");
+            println!("Stopping{}, cycle {} was cut short and isn't stored.", name.map(|name| format!(" {}", name)).unwrap_or_default(), cycle);
+            break;
+        }
+        scan::recheck(&controller.take_suspects(), &mut results, options, &controller);
+        results.sort_by_key(|host| host.address);
+        metadata.finished_at = store::now();
+        //a run is stored every cycle, of a big range mostly addresses that never answer, so only the
+        //hosts that answer and those that stopped since the last cycle are kept
+        let stored: Vec<HostResult> = results.iter().filter(|host| host.is_up() || answered.contains(&host.address)).cloned().collect();
+        answered = results.iter().filter(|host| host.is_up()).map(|host| host.address).collect();
+        if let Err(error) = store::save_scan(&scan_key, &metadata, &stored) {
+            eprintln!("Failed to store the scan, {}", error);
+        }
+        //and those past what is kept would pile up for as long as it watches
+        if let Err(error) = store::prune_scans(&scan_key, options.keep_scans.unwrap_or(DEFAULT_KEEP_SCANS)) {
+            eprintln!("Failed to remove old scans, {}", error);
+        }
+        if let Err(error) = names::save() {
+            eprintln!("Failed to update the name cache, {}", error);
+        }
+
+        //a host only changes state once its streak is long enough, the first cycle takes them as they are
+        let mut events = Vec::new();
+        for host in &results {
+            let streak = streaks.entry(host.address).or_insert(0);
+            *streak = match (host.is_up(), *streak) {
+                (true, streak) if streak > 0 => streak + 1,
+                (true, _) => 1,
+                (false, streak) if streak < 0 => streak - 1,
+                (false, _) => -1,
+            };
+            let kind = match (up.contains(&host.address), host.is_up()) {
+                (false, true) if baseline && *streak < i64::from(debounce.up_after) => continue,
+                (true, false) if baseline && -*streak < i64::from(debounce.down_after) => continue,
+                (false, true) if known.contains(&host.address) => EventKind::Up,
+                (false, true) => EventKind::New,
+                (true, false) => EventKind::Down,
+                _ => continue,
+            };
+            if kind == EventKind::Down {
+                up.remove(&host.address);
+            }
+            else {
+                up.insert(host.address);
+            }
+            events.push(Event { kind, host: host.clone(), change: None });
+        }
+        let known_before = known.len();
+        known.extend(up.iter().copied());
+        if known.len() != known_before || !known_written {
+            match store::save_known(&scan_key, &known) {
+                Ok(()) => known_written = true,
+                Err(error) => eprintln!("Failed to update the known hosts, {}", error),
+            }
+        }
+
+        //a known address answering from another MAC or under another name is a replaced device, or
+        //another machine answering for it
+        for host in results.iter().filter(|host| host.is_up()) {
+            if let Some(change) = identities.entry(host.address).or_default().update(host) {
+                events.push(Event { kind: EventKind::Changed, host: host.clone(), change: Some(change) });
+            }
+        }
+
+        //latency changes of hosts that stay up are events of their own
+        if thresholds.is_set() {
+            for host in results.iter().filter(|host| up.contains(&host.address)) {
+                let Some(rtt) = host.rtt() else {
+                    continue;
+                };
+                let level = thresholds.level(rtt);
+                let previous = levels.insert(host.address, level).unwrap_or(Level::Normal);
+                if level != previous {
+                    let kind = match level {
+                        Level::Normal => EventKind::Normal,
+                        Level::Warning => EventKind::Warning,
+                        Level::Critical => EventKind::Critical,
+                    };
+                    events.push(Event { kind, host: host.clone(), change: None });
+                }
+            }
+            levels.retain(|address, _| up.contains(address));
+        }
+        let pending = results.iter().filter(|host| host.is_up() != up.contains(&host.address)).count();
+
+        //the report of a cycle is printed in one piece, other scopes' reports wait for it
+        let report = io::stdout().lock();
+        println!(
+            "[{}] {}cycle {}: {} of {} up{}{}",
+            metadata::iso8601(metadata.finished_at),
+            prefix,
+            cycle,
+            up.len(),
+            results.len(),
+            if pending > 0 { format!(", {} changing", pending) } else { String::new() },
+            if baseline { String::new() } else { ", baseline set".to_string() }
+        );
+        //silenced hosts are reported here but kept from the notifiers, silences are read again every cycle
+        let silences = silence::load(metadata.finished_at).unwrap_or_else(|error| {
+            eprintln!("Ignoring silences, {}", error);
+            Vec::new()
+        });
+        let mut alerts = Vec::new();
+        for event in events.into_iter().filter(|_| baseline) {
+            let colour = match event.kind {
+                EventKind::New => "\x1b[0;36m",
+                EventKind::Up => "\x1b[0;32m",
+                EventKind::Down => "\x1b[31m",
+                EventKind::Warning => Level::Warning.colour(),
+                EventKind::Critical => Level::Critical.colour(),
+                EventKind::Normal => Level::Normal.colour(),
+                EventKind::Changed => "\x1b[0;35m",
+            };
+            match silence::reason(event.host.address, metadata.finished_at, &silences, windows) {
+                Some(reason) => println!("  {}{}\x1b[0m ({})", colour, event.line(), reason),
+                None => {
+                    println!("  {}{}\x1b[0m", colour, event.line());
+                    alerts.push(event);
+                }
+            }
+        }
+        //--bell rings once a cycle when a device never seen before turns up
+        if options.bell && alerts.iter().any(|event| event.kind == EventKind::New) {
+            print!("\x07");
+        }
+        if options.sparklines {
+            record_rtts(&mut history, &results);
+            print_sparklines(&history, &up, thresholds);
+        }
+        io::stdout().flush().unwrap();
+        drop(report);
+
+        if !alerts.is_empty() {
+            notify::send(notifiers, &scope, &alerts);
+        }
+        baseline = true;
+        service::notify(&format!("STATUS={}cycle {}: {} of {} up", prefix, cycle, up.len(), results.len()));
+
+        //a service wakes up for signals while it waits, the sweep just stored is the state it leaves behind
+        while start.elapsed() < interval && !service::stop_requested() {
+            if service::reloads() != reloads {
+                reloads = service::reloads();
+                reload(&mut settings, options, name);
+            }
+            thread::sleep(SIGNAL_CHECK.min(interval.saturating_sub(start.elapsed())));
+        }
+        if service::stop_requested() {
+            service::notify("STOPPING=1");
+            println!("Stopping{}, the results of cycle {} are stored.", name.map(|name| format!(" {}", name)).unwrap_or_default(), cycle);
+            break;
+        }
+    }
+    Ok(())
+}
+
+//function to read the config file again, a reload that fails keeps the settings that were working
+fn reload(settings: &mut Settings, options: &Options, name: Option<&str>) {
+    service::notify("RELOADING=1");
+    match Settings::load(options, name) {
+        Ok(reloaded) => {
+            *settings = reloaded;
+            let notifiers = &settings.notifiers;
+            println!(
+                "Reloaded the config file{}, notifying {}.",
+                name.map(|name| format!(" for {}", name)).unwrap_or_default(),
+                if notifiers.is_empty() { "nobody".to_string() } else { notify::names(notifiers) }
+            );
+            print_debounce(&settings.debounce);
+        }
+        Err(error) => eprintln!("Keeping the previous settings, the config file can't be reloaded, {}", error),
+    }
+    service::notify("READY=1");
+}
+
+//function to say how many cycles a host has to change for before it is reported, when more than one
+fn print_debounce(debounce: &Debounce) {
+    if debounce.down_after > 1 || debounce.up_after > 1 {
+        println!(
+            "Hosts are reported down after {} missed cycles and up after {} answered ones.",
+            debounce.down_after, debounce.up_after
+        );
+    }
+}
+
+//function to add a cycle to the round trip time history, hosts that didn't answer get a gap
+fn record_rtts(history: &mut HashMap<Ipv4Addr, VecDeque<Option<f64>>>, hosts: &[HostResult]) {
+    for host in hosts {
+        let rtts = history.entry(host.address).or_default();
+        rtts.push_back(host.rtt());
+        if rtts.len() > SPARKLINE_WINDOW {
+            rtts.pop_front();
+        }
+    }
+}
+
+//function to print every up host with its latency trend and latest round trip time, e.g. "10.0.0.5  ▁▁▂▃▅▇  82.10ms"
+fn print_sparklines(history: &HashMap<Ipv4Addr, VecDeque<Option<f64>>>, up: &HashSet<Ipv4Addr>, thresholds: &Thresholds) {
+    let mut addresses: Vec<&Ipv4Addr> = up.iter().collect();
+    addresses.sort();
+    for address in addresses {
+        let Some(rtts) = history.get(address) else {
+            continue;
+        };
+        let rtts: Vec<Option<f64>> = rtts.iter().copied().collect();
+        let latest = rtts.last().copied().flatten();
+        let colour = latest.map(|rtt| thresholds.level(rtt)).unwrap_or(Level::Normal).colour();
+        println!(
+            "    {:<15}  {:<width$}  {}{}\x1b[0m",
+            address,
+            stats::sparkline(&rtts),
+            colour,
+            latest.map(|rtt| format!("{:.2}ms", rtt)).unwrap_or_else(|| "-".to_string()),
+            width = SPARKLINE_WINDOW
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::Outcome;
+
+    fn seen(mac: Option<&str>, hostname: Option<&str>) -> HostResult {
+        HostResult {
+            address: Ipv4Addr::new(10, 0, 0, 5),
+            outcome: Outcome::Up { rtt: Some(1.0), ttl: Some(64), duplicates: 0, replied_from: None },
+            hostname: hostname.map(str::to_string),
+            mac: mac.map(str::to_string),
+            vendor: None,
+            roles: Vec::new(),
+            ports: Vec::new(),
+            found_by: vec!["icmp"],
+            seen_by: None,
+            asn: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn identity_changes_are_reported_once() {
+        let mut identity = Identity::default();
+        assert_eq!(identity.update(&seen(Some("00:1b:21:aa:bb:cc"), Some("printer"))), None);
+        //nothing learned this time is no change
+        assert_eq!(identity.update(&seen(None, None)), None);
+        assert_eq!(identity.update(&seen(Some("00:1B:21:AA:BB:CC"), Some("PRINTER"))), None);
+        assert_eq!(
+            identity.update(&seen(Some("3c:22:fb:00:00:01"), Some("scanner"))).as_deref(),
+            Some("MAC 3c:22:fb:00:00:01, was 00:1B:21:AA:BB:CC; name scanner, was PRINTER")
+        );
+        assert_eq!(identity.update(&seen(Some("3c:22:fb:00:00:01"), Some("scanner"))), None);
+    }
+
+    #[test]
+    fn randomized_macs_are_not_compared() {
+        let mut identity = Identity::default();
+        identity.update(&seen(Some("da:a1:19:00:00:01"), Some("phone")));
+        //a phone that picked another random MAC, and one that stopped randomizing
+        assert_eq!(identity.update(&seen(Some("6e:12:34:56:78:9a"), Some("phone"))), None);
+        assert_eq!(identity.update(&seen(Some("3c:22:fb:00:00:01"), Some("phone"))), None);
+        //a device that went from its own MAC to a random one, only the name change is told
+        assert_eq!(identity.update(&seen(Some("02:42:ac:11:00:02"), Some("laptop"))).as_deref(), Some("name laptop, was phone"));
+    }
+}
@@ -0,0 +1,107 @@
+//rendering live hosts as monitoring configuration, Nagios (and Icinga 1) object definitions,
+//Icinga 2 objects or a Zabbix host import
+
+use crate::cli::Options;
+use crate::host::HostResult;
+use crate::metadata::{iso8601, ScanMetadata};
+
+//function to fill in the host name template, {name} is the hostname when known and the address otherwise
+pub fn host_name(template: &str, host: &HostResult) -> String {
+    let address = host.address.to_string();
+    template
+        .replace("{name}", host.hostname.as_deref().unwrap_or(&address))
+        .replace("{hostname}", host.hostname.as_deref().unwrap_or(""))
+        .replace("{ip_dashed}", &address.replace('.', "-"))
+        .replace("{ip}", &address)
+        .replace("{vendor}", host.vendor.as_deref().unwrap_or(""))
+}
+
+fn live(results: &[HostResult]) -> impl Iterator<Item = &HostResult> {
+    results.iter().filter(|host| host.is_up())
+}
+
+//one define host per live host, all in a hostgroup defined at the top
+pub fn nagios(metadata: &ScanMetadata, options: &Options, results: &[HostResult]) -> String {
+    let group = nagios_name(&options.monitoring_group);
+    let mut config = format!(
+        "# generated by host_disco scan {} at {}\n\ndefine hostgroup {{\n    hostgroup_name  {}\n    alias           {}\n}}\n",
+        metadata.id,
+        iso8601(metadata.finished_at),
+        group,
+        options.monitoring_group
+    );
+    for host in live(results) {
+        let name = host_name(&options.monitoring_name, host);
+        config.push_str(&format!(
+            "\ndefine host {{\n    use             generic-host\n    host_name       {}\n    alias           {}\n    address         {}\n    hostgroups      {}\n}}\n",
+            nagios_name(&name),
+            name,
+            host.address,
+            group
+        ));
+    }
+    config
+}
+
+pub fn icinga(metadata: &ScanMetadata, options: &Options, results: &[HostResult]) -> String {
+    let group = icinga_string(&options.monitoring_group);
+    let mut config = format!(
+        "// generated by host_disco scan {} at {}\n\nobject HostGroup {} {{\n  display_name = {}\n}}\n",
+        metadata.id,
+        iso8601(metadata.finished_at),
+        group,
+        group
+    );
+    for host in live(results) {
+        config.push_str(&format!(
+            "\nobject Host {} {{\n  import \"generic-host\"\n  address = \"{}\"\n  groups = [ {} ]\n}}\n",
+            icinga_string(&host_name(&options.monitoring_name, host)),
+            host.address,
+            group
+        ));
+    }
+    config
+}
+
+//a Zabbix configuration import (Data collection > Hosts > Import) with an agent interface per host
+pub fn zabbix(metadata: &ScanMetadata, options: &Options, results: &[HostResult]) -> String {
+    let group = yaml_string(&options.monitoring_group);
+    let mut config = format!(
+        "# generated by host_disco scan {} at {}\nzabbix_export:\n  version: '6.0'\n  groups:\n    - name: {}\n  hosts:\n",
+        metadata.id,
+        iso8601(metadata.finished_at),
+        group
+    );
+    for host in live(results) {
+        let name = host_name(&options.monitoring_name, host);
+        //the technical host name only allows letters, digits, spaces, dots, dashes and underscores
+        let technical: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || " ._-".contains(c) { c } else { '_' })
+            .collect();
+        config.push_str(&format!(
+            "    - host: {}\n      name: {}\n      groups:\n        - name: {}\n      interfaces:\n        - ip: '{}'\n          interface_ref: if1\n",
+            yaml_string(&technical),
+            yaml_string(&name),
+            group,
+            host.address
+        ));
+    }
+    if live(results).next().is_none() {
+        config = config.replace("  hosts:\n", "  hosts: []\n");
+    }
+    config
+}
+
+//function to make an object name Nagios accepts, no whitespace or commas
+fn nagios_name(value: &str) -> String {
+    value.chars().map(|c| if c.is_whitespace() || c == ',' { '_' } else { c }).collect()
+}
+
+fn icinga_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn yaml_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
@@ -0,0 +1,101 @@
+//a minimal MQTT 3.1.1 client, connect, publish at QoS 0 and disconnect
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+//where to publish and as whom
+pub struct Broker<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub client_id: &'a str,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a str>,
+}
+
+//function to publish every (topic, payload) message in one session
+pub fn publish(broker: &Broker, messages: &[(String, String)], retain: bool) -> Result<(), String> {
+    let address = (broker.host, broker.port)
+        .to_socket_addrs()
+        .map_err(|error| format!("cannot resolve {}, {}", broker.host, error))?
+        .next()
+        .ok_or_else(|| format!("no address for {}", broker.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|error| format!("cannot connect to {}, {}", address, error))?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+
+    stream.write_all(&connect_packet(broker)).map_err(|error| format!("cannot send CONNECT, {}", error))?;
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack).map_err(|error| format!("no CONNACK, {}", error))?;
+    if connack[0] != 0x20 {
+        return Err("broker didn't answer with a CONNACK".to_string());
+    }
+    match connack[3] {
+        0 => {}
+        1 => return Err("broker refused the protocol version".to_string()),
+        2 => return Err("broker refused the client id".to_string()),
+        3 => return Err("broker unavailable".to_string()),
+        4 | 5 => return Err("broker refused the username or password".to_string()),
+        code => return Err(format!("broker refused the connection (code {})", code)),
+    }
+
+    for (topic, payload) in messages {
+        let mut body = string(topic);
+        body.extend_from_slice(payload.as_bytes());
+        stream
+            .write_all(&packet(0x30 | u8::from(retain), &body))
+            .map_err(|error| format!("cannot publish to {}, {}", topic, error))?;
+    }
+    stream.write_all(&[0xe0, 0x00]).map_err(|error| format!("cannot send DISCONNECT, {}", error))
+}
+
+fn connect_packet(broker: &Broker) -> Vec<u8> {
+    //clean session, plus flags for the credentials that are present
+    let mut flags = 0x02;
+    if broker.username.is_some() {
+        flags |= 0x80;
+    }
+    if broker.password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = string("MQTT");
+    body.extend_from_slice(&[4, flags, 0, 60]);
+    body.extend(string(broker.client_id));
+    if let Some(username) = broker.username {
+        body.extend(string(username));
+    }
+    if let Some(password) = broker.password {
+        body.extend(string(password));
+    }
+    packet(0x10, &body)
+}
+
+//function to frame a packet, the remaining length is a base 128 varint
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+//function to encode a length prefixed UTF-8 string
+fn string(value: &str) -> Vec<u8> {
+    let bytes = &value.as_bytes()[..value.len().min(usize::from(u16::MAX))];
+    let mut encoded = (bytes.len() as u16).to_be_bytes().to_vec();
+    encoded.extend_from_slice(bytes);
+    encoded
+}
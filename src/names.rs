@@ -0,0 +1,120 @@
+//the name cache, PTR names and MAC addresses of hosts seen before, kept in names.jsonl next to the stored scans
+//so a repeated scan of the same network doesn't wait on the same reverse lookups again
+//
+//it is loaded by the first worker that needs it and written back once the sweep is over
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::json;
+use crate::store;
+
+//PTR records change rarely, a day old name is still good
+const NAME_TTL: u64 = 24 * 60 * 60;
+//DHCP and replaced hardware move MACs around sooner
+const MAC_TTL: u64 = 60 * 60;
+
+#[derive(Default)]
+struct Entry {
+    //Some(None) is a lookup that found no name, worth remembering too
+    hostname: Option<Option<String>>,
+    named_at: u64,
+    mac: Option<String>,
+    mac_at: u64,
+}
+
+static NAMES: OnceLock<Mutex<HashMap<Ipv4Addr, Entry>>> = OnceLock::new();
+
+fn path() -> Result<PathBuf, String> {
+    Ok(store::data_dir()?.join("names.jsonl"))
+}
+
+//function to get the cache, loading it on first use, an unreadable cache is just empty
+fn names() -> &'static Mutex<HashMap<Ipv4Addr, Entry>> {
+    NAMES.get_or_init(|| Mutex::new(load().unwrap_or_default()))
+}
+
+fn load() -> Result<HashMap<Ipv4Addr, Entry>, String> {
+    let contents = fs::read_to_string(path()?).map_err(|error| error.to_string())?;
+    let entries = contents
+        .lines()
+        .filter_map(|line| json::parse(line).ok())
+        .filter_map(|entry| {
+            let address = entry.get("address")?.as_str()?.parse().ok()?;
+            let text = |key: &str| entry.get(key).and_then(json::Value::as_str).map(str::to_string);
+            let time = |key: &str| entry.get(key).and_then(json::Value::as_u64).unwrap_or(0);
+            let named_at = time("named_at");
+            Some((
+                address,
+                Entry {
+                    hostname: (named_at > 0).then(|| text("hostname")),
+                    named_at,
+                    mac: text("mac"),
+                    mac_at: time("mac_at"),
+                },
+            ))
+        })
+        .collect();
+    Ok(entries)
+}
+
+//function to look up the cached name of an address, None when there is no fresh one
+pub fn hostname(address: Ipv4Addr, now: u64) -> Option<Option<String>> {
+    let names = names().lock().ok()?;
+    let entry = names.get(&address)?;
+    entry.hostname.clone().filter(|_| now.saturating_sub(entry.named_at) <= NAME_TTL)
+}
+
+//function to look up the cached MAC of an address, None when there is no fresh one
+pub fn mac(address: Ipv4Addr, now: u64) -> Option<String> {
+    let names = names().lock().ok()?;
+    let entry = names.get(&address)?;
+    entry.mac.clone().filter(|_| now.saturating_sub(entry.mac_at) <= MAC_TTL)
+}
+
+pub fn remember_hostname(address: Ipv4Addr, hostname: Option<String>, now: u64) {
+    if let Ok(mut names) = names().lock() {
+        let entry = names.entry(address).or_default();
+        entry.hostname = Some(hostname);
+        entry.named_at = now;
+    }
+}
+
+pub fn remember_mac(address: Ipv4Addr, mac: String, now: u64) {
+    if let Ok(mut names) = names().lock() {
+        let entry = names.entry(address).or_default();
+        entry.mac = Some(mac);
+        entry.mac_at = now;
+    }
+}
+
+//function to write the cache back, nothing to do when no lookup used it
+pub fn save() -> Result<(), String> {
+    let Some(names) = NAMES.get() else {
+        return Ok(());
+    };
+    let names = names.lock().map_err(|_| "the name cache is poisoned".to_string())?;
+    let dir = store::data_dir()?;
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    let mut addresses: Vec<&Ipv4Addr> = names.keys().collect();
+    addresses.sort();
+    let lines: String = addresses
+        .into_iter()
+        .map(|address| {
+            let entry = &names[address];
+            format!(
+                "{{\"address\":\"{}\",\"hostname\":{},\"named_at\":{},\"mac\":{},\"mac_at\":{}}}\n",
+                address,
+                json::quote_option(entry.hostname.clone().flatten().as_deref()),
+                entry.named_at,
+                json::quote_option(entry.mac.as_deref()),
+                entry.mac_at
+            )
+        })
+        .collect();
+    let path = path()?;
+    fs::write(&path, lines).map_err(|error| format!("cannot write {}, {}", path.display(), error))
+}
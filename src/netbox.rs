@@ -0,0 +1,101 @@
+//pushing live hosts into NetBox as IP address objects, configured in the [netbox] section
+//
+//  [netbox]
+//  url = https://netbox.example.com
+//  token = 0123456789abcdef0123456789abcdef01234567
+//  status = active                          (optional, the status of created and updated addresses)
+//  tag = host-disco                         (optional, slug of an existing tag to put on every address)
+
+use crate::config::Config;
+use crate::curl;
+use crate::host::HostResult;
+use crate::json;
+use crate::metadata;
+use crate::store;
+use crate::target::Target;
+
+//what an export did
+pub struct Summary {
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+}
+
+//function to create or update an IP address object for every live host
+pub fn export(config: &Config, targets: &[Target], results: &[HostResult]) -> Result<Summary, String> {
+    let section = config
+        .sections
+        .iter()
+        .find(|section| section.name == "netbox")
+        .ok_or("the config file has no [netbox] section")?;
+    let url = section.require("url")?.trim_end_matches('/');
+    let token = section.require("token")?;
+    let status = section.get("status").unwrap_or("active");
+    let tag = section.get("tag");
+
+    let headers = format!(
+        "header = {}\nheader = \"Content-Type: application/json\"\nheader = \"Accept: application/json\"\nfail\n",
+        curl::quote(&format!("Authorization: Token {}", token))
+    );
+    let mut summary = Summary { created: 0, updated: 0, failed: 0 };
+    for host in results.iter().filter(|host| host.is_up()) {
+        //NetBox wants the address with the prefix of the network it sits in
+        let prefix = targets
+            .iter()
+            .find(|target| target.contains(host.address))
+            .map(|target| target.prefix)
+            .unwrap_or(32);
+
+        match push_host(url, &headers, host, prefix, status, tag) {
+            Ok(true) => summary.created += 1,
+            Ok(false) => summary.updated += 1,
+            Err(error) => {
+                summary.failed += 1;
+                eprintln!("NetBox update of {} failed, {}", host.address, error);
+            }
+        }
+    }
+    Ok(summary)
+}
+
+//function to update the address if NetBox has it, create it otherwise, true when it was created
+fn push_host(url: &str, headers: &str, host: &HostResult, prefix: u8, status: &str, tag: Option<&str>) -> Result<bool, String> {
+    let lookup = format!("{}url = {}\n", headers, curl::quote(&format!("{}/api/ipam/ip-addresses/?address={}", url, host.address)));
+    let response = curl::run(&lookup)?;
+    let response = json::parse(&String::from_utf8_lossy(&response)).map_err(|error| format!("unexpected answer from NetBox, {}", error))?;
+    let existing = response
+        .get("results")
+        .and_then(json::Value::as_array)
+        .and_then(|results| results.first())
+        .and_then(|address| address.get("id"))
+        .and_then(json::Value::as_u64);
+
+    //everything learned about the host goes in the description, NetBox keeps MACs on interfaces
+    let mut description = match (&host.mac, &host.vendor) {
+        (Some(mac), Some(vendor)) => format!("MAC {} ({}), ", mac, vendor),
+        (Some(mac), None) => format!("MAC {}, ", mac),
+        _ => String::new(),
+    };
+    description.push_str(&format!("seen up by host_disco {}", metadata::iso8601(store::now())));
+
+    let mut fields = vec![
+        format!("\"status\":{}", json::quote(status)),
+        format!("\"description\":{}", json::quote(&description)),
+    ];
+    if let Some(hostname) = &host.hostname {
+        fields.push(format!("\"dns_name\":{}", json::quote(hostname)));
+    }
+    if let Some(tag) = tag {
+        fields.push(format!("\"tags\":[{{\"slug\":{}}}]", json::quote(tag)));
+    }
+
+    let request = match existing {
+        Some(id) => format!("{}url = {}\nrequest = \"PATCH\"\n", headers, curl::quote(&format!("{}/api/ipam/ip-addresses/{}/", url, id))),
+        None => {
+            fields.push(format!("\"address\":\"{}/{}\"", host.address, prefix));
+            format!("{}url = {}\n", headers, curl::quote(&format!("{}/api/ipam/ip-addresses/", url)))
+        }
+    };
+    curl::run_with_payload(&request, "data-binary", format!("{{{}}}", fields.join(",")).as_bytes())?;
+    Ok(existing.is_none())
+}
@@ -0,0 +1,449 @@
+//sending watch mode events to the notification targets set up in the config file
+//
+//  [notify ops-mail]
+//  type = email
+//  server = smtps://mail.example.com:465    (smtp://host:587 upgrades with STARTTLS, tls = off to send in clear)
+//  username = alerts@example.com
+//  password = secret
+//  from = alerts@example.com
+//  to = ops@example.com, admin@example.com
+//  events = new, down                       (optional, all of new, up, down, warning, critical, normal and
+//                                           changed by default, warning, critical and normal are round trip
+//                                           time changes, changed a known address with another MAC or name)
+//
+//  [notify team-chat]
+//  type = slack                             (or discord for embeds, or webhook for the plain JSON events)
+//  url = https://hooks.slack.com/services/...
+//
+//  [notify home]
+//  type = mqtt
+//  broker = mqtt://192.168.1.10:1883
+//  topic = discovery/{range}/{ip}           ({range}, {ip} and {event} are filled in, this is the default)
+//  username = scanner                       (optional, as is password)
+//  retain = no                              (messages are retained by default so late subscribers get the state)
+//
+//  [notify desk]
+//  type = desktop                           (a notification on this machine's desktop, as --notify-desktop does:
+//                                           notify-send on Linux, osascript on macOS, a toast on Windows)
+
+use std::fs;
+use std::process::Command;
+
+use crate::config::{Config, Section};
+use crate::curl;
+use crate::metadata;
+use crate::json;
+use crate::monitor::{self, Event, EventKind};
+use crate::mqtt;
+use crate::store;
+
+const EVENT_KINDS: [&str; 7] = ["new", "up", "down", "warning", "critical", "normal", "changed"];
+
+pub enum Kind {
+    Email {
+        server: String,
+        tls: bool,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    //an HTTP POST of the events as JSON, shaped for the given chat service
+    Webhook { url: String, format: WebhookFormat },
+    //one message per event and host, published to a topic built from a template
+    Mqtt {
+        host: String,
+        port: u16,
+        topic: String,
+        username: Option<String>,
+        password: Option<String>,
+        retain: bool,
+    },
+    //a native notification on the desktop of the machine running the scan
+    Desktop,
+}
+
+#[derive(Clone, Copy)]
+pub enum WebhookFormat {
+    //the events as they are, for anything that wants to process them
+    Plain,
+    //Block Kit message for Slack incoming webhooks
+    Slack,
+    //an embed for Discord channel webhooks
+    Discord,
+}
+
+//chat services cap the size of a message, events past these are summed up in a final line
+const SLACK_MAX_EVENTS: usize = 40;
+const DISCORD_MAX_FIELDS: usize = 24;
+//a desktop notification shows a few lines, the rest are counted
+const DESKTOP_MAX_EVENTS: usize = 5;
+
+//PowerShell's own app id, toasts from an app id Windows doesn't know are dropped
+const POWERSHELL_APP_ID: &str = "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+
+//a named place events are sent to
+pub struct Notifier {
+    pub name: String,
+    pub kind: Kind,
+    //the event kinds this notifier wants
+    pub events: Vec<String>,
+}
+
+//function to read every [notify NAME] section of the config
+pub fn from_config(config: &Config) -> Result<Vec<Notifier>, String> {
+    let mut notifiers = Vec::new();
+    for (name, section) in config.sections_of("notify") {
+        if name.is_empty() {
+            return Err("notification sections need a name, e.g. [notify ops-mail]".to_string());
+        }
+        let kind = match section.require("type")? {
+            "email" => email(section)?,
+            "webhook" => webhook(section, WebhookFormat::Plain)?,
+            "slack" => webhook(section, WebhookFormat::Slack)?,
+            "discord" => webhook(section, WebhookFormat::Discord)?,
+            "mqtt" => mqtt(section)?,
+            "desktop" => Kind::Desktop,
+            other => {
+                return Err(format!(
+                    "[{}] has unknown type {}, expected email, webhook, slack, discord, mqtt or desktop",
+                    section.name, other
+                ))
+            }
+        };
+
+        let mut events = section.list("events");
+        if let Some(unknown) = events.iter().find(|event| !EVENT_KINDS.contains(&event.as_str())) {
+            return Err(format!("[{}] has unknown event {}, expected {}", section.name, unknown, EVENT_KINDS.join(", ")));
+        }
+        if events.is_empty() {
+            events = EVENT_KINDS.iter().map(|event| event.to_string()).collect();
+        }
+        notifiers.push(Notifier { name: name.to_string(), kind, events });
+    }
+    Ok(notifiers)
+}
+
+fn email(section: &Section) -> Result<Kind, String> {
+    let server = section.require("server")?;
+    if !server.starts_with("smtp://") && !server.starts_with("smtps://") {
+        return Err(format!("[{}] server must be an smtp:// or smtps:// URL", section.name));
+    }
+    let to = section.list("to");
+    if to.is_empty() {
+        return Err(format!("[{}] is missing to", section.name));
+    }
+    Ok(Kind::Email {
+        server: server.to_string(),
+        tls: section.get("tls") != Some("off"),
+        username: section.get("username").map(str::to_string),
+        password: section.get("password").map(str::to_string),
+        from: section.require("from")?.to_string(),
+        to,
+    })
+}
+
+fn webhook(section: &Section, format: WebhookFormat) -> Result<Kind, String> {
+    let url = section.require("url")?;
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("[{}] url must be an http:// or https:// URL", section.name));
+    }
+    Ok(Kind::Webhook { url: url.to_string(), format })
+}
+
+fn mqtt(section: &Section) -> Result<Kind, String> {
+    let broker = section.require("broker")?;
+    if broker.starts_with("mqtts://") {
+        return Err(format!("[{}] mqtts:// isn't supported, use a plain mqtt:// listener", section.name));
+    }
+    let address = broker.strip_prefix("mqtt://").unwrap_or(broker).trim_end_matches('/');
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("[{}] has an invalid broker port {}", section.name, port))?),
+        None => (address, 1883),
+    };
+    Ok(Kind::Mqtt {
+        host: host.to_string(),
+        port,
+        topic: section.get("topic").unwrap_or("discovery/{range}/{ip}").to_string(),
+        username: section.get("username").map(str::to_string),
+        password: section.get("password").map(str::to_string),
+        retain: !matches!(section.get("retain"), Some("no" | "false" | "off")),
+    })
+}
+
+//function to make the notifier --notify-desktop adds, for every kind of event
+pub fn desktop_notifier() -> Notifier {
+    Notifier { name: "desktop".to_string(), kind: Kind::Desktop, events: EVENT_KINDS.iter().map(|event| event.to_string()).collect() }
+}
+
+//function to list the notifier names for the startup message
+pub fn names(notifiers: &[Notifier]) -> String {
+    notifiers.iter().map(|notifier| notifier.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+//function to send the events of one sweep to every notifier that wants some of them
+pub fn send(notifiers: &[Notifier], scope: &str, events: &[Event]) {
+    for notifier in notifiers {
+        let wanted: Vec<&Event> = events.iter().filter(|event| notifier.events.iter().any(|kind| kind == event.kind.label())).collect();
+        if wanted.is_empty() {
+            continue;
+        }
+        let result = match &notifier.kind {
+            Kind::Email { .. } => send_email(&notifier.kind, scope, &wanted),
+            Kind::Webhook { url, format } => post_webhook(url, *format, scope, &wanted),
+            Kind::Mqtt { .. } => publish_mqtt(&notifier.kind, scope, &wanted),
+            Kind::Desktop => {
+                let mut lines: Vec<String> = wanted.iter().take(DESKTOP_MAX_EVENTS).map(|event| event.line()).collect();
+                if wanted.len() > DESKTOP_MAX_EVENTS {
+                    lines.push(format!("and {} more", wanted.len() - DESKTOP_MAX_EVENTS));
+                }
+                desktop(&format!("host_disco: {} on {}", monitor::summary(&wanted), scope), &lines.join("\n"))
+            }
+        };
+        if let Err(error) = result {
+            eprintln!("\x1b[0;33mNotification {} failed\x1b[0m, {}", notifier.name, error);
+        }
+    }
+}
+
+//function to mail the events through curl, credentials go over stdin so they never show up in ps
+fn send_email(kind: &Kind, scope: &str, events: &[&Event]) -> Result<(), String> {
+    let Kind::Email { server, tls, username, password, from, to } = kind else {
+        return Err("not an email notifier".to_string());
+    };
+
+    let now = store::now();
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: host_disco: {} on {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        from,
+        to.join(", "),
+        monitor::summary(events),
+        scope,
+        metadata::rfc5322(now)
+    );
+    message.push_str(&format!("Changes found by the sweep of {} at {}:\r\n\r\n", scope, metadata::iso8601(now)));
+    for event in events {
+        message.push_str(&format!("{}\r\n", event.line()));
+    }
+
+    //the URL path is the name curl greets the server with, without one it would use the file name
+    let url = match server.split_once("://").map(|(_, rest)| rest.contains('/')) {
+        Some(true) => server.clone(),
+        _ => format!("{}/{}", server, local_hostname()),
+    };
+    let mut curl_config = format!("url = {}\nmail-from = {}\n", curl::quote(&url), curl::quote(from));
+    for recipient in to {
+        curl_config.push_str(&format!("mail-rcpt = {}\n", curl::quote(recipient)));
+    }
+    if *tls && server.starts_with("smtp://") {
+        curl_config.push_str("ssl-reqd\n");
+    }
+    if let Some(username) = username {
+        curl_config.push_str(&format!("user = {}\n", curl::quote(&format!("{}:{}", username, password.as_deref().unwrap_or("")))));
+    }
+
+    curl::run_with_payload(&curl_config, "upload-file", message.as_bytes()).map(|_| ())
+}
+
+//function to post the events to a webhook, the URL goes over stdin as it usually holds the secret
+fn post_webhook(url: &str, format: WebhookFormat, scope: &str, events: &[&Event]) -> Result<(), String> {
+    let now = store::now();
+    let title = format!("host_disco: {} on {}", monitor::summary(events), scope);
+    let body = match format {
+        WebhookFormat::Plain => {
+            let items: Vec<String> = events
+                .iter()
+                .map(|event| format!("{{\"event\":{},{}\"host\":{}}}", json::quote(event.kind.label()), change_json(event), event.host.to_json()))
+                .collect();
+            format!(
+                "{{\"scope\":{},\"time\":{},\"summary\":{},\"events\":[{}]}}",
+                json::quote(scope),
+                json::quote(&metadata::iso8601(now)),
+                json::quote(&monitor::summary(events)),
+                items.join(",")
+            )
+        }
+        WebhookFormat::Slack => slack_message(&title, scope, now, events),
+        WebhookFormat::Discord => discord_message(&title, now, events),
+    };
+
+    let curl_config = format!(
+        "url = {}\nfail\nheader = \"Content-Type: application/json\"\n",
+        curl::quote(url)
+    );
+    curl::run_with_payload(&curl_config, "data-binary", body.as_bytes()).map(|_| ())
+}
+
+//function to publish one message per event, the topic levels can't hold a / so ranges use _ instead
+fn publish_mqtt(kind: &Kind, scope: &str, events: &[&Event]) -> Result<(), String> {
+    let Kind::Mqtt { host, port, topic, username, password, retain } = kind else {
+        return Err("not an MQTT notifier".to_string());
+    };
+
+    let range: String = scope
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '+' && *c != '#')
+        .map(|c| if c == '/' { '_' } else { c })
+        .collect();
+    let time = metadata::iso8601(store::now());
+    let messages: Vec<(String, String)> = events
+        .iter()
+        .map(|event| {
+            let topic = topic
+                .replace("{range}", &range)
+                .replace("{ip}", &event.host.address.to_string())
+                .replace("{event}", event.kind.label());
+            let payload = format!(
+                "{{\"event\":{},\"time\":{},\"range\":{},{}\"host\":{}}}",
+                json::quote(event.kind.label()),
+                json::quote(&time),
+                json::quote(scope),
+                change_json(event),
+                event.host.to_json()
+            );
+            (topic, payload)
+        })
+        .collect();
+
+    let client_id = format!("host_disco-{}", std::process::id());
+    let broker = mqtt::Broker {
+        host,
+        port: *port,
+        client_id: &client_id,
+        username: username.as_deref(),
+        password: password.as_deref(),
+    };
+    mqtt::publish(&broker, &messages, *retain)
+}
+
+//function to raise a desktop notification, the title and text go as arguments or through the
+//environment so nothing in them is taken as script
+pub fn desktop(title: &str, text: &str) -> Result<(), String> {
+    let (program, output) = if cfg!(target_os = "macos") {
+        let script = ["on run argv", "display notification (item 2 of argv) with title (item 1 of argv)", "end run"];
+        let output = Command::new("osascript").args(script.iter().flat_map(|line| ["-e", line])).args([title, text]).output();
+        ("osascript", output)
+    }
+    else if cfg!(windows) {
+        let script = format!(
+            "$manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+             $toast = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $lines = $toast.GetElementsByTagName('text'); \
+             [void]$lines.Item(0).AppendChild($toast.CreateTextNode($env:HOST_DISCO_TITLE)); \
+             [void]$lines.Item(1).AppendChild($toast.CreateTextNode($env:HOST_DISCO_TEXT)); \
+             $manager::CreateToastNotifier('{}').Show([Windows.UI.Notifications.ToastNotification]::new($toast))",
+            POWERSHELL_APP_ID
+        );
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .env("HOST_DISCO_TITLE", title)
+            .env("HOST_DISCO_TEXT", text)
+            .output();
+        ("powershell", output)
+    }
+    //the freedesktop notification service on the session D-Bus
+    else {
+        ("notify-send", Command::new("notify-send").args(["--app-name", "host_disco", "--", title, text]).output())
+    };
+    let output = output.map_err(|error| format!("cannot run {}, {}", program, error))?;
+    if !output.status.success() {
+        return Err(format!("{} failed, {}", program, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+//function to build a Slack Block Kit message, a header, one section per event and a context line
+fn slack_message(title: &str, scope: &str, now: u64, events: &[&Event]) -> String {
+    let mut blocks = vec![format!(
+        "{{\"type\":\"header\",\"text\":{{\"type\":\"plain_text\",\"text\":{}}}}}",
+        json::quote(title)
+    )];
+    for event in events.iter().take(SLACK_MAX_EVENTS) {
+        let emoji = match event.kind {
+            EventKind::New => ":new:",
+            EventKind::Up => ":large_green_circle:",
+            EventKind::Down => ":red_circle:",
+            EventKind::Warning => ":warning:",
+            EventKind::Critical => ":rotating_light:",
+            EventKind::Normal => ":white_check_mark:",
+            EventKind::Changed => ":twisted_rightwards_arrows:",
+        };
+        let mut text = format!("{} *{}* `{}`", emoji, event.kind.label().to_uppercase(), event.host.address);
+        if let Some(rtt) = event.host.rtt() {
+            text.push_str(&format!(" {:.2}ms", rtt));
+        }
+        let detail = event.detail();
+        if !detail.is_empty() {
+            text.push_str(&format!("\n{}", detail));
+        }
+        blocks.push(format!("{{\"type\":\"section\",\"text\":{{\"type\":\"mrkdwn\",\"text\":{}}}}}", json::quote(&text)));
+    }
+
+    let mut context = format!("Sweep of {} at {}", scope, metadata::iso8601(now));
+    if events.len() > SLACK_MAX_EVENTS {
+        context.push_str(&format!(", {} more changes not shown", events.len() - SLACK_MAX_EVENTS));
+    }
+    blocks.push(format!(
+        "{{\"type\":\"context\",\"elements\":[{{\"type\":\"mrkdwn\",\"text\":{}}}]}}",
+        json::quote(&context)
+    ));
+    format!("{{\"text\":{},\"blocks\":[{}]}}", json::quote(title), blocks.join(","))
+}
+
+//function to build a Discord embed, one field per event and coloured by the worst change
+fn discord_message(title: &str, now: u64, events: &[&Event]) -> String {
+    let colour = if events.iter().any(|event| event.kind == EventKind::Down || event.kind == EventKind::Critical) {
+        0xe74c3c
+    }
+    else if events.iter().any(|event| event.kind == EventKind::Warning || event.kind == EventKind::Changed) {
+        0xf1c40f
+    }
+    else if events.iter().any(|event| event.kind == EventKind::New) {
+        0x3498db
+    }
+    else {
+        0x2ecc71
+    };
+
+    let mut fields: Vec<String> = events
+        .iter()
+        .take(DISCORD_MAX_FIELDS)
+        .map(|event| {
+            let mut value = event.detail();
+            if let Some(rtt) = event.host.rtt() {
+                value = format!("{:.2}ms {}", rtt, value);
+            }
+            format!(
+                "{{\"name\":{},\"value\":{},\"inline\":false}}",
+                json::quote(&format!("{} {}", event.kind.label().to_uppercase(), event.host.address)),
+                json::quote(if value.trim().is_empty() { "-" } else { value.trim() })
+            )
+        })
+        .collect();
+    if events.len() > DISCORD_MAX_FIELDS {
+        fields.push(format!(
+            "{{\"name\":\"...\",\"value\":{},\"inline\":false}}",
+            json::quote(&format!("{} more changes not shown", events.len() - DISCORD_MAX_FIELDS))
+        ));
+    }
+    format!(
+        "{{\"embeds\":[{{\"title\":{},\"color\":{},\"timestamp\":{},\"fields\":[{}],\"footer\":{{\"text\":\"host_disco\"}}}}]}}",
+        json::quote(title),
+        colour,
+        json::quote(&metadata::iso8601(now)),
+        fields.join(",")
+    )
+}
+
+//function to give the "change" member of a changed host's event, and nothing for the others
+fn change_json(event: &Event) -> String {
+    event.change.as_ref().map(|change| format!("\"change\":{},", json::quote(change))).unwrap_or_default()
+}
+
+fn local_hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
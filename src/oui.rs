@@ -0,0 +1,151 @@
+//MAC vendor lookup, an embedded table of well known OUIs (the first three bytes of a MAC) that
+//update-oui replaces with the full IEEE registry, kept in oui.txt next to the stored scans
+//
+//tables are one OUI per line, six hex digits, a tab and the organisation, e.g. B827EB	Raspberry Pi
+//
+//the embedded table is a sample of a few dozen vendors, not the registry, so a MAC it doesn't know is
+//reported as such once and filtering by vendor is refused until update-oui has been run
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::curl;
+use crate::store;
+
+const EMBEDDED: &str = include_str!("oui.txt");
+
+//the table and whether it is the embedded one
+static TABLE: OnceLock<(Vec<(u32, String)>, bool)> = OnceLock::new();
+//whether a MAC missing from the embedded table has been reported
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+fn path() -> Result<PathBuf, String> {
+    Ok(store::data_dir()?.join("oui.txt"))
+}
+
+//function to get the OUI table, the updated one when there is one, sorted by prefix
+fn table() -> &'static [(u32, String)] {
+    &loaded().0
+}
+
+fn loaded() -> &'static (Vec<(u32, String)>, bool) {
+    TABLE.get_or_init(|| match path().ok().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(updated) => (parse(&updated), false),
+        None => (parse(EMBEDDED), true),
+    })
+}
+
+//function to tell whether only the embedded sample of vendors is there to look MACs up in
+pub fn embedded_only() -> bool {
+    loaded().1
+}
+
+//function to refuse what can't be done without the IEEE registry, e.g. "--vendor-filter"
+pub fn require_registry(what: &str) -> Result<(), String> {
+    if embedded_only() {
+        return Err(format!(
+            "{} needs the IEEE vendor registry, the embedded table only knows {} vendors, run host_disco update-oui https://standards-oui.ieee.org/oui/oui.csv first",
+            what,
+            table().len()
+        ));
+    }
+    Ok(())
+}
+
+fn parse(text: &str) -> Vec<(u32, String)> {
+    let mut table: Vec<(u32, String)> = text
+        .lines()
+        .filter_map(|line| {
+            let (prefix, vendor) = line.split_once('\t')?;
+            Some((u32::from_str_radix(prefix.trim(), 16).ok()?, vendor.trim().to_string()))
+        })
+        .collect();
+    table.sort_by_key(|(prefix, _)| *prefix);
+    table
+}
+
+//function to find the vendor of a MAC address from its OUI
+pub fn vendor(mac: &str) -> Option<&'static str> {
+    let hex: String = mac.chars().filter(char::is_ascii_hexdigit).take(6).collect();
+    let prefix = u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 6)?;
+    let table = table();
+    let found = table.binary_search_by_key(&prefix, |(oui, _)| *oui).ok().map(|index| table[index].1.as_str());
+    //a randomized MAC has no vendor in any table
+    if found.is_none() && embedded_only() && !is_randomized(mac) && !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "No vendor for {} in the embedded table of {} well known vendors, run host_disco update-oui for the IEEE registry.",
+            mac,
+            table.len()
+        );
+    }
+    found
+}
+
+//function to tell a randomized MAC, one with the locally administered bit of the first byte set, as phones
+//and laptops use per network or per connection, so it has no vendor and won't follow a device across scans
+//(virtual machines and containers get locally administered MACs too, e.g. Docker's 02:42:...)
+pub fn is_randomized(mac: &str) -> bool {
+    let hex: String = mac.chars().filter(char::is_ascii_hexdigit).take(2).collect();
+    u8::from_str_radix(&hex, 16).is_ok_and(|first| hex.len() == 2 && first & 0x02 != 0)
+}
+
+//function to replace the OUI table with the MA-L assignments of an IEEE oui.csv, from a file or a URL
+//(https://standards-oui.ieee.org/oui/oui.csv), the embedded table is used again if the file is removed
+pub fn update(source: &str) -> Result<(), String> {
+    let csv = if source.starts_with("http://") || source.starts_with("https://") {
+        let body = curl::run(&format!("url = {}\nfail\nlocation\nmax-time = 300\n", curl::quote(source)))
+            .map_err(|error| format!("cannot download {}, {}", source, error))?;
+        String::from_utf8_lossy(&body).to_string()
+    }
+    else {
+        fs::read_to_string(source).map_err(|error| format!("cannot read {}, {}", source, error))?
+    };
+
+    //Registry,Assignment,Organization Name,Organization Address
+    let mut entries: Vec<(String, String)> = csv
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = csv_fields(line);
+            let (registry, assignment, organisation) = (fields.first()?, fields.get(1)?, fields.get(2)?);
+            let valid = registry == "MA-L" && assignment.len() == 6 && assignment.chars().all(|c| c.is_ascii_hexdigit());
+            valid.then(|| (assignment.to_uppercase(), organisation.trim().to_string()))
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(format!("No MA-L assignments in {}, expected the IEEE oui.csv", source));
+    }
+    entries.sort();
+    entries.dedup_by(|a, b| a.0 == b.0);
+
+    let dir = store::data_dir()?;
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    let path = path()?;
+    let lines: String = entries.iter().map(|(prefix, vendor)| format!("{}\t{}\n", prefix, vendor)).collect();
+    fs::write(&path, lines).map_err(|error| format!("cannot write {}, {}", path.display(), error))?;
+    println!("OUI table updated with {} vendors, written to {}.", entries.len(), path.display());
+    Ok(())
+}
+
+//function to split a CSV line into its fields, quoted fields may hold commas and doubled quotes
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,392 @@
+//rendering the scan results as table, JSON, CSV, XML or CBOR, as a matrix of open ports, in the formats of other
+//scanners for the tools built around them (Nmap XML, masscan JSON, zmap's address list), or as monitoring
+//configuration
+
+use crate::cbor;
+use crate::compress;
+use crate::cli::Options;
+use crate::host::{HostResult, PortState};
+use crate::metadata::{self, ScanMetadata};
+use crate::monitoring;
+
+pub const FORMATS: [&str; 12] = ["table", "json", "csv", "xml", "cbor", "matrix", "nmap", "masscan-json", "zmap", "nagios", "icinga", "zabbix"];
+
+//function to render the results in the given format, addresses that didn't answer and went to a spill
+//file instead are counted in the metadata every format with a header carries, nmap's runstats count
+//them as down, masscan-json and zmap only ever list the hosts that answered
+pub fn render(format: &str, metadata: &ScanMetadata, options: &Options, results: &[HostResult]) -> Result<String, String> {
+    match format {
+        "table" => Ok(table(metadata, results)),
+        "json" => Ok(json(metadata, results)),
+        "csv" => Ok(csv(metadata, results)),
+        "xml" => Ok(xml(metadata, results)),
+        "cbor" => Err("The cbor format is binary, give --output-file to write it to.".to_string()),
+        "matrix" => Ok(matrix(metadata, results)),
+        "nmap" => Ok(nmap(metadata, results)),
+        "masscan-json" => Ok(masscan_json(metadata, results)),
+        "zmap" => Ok(zmap(results)),
+        "nagios" => Ok(monitoring::nagios(metadata, options, results)),
+        "icinga" => Ok(monitoring::icinga(metadata, options, results)),
+        "zabbix" => Ok(monitoring::zabbix(metadata, options, results)),
+        _ => Err(format!("Unknown output format {}, expected one of {}.", format, FORMATS.join(", "))),
+    }
+}
+
+//function to write the rendered results to a file
+pub fn write_file(path: &str, format: &str, metadata: &ScanMetadata, options: &Options, results: &[HostResult]) -> Result<(), String> {
+    let document = match format {
+        "cbor" => cbor::results(metadata, results)?,
+        _ => render(format, metadata, options, results)?.into_bytes(),
+    };
+    compress::write(path, &document, options.compress.as_deref(), options.encryption.as_ref())
+}
+
+//the run metadata goes above the table as name: value lines
+fn table(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let mut table: String = metadata.fields().iter().map(|(name, value)| format!("{}: {}\n", name, value)).collect();
+    table.push('\n');
+    //the name and MAC columns are as wide as their longest value
+    let shown: Vec<&HostResult> = results.iter().filter(|host| host.is_up() || !host.ports.is_empty() || host.seen_by.is_some()).collect();
+    let name_width = shown.iter().filter_map(|host| host.hostname.as_ref()).map(|name| name.len()).max().unwrap_or(0).max(8);
+    let mac_width = shown.iter().map(|host| mac_cell(host).len()).max().unwrap_or(0).max(3);
+    let roles_width = shown.iter().map(|host| host.roles.join(",").len()).max().unwrap_or(0).max(5);
+    table += &format!(
+        "{:<16} {:<7} {:>10} {:>4}  {:<name_width$}  {:<mac_width$}  {:<roles_width$}  {}\n",
+        "ADDRESS", "STATUS", "RTT", "HOPS", "HOSTNAME", "MAC", "ROLES", "PORTS"
+    );
+    for host in shown {
+        let rtt = host.rtt().map(|rtt| format!("{:.2}ms", rtt)).unwrap_or_default();
+        let mut ports: Vec<String> = host
+            .ports
+            .iter()
+            .map(|port| {
+                let mut cell = format!("{}/{} {}", port.port, port.protocol, port.state.label());
+                if let Some(service) = &port.service {
+                    cell.push_str(&format!(" ({})", service));
+                }
+                if let Some(info) = &port.info {
+                    cell.push_str(&format!(" [{}]", info));
+                }
+                cell
+            })
+            .collect();
+        ports.extend(host.seen_by.as_ref().map(|seen_by| format!("seen by {}", seen_by)));
+        ports.extend(host.asn.clone());
+        table.push_str(&format!(
+            "{:<16} {:<7} {:>10} {:>4}  {:<name_width$}  {:<mac_width$}  {:<roles_width$}  {}\n",
+            host.address,
+            host.status(),
+            rtt,
+            host.hops().map(|hops| hops.to_string()).unwrap_or_default(),
+            host.hostname.as_deref().unwrap_or(""),
+            mac_cell(host),
+            host.roles.join(","),
+            ports.join(", ")
+        ));
+    }
+    table
+}
+
+//live hosts as rows and the probed ports as columns, ✓ open, ✗ closed or filtered and ? open|filtered,
+//with a last row counting the hosts each port is open on, so services missing on some hosts stand out
+fn matrix(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let mut matrix: String = metadata.fields().iter().map(|(name, value)| format!("{}: {}\n", name, value)).collect();
+    matrix.push('\n');
+    let hosts: Vec<&HostResult> = results.iter().filter(|host| host.is_up()).collect();
+    //the ports in the order they were probed, TCP then UDP
+    let mut columns: Vec<(u16, &str)> = Vec::new();
+    for port in hosts.iter().flat_map(|host| host.ports.iter()) {
+        if !columns.contains(&(port.port, port.protocol)) {
+            columns.push((port.port, port.protocol));
+        }
+    }
+    columns.sort_by_key(|(port, protocol)| (*protocol == "udp", *port));
+    let labels: Vec<String> = columns.iter().map(|(port, protocol)| format!("{}/{}", port, protocol)).collect();
+
+    matrix += &format!("{:<16}", "ADDRESS");
+    for label in &labels {
+        matrix += &format!(" {:^width$}", label, width = label.len());
+    }
+    matrix.push('\n');
+    for host in &hosts {
+        matrix += &format!("{:<16}", host.address);
+        for ((port, protocol), label) in columns.iter().zip(&labels) {
+            let cell = match host.ports.iter().find(|result| result.port == *port && result.protocol == *protocol).map(|result| result.state) {
+                Some(PortState::Open) => "✓",
+                Some(PortState::OpenFiltered) => "?",
+                Some(PortState::Closed | PortState::Filtered) => "✗",
+                None => "-",
+            };
+            matrix += &format!(" {:^width$}", cell, width = label.len());
+        }
+        matrix.push('\n');
+    }
+    matrix += &format!("{:<16}", "OPEN ON");
+    for ((port, protocol), label) in columns.iter().zip(&labels) {
+        let open = hosts
+            .iter()
+            .filter(|host| host.ports.iter().any(|result| result.port == *port && result.protocol == *protocol && result.state == PortState::Open))
+            .count();
+        matrix += &format!(" {:^width$}", format!("{}/{}", open, hosts.len()), width = label.len());
+    }
+    matrix.push('\n');
+    matrix
+}
+
+//function to show a MAC with its vendor, e.g. "B8:27:EB:12:34:56 (Raspberry Pi)" or "DA:A1:19:0C:5E:21 (randomized)"
+fn mac_cell(host: &HostResult) -> String {
+    match (&host.mac, &host.vendor) {
+        (Some(mac), Some(vendor)) => format!("{} ({})", mac, vendor),
+        (Some(mac), None) if host.randomized_mac() == Some(true) => format!("{} (randomized)", mac),
+        (Some(mac), None) => mac.clone(),
+        _ => String::new(),
+    }
+}
+
+fn json(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let hosts: Vec<String> = results.iter().map(HostResult::to_json).collect();
+    format!("{{\"scan\":{},\n\"hosts\":[\n{}\n]}}\n", metadata.to_json(), hosts.join(",\n"))
+}
+
+//one row per port, hosts without ports get a single row with the port columns empty
+//the run metadata goes in # comment lines before the header
+fn csv(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let mut csv: String = metadata.fields().iter().map(|(name, value)| format!("# {}: {}\n", name, value)).collect();
+    csv.push_str("address,status,reason,rtt_ms,ttl,hops,hostname,mac,vendor,mac_randomized,roles,seen_by,asn,source,port,protocol,state,service,info\n");
+    for host in results {
+        let prefix = format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            host.address,
+            host.status(),
+            csv_field(&host.reason().unwrap_or_default()),
+            host.rtt().map(|rtt| format!("{:.3}", rtt)).unwrap_or_default(),
+            host.ttl().map(|ttl| ttl.to_string()).unwrap_or_default(),
+            host.hops().map(|hops| hops.to_string()).unwrap_or_default(),
+            csv_field(host.hostname.as_deref().unwrap_or("")),
+            host.mac.as_deref().unwrap_or(""),
+            csv_field(host.vendor.as_deref().unwrap_or("")),
+            host.randomized_mac().map(|randomized| if randomized { "yes" } else { "no" }).unwrap_or(""),
+            host.roles.join(";"),
+            csv_field(host.seen_by.as_deref().unwrap_or("")),
+            csv_field(host.asn.as_deref().unwrap_or("")),
+            host.source.as_deref().unwrap_or("")
+        );
+        if host.ports.is_empty() {
+            csv.push_str(&format!("{},,,,,\n", prefix));
+        }
+        for port in &host.ports {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                prefix,
+                port.port,
+                port.protocol,
+                port.state.label(),
+                csv_field(port.service.as_deref().unwrap_or("")),
+                csv_field(port.info.as_deref().unwrap_or(""))
+            ));
+        }
+    }
+    csv
+}
+
+fn xml(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<scan");
+    for (name, value) in metadata.fields().iter().filter(|(name, _)| !["command", "owner", "source"].contains(name)) {
+        xml.push_str(&format!(" {}=\"{}\"", name, xml_escape(value)));
+    }
+    xml.push_str(">\n  <parameters>\n");
+    for parameter in &metadata.parameters {
+        xml.push_str(&format!("    <parameter>{}</parameter>\n", xml_escape(parameter)));
+    }
+    xml.push_str("  </parameters>\n");
+    for owner in &metadata.owners {
+        xml.push_str(&format!("  <owner range=\"{}\"", xml_escape(&owner.range)));
+        for (name, value) in [("netname", &owner.netname), ("org", &owner.org), ("abuse", &owner.abuse)] {
+            if let Some(value) = value {
+                xml.push_str(&format!(" {}=\"{}\"", name, xml_escape(value)));
+            }
+        }
+        xml.push_str("/>\n");
+    }
+    for source in &metadata.sources {
+        xml.push_str(&format!(
+            "  <source id=\"{}\" finished=\"{}\" file=\"{}\"/>\n",
+            xml_escape(&source.id),
+            metadata::iso8601(source.finished_at),
+            xml_escape(&source.file)
+        ));
+    }
+    for host in results {
+        xml.push_str(&format!("  <host address=\"{}\" status=\"{}\"", host.address, host.status()));
+        if let Some(reason) = host.reason() {
+            xml.push_str(&format!(" reason=\"{}\"", xml_escape(&reason)));
+        }
+        if let Some(rtt) = host.rtt() {
+            xml.push_str(&format!(" rtt_ms=\"{:.3}\"", rtt));
+        }
+        if let (Some(ttl), Some(hops)) = (host.ttl(), host.hops()) {
+            xml.push_str(&format!(" ttl=\"{}\" hops=\"{}\"", ttl, hops));
+        }
+        for (name, value) in [("hostname", &host.hostname), ("mac", &host.mac), ("vendor", &host.vendor), ("seen_by", &host.seen_by), ("asn", &host.asn), ("source", &host.source)] {
+            if let Some(value) = value {
+                xml.push_str(&format!(" {}=\"{}\"", name, xml_escape(value)));
+            }
+        }
+        if host.randomized_mac() == Some(true) {
+            xml.push_str(" mac_randomized=\"yes\"");
+        }
+        if !host.roles.is_empty() {
+            xml.push_str(&format!(" roles=\"{}\"", xml_escape(&host.roles.join(" "))));
+        }
+        if host.ports.is_empty() {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        for port in &host.ports {
+            xml.push_str(&format!(
+                "    <port number=\"{}\" protocol=\"{}\" state=\"{}\"",
+                port.port,
+                port.protocol,
+                port.state.label()
+            ));
+            if let Some(service) = &port.service {
+                xml.push_str(&format!(" service=\"{}\"", xml_escape(service)));
+            }
+            if let Some(info) = &port.info {
+                xml.push_str(&format!(" info=\"{}\"", xml_escape(info)));
+            }
+            xml.push_str("/>\n");
+        }
+        xml.push_str("  </host>\n");
+    }
+    xml.push_str("</scan>\n");
+    xml
+}
+
+//function to quote a CSV field when it contains a separator, quote or newline
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+    else {
+        value.to_string()
+    }
+}
+
+//the live hosts in Nmap's XML output format, which Metasploit's db_import (and most other tools that take
+//scan results) recognise by its nmaprun element, with addresses, names, MACs and the probed ports
+fn nmap(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let spilled = metadata.spilled.as_ref().map_or(0, |(_, count)| *count);
+    let command = xml_escape(&metadata.parameters.join(" "));
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE nmaprun>\n");
+    xml.push_str(&format!(
+        "<nmaprun scanner=\"host_disco\" args=\"{}\" start=\"{}\" startstr=\"{}\" version=\"{}\" xmloutputversion=\"1.05\">\n",
+        command,
+        metadata.started_at,
+        metadata::iso8601(metadata.started_at),
+        metadata.version
+    ));
+    let up: Vec<&HostResult> = results.iter().filter(|host| host.is_up()).collect();
+    for host in &up {
+        xml.push_str(&format!("<host starttime=\"{}\" endtime=\"{}\">", metadata.started_at, metadata.finished_at));
+        let reason = if host.found_by.contains(&"icmp") || host.found_by.is_empty() {
+            "echo-reply"
+        }
+        else if host.found_by.contains(&"arp") {
+            "arp-response"
+        }
+        else if host.ports.iter().any(|port| port.protocol == "tcp" && port.state == PortState::Open) {
+            "syn-ack"
+        }
+        else {
+            "conn-refused"
+        };
+        xml.push_str(&format!(
+            "<status state=\"up\" reason=\"{}\" reason_ttl=\"{}\"/>\n",
+            reason,
+            host.ttl().unwrap_or(0)
+        ));
+        xml.push_str(&format!("<address addr=\"{}\" addrtype=\"ipv4\"/>\n", host.address));
+        if let Some(mac) = &host.mac {
+            match &host.vendor {
+                Some(vendor) => xml.push_str(&format!("<address addr=\"{}\" addrtype=\"mac\" vendor=\"{}\"/>\n", mac, xml_escape(vendor))),
+                None => xml.push_str(&format!("<address addr=\"{}\" addrtype=\"mac\"/>\n", mac)),
+            }
+        }
+        match &host.hostname {
+            Some(name) => xml.push_str(&format!("<hostnames>\n<hostname name=\"{}\" type=\"PTR\"/>\n</hostnames>\n", xml_escape(name))),
+            None => xml.push_str("<hostnames>\n</hostnames>\n"),
+        }
+        if !host.ports.is_empty() {
+            xml.push_str("<ports>");
+            for port in &host.ports {
+                //the states and reasons nmap gives for what a connect and a UDP probe can see
+                let (state, reason) = match port.state {
+                    PortState::Open => ("open", if port.protocol == "udp" { "udp-response" } else { "syn-ack" }),
+                    PortState::Closed => ("closed", if port.protocol == "udp" { "port-unreach" } else { "conn-refused" }),
+                    PortState::Filtered => ("filtered", "no-response"),
+                    PortState::OpenFiltered => ("open|filtered", "no-response"),
+                };
+                xml.push_str(&format!(
+                    "<port protocol=\"{}\" portid=\"{}\"><state state=\"{}\" reason=\"{}\" reason_ttl=\"0\"/>",
+                    port.protocol, port.port, state, reason
+                ));
+                if let Some(service) = &port.service {
+                    let product = port.info.as_ref().map(|info| format!(" extrainfo=\"{}\"", xml_escape(info))).unwrap_or_default();
+                    xml.push_str(&format!("<service name=\"{}\"{} method=\"table\" conf=\"3\"/>", xml_escape(service), product));
+                }
+                xml.push_str("</port>\n");
+            }
+            xml.push_str("</ports>\n");
+        }
+        xml.push_str("</host>\n");
+    }
+    xml.push_str(&format!(
+        "<runstats><finished time=\"{}\" timestr=\"{}\" elapsed=\"{}\" exit=\"success\"/><hosts up=\"{}\" down=\"{}\" total=\"{}\"/>\n</runstats>\n</nmaprun>\n",
+        metadata.finished_at,
+        metadata::iso8601(metadata.finished_at),
+        metadata.finished_at.saturating_sub(metadata.started_at),
+        up.len(),
+        results.len() - up.len() + spilled,
+        results.len() + spilled
+    ));
+    xml
+}
+
+//masscan's -oJ output, a record per answer, ICMP as port 0 the way masscan --ping writes it, the
+//records separated by lines holding a comma
+fn masscan_json(metadata: &ScanMetadata, results: &[HostResult]) -> String {
+    let mut records = Vec::new();
+    for host in results.iter().filter(|host| host.is_up()) {
+        let ttl = host.ttl().unwrap_or(0);
+        let mut answers = Vec::new();
+        if host.found_by.contains(&"icmp") || host.found_by.is_empty() {
+            answers.push((0, "icmp", "none"));
+        }
+        for port in host.ports.iter().filter(|port| port.state == PortState::Open) {
+            answers.push((port.port, port.protocol, if port.protocol == "udp" { "none" } else { "syn-ack" }));
+        }
+        for (port, protocol, reason) in answers {
+            records.push(format!(
+                "{{   \"ip\": \"{}\",   \"timestamp\": \"{}\", \"ports\": [ {{\"port\": {}, \"proto\": \"{}\", \"status\": \"open\", \"reason\": \"{}\", \"ttl\": {}}} ] }}\n",
+                host.address, metadata.finished_at, port, protocol, reason, ttl
+            ));
+        }
+    }
+    format!("[\n{}]\n", records.join(",\n"))
+}
+
+//zmap's default output, the address of every host that answered, one per line
+fn zmap(results: &[HostResult]) -> String {
+    results.iter().filter(|host| host.is_up()).map(|host| format!("{}\n", host.address)).collect()
+}
+
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
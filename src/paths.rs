@@ -0,0 +1,103 @@
+//comparing the paths to the targets from several local interfaces (--paths eth0,wlan0,tun0), every
+//address pinged out of each interface in turn with ping -I and the round trip times set side by side,
+//to tell a routing or path problem (a VPN that doesn't carry a subnet, Wi-Fi adding latency) from a host one
+
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::probe::{self, Outcome};
+use crate::stats;
+
+pub const DEFAULT_WORKERS: usize = 32;
+//a path is flagged as slow when it takes this many times as long as the quickest one, and at least SLOW_MARGIN_MS longer
+const SLOW_FACTOR: f64 = 2.0;
+const SLOW_MARGIN_MS: f64 = 1.0;
+
+//function to check the interfaces exist before anything is sent
+pub fn check(interfaces: &[String]) -> Result<(), String> {
+    match interfaces.iter().find(|interface| !Path::new("/sys/class/net").join(interface).exists()) {
+        Some(missing) => Err(format!("there is no interface {}", missing)),
+        None => Ok(()),
+    }
+}
+
+//function to ping every address from every interface and print the round trip times side by side
+pub fn compare(addresses: &[Ipv4Addr], interfaces: &[String], workers: usize) {
+    println!("Pinging {} addresses from {}.", addresses.len(), interfaces.join(", "));
+    let next = AtomicUsize::new(0);
+    let rows: Mutex<Vec<(Ipv4Addr, Vec<Option<f64>>)>> = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, addresses.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(address) = addresses.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    //the paths are tried one after another so they see the host at about the same moment
+                    let rtts: Vec<Option<f64>> = interfaces
+                        .iter()
+                        .map(|interface| match probe::ping_via(&address.to_string(), Some(interface)) {
+                            //a reply without a time still says the path works, it shows as 0
+                            Outcome::Up { rtt, .. } => Some(rtt.unwrap_or(0.0)),
+                            _ => None,
+                        })
+                        .collect();
+                    if rtts.iter().any(Option::is_some) {
+                        rows.lock().unwrap().push((*address, rtts));
+                    }
+                }
+            });
+        }
+    });
+    let mut rows = rows.into_inner().unwrap();
+    rows.sort_by_key(|(address, _)| *address);
+
+    println!();
+    if rows.is_empty() {
+        println!("No host answered on any path.");
+        return;
+    }
+    let width = interfaces.iter().map(String::len).max().unwrap_or(0).max(10);
+    let mut header = format!("{:<16}", "ADDRESS");
+    for interface in interfaces {
+        header.push_str(&format!(" {:>width$}", interface));
+    }
+    println!("{}", header);
+    for (address, rtts) in &rows {
+        let best = rtts.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+        let mut line = format!("{:<16}", address);
+        for rtt in rtts {
+            let cell = match rtt {
+                Some(rtt) => format!("{:.2}ms", rtt),
+                None => "-".to_string(),
+            };
+            //padded before colouring, the escape codes would throw the width out
+            let cell = format!("{:>width$}", cell);
+            match rtt {
+                Some(rtt) if *rtt > best * SLOW_FACTOR && *rtt - best >= SLOW_MARGIN_MS => line.push_str(&format!(" \x1b[0;33m{}\x1b[0m", cell)),
+                Some(_) => line.push_str(&format!(" {}", cell)),
+                None => line.push_str(&format!(" \x1b[31m{}\x1b[0m", cell)),
+            }
+        }
+        println!("{}", line);
+    }
+
+    println!();
+    for (index, interface) in interfaces.iter().enumerate() {
+        let mut rtts: Vec<f64> = rows.iter().filter_map(|(_, rtts)| rtts[index]).collect();
+        rtts.sort_by(f64::total_cmp);
+        let median = if rtts.is_empty() { String::new() } else { format!(", median {:.2}ms", stats::percentile(&rtts, 50.0)) };
+        println!("{}: {} of {} hosts answered{}.", interface, rtts.len(), rows.len(), median);
+    }
+    let partial: Vec<String> = rows
+        .iter()
+        .filter(|(_, rtts)| rtts.iter().any(Option::is_none))
+        .map(|(address, rtts)| {
+            let missing: Vec<&str> = interfaces.iter().zip(rtts).filter(|(_, rtt)| rtt.is_none()).map(|(interface, _)| interface.as_str()).collect();
+            format!("{} (not via {})", address, missing.join(", "))
+        })
+        .collect();
+    if !partial.is_empty() {
+        println!("Only reachable on some paths: {}.", partial.join(", "));
+    }
+}
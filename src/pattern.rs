@@ -0,0 +1,228 @@
+//a small regular expression matcher for names, enough for naming conventions like ^db-[0-9]+\.prod
+//
+//supports literals, ., ^, $, [a-z] and [^...] classes, \d \w \s (and \D \W \S), escaped characters,
+//( ) groups, | alternation and the *, +, ? and {n,m} quantifiers, matching is case insensitive
+
+pub struct Pattern {
+    source: String,
+    alternatives: Vec<Vec<Node>>,
+}
+
+enum Node {
+    Char(char),
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> },
+}
+
+impl Node {
+    //function to test a single character against a node that consumes exactly one
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Node::Char(expected) => *expected == c,
+            Node::Any => true,
+            Node::Class { ranges, negated } => ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated,
+            _ => false,
+        }
+    }
+}
+
+impl Pattern {
+    //function to compile a pattern, naming the problem if it isn't valid
+    pub fn new(source: &str) -> Result<Pattern, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut position = 0;
+        let alternatives = parse_alternatives(&chars, &mut position)
+            .map_err(|error| format!("Invalid pattern {}, {}", source, error))?;
+        if position < chars.len() {
+            return Err(format!("Invalid pattern {}, unmatched )", source));
+        }
+        Ok(Pattern { source: source.to_string(), alternatives })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    //function to test whether the pattern matches anywhere in the text, ^ and $ anchor it
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.to_lowercase().chars().collect();
+        (0..=text.len()).any(|start| self.alternatives.iter().any(|nodes| match_here(nodes, &text, start, &|_| true)))
+    }
+}
+
+fn parse_alternatives(chars: &[char], position: &mut usize) -> Result<Vec<Vec<Node>>, String> {
+    let mut alternatives = vec![parse_sequence(chars, position)?];
+    while chars.get(*position) == Some(&'|') {
+        *position += 1;
+        alternatives.push(parse_sequence(chars, position)?);
+    }
+    Ok(alternatives)
+}
+
+fn parse_sequence(chars: &[char], position: &mut usize) -> Result<Vec<Node>, String> {
+    let mut nodes = Vec::new();
+    while let Some(&c) = chars.get(*position) {
+        if c == '|' || c == ')' {
+            break;
+        }
+        *position += 1;
+        let node = match c {
+            '(' => {
+                let group = parse_alternatives(chars, position)?;
+                if chars.get(*position) != Some(&')') {
+                    return Err("unclosed (".to_string());
+                }
+                *position += 1;
+                Node::Group(group)
+            }
+            '[' => parse_class(chars, position)?,
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => parse_escape(chars, position)?,
+            '*' | '+' | '?' | '{' => return Err(format!("nothing to repeat before {}", c)),
+            c => Node::Char(lower(c)),
+        };
+        let node = parse_quantifier(chars, position, node)?;
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+//function to wrap a node in the quantifier that follows it, if any
+fn parse_quantifier(chars: &[char], position: &mut usize, node: Node) -> Result<Node, String> {
+    let (min, max) = match chars.get(*position) {
+        Some('*') => (0, None),
+        Some('+') => (1, None),
+        Some('?') => (0, Some(1)),
+        Some('{') => {
+            let close = chars[*position..].iter().position(|c| *c == '}').ok_or("unclosed {")? + *position;
+            let inside: String = chars[*position + 1..close].iter().collect();
+            let number = |value: &str| value.trim().parse::<usize>().map_err(|_| format!("bad repeat count {{{}}}", inside));
+            let bounds = match inside.split_once(',') {
+                Some((min, "")) => (number(min)?, None),
+                Some((min, max)) => (number(min)?, Some(number(max)?)),
+                None => (number(&inside)?, Some(number(&inside)?)),
+            };
+            *position = close;
+            bounds
+        }
+        _ => return Ok(node),
+    };
+    if matches!(node, Node::Start | Node::End) {
+        return Err("nothing to repeat".to_string());
+    }
+    if max.is_some_and(|max| max < min) {
+        return Err("repeat count out of order".to_string());
+    }
+    *position += 1;
+    Ok(Node::Repeat { node: Box::new(node), min, max })
+}
+
+fn parse_class(chars: &[char], position: &mut usize) -> Result<Node, String> {
+    let negated = chars.get(*position) == Some(&'^');
+    if negated {
+        *position += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let Some(&c) = chars.get(*position) else {
+            return Err("unclosed [".to_string());
+        };
+        *position += 1;
+        //a ] straight after the opening bracket is a literal one
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        let low = if c == '\\' {
+            match parse_escape(chars, position)? {
+                Node::Char(c) => c,
+                Node::Class { ranges: escaped, negated: false } => {
+                    ranges.extend(escaped);
+                    continue;
+                }
+                _ => return Err("negated escapes aren't supported inside [ ]".to_string()),
+            }
+        }
+        else {
+            c
+        };
+        let high = match (chars.get(*position), chars.get(*position + 1)) {
+            (Some('-'), Some(&high)) if high != ']' => {
+                *position += 2;
+                high
+            }
+            _ => low,
+        };
+        if high < low {
+            return Err(format!("range {}-{} out of order", low, high));
+        }
+        ranges.push((lower(low), lower(high)));
+    }
+    Ok(Node::Class { ranges, negated })
+}
+
+fn parse_escape(chars: &[char], position: &mut usize) -> Result<Node, String> {
+    let Some(&c) = chars.get(*position) else {
+        return Err("trailing \\".to_string());
+    };
+    *position += 1;
+    let class = |ranges: Vec<(char, char)>, negated| Node::Class { ranges, negated };
+    let digits = vec![('0', '9')];
+    let word = vec![('a', 'z'), ('0', '9'), ('_', '_')];
+    let space = vec![(' ', ' '), ('\t', '\r')];
+    Ok(match c {
+        'd' => class(digits, false),
+        'D' => class(digits, true),
+        'w' => class(word, false),
+        'W' => class(word, true),
+        's' => class(space, false),
+        'S' => class(space, true),
+        c => Node::Char(lower(c)),
+    })
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+//function to match a sequence of nodes at a position, calling next with where the match ended,
+//backtracking into earlier choices whenever next turns the ending down
+fn match_here(nodes: &[Node], text: &[char], position: usize, next: &dyn Fn(usize) -> bool) -> bool {
+    let Some((node, rest)) = nodes.split_first() else {
+        return next(position);
+    };
+    match node {
+        Node::Start => position == 0 && match_here(rest, text, position, next),
+        Node::End => position == text.len() && match_here(rest, text, position, next),
+        Node::Group(alternatives) => alternatives
+            .iter()
+            .any(|alternative| match_here(alternative, text, position, &|end| match_here(rest, text, end, next))),
+        Node::Repeat { node, min, max } => match_repeat(node, (*min, *max), 0, rest, text, position, next),
+        single => position < text.len() && single.accepts(text[position]) && match_here(rest, text, position + 1, next),
+    }
+}
+
+//function to match a repeated node greedily, one more repetition is tried before the rest of the sequence
+fn match_repeat(
+    node: &Node,
+    (min, max): (usize, Option<usize>),
+    count: usize,
+    rest: &[Node],
+    text: &[char],
+    position: usize,
+    next: &dyn Fn(usize) -> bool,
+) -> bool {
+    //a repetition that consumed nothing would go round forever once the minimum is met
+    let more = max.is_none_or(|max| count < max)
+        && match_here(std::slice::from_ref(node), text, position, &|end| {
+            (end != position || count < min) && match_repeat(node, (min, max), count + 1, rest, text, end, next)
+        });
+    more || (count >= min && match_here(rest, text, position, next))
+}
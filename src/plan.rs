@@ -0,0 +1,265 @@
+//the probe plan, which addresses a sweep would probe in which order, how and how fast, printed by
+//--dry-run in place of the sweep so the scope can be checked before anything is sent
+//
+//--export-plan writes it to a file for approval and --execute-plan later runs exactly that plan, the
+//file is signed with HMAC-SHA256 with the key in the config file. both need the key, a bare hash would
+//only catch accidents as whoever changes a plan can hash it again
+//
+//  [plan]
+//  key = a long random secret shared by whoever exports and executes plans
+
+use std::fs;
+use std::net::Ipv4Addr;
+
+use crate::cli::{self, Options};
+use crate::concurrency::Controller;
+use crate::config;
+use crate::json;
+use crate::metadata;
+use crate::probe;
+use crate::sha256;
+use crate::store;
+
+pub struct Plan {
+    pub created_at: u64,
+    pub user: String,
+    pub version: String,
+    //the command line the plan was made with, less --dry-run and --export-plan
+    pub arguments: Vec<String>,
+    //the targets as expanded, e.g. 10.0.0.0/24
+    pub targets: Vec<String>,
+    //in the order they would be probed
+    pub addresses: Vec<Ipv4Addr>,
+    //what every address gets and what live hosts get on top
+    pub methods: Vec<String>,
+    pub timing: Vec<String>,
+}
+
+impl Plan {
+    //function to work out the plan for the addresses left after exclusions and ordering
+    pub fn new(addresses: &[Ipv4Addr], targets: &[&str], arguments: &[String], options: &Options, controller: &Controller) -> Plan {
+        let mut methods = vec!["ICMP echo request to every address (ping -c 1)".to_string()];
+        let resolver = match (&options.doh, options.resolvers.as_slice()) {
+            (Some(url), _) => format!("over DNS-over-HTTPS to {}", url),
+            (None, []) => "with the system resolver".to_string(),
+            (None, resolvers) => format!("to {}", resolvers.join(", ")),
+        };
+        let cached = if options.no_cache { "" } else { ", names in the name cache are reused" };
+        methods.push(format!("reverse DNS lookup of live hosts {}{}", resolver, cached));
+        methods.push("MAC address of live hosts from the kernel neighbour table (nothing sent)".to_string());
+        methods.push("addresses that don't answer ICMP count as up when the neighbour table has them (nothing sent)".to_string());
+        if options.whois {
+            methods.push("whois lookup of each public target range at IANA and its registry (not sent to the targets)".to_string());
+        }
+        if let Some(path) = &options.asn_data {
+            methods.push(format!("ASN and owner of live public hosts from {} (nothing sent)", path));
+        }
+        if !options.tcp_ping.is_empty() {
+            methods.push(format!("TCP connect to ports {} of addresses that don't answer ICMP", join_ports(&options.tcp_ping)));
+        }
+        if !options.ports.is_empty() {
+            methods.push(format!("TCP connect to ports {} of live hosts", join_ports(&options.ports)));
+        }
+        if !options.udp_ports.is_empty() {
+            methods.push(format!("UDP probes to ports {} of live hosts", join_ports(&options.udp_ports)));
+        }
+        if !options.profiles.is_empty() {
+            methods.push(format!("{} profile checks of live hosts", options.profiles.join(", ")));
+        }
+        if options.collect_ssh_keys {
+            methods.push("ssh-keyscan of live hosts with port 22 open".to_string());
+        }
+        if let Some(trunk) = &options.trunk {
+            let ids: Vec<String> = options.vlans.iter().map(|(id, _)| id.to_string()).collect();
+            methods.push(format!(
+                "VLANs {} probed tagged on {}, through subinterfaces created for the scan when missing",
+                ids.join(", "),
+                trunk
+            ));
+        }
+        if !options.snmp_arp.is_empty() {
+            methods.push(format!("SNMPv2c walks of the ARP and MAC address tables of {} after the sweep", options.snmp_arp.join(", ")));
+        }
+        if let Some(interface) = &options.lldp {
+            methods.push(format!(
+                "listening for LLDP and CDP announcements on {} for at least {}s (nothing sent)",
+                interface,
+                options.lldp_time.as_secs()
+            ));
+        }
+        if let Some(path) = &options.pcap {
+            methods.push(format!("tcpdump capture of the target ranges to {}", path));
+        }
+
+        let workers = match (options.concurrency, options.ramp) {
+            (Some(workers), Some(ramp)) => format!("1 worker, ramping up to {} over {}s", workers, ramp.as_secs()),
+            (Some(workers), None) => format!("{} workers", workers),
+            (None, Some(ramp)) => format!("1 worker, ramping up to {} over {}s", controller.ceiling(), ramp.as_secs()),
+            (None, None) => format!("{} workers, doubling up to {} while probes come back clean", controller.active(), controller.ceiling()),
+        };
+        let mut timing = vec![
+            workers,
+            format!(
+                "probes that fail on this machine are retried up to {} times, {}ms apart and doubling",
+                options.retries,
+                probe::BACKOFF_START.as_millis()
+            ),
+            "no pause between probes unless ICMP rate limiting is detected, which slows the sweep down".to_string(),
+        ];
+        if options.enrich_after {
+            timing.push("live hosts are named, port scanned and identified after the sweep, not alongside it".to_string());
+        }
+        //the flags that made this a plan rather than a scan are left out, so executing it scans
+        let mut kept = Vec::new();
+        let mut words = arguments.iter();
+        while let Some(word) = words.next() {
+            match word.as_str() {
+                "--dry-run" => {}
+                "--export-plan" => {
+                    words.next();
+                }
+                _ => kept.push(word.clone()),
+            }
+        }
+        Plan {
+            created_at: store::now(),
+            user: metadata::invoking_user(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            arguments: kept,
+            targets: targets.iter().map(|target| target.to_string()).collect(),
+            addresses: addresses.to_vec(),
+            methods,
+            timing,
+        }
+    }
+
+    //function to write the plan and its signature to a file
+    pub fn save(&self, path: &str, config_path: Option<&str>) -> Result<(), String> {
+        let key = signing_key(config_path)?;
+        let body = self.to_json();
+        let signature = sign(&body, &key);
+        let signed = format!("{},\"signature\":{}}}\n", body.trim_end_matches('}'), json::quote(&signature));
+        fs::write(path, signed).map_err(|error| format!("cannot write {}, {}", path, error))
+    }
+
+    //function to read a plan back, refusing one whose signature doesn't match its contents
+    pub fn load(path: &str, config_path: Option<&str>) -> Result<Plan, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+        let value = json::parse(&text).map_err(|error| format!("{} is not a plan, {}", path, error))?;
+        let strings = |key: &str| -> Vec<String> {
+            value
+                .get(key)
+                .and_then(json::Value::as_array)
+                .map(|items| items.iter().filter_map(json::Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+        let plan = Plan {
+            created_at: value.get("created_at").and_then(json::Value::as_u64).ok_or_else(|| format!("{} is not a plan", path))?,
+            user: value.get("user").and_then(json::Value::as_str).unwrap_or("unknown").to_string(),
+            version: value.get("version").and_then(json::Value::as_str).unwrap_or("").to_string(),
+            arguments: strings("arguments"),
+            targets: strings("targets"),
+            addresses: strings("addresses").iter().filter_map(|address| address.parse().ok()).collect(),
+            methods: strings("methods"),
+            timing: strings("timing"),
+        };
+
+        //the signature covers everything else, written out the same way it was when exported
+        let signature = value.get("signature").and_then(json::Value::as_str).unwrap_or("");
+        let key = signing_key(config_path)?;
+        if !signature.starts_with("hmac-sha256:") {
+            return Err(format!("{} isn't signed, plans have to be signed with the key in the config file", path));
+        }
+        if sign(&plan.to_json(), &key) != signature {
+            return Err(format!("{} was changed after it was exported, or signed with another key", path));
+        }
+        if plan.targets.is_empty() || plan.addresses.is_empty() {
+            return Err(format!("{} has no addresses to probe", path));
+        }
+        Ok(plan)
+    }
+
+    //function to turn the plan back into the options it was made with, the targets as they were expanded
+    //so stdin and target files aren't read again, and the config file of the run executing it
+    pub fn options(&self, config_path: Option<String>) -> Result<Options, String> {
+        let mut options = cli::parse_args(&self.arguments).map_err(|error| format!("the plan's options are invalid, {}", error))?;
+        options.targets = self.targets.clone();
+        options.target_files.clear();
+        options.scopes.clear();
+        options.name_patterns.clear();
+        options.local = false;
+        options.docker_networks = false;
+        options.targets_from.clear();
+        options.config = config_path;
+        Ok(options)
+    }
+
+    fn to_json(&self) -> String {
+        let strings = |items: &[String]| items.iter().map(|item| json::quote(item)).collect::<Vec<String>>().join(",");
+        let addresses: Vec<String> = self.addresses.iter().map(|address| format!("\"{}\"", address)).collect();
+        format!(
+            "{{\"created\":{},\"created_at\":{},\"user\":{},\"version\":{},\"arguments\":[{}],\"targets\":[{}],\"methods\":[{}],\"timing\":[{}],\"addresses\":[{}]}}",
+            json::quote(&metadata::iso8601(self.created_at)),
+            self.created_at,
+            json::quote(&self.user),
+            json::quote(&self.version),
+            strings(&self.arguments),
+            strings(&self.targets),
+            strings(&self.methods),
+            strings(&self.timing),
+            addresses.join(",")
+        )
+    }
+
+    //function to print the plan, runs of consecutive addresses as first - last
+    pub fn print(&self) {
+        println!("Probe plan, {} addresses in this order:", self.addresses.len());
+        let mut index = 0;
+        while index < self.addresses.len() {
+            let first = self.addresses[index];
+            let mut last = index;
+            while last + 1 < self.addresses.len() && u32::from(self.addresses[last + 1]) == u32::from(self.addresses[last]).wrapping_add(1) {
+                last += 1;
+            }
+            match last - index {
+                0 => println!("    {}", first),
+                run => println!("    {} - {} ({} addresses)", first, self.addresses[last], run + 1),
+            }
+            index = last + 1;
+        }
+        println!();
+        println!("Methods:");
+        for method in &self.methods {
+            println!("    {}", method);
+        }
+        println!();
+        println!("Timing:");
+        for timing in &self.timing {
+            println!("    {}", timing);
+        }
+        println!();
+        println!("Dry run, nothing was sent.");
+    }
+}
+
+//function to read the signing key from the [plan] section, plans aren't exported or executed without one
+fn signing_key(config_path: Option<&str>) -> Result<String, String> {
+    let config = config::load(config_path)?;
+    config
+        .sections
+        .iter()
+        .find(|section| section.name == "plan")
+        .and_then(|section| section.get("key"))
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| "the config file has no [plan] key to sign plans with, add one shared by whoever exports and executes them".to_string())
+}
+
+//function to sign a plan with the key
+fn sign(body: &str, key: &str) -> String {
+    format!("hmac-sha256:{}", sha256::hex(&sha256::hmac(key.as_bytes(), body.as_bytes())))
+}
+
+fn join_ports(ports: &[u16]) -> String {
+    ports.iter().map(u16::to_string).collect::<Vec<String>>().join(", ")
+}
@@ -0,0 +1,174 @@
+//TCP connect probing of ports on live hosts
+
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::host::{PortResult, PortState};
+
+//how long a connect may take before the port is called filtered
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+//well known services, used to guess what is listening on an open port
+const SERVICES: [(u16, &str); 42] = [
+    (7, "echo"),
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (53, "domain"),
+    (79, "finger"),
+    (80, "http"),
+    (88, "kerberos"),
+    (110, "pop3"),
+    (111, "rpcbind"),
+    (119, "nntp"),
+    (123, "ntp"),
+    (135, "msrpc"),
+    (137, "netbios-ns"),
+    (139, "netbios-ssn"),
+    (143, "imap"),
+    (161, "snmp"),
+    (389, "ldap"),
+    (443, "https"),
+    (445, "microsoft-ds"),
+    (465, "smtps"),
+    (502, "modbus"),
+    (514, "syslog"),
+    (548, "afp"),
+    (554, "rtsp"),
+    (587, "submission"),
+    (631, "ipp"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (873, "rsync"),
+    (1433, "ms-sql-s"),
+    (2049, "nfs"),
+    (3306, "mysql"),
+    (3389, "ms-wbt-server"),
+    (5432, "postgresql"),
+    (5900, "vnc"),
+    (8080, "http-proxy"),
+    (8443, "https-alt"),
+    (9100, "jetdirect"),
+    (44818, "ethernet-ip"),
+    (47808, "bacnet"),
+];
+
+//short names people actually type for services whose official name is less obvious
+const ALIASES: [(&str, u16); 10] = [
+    ("dns", 53),
+    ("smb", 445),
+    ("rdp", 3389),
+    ("mssql", 1433),
+    ("postgres", 5432),
+    ("http-alt", 8080),
+    ("https-alt", 8443),
+    ("printer", 9100),
+    ("netbios", 139),
+    ("msrpc", 135),
+];
+
+//the most frequently open TCP ports, most common first, used by --top-ports
+pub const TOP_PORTS: [u16; 100] = [
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53,
+    135, 3306, 8080, 1723, 111, 995, 993, 5900, 1025, 587, 8888, 199,
+    1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000,
+    8443, 8000, 32768, 554, 26, 1433, 49152, 2001, 515, 8008, 49154, 1027,
+    5666, 646, 5000, 5631, 631, 49153, 8081, 2049, 88, 79, 5800, 106,
+    2121, 1110, 49155, 6000, 513, 990, 5357, 427, 49156, 543, 544, 5101,
+    144, 7, 389, 8009, 3128, 444, 9999, 5009, 7070, 5190, 3000, 5432,
+    1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717,
+    4899, 9100, 119, 37,
+];
+
+//function to parse a port list like "22,80,8000-8010" or "ssh,http,https,rdp"
+
+pub fn parse_port_list(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) if !start.is_empty() && start.chars().all(|c| c.is_ascii_digit()) => {
+                let start = parse_port(start)?;
+                let end = parse_port(end)?;
+                if start > end {
+                    return Err(format!("Invalid port range {}.", part));
+                }
+                ports.extend(start..=end);
+            }
+            _ => ports.push(parse_port(part)?),
+        }
+    }
+
+    if ports.is_empty() {
+        return Err("No ports given.".to_string());
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+//function to parse a single port given as a number or a service name
+fn parse_port(value: &str) -> Result<u16, String> {
+    let value = value.trim();
+    if let Ok(port) = value.parse::<u16>() {
+        if port > 0 {
+            return Ok(port);
+        }
+    }
+    port_by_name(value).ok_or_else(|| format!("Invalid port or unknown service {}.", value))
+}
+
+//function to look up a port by service name or alias, ignoring case
+fn port_by_name(name: &str) -> Option<u16> {
+    let name = name.to_ascii_lowercase();
+    SERVICES
+        .iter()
+        .map(|(port, service)| (*service, *port))
+        .chain(ALIASES.iter().copied())
+        .find(|(service, _)| *service == name)
+        .map(|(_, port)| port)
+}
+
+//function to get the n most common ports
+pub fn top_ports(count: usize) -> Result<Vec<u16>, String> {
+    if count == 0 || count > TOP_PORTS.len() {
+        return Err(format!("--top-ports must be between 1 and {}.", TOP_PORTS.len()));
+    }
+    Ok(TOP_PORTS[..count].to_vec())
+}
+
+//function to guess the service name of a well known port
+pub fn service_name(port: u16) -> Option<&'static str> {
+    SERVICES.iter().find(|(known, _)| *known == port).map(|(_, name)| *name)
+}
+
+//function to check whether a host answers TCP on any of the ports, a connection or a reset
+pub fn answers(address: Ipv4Addr, ports: &[u16]) -> bool {
+    ports.iter().any(|&port| match TcpStream::connect_timeout(&SocketAddr::from((address, port)), CONNECT_TIMEOUT) {
+        Ok(_) => true,
+        Err(error) => error.kind() == ErrorKind::ConnectionRefused,
+    })
+}
+
+//function to connect to each port and record whether it is open, closed or filtered
+pub fn scan_tcp(address: Ipv4Addr, ports: &[u16]) -> Vec<PortResult> {
+    ports
+        .iter()
+        .map(|&port| {
+            let state = match TcpStream::connect_timeout(&SocketAddr::from((address, port)), CONNECT_TIMEOUT) {
+                Ok(_) => PortState::Open,
+                //a reset means the host answered but nothing listens there
+                Err(error) if error.kind() == ErrorKind::ConnectionRefused => PortState::Closed,
+                Err(_) => PortState::Filtered,
+            };
+            PortResult {
+                port,
+                protocol: "tcp",
+                state,
+                service: service_name(port).map(str::to_string),
+                info: None,
+            }
+        })
+        .collect()
+}
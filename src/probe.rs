@@ -0,0 +1,187 @@
+//probing a single address with the system ping command
+
+use std::net::Ipv4Addr;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::replay;
+
+//first wait before retrying a probe that failed locally, doubled after every attempt
+pub const BACKOFF_START: Duration = Duration::from_millis(100);
+
+//messages ping prints when the problem is on the scanning host rather than the network
+const TRANSIENT_ERRORS: [&str; 4] = [
+    "No buffer space available",
+    "Resource temporarily unavailable",
+    "Cannot allocate memory",
+    "Too many open files",
+];
+
+#[derive(Clone)]
+pub enum Outcome {
+    //ttl is the TTL the reply arrived with, duplicates counts extra replies to the one request,
+    //replied_from is set when the reply came from another address than the one probed (NAT, proxy ARP
+    //or a misconfigured device)
+    Up { rtt: Option<f64>, ttl: Option<u8>, duplicates: u32, replied_from: Option<Ipv4Addr> },
+    Down(DownReason),
+    LocalError(String),
+}
+
+//why an address is considered down, when ping tells us
+#[derive(Clone)]
+pub enum DownReason {
+    //no reply of any kind came back
+    Timeout,
+    //a router answered with an ICMP destination unreachable
+    Unreachable { from: String },
+    //a router or firewall answered that the traffic is administratively prohibited
+    Prohibited { from: String },
+    //the scanning host couldn't send the probe (no route, interface down)
+    SendError(String),
+}
+
+impl DownReason {
+    //function to give each reason a short label for the output and summary
+    pub fn label(&self) -> &'static str {
+        match self {
+            DownReason::Timeout => "timeout",
+            DownReason::Unreachable { .. } => "unreachable",
+            DownReason::Prohibited { .. } => "admin-prohibited",
+            DownReason::SendError(_) => "send error",
+        }
+    }
+}
+
+//the final outcome of probing one address and how many retries it took to get there
+pub struct Probe {
+    pub outcome: Outcome,
+    pub retries: u32,
+}
+
+//function to ping an address, retrying with exponential backoff while the failure is a local one
+pub fn ping_with_retry(address: &str, max_retries: u32) -> Probe {
+    let mut retries = 0;
+    let mut backoff = BACKOFF_START;
+
+    loop {
+        let outcome = ping(address);
+        if !matches!(outcome, Outcome::LocalError(_)) || retries == max_retries {
+            return Probe { outcome, retries };
+        }
+        thread::sleep(backoff);
+        backoff *= 2;
+        retries += 1;
+    }
+}
+
+//function to ping an address once and work out whether it answered
+//every probe is its own ping process with its own ICMP identifier, so ping matches the replies to the
+//right request and concurrent probes can't be credited with each other's replies
+pub fn ping(address: &str) -> Outcome {
+    ping_via(address, None)
+}
+
+//function to ping an address once out of a given interface, or the one the routing table picks
+pub fn ping_via(address: &str, interface: Option<&str>) -> Outcome {
+    //a replay answers with the canned output instead
+    let (ping_stdout, ping_stderr) = match replay::ping_output(address) {
+        Some(output) => output,
+        None => match run_ping(address, interface) {
+            Ok(output) => output,
+            //not being able to fork or exec ping at all is always a local problem
+            Err(error) => return Outcome::LocalError(error),
+        },
+    };
+
+    if ping_stdout.contains("1 received") {
+        let (duplicates, replied_from) = reply_details(&ping_stdout, address);
+        return Outcome::Up { rtt: parse_rtt(&ping_stdout), ttl: parse_ttl(&ping_stdout), duplicates, replied_from };
+    }
+
+    if let Some(error) = TRANSIENT_ERRORS.iter().find(|error| ping_stderr.contains(*error)) {
+        return Outcome::LocalError(error.to_string());
+    }
+    Outcome::Down(down_reason(&ping_stdout, &ping_stderr))
+}
+
+//function to run ping once, returning what it printed on stdout and stderr
+//ping reads the count out of "-c 1" as one argument the same as two
+#[allow(clippy::suspicious_command_arg_space)]
+fn run_ping(address: &str, interface: Option<&str>) -> Result<(String, String), String> {
+    let mut command = Command::new("ping");
+    //-I binds the probe to the interface, it leaves by that path whatever the routing table says
+    if let Some(interface) = interface {
+        command.arg("-I").arg(interface);
+    }
+    //start the process of pinging the address
+    let ping_out = command
+        .arg(address)                             //provides the address as an argument to the ping command
+        .arg("-c 1")                              //adds the -c 1 argument, telling the command to only run once (ping will run until interrupted by default)
+        .stdout(Stdio::piped())                   //captures the output of the ping command
+        .stderr(Stdio::piped())                   //captures errors so local failures can be told apart from silence
+        .output()
+        .map_err(|error| error.to_string())?;
+    Ok((String::from_utf8_lossy(&ping_out.stdout).to_string(), String::from_utf8_lossy(&ping_out.stderr).to_string()))
+}
+
+//function to work out why ping got no echo reply, e.g.
+//"From 10.0.0.1 icmp_seq=1 Destination Host Unreachable" or "ping: sendmsg: Network is unreachable"
+fn down_reason(ping_stdout: &str, ping_stderr: &str) -> DownReason {
+    for line in ping_stdout.lines() {
+        let Some(rest) = line.strip_prefix("From ") else {
+            continue;
+        };
+        let from = rest.split_whitespace().next().unwrap_or("").trim_end_matches(':').to_string();
+        if line.contains("Prohibited") || line.contains("Packet filtered") {
+            return DownReason::Prohibited { from };
+        }
+        if line.contains("Unreachable") {
+            return DownReason::Unreachable { from };
+        }
+    }
+
+    //anything ping complains about on stderr means the probe never left this host
+    if let Some(line) = ping_stderr.lines().find(|line| !line.trim().is_empty()) {
+        let error = line.rsplit(": ").next().unwrap_or(line).trim();
+        return DownReason::SendError(error.to_string());
+    }
+    DownReason::Timeout
+}
+
+//function to read what the echo replies say about themselves, how many duplicates came back and whether
+//they came from somewhere else, e.g. "64 bytes from 10.0.0.9: icmp_seq=1 ttl=64 time=0.045 ms (DUP!)"
+//ping stops at the first reply, so only duplicates arriving right behind it are seen
+fn reply_details(ping_stdout: &str, address: &str) -> (u32, Option<Ipv4Addr>) {
+    let marked = ping_stdout.lines().filter(|line| line.ends_with("(DUP!)")).count() as u32;
+    let counted = ping_stdout
+        .split(", ")
+        .find_map(|part| part.trim().strip_prefix('+')?.strip_suffix(" duplicates")?.parse().ok())
+        .unwrap_or(0);
+
+    //"64 bytes from 10.0.0.9: ..." or "64 bytes from name (10.0.0.9): ..."
+    let source = ping_stdout.lines().find_map(|line| {
+        let from = line.split_once(" bytes from ")?.1.split(": ").next()?;
+        let from = from.rsplit_once('(').map(|(_, rest)| rest.trim_end_matches(')')).unwrap_or(from);
+        from.parse::<Ipv4Addr>().ok()
+    });
+    let replied_from = source.filter(|source| address.parse::<Ipv4Addr>().is_ok_and(|address| address != *source));
+    (marked.max(counted), replied_from)
+}
+
+//function to pull the TTL of the reply out of the ping output (e.g. "ttl=63")
+fn parse_ttl(ping_stdout: &str) -> Option<u8> {
+    let start = ping_stdout.find("ttl=")? + "ttl=".len();
+    let value: String = ping_stdout[start..].chars().take_while(char::is_ascii_digit).collect();
+    value.parse().ok()
+}
+
+//function to pull the round trip time in milliseconds out of the ping output (e.g. "time=0.045 ms")
+fn parse_rtt(ping_stdout: &str) -> Option<f64> {
+    let start = ping_stdout.find("time=")? + "time=".len();
+    let value: String = ping_stdout[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    value.parse().ok()
+}
@@ -0,0 +1,71 @@
+//machine readable progress records for wrappers and GUIs, one JSON object per line
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+//how often a record is written while the scan runs
+const INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ProgressReporter {
+    sink: Box<dyn Write>,
+    started: Instant,
+    last_emit: Option<Instant>,
+    //the slice of a sharded sweep this run is, so a wrapper watching several can tell them apart
+    shard: Option<(u32, u32)>,
+}
+
+impl ProgressReporter {
+    //function to create a reporter writing to stderr, or to a file or named pipe when a path is given
+    pub fn new(path: Option<&str>, shard: Option<(u32, u32)>) -> Result<ProgressReporter, String> {
+        let sink: Box<dyn Write> = match path {
+            Some(path) => Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|error| format!("cannot open {}, {}", path, error))?,
+            ),
+            None => Box::new(io::stderr()),
+        };
+
+        Ok(ProgressReporter {
+            sink,
+            started: Instant::now(),
+            last_emit: None,
+            shard,
+        })
+    }
+
+    //function to write a record if enough time has passed since the last one
+    pub fn update(&mut self, done: usize, total: usize, found: usize) {
+        if self.last_emit.is_some_and(|last| last.elapsed() < INTERVAL) {
+            return;
+        }
+        self.emit("progress", done, total, found);
+    }
+
+    //function to always write a final record once the scan is over
+    pub fn finish(&mut self, done: usize, total: usize, found: usize) {
+        self.emit("finished", done, total, found);
+    }
+
+    fn emit(&mut self, event: &str, done: usize, total: usize, found: usize) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let percent = if total == 0 { 100.0 } else { done as f64 * 100.0 / total as f64 };
+        let rate = if elapsed > 0.0 { done as f64 / elapsed } else { 0.0 };
+        let shard = match self.shard {
+            Some((shard, shards)) => format!(",\"shard\":{},\"shards\":{}", shard, shards),
+            None => String::new(),
+        };
+
+        //a wrapper that went away shouldn't stop the scan, so write errors are ignored
+        let _ = writeln!(
+            self.sink,
+            "{{\"event\":\"{}\",\"done\":{},\"total\":{},\"percent\":{:.1},\"rate\":{:.2},\"found\":{},\"elapsed\":{:.1}{}}}",
+            event, done, total, percent, rate, found, elapsed, shard
+        );
+        let _ = self.sink.flush();
+        self.last_emit = Some(Instant::now());
+    }
+}
@@ -0,0 +1,261 @@
+//replaying canned replies instead of probing, for offline demos and end to end checks of the output
+//
+//the replies come from a fixture of ping output, one block per address as ping printed it
+//(starting at its "PING 10.0.0.1 (10.0.0.1) 56(84) bytes of data." line, "ping: ..." lines are its
+//errors, # lines are comments), or from a pcap of an earlier sweep (e.g. one taken with --pcap), whose
+//echo requests and replies are turned back into ping output, so both go through the same parsing as a
+//live sweep. addresses without replies come out as timeouts
+//
+//nothing is sent while replaying, so names, MACs, ports and the profiles are left out
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::OnceLock;
+
+use crate::compress;
+
+//canned stdout and stderr of ping per address, set once when replaying
+static REPLIES: OnceLock<HashMap<Ipv4Addr, (String, String)>> = OnceLock::new();
+
+//pcap link types whose IPv4 packets we can find
+const LINK_NULL: u32 = 0;
+const LINK_ETHERNET: u32 = 1;
+const LINK_RAW: u32 = 101;
+const LINK_LINUX_SLL: u32 = 113;
+const LINK_IPV4: u32 = 228;
+const LINK_LINUX_SLL2: u32 = 276;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+//destination unreachable codes meaning a filter dropped the packet
+const ICMP_PROHIBITED: [u8; 3] = [9, 10, 13];
+
+//function to load a fixture or pcap and replay it for the rest of the run, returning how many
+//addresses have replies in it
+pub fn start(path: &str) -> Result<usize, String> {
+    let contents = compress::read(path)?;
+    let replies = if contents.len() >= 4 && pcap_magic(&contents[..4]).is_some() {
+        from_pcap(&contents).map_err(|error| format!("cannot replay {}, {}", path, error))?
+    }
+    else {
+        from_fixture(&String::from_utf8_lossy(&contents))
+    };
+    if replies.is_empty() {
+        return Err(format!("no replies to replay in {}", path));
+    }
+    let count = replies.len();
+    REPLIES.set(replies).map_err(|_| "already replaying".to_string())?;
+    Ok(count)
+}
+
+pub fn active() -> bool {
+    REPLIES.get().is_some()
+}
+
+//function to get what ping would have printed for an address, None when not replaying
+pub fn ping_output(address: &str) -> Option<(String, String)> {
+    let replies = REPLIES.get()?;
+    let address: Option<Ipv4Addr> = address.parse().ok();
+    Some(address.and_then(|address| replies.get(&address).cloned()).unwrap_or_default())
+}
+
+//function to list the addresses in the replay, in order
+pub fn addresses() -> Vec<Ipv4Addr> {
+    let mut addresses: Vec<Ipv4Addr> = REPLIES.get().map(|replies| replies.keys().copied().collect()).unwrap_or_default();
+    addresses.sort();
+    addresses
+}
+
+//function to split a fixture into the ping output of each address
+fn from_fixture(text: &str) -> HashMap<Ipv4Addr, (String, String)> {
+    let mut replies: HashMap<Ipv4Addr, (String, String)> = HashMap::new();
+    let mut current = None;
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        //"PING 10.0.0.1 (10.0.0.1) ..." or "PING name (10.0.0.1) ..."
+        if let Some(rest) = line.strip_prefix("PING ") {
+            let address = rest
+                .split_once('(')
+                .and_then(|(_, rest)| rest.split(')').next())
+                .or_else(|| rest.split_whitespace().next())
+                .and_then(|address| address.parse().ok());
+            current = address;
+        }
+        let Some(address) = current else {
+            continue;
+        };
+        let (stdout, stderr) = replies.entry(address).or_default();
+        if line.starts_with("ping: ") {
+            stderr.push_str(line);
+            stderr.push('\n');
+        }
+        else {
+            stdout.push_str(line);
+            stdout.push('\n');
+        }
+    }
+    replies
+}
+
+//whether a pcap is little endian and counts nanoseconds, from its magic number
+fn pcap_magic(bytes: &[u8]) -> Option<(bool, bool)> {
+    match bytes {
+        [0xd4, 0xc3, 0xb2, 0xa1] => Some((true, false)),
+        [0xa1, 0xb2, 0xc3, 0xd4] => Some((false, false)),
+        [0x4d, 0x3c, 0xb2, 0xa1] => Some((true, true)),
+        [0xa1, 0xb2, 0x3c, 0x4d] => Some((false, true)),
+        _ => None,
+    }
+}
+
+//an echo request we sent, and what came back for it
+struct Exchange {
+    target: Ipv4Addr,
+    sequence: u16,
+    sent_at: f64,
+    //source, ttl and arrival of every echo reply
+    replies: Vec<(Ipv4Addr, u8, f64)>,
+    //the router that sent destination unreachable and whether it was a filter
+    unreachable: Option<(Ipv4Addr, bool)>,
+}
+
+//a captured frame and the time it was captured at
+pub type Frame<'a> = (f64, &'a [u8]);
+
+//function to split a pcap into its link type and its frames
+pub fn pcap_frames(contents: &[u8]) -> Result<(u32, Vec<Frame<'_>>), String> {
+    let (little_endian, nanoseconds) = contents.get(..4).and_then(pcap_magic).ok_or("not a pcap file")?;
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = contents.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+    let link_type = read_u32(20).ok_or("the pcap header is cut short")?;
+
+    let mut frames = Vec::new();
+    let mut offset = 24;
+    while let (Some(seconds), Some(fraction), Some(length)) = (read_u32(offset), read_u32(offset + 4), read_u32(offset + 8)) {
+        let start = offset + 16;
+        let Some(frame) = contents.get(start..start + length as usize) else {
+            break;
+        };
+        offset = start + length as usize;
+        frames.push((seconds as f64 + fraction as f64 / if nanoseconds { 1e9 } else { 1e6 }, frame));
+    }
+    Ok((link_type, frames))
+}
+
+//function to pair up the echo requests and replies in a pcap and write them out as ping would have
+fn from_pcap(contents: &[u8]) -> Result<HashMap<Ipv4Addr, (String, String)>, String> {
+    let (link_type, frames) = pcap_frames(contents)?;
+    if ![LINK_NULL, LINK_ETHERNET, LINK_RAW, LINK_LINUX_SLL, LINK_IPV4, LINK_LINUX_SLL2].contains(&link_type) {
+        return Err(format!("unsupported link type {}", link_type));
+    }
+
+    //exchanges by ICMP identifier and sequence number, replies may come from another address
+    let mut exchanges: HashMap<(u16, u16), Exchange> = HashMap::new();
+    for (time, frame) in frames {
+        let Some(packet) = ipv4_packet(frame, link_type) else {
+            continue;
+        };
+
+        //IPv4 header, ICMP is protocol 1
+        let header_length = (packet[0] & 0x0f) as usize * 4;
+        let Some(icmp) = packet.get(header_length..).filter(|icmp| icmp.len() >= 8 && packet[9] == 1) else {
+            continue;
+        };
+        let (ttl, source, destination) = (packet[8], address_at(packet, 12), address_at(packet, 16));
+        let id_sequence = |icmp: &[u8]| (u16::from_be_bytes([icmp[4], icmp[5]]), u16::from_be_bytes([icmp[6], icmp[7]]));
+        match icmp[0] {
+            ICMP_ECHO_REQUEST => {
+                let (id, sequence) = id_sequence(icmp);
+                exchanges.entry((id, sequence)).or_insert(Exchange {
+                    target: destination,
+                    sequence,
+                    sent_at: time,
+                    replies: Vec::new(),
+                    unreachable: None,
+                });
+            }
+            ICMP_ECHO_REPLY => {
+                if let Some(exchange) = exchanges.get_mut(&id_sequence(icmp)) {
+                    exchange.replies.push((source, ttl, time));
+                }
+            }
+            //the unreachable quotes our request, its IP header and the first 8 bytes of the ICMP
+            ICMP_UNREACHABLE => {
+                let quoted = &icmp[8..];
+                let quoted_icmp = quoted.get((quoted.first().copied().unwrap_or(0) & 0x0f) as usize * 4..);
+                if let Some(quoted_icmp) = quoted_icmp.filter(|quoted_icmp| quoted_icmp.len() >= 8) {
+                    if let Some(exchange) = exchanges.get_mut(&id_sequence(quoted_icmp)) {
+                        exchange.unreachable = Some((source, ICMP_PROHIBITED.contains(&icmp[1])));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut exchanges: Vec<Exchange> = exchanges.into_values().collect();
+    exchanges.sort_by(|a, b| a.sent_at.total_cmp(&b.sent_at));
+    let mut replies: HashMap<Ipv4Addr, (String, String)> = HashMap::new();
+    for exchange in &exchanges {
+        //a later exchange with a reply wins over an earlier one without, as a retry would
+        if replies.get(&exchange.target).is_some_and(|(stdout, _)| stdout.contains(" 1 received")) {
+            continue;
+        }
+        let mut stdout = format!("PING {0} ({0}) 56(84) bytes of data.\n", exchange.target);
+        for (index, (source, ttl, time)) in exchange.replies.iter().enumerate() {
+            stdout.push_str(&format!(
+                "64 bytes from {}: icmp_seq={} ttl={} time={:.3} ms{}\n",
+                source,
+                exchange.sequence,
+                ttl,
+                (time - exchange.sent_at) * 1000.0,
+                if index > 0 { " (DUP!)" } else { "" }
+            ));
+        }
+        match exchange.unreachable {
+            Some((router, true)) if exchange.replies.is_empty() => {
+                stdout.push_str(&format!("From {} icmp_seq={} Packet filtered\n", router, exchange.sequence));
+            }
+            Some((router, false)) if exchange.replies.is_empty() => {
+                stdout.push_str(&format!("From {} icmp_seq={} Destination Host Unreachable\n", router, exchange.sequence));
+            }
+            _ => {}
+        }
+        let received = if exchange.replies.is_empty() { 0 } else { 1 };
+        let duplicates = match exchange.replies.len() {
+            0 | 1 => String::new(),
+            count => format!(" +{} duplicates,", count - 1),
+        };
+        stdout.push_str(&format!(
+            "\n--- {} ping statistics ---\n1 packets transmitted, {} received,{} 0% packet loss, time 0ms\n",
+            exchange.target, received, duplicates
+        ));
+        replies.insert(exchange.target, (stdout, String::new()));
+    }
+    Ok(replies)
+}
+
+//function to find the IPv4 packet in a captured frame
+fn ipv4_packet(frame: &[u8], link_type: u32) -> Option<&[u8]> {
+    let ethertype = |offset: usize| Some(u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]));
+    let start = match link_type {
+        LINK_RAW | LINK_IPV4 => 0,
+        LINK_NULL => 4,
+        //a VLAN tag moves the ethertype on by four bytes
+        LINK_ETHERNET if ethertype(12)? == 0x8100 && ethertype(16)? == 0x0800 => 18,
+        LINK_ETHERNET if ethertype(12)? == 0x0800 => 14,
+        LINK_LINUX_SLL if ethertype(14)? == 0x0800 => 16,
+        LINK_LINUX_SLL2 if ethertype(0)? == 0x0800 => 20,
+        _ => return None,
+    };
+    frame.get(start..).filter(|packet| packet.len() >= 20 && packet[0] >> 4 == 4)
+}
+
+fn address_at(packet: &[u8], offset: usize) -> Ipv4Addr {
+    Ipv4Addr::new(packet[offset], packet[offset + 1], packet[offset + 2], packet[offset + 3])
+}
@@ -0,0 +1,240 @@
+//running the probes for a list of addresses on a pool of worker threads
+
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ad;
+use crate::cli::Options;
+use crate::concurrency::Controller;
+use crate::enrich;
+use crate::host::{HostResult, PortState};
+use crate::identify;
+use crate::ports;
+use crate::probe::{self, DownReason, Outcome};
+use crate::replay;
+use crate::service;
+use crate::sshkeys;
+use crate::target::Addresses;
+use crate::udp;
+use crate::web;
+
+//how long a worker parked by the controller waits before checking again
+const PARKED_WAIT: Duration = Duration::from_millis(20);
+//most workers enriching live hosts at once
+const MAX_ENRICHMENT_WORKERS: usize = 64;
+//the finished addresses waiting for the calling thread, and the live hosts waiting for enrichment, at most
+//a full queue holds the workers up until there is room, so a slow sink (a terminal over SSH, a progress
+//file on a network filesystem, a webhook) slows the sweep down rather than filling memory
+const RESULT_QUEUE: usize = 1024;
+const LIVE_QUEUE: usize = 4096;
+
+//one finished address, handed back to the main thread for printing
+pub struct Finished {
+    pub host: HostResult,
+    pub retries: u32,
+    //how long the address took to probe, and to enrich when it was up, for the performance summary
+    pub probing: Duration,
+    pub enrichment: Duration,
+}
+
+//function to probe every address, calling on_finished on the calling thread as each one completes
+//live hosts are handed to a pool of enrichment workers of their own, so a slow reverse lookup or port
+//scan doesn't hold up the probes until LIVE_QUEUE hosts are waiting, unless --enrich-after leaves them
+//for after the sweep
+pub fn run(addresses: &Addresses, options: &Options, controller: &Controller, mut on_finished: impl FnMut(Finished)) {
+    let next_index = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::sync_channel(RESULT_QUEUE);
+    let (live_sender, live_receiver) = mpsc::sync_channel(LIVE_QUEUE);
+    let enrich_now = !options.enrich_after && !replay::active();
+
+    thread::scope(|scope| {
+        for worker in 0..controller.ceiling().min(addresses.len()) {
+            let sender = sender.clone();
+            let live_sender = live_sender.clone();
+            let next_index = &next_index;
+            scope.spawn(move || loop {
+                //a stop signal ends the sweep where it is, what is in flight still comes back
+                if service::stop_requested() {
+                    break;
+                }
+                //workers above the current limit sit out until the controller lets them in, all of them while paused
+                if worker >= controller.active() || controller.is_paused() {
+                    if next_index.load(Ordering::Relaxed) >= addresses.len() {
+                        break;
+                    }
+                    thread::sleep(PARKED_WAIT);
+                    continue;
+                }
+
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(address) = addresses.get(index) else {
+                    break;
+                };
+                //a throttled scan waits a little before every probe
+                let pace = controller.pace();
+                if !pace.is_zero() {
+                    thread::sleep(pace);
+                }
+                let finished = probe_host(address, options);
+                let queue = if enrich_now && finished.host.is_up() { &live_sender } else { &sender };
+                if queue.send(finished).is_err() {
+                    break;
+                }
+            });
+        }
+        //the enrichment workers stop once the probe workers are done and the queue is empty
+        drop(live_sender);
+        enrichment_pool(scope, enrichment_workers(controller.ceiling(), addresses.len()), live_receiver, &sender, options);
+        drop(sender);
+
+        for finished in receiver {
+            on_finished(finished);
+        }
+    });
+}
+
+//function to enrich live hosts the sweep left alone (--enrich-after), calling on_finished as each one is done
+pub fn enrich_later(hosts: Vec<HostResult>, options: &Options, controller: &Controller, mut on_finished: impl FnMut(Finished)) {
+    let (sender, receiver) = mpsc::sync_channel(RESULT_QUEUE);
+    let (live_sender, live_receiver) = mpsc::sync_channel(LIVE_QUEUE);
+    let workers = enrichment_workers(controller.ceiling(), hosts.len());
+    thread::scope(|scope| {
+        //the hosts are queued from a thread of their own, the queue fills up faster than it is worked off
+        scope.spawn(move || {
+            for host in hosts {
+                if live_sender.send(Finished { host, retries: 0, probing: Duration::ZERO, enrichment: Duration::ZERO }).is_err() {
+                    break;
+                }
+            }
+        });
+        enrichment_pool(scope, workers, live_receiver, &sender, options);
+        drop(sender);
+        for finished in receiver {
+            on_finished(finished);
+        }
+    });
+}
+
+//a quarter of the probe workers enrich, enough as only live hosts get that far
+fn enrichment_workers(probe_workers: usize, hosts: usize) -> usize {
+    (probe_workers / 4).clamp(1, MAX_ENRICHMENT_WORKERS).min(hosts.max(1))
+}
+
+//function to start the enrichment workers, taking live hosts off the queue and passing them on enriched
+fn enrichment_pool<'scope>(
+    scope: &'scope thread::Scope<'scope, '_>,
+    workers: usize,
+    queue: mpsc::Receiver<Finished>,
+    sender: &mpsc::SyncSender<Finished>,
+    options: &'scope Options,
+) {
+    let queue = Arc::new(Mutex::new(queue));
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+        scope.spawn(move || loop {
+            //the lock is only held while waiting for the next host, not while enriching it
+            let next = queue.lock().unwrap().recv();
+            let Ok(mut finished) = next else {
+                break;
+            };
+            let started = Instant::now();
+            enrich_host(&mut finished.host, options);
+            finished.enrichment = started.elapsed();
+            if sender.send(finished).is_err() {
+                break;
+            }
+        });
+    }
+}
+
+//function to probe addresses that timed out while rate limited again, replacing the results of those now up
+//returns the addresses that turned out to be up
+//a suspect spilled to disk has no result to replace, the one now up is added
+pub fn recheck(suspects: &[Ipv4Addr], results: &mut Vec<HostResult>, options: &Options, controller: &Controller) -> Vec<Ipv4Addr> {
+    let mut recovered = Vec::new();
+    run(&Addresses::Listed(suspects.to_vec()), options, controller, |finished| {
+        if !finished.host.is_up() {
+            return;
+        }
+        recovered.push(finished.host.address);
+        match results.iter_mut().find(|host| host.address == finished.host.address) {
+            Some(slot) => *slot = finished.host,
+            None => results.push(finished.host),
+        }
+    });
+    recovered.sort();
+    recovered
+}
+
+//function to find out whether one address is up, by ping and the fallbacks when it doesn't answer
+pub fn probe_host(address: Ipv4Addr, options: &Options) -> Finished {
+    let started = Instant::now();
+    //ping the address, local errors are retried rather than reported as down hosts
+    let probe = probe::ping_with_retry(&address.to_string(), options.retries);
+
+    let mut host = HostResult {
+        address,
+        outcome: probe.outcome,
+        hostname: None,
+        mac: None,
+        vendor: None,
+        roles: Vec::new(),
+        ports: Vec::new(),
+        found_by: Vec::new(),
+        seen_by: None,
+        asn: None,
+        source: None,
+    };
+    if host.is_up() {
+        host.found_by.push("icmp");
+    }
+    //a host dropping ICMP may still answer ARP on the local segment, or TCP on a --tcp-ping port
+    else if matches!(host.outcome, Outcome::Down(DownReason::Timeout)) && !replay::active() {
+        if enrich::mac_address(address).is_some() {
+            host.found_by.push("arp");
+        }
+        if ports::answers(address, &options.tcp_ping) {
+            host.found_by.push("tcp");
+        }
+        if !host.found_by.is_empty() {
+            host.outcome = Outcome::Up { rtt: None, ttl: None, duplicates: 0, replied_from: None };
+        }
+    }
+    Finished {
+        host,
+        retries: probe.retries,
+        probing: started.elapsed(),
+        enrichment: Duration::ZERO,
+    }
+}
+
+//function to name a live host, probe its requested ports and identify what runs on them
+pub fn enrich_host(host: &mut HostResult, options: &Options) {
+    let address = host.address;
+    enrich::enrich(host, options);
+    host.ports = ports::scan_tcp(address, &options.ports);
+    host.ports.extend(udp::scan_udp(address, &options.udp_ports));
+    identify::identify(host, &options.profiles);
+    if options.profiles.iter().any(|profile| profile == "ad") {
+        ad::classify(host);
+    }
+    if options.profiles.iter().any(|profile| profile == "web") {
+        web::identify(host, options);
+    }
+    if options.collect_ssh_keys {
+        sshkeys::collect(host);
+    }
+
+    //answering ICMP doesn't rule out the others, together they show what a firewall lets through
+    if host.found_by.contains(&"icmp") && enrich::mac_address(address).is_some() {
+        host.found_by.push("arp");
+    }
+    let tcp_answered = host.ports.iter().any(|port| port.protocol == "tcp" && matches!(port.state, PortState::Open | PortState::Closed));
+    if tcp_answered && !host.found_by.contains(&"tcp") {
+        host.found_by.push("tcp");
+    }
+}
@@ -0,0 +1,69 @@
+//named scan scopes from the config file, so an engagement's ranges don't have to be typed out every time
+//
+//  [scope prod]
+//  include = 10.1.0.0/16, 10.2.0.5
+//  exclude = 10.1.250.0/24
+//
+//  [scope prod-no-dmz]
+//  include = prod
+//  exclude = 10.1.99.0/24
+//
+//include and exclude take addresses, ranges and the names of other scopes
+
+use std::collections::BTreeSet;
+use std::net::Ipv4Addr;
+
+use crate::config::Config;
+use crate::subnet;
+use crate::target;
+
+//function to turn a scope into the CIDR blocks that cover exactly its addresses, e.g. 10.1.0.0/17
+pub fn blocks(config: &Config, name: &str) -> Result<Vec<String>, String> {
+    let addresses: Vec<Ipv4Addr> = addresses(config, name, &mut Vec::new())?.into_iter().collect();
+    if addresses.is_empty() {
+        return Err(format!("[scope {}] leaves no addresses to scan", name));
+    }
+    Ok(subnet::aggregate(&addresses)
+        .into_iter()
+        .map(|(network, prefix)| format!("{}/{}", network, prefix))
+        .collect())
+}
+
+//function to collect the addresses of a scope, following the scopes it names,
+//the chain of scopes being resolved is kept to catch one that includes itself
+fn addresses(config: &Config, name: &str, chain: &mut Vec<String>) -> Result<BTreeSet<Ipv4Addr>, String> {
+    if chain.iter().any(|seen| seen == name) {
+        return Err(format!("[scope {}] refers back to itself through {}", name, chain.join(" -> ")));
+    }
+    let Some((_, section)) = config.sections_of("scope").find(|(scope, _)| *scope == name) else {
+        return Err(format!("No [scope {}] in the config file", name));
+    };
+    let include = section.list("include");
+    if include.is_empty() {
+        return Err(format!("[scope {}] is missing include", name));
+    }
+
+    chain.push(name.to_string());
+    let mut addresses = BTreeSet::new();
+    for entry in &include {
+        addresses.extend(entry_addresses(config, name, entry, chain)?);
+    }
+    for entry in &section.list("exclude") {
+        for address in entry_addresses(config, name, entry, chain)? {
+            addresses.remove(&address);
+        }
+    }
+    chain.pop();
+    Ok(addresses)
+}
+
+//function to expand one include or exclude item, an address, a range or another scope
+fn entry_addresses(config: &Config, name: &str, entry: &str, chain: &mut Vec<String>) -> Result<BTreeSet<Ipv4Addr>, String> {
+    if entry.contains('/') || target::validate_ip_address(entry) {
+        let expanded = target::expand(entry, None).map_err(|error| format!("[scope {}] {}, {}", name, entry, error))?;
+        Ok(expanded.addresses().collect())
+    }
+    else {
+        addresses(config, entry, chain)
+    }
+}
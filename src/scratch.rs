@@ -0,0 +1,73 @@
+//private scratch files for what other programs read or write by path, the curl payloads and the LLDP
+//capture, kept in a directory of their own only we can enter
+//
+//the shared temporary directory is anyone's to write to, so a predictable name there could already be
+//a symlink to a file someone wants clobbered, and a payload written to it readable by every user. the
+//directory is made fresh, 0700, with a name that fails to create rather than reusing what is there, and
+//each file in it is created new and 0600. it is removed with everything in it when dropped. on Windows
+//the temporary directory is already the user's own, the modes are left to it
+
+use std::env;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//how many names to try before giving up on someone having taken them all
+const ATTEMPTS: usize = 16;
+
+//numbers the directories so those made at the same time don't share a name
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Scratch {
+    path: PathBuf,
+}
+
+impl Scratch {
+    //function to make a new private directory in the temporary directory
+    pub fn create() -> Result<Scratch, String> {
+        let temp = env::temp_dir();
+        for _ in 0..ATTEMPTS {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+            let path = temp.join(format!("host_disco-{}-{}-{:08x}", process::id(), COUNT.fetch_add(1, Ordering::Relaxed), nanos));
+            #[cfg(unix)]
+            let created = DirBuilder::new().mode(0o700).create(&path);
+            #[cfg(not(unix))]
+            let created = DirBuilder::new().create(&path);
+            match created {
+                Ok(()) => return Ok(Scratch { path }),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(format!("cannot create a directory in {}, {}", temp.display(), error)),
+            }
+        }
+        Err(format!("cannot create a directory in {}, every name tried was taken", temp.display()))
+    }
+
+    //function to create a new file in the directory only we can read, to write to or hand to a program
+    pub fn file(&self, name: &str) -> Result<(PathBuf, File), String> {
+        let path = self.path.join(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options.open(&path).map_err(|error| format!("cannot create {}, {}", path.display(), error))?;
+        Ok((path, file))
+    }
+
+    //function to write the contents to a new file in the directory, returning its path
+    pub fn write(&self, name: &str, contents: &[u8]) -> Result<PathBuf, String> {
+        let (path, mut file) = self.file(name)?;
+        file.write_all(contents).map_err(|error| format!("cannot write {}, {}", path.display(), error))?;
+        Ok(path)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,98 @@
+//running watch mode as a long running service (--service), for systemd units with Type=notify
+//
+//readiness and a status line go to systemd over $NOTIFY_SOCKET, SIGHUP reloads the config file and
+//SIGTERM or SIGINT stop the service, a sweep in progress ends early and isn't stored, the last full one
+//is the state it leaves behind. a sweep through VLAN subinterfaces catches the signals too, so it can
+//take them down before it exits
+//
+//  [Service]
+//  Type=notify
+//  ExecStart=/usr/local/bin/host_disco --watch 5m --service 10.0.0.0/24
+//  ExecReload=/bin/kill -HUP $MAINPID
+//
+//with --watch-scopes instead of --watch and targets, one service watches every scope of the config file
+//
+//systemd and its notify socket are Linux's, elsewhere the signals keep their default action and there is
+//nobody to notify, --service is refused there
+
+#[cfg(target_os = "linux")]
+use std::env;
+#[cfg(target_os = "linux")]
+use std::ffi::c_int;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+#[cfg(target_os = "linux")]
+const SIGHUP: c_int = 1;
+#[cfg(target_os = "linux")]
+const SIGINT: c_int = 2;
+#[cfg(target_os = "linux")]
+const SIGTERM: c_int = 15;
+
+//set by the signal handler, which can't do more than this safely, SIGHUPs are counted so every
+//scope --watch-scopes runs sees each of them
+static RELOADS: AtomicU64 = AtomicU64::new(0);
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    //signal(2) from the C library std already links, the handler is passed as its address
+    fn signal(signum: c_int, handler: usize) -> usize;
+}
+
+#[cfg(target_os = "linux")]
+extern "C" fn on_signal(signum: c_int) {
+    if signum == SIGHUP {
+        RELOADS.fetch_add(1, Ordering::SeqCst);
+    }
+    else {
+        STOP.store(true, Ordering::SeqCst);
+    }
+}
+
+//function to catch SIGHUP, SIGTERM and SIGINT instead of dying on them
+#[cfg(target_os = "linux")]
+pub fn install_handlers() {
+    let handler = on_signal as extern "C" fn(c_int) as usize;
+    for signum in [SIGHUP, SIGINT, SIGTERM] {
+        //the handler only stores to atomics, which is async-signal-safe
+        unsafe {
+            signal(signum, handler);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn install_handlers() {}
+
+//function to count the SIGHUPs so far, a watch loop reloads when the count moved since it last looked
+pub fn reloads() -> u64 {
+    RELOADS.load(Ordering::SeqCst)
+}
+
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+//function to tell systemd about the service's state, e.g. "READY=1" or "STATUS=...", a no-op when
+//not started by systemd, an address starting with @ is in the abstract namespace
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    let address = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(path.as_ref()),
+    };
+    if let (Ok(address), Ok(socket)) = (address, UnixDatagram::unbound()) {
+        let _ = socket.send_to_addr(state.as_bytes(), &address);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
@@ -0,0 +1,45 @@
+//detached signatures over the files a scan writes (--sign-with), so the recipients of an assessment can
+//check the results weren't changed after the scan: with an SSH key through ssh-keygen -Y sign (FILE.sig,
+//a key file or the public half of one held by ssh-agent), or with a key in the gpg keyring (FILE.asc)
+//
+//the signature covers the file as written, after any compression and encryption
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+//the namespace of the SSH signatures, verifying takes the same one so a report signature can't be
+//passed off as one made for something else, e.g. a git commit
+const NAMESPACE: &str = "host_disco";
+
+//function to sign a file, returning where the signature went
+pub fn sign(path: &str, key: &str) -> Result<String, String> {
+    let ssh = Path::new(key).is_file();
+    let signature = format!("{}.{}", path, if ssh { "sig" } else { "asc" });
+    //ssh-keygen asks before overwriting an old signature
+    let _ = fs::remove_file(&signature);
+    let (program, output) = if ssh {
+        ("ssh-keygen", Command::new("ssh-keygen").args(["-q", "-Y", "sign", "-f", key, "-n", NAMESPACE, path]).stdin(Stdio::inherit()).output())
+    }
+    else {
+        let gpg = Command::new("gpg")
+            .args(["--batch", "--yes", "--quiet", "--armor", "--detach-sign", "--local-user", key, "--output", &signature, path])
+            .output();
+        ("gpg", gpg)
+    };
+    let output = output.map_err(|error| format!("cannot run {}, {}", program, error))?;
+    if !output.status.success() {
+        return Err(format!("{} failed, {}", program, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(signature)
+}
+
+//function to tell the recipients how to check the signature
+pub fn verify_command(path: &str, signature: &str) -> String {
+    if signature.ends_with(".sig") {
+        format!("ssh-keygen -Y verify -f ALLOWED_SIGNERS -I SIGNER -n {} -s {} < {}", NAMESPACE, signature, path)
+    }
+    else {
+        format!("gpg --verify {} {}", signature, path)
+    }
+}
@@ -0,0 +1,224 @@
+//silences and maintenance windows, watch mode still records the state changes of the hosts they cover but doesn't notify
+//
+//ad-hoc silences are kept in silences.jsonl next to the stored scans:
+//  host_disco silence 192.168.1.20 2h "patching"
+//
+//maintenance windows come from the config file, times are UTC and a window may run past midnight:
+//  [maintenance backups]
+//  hosts = 192.168.1.0/24, 10.0.0.5
+//  days = sat, sun
+//  hours = 22:00-02:00
+
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::json;
+use crate::metadata;
+use crate::store;
+use crate::subnet;
+use crate::target;
+
+const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+//an ad-hoc silence of an address or range
+pub struct Silence {
+    pub scope: String,
+    pub until: u64,
+    pub reason: String,
+}
+
+//a recurring maintenance window from the config file
+pub struct Window {
+    pub name: String,
+    hosts: Vec<String>,
+    //days of the week it opens on, 0 is Sunday, empty is every day
+    days: Vec<u64>,
+    //minutes after midnight UTC
+    start: u64,
+    end: u64,
+}
+
+impl Window {
+    //function to tell whether the window is open at a time, a window past midnight belongs to the day it opened
+    fn is_open(&self, now: u64) -> bool {
+        let minute = now % 86_400 / 60;
+        let today = (now / 86_400 + 4) % 7;
+        let (inside, opened_on) = if self.start <= self.end {
+            (minute >= self.start && minute < self.end, today)
+        }
+        else if minute >= self.start {
+            (true, today)
+        }
+        else {
+            (minute < self.end, (today + 6) % 7)
+        };
+        inside && (self.days.is_empty() || self.days.contains(&opened_on))
+    }
+}
+
+fn path() -> Result<PathBuf, String> {
+    Ok(store::data_dir()?.join("silences.jsonl"))
+}
+
+//function to check an address or range a silence is given for
+pub fn validate_scope(scope: &str) -> Result<(), String> {
+    if scope.contains('/') {
+        target::validate_ip_cidr(scope).map(|_| ())
+    }
+    else if target::validate_ip_address(scope) {
+        Ok(())
+    }
+    else {
+        Err(format!("{} is not an IP address or IP/CIDR range", scope))
+    }
+}
+
+//function to test whether an address falls in an address or range
+fn covers(scope: &str, address: Ipv4Addr) -> bool {
+    let (ip, prefix) = match scope.split_once('/') {
+        Some((ip, prefix)) => (ip, prefix.trim().parse().unwrap_or(32)),
+        None => (scope, 32),
+    };
+    let Ok(ip) = ip.trim().parse::<Ipv4Addr>() else {
+        return false;
+    };
+    u32::from(ip) & subnet::mask(prefix) == u32::from(address) & subnet::mask(prefix)
+}
+
+//function to load the silences that haven't run out yet, a missing file is no silences
+pub fn load(now: u64) -> Result<Vec<Silence>, String> {
+    let path = path()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("cannot read {}, {}", path.display(), error)),
+    };
+    let silences = contents
+        .lines()
+        .filter_map(|line| json::parse(line).ok())
+        .filter_map(|entry| {
+            Some(Silence {
+                scope: entry.get("scope")?.as_str()?.to_string(),
+                until: entry.get("until")?.as_u64()?,
+                reason: entry.get("reason").and_then(json::Value::as_str).unwrap_or_default().to_string(),
+            })
+        })
+        .filter(|silence| silence.until > now)
+        .collect();
+    Ok(silences)
+}
+
+//function to write the silences back, the ones that ran out are dropped by load
+fn save(silences: &[Silence]) -> Result<(), String> {
+    let path = path()?;
+    let dir = store::data_dir()?;
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    let lines: String = silences
+        .iter()
+        .map(|silence| {
+            format!(
+                "{{\"scope\":{},\"until\":{},\"reason\":{}}}\n",
+                json::quote(&silence.scope),
+                silence.until,
+                json::quote(&silence.reason)
+            )
+        })
+        .collect();
+    fs::write(&path, lines).map_err(|error| format!("cannot write {}, {}", path.display(), error))
+}
+
+//function to silence an address or range for a while, replacing any silence it already had
+pub fn add(scope: &str, duration: Duration, reason: &str) -> Result<(), String> {
+    let now = store::now();
+    let mut silences = load(now)?;
+    silences.retain(|silence| silence.scope != scope);
+    let until = now + duration.as_secs();
+    silences.push(Silence { scope: scope.to_string(), until, reason: reason.to_string() });
+    save(&silences)?;
+    println!("Silenced {} until {}{}.", scope, metadata::iso8601(until), reason_suffix(reason));
+    Ok(())
+}
+
+//function to lift the silence of an address or range
+pub fn clear(scope: &str) -> Result<(), String> {
+    let mut silences = load(store::now())?;
+    let count = silences.len();
+    silences.retain(|silence| silence.scope != scope);
+    if silences.len() == count {
+        return Err(format!("{} is not silenced", scope));
+    }
+    save(&silences)?;
+    println!("Silence of {} lifted.", scope);
+    Ok(())
+}
+
+//function to print the silences in force
+pub fn list() -> Result<(), String> {
+    let silences = load(store::now())?;
+    if silences.is_empty() {
+        println!("Nothing is silenced.");
+    }
+    for silence in silences {
+        println!("{:<18}  until {}{}", silence.scope, metadata::iso8601(silence.until), reason_suffix(&silence.reason));
+    }
+    Ok(())
+}
+
+fn reason_suffix(reason: &str) -> String {
+    if reason.is_empty() {
+        String::new()
+    }
+    else {
+        format!(", {}", reason)
+    }
+}
+
+//function to read the [maintenance NAME] sections of the config file
+pub fn windows(config: &Config) -> Result<Vec<Window>, String> {
+    let mut windows = Vec::new();
+    for (name, section) in config.sections_of("maintenance") {
+        let hosts = section.list("hosts");
+        for host in &hosts {
+            validate_scope(host).map_err(|error| format!("[maintenance {}] {}", name, error))?;
+        }
+        let days = section
+            .list("days")
+            .iter()
+            .map(|day| {
+                let day = day.to_lowercase();
+                DAYS.iter()
+                    .position(|name| day.starts_with(name))
+                    .map(|index| index as u64)
+                    .ok_or_else(|| format!("[maintenance {}] has unknown day {}", name, day))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let hours = section.require("hours")?;
+        let parsed = hours.split_once('-').and_then(|(start, end)| Some((minutes(start)?, minutes(end)?)));
+        let Some((start, end)) = parsed else {
+            return Err(format!("[maintenance {}] hours should be like 22:00-02:00, not {}", name, hours));
+        };
+        windows.push(Window { name: name.to_string(), hosts, days, start, end });
+    }
+    Ok(windows)
+}
+
+//function to turn HH:MM into minutes after midnight
+fn minutes(time: &str) -> Option<u64> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u64, u64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+//function to find why an address is silenced right now, e.g. "silenced until 2024-05-01T12:00:00Z, patching"
+pub fn reason(address: Ipv4Addr, now: u64, silences: &[Silence], windows: &[Window]) -> Option<String> {
+    if let Some(silence) = silences.iter().find(|silence| covers(&silence.scope, address)) {
+        return Some(format!("silenced until {}{}", metadata::iso8601(silence.until), reason_suffix(&silence.reason)));
+    }
+    windows
+        .iter()
+        .find(|window| window.is_open(now) && (window.hosts.is_empty() || window.hosts.iter().any(|host| covers(host, address))))
+        .map(|window| format!("maintenance {}", window.name))
+}
@@ -0,0 +1,362 @@
+//harvesting the ARP and MAC address tables of routers and switches over SNMPv2c (--snmp-arp), the
+//hosts the infrastructure has seen even on segments the probes can't reach or where hosts drop them
+//
+//  [snmp]
+//  community = s3cret
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::host::HostResult;
+use crate::oui;
+
+const PORT: u16 = 161;
+const TIMEOUT: Duration = Duration::from_secs(2);
+const ATTEMPTS: usize = 3;
+//rows asked for in each GetBulkRequest, small enough for the answer to fit a datagram
+const MAX_REPETITIONS: i64 = 20;
+
+//ipNetToMediaPhysAddress, indexed by ifIndex and the IPv4 address
+const ARP_TABLE: &[u32] = &[1, 3, 6, 1, 2, 1, 4, 22, 1, 2];
+//dot1dTpFdbPort, indexed by the MAC address, the bridge port it was learned on
+const FDB_TABLE: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 4, 3, 1, 2];
+//dot1qTpFdbPort, indexed by the VLAN and the MAC address, for switches that only keep per-VLAN tables
+const QFDB_TABLE: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 7, 1, 2, 2, 1, 2];
+//dot1dBasePortIfIndex, the interface of each bridge port
+const BRIDGE_PORT_TABLE: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 1, 4, 1, 2];
+//ifName
+const INTERFACE_NAMES: &[u32] = &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 1];
+
+//what a device's tables said about the hosts it has seen
+pub struct Harvest {
+    pub device: String,
+    //address and MAC from the ARP table
+    pub arp: Vec<(Ipv4Addr, String)>,
+    //MAC and the port it was learned on from the bridge tables, with how many MACs that port has
+    pub macs: Vec<(String, String, usize)>,
+}
+
+//the values the tables hold
+enum Value {
+    Integer(i64),
+    Bytes(Vec<u8>),
+    Other,
+}
+
+//one row of a table, its OID and value
+type Row = (Vec<u32>, Value);
+
+//function to read the tables of one device, the ARP table of a router and the MAC table of a switch,
+//a device with neither is an error
+pub fn harvest(device: &str, config: &Config) -> Result<Harvest, String> {
+    let community = config
+        .sections
+        .iter()
+        .find(|section| section.name == "snmp")
+        .and_then(|section| section.get("community"))
+        .unwrap_or("public");
+    let address = (device, PORT)
+        .to_socket_addrs()
+        .map_err(|error| format!("cannot resolve {}, {}", device, error))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| format!("no IPv4 address for {}", device))?;
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|error| format!("cannot open a UDP socket, {}", error))?;
+    socket.connect(address).map_err(|error| format!("cannot reach {}, {}", device, error))?;
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    let mut session = Session { socket, community, request_id: std::process::id() as i64 & 0x7fff_0000 };
+
+    let mut arp = Vec::new();
+    for (oid, value) in session.walk(ARP_TABLE).map_err(|error| format!("{}, {}", device, error))? {
+        let index = &oid[ARP_TABLE.len()..];
+        if let (Some([_, a, b, c, d]), Value::Bytes(mac)) = (octets(index).as_deref(), &value) {
+            if let Some(mac) = mac_text(mac) {
+                arp.push((Ipv4Addr::new(*a, *b, *c, *d), mac));
+            }
+        }
+    }
+
+    //a router has no bridge tables, that's no reason to give up on its ARP table
+    let mut learned: Vec<(Vec<u32>, u32)> = Vec::new();
+    for (table, skip) in [(FDB_TABLE, 0), (QFDB_TABLE, 1)] {
+        let rows = session.walk(table).unwrap_or_default();
+        learned.extend(rows.into_iter().filter_map(|(oid, value)| match value {
+            Value::Integer(port) if port > 0 => Some((oid[table.len() + skip..].to_vec(), port as u32)),
+            _ => None,
+        }));
+        if !learned.is_empty() {
+            break;
+        }
+    }
+    let mut macs = Vec::new();
+    if !learned.is_empty() {
+        let interfaces = integers(session.walk(BRIDGE_PORT_TABLE).unwrap_or_default(), BRIDGE_PORT_TABLE.len());
+        let names: HashMap<u32, String> = session
+            .walk(INTERFACE_NAMES)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(oid, value)| match (oid.get(INTERFACE_NAMES.len()), value) {
+                (Some(index), Value::Bytes(name)) => Some((*index, String::from_utf8_lossy(&name).into_owned())),
+                _ => None,
+            })
+            .collect();
+        let mut per_port: HashMap<u32, usize> = HashMap::new();
+        for (_, port) in &learned {
+            *per_port.entry(*port).or_default() += 1;
+        }
+        for (index, port) in &learned {
+            let Some(mac) = octets(index).as_deref().and_then(mac_text) else {
+                continue;
+            };
+            let name = interfaces
+                .get(port)
+                .and_then(|interface| names.get(interface))
+                .cloned()
+                .unwrap_or_else(|| format!("port {}", port));
+            macs.push((mac, name, per_port[port]));
+        }
+    }
+
+    if arp.is_empty() && macs.is_empty() {
+        return Err(format!("{} has no ARP or MAC address table entries", device));
+    }
+    Ok(Harvest { device: device.to_string(), arp, macs })
+}
+
+//function to fill in the MAC of scanned hosts the ARP tables know, and mark those that didn't answer as
+//seen by the router, and by the switch port their MAC was learned on, returns how many were marked
+pub fn merge(harvests: &[Harvest], results: &mut [HostResult]) -> usize {
+    let mut arp: HashMap<Ipv4Addr, (&str, &str)> = HashMap::new();
+    //a MAC is learned on every uplink it crosses too, the port with the fewest MACs is the one it's plugged into
+    let mut ports: HashMap<&str, (String, usize)> = HashMap::new();
+    for harvest in harvests {
+        for (address, mac) in &harvest.arp {
+            arp.entry(*address).or_insert((mac, &harvest.device));
+        }
+        for (mac, port, count) in &harvest.macs {
+            let seen = (format!("{} {}", harvest.device, port), *count);
+            ports.entry(mac).and_modify(|best| if *count < best.1 { *best = seen.clone() }).or_insert(seen);
+        }
+    }
+
+    let mut marked = 0;
+    for host in results.iter_mut() {
+        let Some((mac, router)) = arp.get(&host.address) else {
+            continue;
+        };
+        if host.mac.is_none() {
+            host.mac = Some(mac.to_string());
+            host.vendor = oui::vendor(mac).map(str::to_string);
+        }
+        if !host.is_up() {
+            host.seen_by = Some(match ports.get(mac) {
+                //a layer 3 switch is both
+                Some((port, _)) if port.starts_with(&format!("{} ", router)) => port.clone(),
+                Some((port, _)) => format!("{}, {}", router, port),
+                None => router.to_string(),
+            });
+            marked += 1;
+        }
+    }
+    marked
+}
+
+//function to pick the integer rows of a table walk, keyed by the last sub-identifier of their index
+fn integers(rows: Vec<Row>, root: usize) -> HashMap<u32, u32> {
+    rows.into_iter()
+        .filter_map(|(oid, value)| match (oid.get(root), value) {
+            (Some(index), Value::Integer(integer)) => Some((*index, integer as u32)),
+            _ => None,
+        })
+        .collect()
+}
+
+//function to read an index of octets, the addresses the tables are indexed by
+fn octets(index: &[u32]) -> Option<Vec<u8>> {
+    index.iter().map(|&octet| u8::try_from(octet).ok()).collect()
+}
+
+//function to write six octets as a MAC address, the way the neighbour table does
+fn mac_text(octets: &[u8]) -> Option<String> {
+    if octets.len() != 6 || octets.iter().all(|&octet| octet == 0) {
+        return None;
+    }
+    Some(octets.iter().map(|octet| format!("{:02X}", octet)).collect::<Vec<String>>().join(":"))
+}
+
+struct Session<'a> {
+    socket: UdpSocket,
+    community: &'a str,
+    request_id: i64,
+}
+
+impl Session<'_> {
+    //function to read every row under a table's OID with GetBulkRequests
+    fn walk(&mut self, root: &[u32]) -> Result<Vec<Row>, String> {
+        let mut rows = Vec::new();
+        let mut last = root.to_vec();
+        loop {
+            let varbinds = self.get_bulk(&last)?;
+            if varbinds.is_empty() {
+                return Ok(rows);
+            }
+            for (oid, value) in varbinds {
+                //past the end of the table, or a device walking backwards
+                if !oid.starts_with(root) || oid <= last || matches!(value, Value::Other) {
+                    return Ok(rows);
+                }
+                last = oid.clone();
+                rows.push((oid, value));
+            }
+        }
+    }
+
+    fn get_bulk(&mut self, oid: &[u32]) -> Result<Vec<Row>, String> {
+        self.request_id += 1;
+        let varbind = tlv(0x30, &[encode_oid(oid), vec![0x05, 0x00]].concat());
+        let pdu = tlv(
+            0xa5,
+            &[
+                tlv(0x02, &encode_integer(self.request_id)),
+                tlv(0x02, &encode_integer(0)),
+                tlv(0x02, &encode_integer(MAX_REPETITIONS)),
+                tlv(0x30, &varbind),
+            ]
+            .concat(),
+        );
+        //version 1 is SNMPv2c
+        let message = tlv(0x30, &[tlv(0x02, &encode_integer(1)), tlv(0x04, self.community.as_bytes()), pdu].concat());
+
+        let mut buf = [0u8; 65535];
+        for _ in 0..ATTEMPTS {
+            self.socket.send(&message).map_err(|error| format!("cannot send, {}", error))?;
+            //answers to an earlier, timed out request are dropped
+            while let Ok(len) = self.socket.recv(&mut buf) {
+                match parse_response(&buf[..len]) {
+                    Some((id, _, _)) if id != self.request_id => continue,
+                    Some((_, 0, varbinds)) => return Ok(varbinds),
+                    Some((_, 2, _)) => return Ok(Vec::new()),
+                    Some((_, status, _)) => return Err(format!("the device answered with error status {}", status)),
+                    None => return Err("the device's answer couldn't be read".to_string()),
+                }
+            }
+        }
+        Err("no answer, check the address and the community".to_string())
+    }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        encoded.push(len as u8);
+    }
+    else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|&byte| byte == 0).collect();
+        encoded.push(0x80 | bytes.len() as u8);
+        encoded.extend(bytes);
+    }
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+//two's complement in as few octets as keep the sign
+fn encode_integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7 && ((bytes[start] == 0 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0)) {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+//the first two sub-identifiers share an octet, the rest are base 128 with the high bit marking continuation
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut content = vec![(oid[0] * 40 + oid[1]) as u8];
+    for &sub in &oid[2..] {
+        let mut septets = vec![(sub & 0x7f) as u8];
+        let mut rest = sub >> 7;
+        while rest > 0 {
+            septets.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        content.extend(septets.iter().rev());
+    }
+    tlv(0x06, &content)
+}
+
+//function to split one TLV off the front of the input, its tag, content and what follows
+fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, mut rest) = rest.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    }
+    else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count].iter().fold(0usize, |len, &byte| len << 8 | byte as usize);
+        rest = &rest[count..];
+        len
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+fn decode_integer(content: &[u8]) -> i64 {
+    let start = if content.first().is_some_and(|&byte| byte & 0x80 != 0) { -1 } else { 0 };
+    content.iter().fold(start, |value, &byte| value << 8 | byte as i64)
+}
+
+fn decode_oid(content: &[u8]) -> Vec<u32> {
+    let Some((&first, rest)) = content.split_first() else {
+        return Vec::new();
+    };
+    let mut oid = vec![(first / 40) as u32, (first % 40) as u32];
+    let mut sub = 0u32;
+    for &byte in rest {
+        sub = sub << 7 | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            oid.push(sub);
+            sub = 0;
+        }
+    }
+    oid
+}
+
+//function to read a GetResponse, its request id, error status and variable bindings
+fn parse_response(datagram: &[u8]) -> Option<(i64, i64, Vec<Row>)> {
+    let (_, message, _) = read_tlv(datagram)?;
+    let (_, _, rest) = read_tlv(message)?; //version
+    let (_, _, rest) = read_tlv(rest)?; //community
+    let (tag, pdu, _) = read_tlv(rest)?;
+    if tag != 0xa2 {
+        return None;
+    }
+    let (_, id, rest) = read_tlv(pdu)?;
+    let (_, status, rest) = read_tlv(rest)?;
+    let (_, _, rest) = read_tlv(rest)?; //error index
+    let (_, mut list, _) = read_tlv(rest)?;
+
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        let (_, varbind, rest) = read_tlv(list)?;
+        list = rest;
+        let (_, oid, rest) = read_tlv(varbind)?;
+        let (tag, content, _) = read_tlv(rest)?;
+        let value = match tag {
+            //INTEGER, Counter32, Gauge32, TimeTicks
+            0x02 | 0x41 | 0x42 | 0x43 => Value::Integer(decode_integer(content)),
+            0x04 => Value::Bytes(content.to_vec()),
+            //noSuchObject, noSuchInstance, endOfMibView and anything the tables don't hold
+            _ => Value::Other,
+        };
+        varbinds.push((decode_oid(oid), value));
+    }
+    Some((decode_integer(id), decode_integer(status), varbinds))
+}
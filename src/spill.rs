@@ -0,0 +1,69 @@
+//spilling the addresses that didn't answer to disk as the sweep goes (--spill FILE, and on its own for
+//sweeps of THRESHOLD addresses or more), one JSON object per line in the shape of the json format, so
+//sweeping a /8 keeps only its live hosts in memory and the summary counts the rest back from the file
+//
+//the output formats and the stored scan then have the live hosts only, the spill file is the record of
+//the others and is left in place, next to the stored scans unless --spill names it
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::Ipv4Addr;
+
+use crate::host::HostResult;
+use crate::json;
+use crate::store;
+
+pub const THRESHOLD: usize = 1 << 20;
+
+pub struct Spill {
+    path: String,
+    writer: BufWriter<File>,
+    pub count: usize,
+}
+
+impl Spill {
+    pub fn create(path: &str) -> Result<Spill, String> {
+        let file = File::create(path).map_err(|error| format!("cannot create {}, {}", path, error))?;
+        Ok(Spill { path: path.to_string(), writer: BufWriter::new(file), count: 0 })
+    }
+
+    //function to spill to spill/SCAN_ID.jsonl in the data directory
+    pub fn create_default(scan_id: &str) -> Result<Spill, String> {
+        let dir = store::data_dir()?.join("spill");
+        fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+        Spill::create(&dir.join(format!("{}.jsonl", scan_id)).to_string_lossy())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn push(&mut self, host: &HostResult) -> Result<(), String> {
+        writeln!(self.writer, "{}", host.to_json()).map_err(|error| format!("cannot write {}, {}", self.path, error))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    //function to write out what is still buffered, before the file is read back
+    pub fn finish(mut self) -> Result<String, String> {
+        self.writer.flush().map_err(|error| format!("cannot write {}, {}", self.path, error))?;
+        Ok(self.path)
+    }
+}
+
+//function to read the spilled hosts back a line at a time, leaving out those since found up by a recheck
+pub fn read(path: &str, recovered: &HashSet<Ipv4Addr>, mut on_host: impl FnMut(HostResult)) -> Result<(), String> {
+    let file = File::open(path).map_err(|error| format!("cannot read {}, {}", path, error))?;
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|error| format!("cannot read {}, {}", path, error))?;
+        let host = json::parse(&line)
+            .ok()
+            .and_then(|value| HostResult::from_json(&value))
+            .ok_or_else(|| format!("line {} of {} isn't a host", number + 1, path))?;
+        if !recovered.contains(&host.address) {
+            on_host(host);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//collecting SSH host key fingerprints with ssh-keyscan, so a reinstalled or impersonated machine shows up
+//as a changed fingerprint between scans
+
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::process::{Command, Stdio};
+
+use crate::host::{HostResult, PortState};
+
+pub const SSH_PORT: u16 = 22;
+
+//function to put the server version and key fingerprints in the info of an open port 22
+pub fn collect(host: &mut HostResult) {
+    let address = host.address;
+    let Some(port) = host
+        .ports
+        .iter_mut()
+        .find(|port| port.port == SSH_PORT && port.protocol == "tcp" && port.state == PortState::Open)
+    else {
+        return;
+    };
+    if let Some(keys) = fingerprints(address) {
+        port.info = Some(keys);
+    }
+}
+
+//function to scan the host keys and fingerprint them, e.g. "OpenSSH_9.6, ED25519 SHA256:xPnI6..."
+fn fingerprints(address: Ipv4Addr) -> Option<String> {
+    let scan = Command::new("ssh-keyscan")
+        .args(["-T", "3", "-p", &SSH_PORT.to_string(), "-t", "ed25519,ecdsa,rsa"])
+        .arg(address.to_string())
+        .output()
+        .ok()?;
+
+    //the server version comes on stderr as "# 10.0.0.5:22 SSH-2.0-OpenSSH_9.6"
+    let version = String::from_utf8_lossy(&scan.stderr)
+        .lines()
+        .find_map(|line| line.strip_prefix("# ")?.split_whitespace().nth(1)?.strip_prefix("SSH-2.0-").map(str::to_string));
+    if scan.stdout.is_empty() {
+        return version;
+    }
+
+    //ssh-keygen prints "256 SHA256:xPnI6... 10.0.0.5 (ED25519)" for every key it is given
+    let mut keygen = Command::new("ssh-keygen")
+        .args(["-l", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    keygen.stdin.take()?.write_all(&scan.stdout).ok()?;
+    let listing = keygen.wait_with_output().ok()?;
+
+    let mut parts: Vec<String> = version.into_iter().collect();
+    for line in String::from_utf8_lossy(&listing.stdout).lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let (Some(fingerprint), Some(kind)) = (words.get(1), words.last()) {
+            parts.push(format!("{} {}", kind.trim_matches(|c| c == '(' || c == ')'), fingerprint));
+        }
+    }
+    Some(parts.join(", "))
+}
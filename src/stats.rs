@@ -0,0 +1,138 @@
+//round trip time and performance statistics printed at the end of a scan, and sparklines of a host's history
+
+use std::time::Duration;
+
+//upper bounds (in ms) of each histogram bucket, anything above the last bound goes in a final bucket
+const BUCKET_BOUNDS: [f64; 7] = [1.0, 5.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+
+//widest bar drawn in the histogram
+const BAR_WIDTH: usize = 40;
+
+//function to print an RTT histogram and percentiles across all up hosts
+pub fn print_rtt_summary(rtts: &[f64]) {
+    if rtts.is_empty() {
+        println!("No round trip times were recorded.");
+        return;
+    }
+
+    let mut sorted = rtts.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    //count how many hosts fall in each bucket
+    let mut counts = [0usize; BUCKET_BOUNDS.len() + 1];
+    for rtt in &sorted {
+        let bucket = BUCKET_BOUNDS.iter().position(|bound| rtt < bound).unwrap_or(BUCKET_BOUNDS.len());
+        counts[bucket] += 1;
+    }
+    let max_count = *counts.iter().max().unwrap_or(&1);
+
+    println!("Round trip time distribution across {} up hosts:", sorted.len());
+    for (bucket, count) in counts.iter().enumerate() {
+        let bar_length = (count * BAR_WIDTH).div_ceil(max_count);
+        println!("{:>10} | {} {}", bucket_label(bucket), "█".repeat(bar_length), count);
+    }
+
+    let average = sorted.iter().sum::<f64>() / sorted.len() as f64;
+    println!();
+    println!("min {:.2}ms, avg {:.2}ms, max {:.2}ms", sorted[0], average, sorted[sorted.len() - 1]);
+    println!(
+        "p50 {:.2}ms, p90 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms",
+        percentile(&sorted, 50.0),
+        percentile(&sorted, 90.0),
+        percentile(&sorted, 95.0),
+        percentile(&sorted, 99.0)
+    );
+}
+
+//function to get a percentile from an already sorted list using the nearest-rank method
+pub fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+//function to turn a bucket index into a label like "5-20ms"
+fn bucket_label(bucket: usize) -> String {
+    if bucket == 0 {
+        format!("<{}ms", BUCKET_BOUNDS[0])
+    }
+    else if bucket == BUCKET_BOUNDS.len() {
+        format!(">{}ms", BUCKET_BOUNDS[bucket - 1])
+    }
+    else {
+        format!("{}-{}ms", BUCKET_BOUNDS[bucket - 1], BUCKET_BOUNDS[bucket])
+    }
+}
+
+//function to draw round trip times as a sparkline scaled between their own lowest and highest,
+//missed samples are left blank, e.g. "▁▁▂▃ ▅▇"
+pub fn sparkline(rtts: &[Option<f64>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let known: Vec<f64> = rtts.iter().flatten().copied().collect();
+    let low = known.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = known.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    rtts.iter()
+        .map(|rtt| match rtt {
+            Some(_) if high <= low => BARS[0],
+            Some(rtt) => BARS[(((rtt - low) / (high - low)) * (BARS.len() - 1) as f64).round() as usize],
+            None => ' ',
+        })
+        .collect()
+}
+
+//how a sweep went, for tuning the concurrency, retries and enrichment with data
+pub struct Performance {
+    pub duration: Duration,
+    //turning the targets into the list of addresses, and probing them (rechecks included)
+    pub expansion: Duration,
+    pub sweep: Duration,
+    //addresses probed rather than taken from the result cache
+    pub probed: usize,
+    pub retries: u32,
+    pub timeouts: usize,
+    pub errors: usize,
+    //the time the workers spent on each phase, added up over the addresses
+    pub probing: Duration,
+    pub enrichment: Duration,
+    //how far into the sweep each live host was found
+    pub found_at: Vec<Duration>,
+}
+
+impl Performance {
+    //function to print the performance block
+    pub fn print(&self) {
+        let rate = self.probed as f64 / self.sweep.as_secs_f64().max(0.001);
+        let per = |total: Duration, count: usize| total.as_secs_f64() / count.max(1) as f64;
+        println!("Performance:");
+        println!(
+            "    duration      {:.2}s (target expansion {:.2}s, sweep {:.2}s, the rest {:.2}s)",
+            self.duration.as_secs_f64(),
+            self.expansion.as_secs_f64(),
+            self.sweep.as_secs_f64(),
+            self.duration.saturating_sub(self.expansion + self.sweep).as_secs_f64()
+        );
+        println!("    probe rate    {:.1} addresses/s over {} addresses", rate, self.probed);
+        println!("    retries       {} after local errors", self.retries);
+        println!("    timeouts      {}", self.timeouts);
+        println!("    local errors  {}", self.errors);
+        println!(
+            "    probing       {:.2}s of worker time, {:.3}s per address",
+            self.probing.as_secs_f64(),
+            per(self.probing, self.probed)
+        );
+        println!(
+            "    enrichment    {:.2}s of worker time, {:.3}s per live host",
+            self.enrichment.as_secs_f64(),
+            per(self.enrichment, self.found_at.len())
+        );
+        let mut found: Vec<f64> = self.found_at.iter().map(Duration::as_secs_f64).collect();
+        found.sort_by(f64::total_cmp);
+        if let Some(last) = found.last() {
+            println!(
+                "    discovery     {} live hosts, half found by {:.2}s, the last at {:.2}s",
+                found.len(),
+                percentile(&found, 50.0),
+                last
+            );
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//results kept on disk between runs, under $XDG_DATA_HOME/host_disco (~/.local/share/host_disco)
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::host::{HostResult, PortResult};
+use crate::json;
+use crate::metadata::ScanMetadata;
+
+//a host result and when it was probed, in seconds since the epoch
+pub struct CachedHost {
+    pub probed_at: u64,
+    pub host: HostResult,
+}
+
+//function to find the directory results are stored in
+pub fn data_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir).join("host_disco"));
+    }
+    match env::var_os("HOME").filter(|dir| !dir.is_empty()) {
+        Some(home) => Ok(PathBuf::from(home).join(".local/share/host_disco")),
+        None => Err("neither XDG_DATA_HOME nor HOME is set".to_string()),
+    }
+}
+
+//function to get the current time in seconds since the epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+fn cache_path() -> Result<PathBuf, String> {
+    Ok(data_dir()?.join("cache.jsonl"))
+}
+
+//function to load the per-address cache, a missing cache is just empty
+pub fn load_cache() -> Result<HashMap<Ipv4Addr, CachedHost>, String> {
+    let path = cache_path()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(error) => return Err(format!("cannot read {}, {}", path.display(), error)),
+    };
+
+    //one {"probed_at":...,"host":{...}} object per line, lines that don't parse are dropped
+    let mut cache = HashMap::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let Ok(entry) = json::parse(line) else {
+            continue;
+        };
+        let probed_at = entry.get("probed_at").and_then(json::Value::as_u64);
+        let host = entry.get("host").and_then(HostResult::from_json);
+        if let (Some(probed_at), Some(host)) = (probed_at, host) {
+            cache.insert(host.address, CachedHost { probed_at, host });
+        }
+    }
+    Ok(cache)
+}
+
+//function to record this run's results in the cache, hosts that are no longer up are dropped
+pub fn update_cache(cache: &mut HashMap<Ipv4Addr, CachedHost>, results: &[HostResult], probed_at: u64) -> Result<(), String> {
+    for host in results {
+        if host.is_up() {
+            cache.insert(host.address, CachedHost { probed_at, host: host.clone() });
+        }
+        else {
+            cache.remove(&host.address);
+        }
+    }
+
+    let path = cache_path()?;
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    let mut entries: Vec<&CachedHost> = cache.values().collect();
+    entries.sort_by_key(|entry| entry.host.address);
+    let lines: String = entries
+        .iter()
+        .map(|entry| format!("{{\"probed_at\":{},\"host\":{}}}\n", entry.probed_at, entry.host.to_json()))
+        .collect();
+    fs::write(&path, lines).map_err(|error| format!("cannot write {}, {}", path.display(), error))
+}
+
+//function to reuse a cached host when it is fresh and covered every port asked for this time
+pub fn reuse(entry: &CachedHost, max_age: Duration, now: u64, tcp_ports: &[u16], udp_ports: &[u16]) -> Option<HostResult> {
+    if now.saturating_sub(entry.probed_at) > max_age.as_secs() {
+        return None;
+    }
+    let wanted = |port: &PortResult| match port.protocol {
+        "udp" => udp_ports.contains(&port.port),
+        _ => tcp_ports.contains(&port.port),
+    };
+    let ports: Vec<_> = entry.host.ports.iter().filter(|port| wanted(port)).cloned().collect();
+    if ports.len() < tcp_ports.len() + udp_ports.len() {
+        return None;
+    }
+    Some(HostResult { ports, ..entry.host.clone() })
+}
+
+//how many stored runs of a range to keep
+#[derive(Clone, Copy)]
+pub enum Keep {
+    //the latest this many
+    Runs(usize),
+    //those started within this long of now, and always the latest
+    Age(Duration),
+}
+
+//a stored run of a range, its file named after the second it started, with -1, -2 and so on after it
+//for other runs of the range that started in the same second, e.g. 1700000000-1.json
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RunName {
+    pub started_at: u64,
+    pub sequence: u32,
+}
+
+impl RunName {
+    fn file_name(&self) -> String {
+        match self.sequence {
+            0 => format!("{}.json", self.started_at),
+            sequence => format!("{}-{}.json", self.started_at, sequence),
+        }
+    }
+
+    fn parse(file_name: &str) -> Option<RunName> {
+        let stem = file_name.strip_suffix(".json")?;
+        let (started_at, sequence) = match stem.split_once('-') {
+            Some((started_at, sequence)) => (started_at, sequence.parse().ok().filter(|sequence| *sequence > 0)?),
+            None => (stem, 0),
+        };
+        Some(RunName { started_at: started_at.parse().ok()?, sequence })
+    }
+}
+
+//a scan read back from the result store
+pub struct StoredScan {
+    //the id of the run, empty for runs stored before scans had one
+    pub id: String,
+    pub started_at: u64,
+    pub hosts: Vec<HostResult>,
+}
+
+//function to turn the scanned ranges into a directory name, e.g. 10.0.0.0/24 becomes 10.0.0.0_24
+pub fn scan_key(labels: &[&str]) -> String {
+    labels
+        .join(",")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == ',' { c } else { '_' })
+        .collect()
+}
+
+//function to keep a finished scan, each range gets a directory with one file per run, a run never
+//replaces another that started in the same second
+pub fn save_scan(key: &str, metadata: &ScanMetadata, results: &[HostResult]) -> Result<PathBuf, String> {
+    let dir = data_dir()?.join("scans").join(key);
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}, {}", dir.display(), error))?;
+    let hosts: Vec<String> = results.iter().map(HostResult::to_json).collect();
+    let document = format!(
+        "{{\"started_at\":{},\"scan\":{},\n\"hosts\":[\n{}\n]}}\n",
+        metadata.started_at,
+        metadata.to_json(),
+        hosts.join(",\n")
+    );
+    for sequence in 0.. {
+        let path = dir.join(RunName { started_at: metadata.started_at, sequence }.file_name());
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(format!("cannot create {}, {}", path.display(), error)),
+        };
+        file.write_all(document.as_bytes()).map_err(|error| format!("cannot write {}, {}", path.display(), error))?;
+        return Ok(path);
+    }
+    Err(format!("cannot store the scan in {}, every name for its second is taken", dir.display()))
+}
+
+//function to list the ranges that have stored runs, by the key scan_key made of them
+pub fn scan_keys() -> Result<Vec<String>, String> {
+    let dir = data_dir()?.join("scans");
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("cannot read {}, {}", dir.display(), error)),
+    };
+    let mut keys: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    keys.sort();
+    Ok(keys)
+}
+
+//function to list the stored runs of a range, oldest first
+pub fn scan_runs(key: &str) -> Result<Vec<RunName>, String> {
+    let dir = data_dir()?.join("scans").join(key);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("cannot read {}, {}", dir.display(), error)),
+    };
+
+    let mut runs: Vec<RunName> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| RunName::parse(entry.file_name().to_str()?))
+        .collect();
+    runs.sort_unstable();
+    Ok(runs)
+}
+
+//function to remove the runs of a range past what is kept, never the latest, returning how many went
+pub fn prune_scans(key: &str, keep: Keep) -> Result<usize, String> {
+    let dir = data_dir()?.join("scans").join(key);
+    let runs = scan_runs(key)?;
+    let older = &runs[..runs.len().saturating_sub(1)];
+    let doomed: Vec<&RunName> = match keep {
+        Keep::Runs(kept) => older.iter().take(runs.len().saturating_sub(kept.max(1))).collect(),
+        Keep::Age(age) => older.iter().filter(|run| run.started_at < now().saturating_sub(age.as_secs())).collect(),
+    };
+    let mut removed = 0;
+    for run in doomed {
+        let path = dir.join(run.file_name());
+        fs::remove_file(&path).map_err(|error| format!("cannot remove {}, {}", path.display(), error))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+//the addresses of a range that were ever up while it was watched, one per line in known.txt beside its
+//runs, so watch mode tells new devices from returning ones without reading every run
+fn known_path(key: &str) -> Result<PathBuf, String> {
+    Ok(data_dir()?.join("scans").join(key).join("known.txt"))
+}
+
+//function to load the addresses ever seen up in a range, None when they were never written down
+pub fn load_known(key: &str) -> Result<Option<HashSet<Ipv4Addr>>, String> {
+    let path = known_path(key)?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(contents.lines().filter_map(|line| line.trim().parse().ok()).collect())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("cannot read {}, {}", path.display(), error)),
+    }
+}
+
+//function to write down the addresses ever seen up in a range
+pub fn save_known(key: &str, known: &HashSet<Ipv4Addr>) -> Result<(), String> {
+    let path = known_path(key)?;
+    let mut addresses: Vec<&Ipv4Addr> = known.iter().collect();
+    addresses.sort();
+    let lines: String = addresses.iter().map(|address| format!("{}\n", address)).collect();
+    fs::write(&path, lines).map_err(|error| format!("cannot write {}, {}", path.display(), error))
+}
+
+//function to load one stored run of a range
+pub fn load_scan(key: &str, run: RunName) -> Result<StoredScan, String> {
+    let path = data_dir()?.join("scans").join(key).join(run.file_name());
+    let contents = fs::read_to_string(&path).map_err(|error| format!("cannot read {}, {}", path.display(), error))?;
+    let document = json::parse(&contents).map_err(|error| format!("cannot parse {}, {}", path.display(), error))?;
+    let id = document.get("scan").and_then(|scan| scan.get("id")).and_then(json::Value::as_str).unwrap_or("").to_string();
+    let hosts = document
+        .get("hosts")
+        .and_then(json::Value::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(HostResult::from_json)
+        .collect();
+    Ok(StoredScan { id, started_at: run.started_at, hosts })
+}
+
+//function to load the most recent stored scan of a range, None if it was never scanned
+pub fn load_latest_scan(key: &str) -> Result<Option<StoredScan>, String> {
+    match scan_runs(key)?.last() {
+        Some(run) => load_scan(key, *run).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_names_round_trip_and_sort_by_second_then_sequence() {
+        let first = RunName { started_at: 1700000000, sequence: 0 };
+        let second = RunName { started_at: 1700000000, sequence: 1 };
+        assert_eq!(first.file_name(), "1700000000.json");
+        assert_eq!(second.file_name(), "1700000000-1.json");
+        assert_eq!(RunName::parse("1700000000-1.json"), Some(second));
+        assert_eq!(RunName::parse("1700000000.json"), Some(first));
+        assert!(first < second && second < RunName { started_at: 1700000001, sequence: 0 });
+        //the list of known hosts and runs that aren't named as saved are left alone
+        assert_eq!(RunName::parse("known.txt"), None);
+        assert_eq!(RunName::parse("1700000000-0.json"), None);
+        assert_eq!(RunName::parse("1700000000-x.json"), None);
+    }
+}
@@ -0,0 +1,204 @@
+//subnet math shared by the scanner and the net subcommand
+
+use std::net::Ipv4Addr;
+
+use crate::target;
+
+//largest number of split subnets listed before the rest are summarised
+const SPLIT_LIST_LIMIT: usize = 64;
+
+//special purpose blocks, anything outside all of them is public address space
+const SPECIAL_BLOCKS: [(Ipv4Addr, u8, &str); 16] = [
+    (Ipv4Addr::new(0, 0, 0, 0), 8, "bogon"),
+    (Ipv4Addr::new(10, 0, 0, 0), 8, "private"),
+    (Ipv4Addr::new(100, 64, 0, 0), 10, "shared (CGNAT)"),
+    (Ipv4Addr::new(127, 0, 0, 0), 8, "loopback"),
+    (Ipv4Addr::new(169, 254, 0, 0), 16, "link-local"),
+    (Ipv4Addr::new(172, 16, 0, 0), 12, "private"),
+    (Ipv4Addr::new(192, 0, 0, 0), 24, "bogon"),
+    (Ipv4Addr::new(192, 0, 2, 0), 24, "bogon"),
+    (Ipv4Addr::new(192, 88, 99, 0), 24, "bogon"),
+    (Ipv4Addr::new(192, 168, 0, 0), 16, "private"),
+    (Ipv4Addr::new(198, 18, 0, 0), 15, "bogon"),
+    (Ipv4Addr::new(198, 51, 100, 0), 24, "bogon"),
+    (Ipv4Addr::new(203, 0, 113, 0), 24, "bogon"),
+    (Ipv4Addr::new(224, 0, 0, 0), 4, "multicast"),
+    (Ipv4Addr::new(240, 0, 0, 0), 4, "bogon"),
+    (Ipv4Addr::new(255, 255, 255, 255), 32, "bogon"),
+];
+
+//function to classify a range as private, link-local, multicast, public, etc.,
+//a range that isn't entirely inside one special block contains public space and is called public
+pub fn classify(network: Ipv4Addr, prefix: u8) -> &'static str {
+    let network = u32::from(network);
+    for (block, block_prefix, class) in SPECIAL_BLOCKS {
+        if prefix >= block_prefix && network & mask(block_prefix) == u32::from(block) {
+            return class;
+        }
+    }
+    "public"
+}
+
+//function to build the subnet mask for a prefix length as a u32
+pub fn mask(prefix: u8) -> u32 {
+    (!0u32).checked_shl(32 - prefix as u32).unwrap_or(0)
+}
+
+//function to count the addresses hosts can actually use, /31 and /32 have no network or broadcast address
+pub fn usable_hosts(prefix: u8) -> u64 {
+    match prefix {
+        32 => 1,
+        31 => 2,
+        _ => (1u64 << (32 - prefix)) - 2,
+    }
+}
+
+//function to print everything about a network for the net subcommand
+pub fn print_net_info(input: &str, split: Option<u8>) -> Result<(), String> {
+    let (ip_address, prefix) = target::validate_ip_cidr(input)?;
+    let ip_address_u32 = u32::from(ip_address.parse::<Ipv4Addr>().unwrap());
+
+    let netmask = mask(prefix);
+    let network = ip_address_u32 & netmask;
+    let broadcast = network | !netmask;
+    let (first_host, last_host) = if prefix >= 31 { (network, broadcast) } else { (network + 1, broadcast - 1) };
+
+    println!("Network:      {}/{}", Ipv4Addr::from(network), prefix);
+    println!("Broadcast:    {}", Ipv4Addr::from(broadcast));
+    println!("Netmask:      {}", Ipv4Addr::from(netmask));
+    println!("Wildcard:     {}", Ipv4Addr::from(!netmask));
+    println!("Host range:   {} - {}", Ipv4Addr::from(first_host), Ipv4Addr::from(last_host));
+    println!("Total:        {}", 1u64 << (32 - prefix));
+    println!("Usable hosts: {}", usable_hosts(prefix));
+
+    //split into the next prefix down unless the user picked one
+    if prefix == 32 {
+        return Ok(());
+    }
+    let split_prefix = split.unwrap_or(prefix + 1);
+    if split_prefix <= prefix || split_prefix > 32 {
+        return Err(format!("Split prefix must be between {} and 32.", prefix + 1));
+    }
+
+    let count = 1u64 << (split_prefix - prefix);
+    let step = 1u64 << (32 - split_prefix);
+    println!();
+    println!("Split into {} /{} subnets ({} usable hosts each):", count, split_prefix, usable_hosts(split_prefix));
+    for i in 0..count.min(SPLIT_LIST_LIMIT as u64) {
+        println!("    {}/{}", Ipv4Addr::from((network as u64 + i * step) as u32), split_prefix);
+    }
+    if count > SPLIT_LIST_LIMIT as u64 {
+        println!("    ... and {} more", count - SPLIT_LIST_LIMIT as u64);
+    }
+
+    Ok(())
+}
+
+//function to collapse a set of addresses into the fewest CIDR blocks that cover exactly those addresses
+pub fn aggregate(addresses: &[Ipv4Addr]) -> Vec<(Ipv4Addr, u8)> {
+    let mut sorted: Vec<u32> = addresses.iter().map(|address| u32::from(*address)).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < sorted.len() {
+        //find the end of this run of contiguous addresses
+        let start = sorted[i];
+        let mut end = start;
+        while i + 1 < sorted.len() && end != u32::MAX && sorted[i + 1] == end + 1 {
+            end = sorted[i + 1];
+            i += 1;
+        }
+        i += 1;
+        blocks.extend(range_blocks(start, end));
+    }
+
+    blocks
+}
+
+//function to cover a run of addresses with the largest aligned blocks that fit, left to right
+pub fn range_blocks(start: u32, end: u32) -> Vec<(Ipv4Addr, u8)> {
+    let mut blocks = Vec::new();
+    let mut current = start as u64;
+    while current <= end as u64 {
+        let alignment = if current == 0 { 32 } else { current.trailing_zeros().min(32) };
+        let mut size_bits = alignment;
+        while current + (1u64 << size_bits) - 1 > end as u64 {
+            size_bits -= 1;
+        }
+        blocks.push((Ipv4Addr::from(current as u32), (32 - size_bits) as u8));
+        current += 1u64 << size_bits;
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(text: &str) -> u32 {
+        u32::from(text.parse::<Ipv4Addr>().unwrap())
+    }
+
+    #[test]
+    fn mask_covers_every_prefix() {
+        assert_eq!(mask(0), 0);
+        assert_eq!(mask(8), 0xff00_0000);
+        assert_eq!(mask(31), 0xffff_fffe);
+        assert_eq!(mask(32), u32::MAX);
+    }
+
+    #[test]
+    fn usable_hosts_leave_out_network_and_broadcast() {
+        assert_eq!(usable_hosts(24), 254);
+        assert_eq!(usable_hosts(31), 2);
+        assert_eq!(usable_hosts(32), 1);
+        assert_eq!(usable_hosts(0), (1u64 << 32) - 2);
+    }
+
+    #[test]
+    fn classify_needs_the_whole_range_inside_a_block() {
+        assert_eq!(classify(Ipv4Addr::new(10, 1, 0, 0), 16), "private");
+        assert_eq!(classify(Ipv4Addr::new(192, 168, 1, 0), 24), "private");
+        assert_eq!(classify(Ipv4Addr::new(8, 8, 8, 0), 24), "public");
+        //10.0.0.0/7 reaches into 11.0.0.0/8, which is public
+        assert_eq!(classify(Ipv4Addr::new(10, 0, 0, 0), 7), "public");
+    }
+
+    #[test]
+    fn range_blocks_take_the_largest_aligned_blocks() {
+        assert_eq!(
+            range_blocks(address("10.0.0.1"), address("10.0.0.6")),
+            vec![
+                (Ipv4Addr::new(10, 0, 0, 1), 32),
+                (Ipv4Addr::new(10, 0, 0, 2), 31),
+                (Ipv4Addr::new(10, 0, 0, 4), 31),
+                (Ipv4Addr::new(10, 0, 0, 6), 32),
+            ]
+        );
+        assert_eq!(range_blocks(address("10.0.0.0"), address("10.0.0.255")), vec![(Ipv4Addr::new(10, 0, 0, 0), 24)]);
+        assert_eq!(range_blocks(7, 7), vec![(Ipv4Addr::from(7), 32)]);
+    }
+
+    #[test]
+    fn range_blocks_reach_both_ends_of_the_address_space() {
+        assert_eq!(range_blocks(0, u32::MAX), vec![(Ipv4Addr::new(0, 0, 0, 0), 0)]);
+        assert_eq!(range_blocks(u32::MAX - 1, u32::MAX), vec![(Ipv4Addr::new(255, 255, 255, 254), 31)]);
+    }
+
+    #[test]
+    fn aggregate_merges_runs_and_ignores_order_and_duplicates() {
+        let mut addresses: Vec<Ipv4Addr> = (0..=255).rev().map(|last| Ipv4Addr::new(10, 0, 0, last)).collect();
+        addresses.push(Ipv4Addr::new(10, 0, 0, 7));
+        addresses.push(Ipv4Addr::new(10, 0, 2, 1));
+        assert_eq!(aggregate(&addresses), vec![(Ipv4Addr::new(10, 0, 0, 0), 24), (Ipv4Addr::new(10, 0, 2, 1), 32)]);
+    }
+
+    #[test]
+    fn aggregate_stops_at_the_last_address() {
+        let addresses = [Ipv4Addr::new(255, 255, 255, 254), Ipv4Addr::new(255, 255, 255, 255)];
+        assert_eq!(aggregate(&addresses), vec![(Ipv4Addr::new(255, 255, 255, 254), 31)]);
+        assert!(aggregate(&[]).is_empty());
+    }
+}
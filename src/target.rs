@@ -0,0 +1,533 @@
+//turning what the user typed (or piped in) into the list of addresses to probe
+
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+
+use crate::compress;
+use crate::dns::Resolver;
+use crate::subnet;
+
+//the most names a --name-pattern may stand for, each is a lookup of its own
+const MAX_PATTERN_NAMES: usize = 65_536;
+
+//one thing the user asked to scan, the addresses it covers are the network's, worked out as they are needed
+pub struct Target {
+    pub label: String,
+    pub network: Ipv4Addr,
+    pub prefix: u8,
+}
+
+//the addresses a sweep probes, in order and each once, kept as runs of consecutive addresses so a /8 is
+//a handful of numbers rather than 16 million, until something reorders or leaves out single addresses
+pub enum Addresses {
+    //the first address of each run, how many it has and how many come before it
+    Runs(Vec<(u32, u64, u64)>),
+    Listed(Vec<Ipv4Addr>),
+}
+
+impl Target {
+    //function to count the addresses the target covers
+    pub fn size(&self) -> u64 {
+        1u64 << (32 - self.prefix)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = Ipv4Addr> {
+        let first = u32::from(self.network);
+        (0..self.size()).map(move |offset| Ipv4Addr::from(first + offset as u32))
+    }
+
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & subnet::mask(self.prefix) == u32::from(self.network)
+    }
+}
+
+impl Addresses {
+    pub fn len(&self) -> usize {
+        match self {
+            Addresses::Runs(runs) => runs.last().map(|(_, length, before)| before + length).unwrap_or(0) as usize,
+            Addresses::Listed(addresses) => addresses.len(),
+        }
+    }
+
+    //function to get the address at a position in the order, for workers taking the next one
+    pub fn get(&self, index: usize) -> Option<Ipv4Addr> {
+        match self {
+            Addresses::Runs(runs) => {
+                let index = index as u64;
+                let run = runs.partition_point(|(_, _, before)| *before <= index).checked_sub(1)?;
+                let (first, length, before) = runs[run];
+                (index - before < length).then(|| Ipv4Addr::from(first + (index - before) as u32))
+            }
+            Addresses::Listed(addresses) => addresses.get(index).copied(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        (0..self.len()).map_while(|index| self.get(index))
+    }
+
+    //function to get the addresses as a list that can be reordered or filtered, listing the runs the first time
+    pub fn list(&mut self) -> &mut Vec<Ipv4Addr> {
+        if let Addresses::Runs(_) = self {
+            *self = Addresses::Listed(self.iter().collect());
+        }
+        match self {
+            Addresses::Listed(addresses) => addresses,
+            Addresses::Runs(_) => unreachable!(),
+        }
+    }
+
+    //function to keep the shard-th of shards equal slices of the addresses (from 1), the same slice for
+    //every run given the same targets, so separate runs split a sweep between them without overlap
+    pub fn shard(&self, shard: u32, shards: u32) -> Addresses {
+        let total = self.len() as u64;
+        let start = total * u64::from(shard - 1) / u64::from(shards);
+        let end = total * u64::from(shard) / u64::from(shards);
+        match self {
+            Addresses::Runs(runs) => Addresses::Runs(
+                runs.iter()
+                    .filter_map(|(first, length, before)| {
+                        let (from, to) = (start.max(*before), end.min(before + length));
+                        (from < to).then(|| (first + (from - before) as u32, to - from, from - start))
+                    })
+                    .collect(),
+            ),
+            Addresses::Listed(addresses) => Addresses::Listed(addresses[start as usize..end as usize].to_vec()),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Ipv4Addr> {
+        self.iter().collect()
+    }
+
+    //function to cover the addresses with as few CIDR blocks as possible, without listing the runs
+    pub fn blocks(&self) -> Vec<(Ipv4Addr, u8)> {
+        match self {
+            Addresses::Runs(runs) => {
+                let mut runs: Vec<(u32, u64)> = runs.iter().map(|(first, length, _)| (*first, *length)).collect();
+                runs.sort_unstable();
+                runs.iter().flat_map(|(first, length)| subnet::range_blocks(*first, first + (*length - 1) as u32)).collect()
+            }
+            Addresses::Listed(addresses) => subnet::aggregate(addresses),
+        }
+    }
+
+    //function to cover the addresses other than those taken with as few CIDR blocks as possible, the runs
+    //are cut around the taken addresses rather than listed, e.g. the free space of a /8 with a few hosts up
+    pub fn blocks_without(&self, taken: &[Ipv4Addr]) -> Vec<(Ipv4Addr, u8)> {
+        let mut taken: Vec<u32> = taken.iter().map(|address| u32::from(*address)).collect();
+        taken.sort_unstable();
+        taken.dedup();
+        let mut blocks = Vec::new();
+        for (first, last) in self.ranges() {
+            let mut start = u64::from(first);
+            let from = taken.partition_point(|address| *address < first);
+            for address in taken[from..].iter().take_while(|address| **address <= last) {
+                if u64::from(*address) > start {
+                    blocks.extend(subnet::range_blocks(start as u32, address - 1));
+                }
+                start = u64::from(*address) + 1;
+            }
+            if start <= u64::from(last) {
+                blocks.extend(subnet::range_blocks(start as u32, last));
+            }
+        }
+        blocks
+    }
+
+    //function to give the addresses as sorted runs of consecutive addresses, first and last, merging
+    //runs that touch so a block can span them
+    fn ranges(&self) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = match self {
+            Addresses::Runs(runs) => runs
+                .iter()
+                .filter(|(_, length, _)| *length > 0)
+                .map(|(first, length, _)| (*first, first + (*length - 1) as u32))
+                .collect(),
+            Addresses::Listed(addresses) => addresses.iter().map(|address| (u32::from(*address), u32::from(*address))).collect(),
+        };
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::new();
+        for (first, last) in ranges {
+            match merged.last_mut() {
+                Some(previous) if u64::from(first) <= u64::from(previous.1) + 1 => previous.1 = previous.1.max(last),
+                _ => merged.push((first, last)),
+            }
+        }
+        merged
+    }
+}
+
+//function to expand an IP/CIDR, a single IP or a hostname into a target,
+//hostnames go through the given resolver or the system one when there isn't one
+pub fn expand(input: &str, resolver: Option<&Resolver>) -> Result<Target, String> {
+    if input.contains('/') {
+        let (ip_address, cidr_prefix) = validate_ip_cidr(input)?;
+
+        //work out the network address from the ip and subnet mask
+        let ip_address_u32 = u32::from(ip_address.parse::<Ipv4Addr>().unwrap());
+        let subnet_mask_u32 = subnet::mask(cidr_prefix);
+        let network_u32 = ip_address_u32 & subnet_mask_u32;
+
+        return Ok(Target {
+            label: format!("{}/{}", Ipv4Addr::from(network_u32), cidr_prefix),
+            network: Ipv4Addr::from(network_u32),
+            prefix: cidr_prefix,
+        });
+    }
+
+    //a bare address is treated as a /32
+    let address = if validate_ip_address(input) {
+        input.parse::<Ipv4Addr>().unwrap()
+    }
+    else if let Some(resolver) = resolver {
+        resolver
+            .lookup(input)?
+            .ok_or_else(|| "Hostname has no IPv4 address.".to_string())?
+    }
+    else {
+        resolve_hostname(input)?
+    };
+
+    Ok(Target {
+        label: input.to_string(),
+        network: address,
+        prefix: 32,
+    })
+}
+
+//function to read newline separated targets from stdin, skipping blank lines and # comments
+pub fn read_stdin_targets() -> Vec<String> {
+    target_lines(io::stdin().lock())
+}
+
+//function to read the targets of a file, in the same format as on stdin
+pub fn read_target_file(path: &str) -> Result<Vec<String>, String> {
+    let contents = compress::read(path)?;
+    Ok(target_lines(contents.as_slice()))
+}
+
+fn target_lines(reader: impl BufRead) -> Vec<String> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+//function to expand a hostname pattern into the names it stands for, {01..20} being the numbers padded to
+//the width written and {web,db} each of a list, e.g. web{01..03}.corp.local is web01, web02 and web03
+pub fn expand_pattern(pattern: &str) -> Result<Vec<String>, String> {
+    let mut names = vec![String::new()];
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}').map(|close| open + close).ok_or_else(|| format!("{} has a {{ without a }}", pattern))?;
+        let choices = pattern_choices(&rest[open + 1..close], MAX_PATTERN_NAMES / names.len()).ok_or_else(|| {
+            format!("{} has an invalid {{}}, expected a range like {{01..20}} or a list like {{web,db}}, at most {} names in all", pattern, MAX_PATTERN_NAMES)
+        })?;
+        let literal = &rest[..open];
+        names = names.iter().flat_map(|name| choices.iter().map(move |choice| format!("{}{}{}", name, literal, choice))).collect();
+        rest = &rest[close + 1..];
+    }
+    if rest.contains('}') {
+        return Err(format!("{} has a }} without a {{", pattern));
+    }
+    for name in &mut names {
+        name.push_str(rest);
+    }
+    Ok(names)
+}
+
+//function to list what a {} of a pattern stands for, None when it is neither a range nor a list or has more than limit
+fn pattern_choices(group: &str, limit: usize) -> Option<Vec<String>> {
+    if let Some((first, last)) = group.split_once("..") {
+        let (start, end): (u64, u64) = (first.parse().ok()?, last.parse().ok()?);
+        if start.abs_diff(end) >= limit as u64 {
+            return None;
+        }
+        //a leading zero pads every number to the width written, as in the shell
+        let width = if first.starts_with('0') || last.starts_with('0') { first.len().max(last.len()) } else { 0 };
+        let numbers: Vec<u64> = if start <= end { (start..=end).collect() } else { (end..=start).rev().collect() };
+        return Some(numbers.iter().map(|number| format!("{:0width$}", number, width = width)).collect());
+    }
+    let items: Vec<String> = group.split(',').map(str::to_string).collect();
+    (items.len() > 1 && items.len() <= limit).then_some(items)
+}
+
+//function to get the first and last address of a target as numbers, targets are always contiguous
+fn span(target: &Target) -> (u32, u32) {
+    let first = u32::from(target.network);
+    (first, first + (target.size() - 1) as u32)
+}
+
+//function to describe the targets that cover some of the same addresses, e.g. "10.0.0.0/24 already covers 10.0.0.5/32"
+pub fn overlaps(targets: &[Target]) -> Vec<String> {
+    let mut overlaps = Vec::new();
+    //a target given twice is noted once, its copies aren't compared again
+    let mut distinct: Vec<&Target> = Vec::new();
+    for target in targets {
+        if distinct.iter().any(|seen| seen.label == target.label) {
+            let note = format!("{} is listed more than once", target.label);
+            if !overlaps.contains(&note) {
+                overlaps.push(note);
+            }
+        }
+        else {
+            distinct.push(target);
+        }
+    }
+    for (index, a) in distinct.iter().enumerate() {
+        for b in &distinct[index + 1..] {
+            let ((a_first, a_last), (b_first, b_last)) = (span(a), span(b));
+            if a_first.max(b_first) > a_last.min(b_last) {
+                continue;
+            }
+            let shared = u64::from(a_last.min(b_last) - a_first.max(b_first)) + 1;
+            overlaps.push(match (a_first <= b_first && a_last >= b_last, b_first <= a_first && b_last >= a_last) {
+                (true, true) => format!("{} and {} are the same addresses", a.label, b.label),
+                (true, false) => format!("{} already covers {}", a.label, b.label),
+                (false, true) => format!("{} already covers {}", b.label, a.label),
+                (false, false) => format!("{} and {} share {} addresses", a.label, b.label, shared),
+            });
+        }
+    }
+    overlaps
+}
+
+//function to list the addresses of all targets in order, each only once, as runs
+//the parts of a target earlier targets already cover are cut out of it
+pub fn unique_addresses(targets: &[Target]) -> Addresses {
+    let mut covered: Vec<(u32, u32)> = Vec::new();
+    let mut runs = Vec::new();
+    let mut before = 0;
+    for target in targets {
+        let (first, last) = span(target);
+        let mut next = u64::from(first);
+        for (covered_first, covered_last) in covered.iter().filter(|(covered_first, covered_last)| *covered_last >= first && *covered_first <= last) {
+            if u64::from(*covered_first) > next {
+                let length = u64::from(*covered_first) - next;
+                runs.push((next as u32, length, before));
+                before += length;
+            }
+            next = next.max(u64::from(*covered_last) + 1);
+        }
+        if next <= u64::from(last) {
+            let length = u64::from(last) - next + 1;
+            runs.push((next as u32, length, before));
+            before += length;
+        }
+        //kept sorted and merged, so the ranges a target overlaps are in order
+        covered.push((first, last));
+        covered.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::new();
+        for (first, last) in covered {
+            match merged.last_mut() {
+                Some(previous) if u64::from(first) <= u64::from(previous.1) + 1 => previous.1 = previous.1.max(last),
+                _ => merged.push((first, last)),
+            }
+        }
+        covered = merged;
+    }
+    Addresses::Runs(runs)
+}
+
+//function to put the addresses most likely to be in use first, hosts seen up before, then gateways
+//on .1 and .254, then the low and high ends of a subnet where servers and printers tend to be given
+//static addresses, and the rest in order
+pub fn prioritise(addresses: &mut [Ipv4Addr], seen: &HashSet<Ipv4Addr>) {
+    addresses.sort_by_key(|address| {
+        let last = address.octets()[3];
+        let rank = if seen.contains(address) {
+            0
+        }
+        else {
+            match last {
+                1 | 254 => 1,
+                2..=20 | 250..=253 => 2,
+                _ => 3,
+            }
+        };
+        (rank, *address)
+    });
+}
+
+//function to list every target an address belongs to
+pub fn scopes_of(address: Ipv4Addr, targets: &[Target]) -> Vec<&str> {
+    let address = u32::from(address);
+    targets
+        .iter()
+        .filter(|target| {
+            let (first, last) = span(target);
+            (first..=last).contains(&address)
+        })
+        .map(|target| target.label.as_str())
+        .collect()
+}
+
+//function to look up the first IPv4 address of a hostname with the system resolver
+fn resolve_hostname(hostname: &str) -> Result<Ipv4Addr, String> {
+    let addresses = (hostname, 0)
+        .to_socket_addrs()
+        .map_err(|_| "Invalid IP address or unknown hostname.".to_string())?;
+
+    for address in addresses {
+        if let SocketAddr::V4(v4) = address {
+            return Ok(*v4.ip());
+        }
+    }
+    Err("Hostname has no IPv4 address.".to_string())
+}
+
+//function for validating the IP and CIDR provided by the user
+pub fn validate_ip_cidr(input: &str) -> Result<(String, u8), String> {
+    let parts: Vec<&str> = input.split('/').collect();
+    if parts.len() != 2 {
+        return Err("Invalid format, expected IP with CIDR prefix.".to_string());
+    }
+
+    //trim the IP from the ip/cidr combo
+    let ip_address = parts[0].trim();
+    if !validate_ip_address(ip_address) {
+        return Err("Invalid IP address.".to_string());
+    }
+
+    //trim the cidr from the ip/cidr combo
+    let cidr_prefix: u8 = match parts[1].trim().parse() {
+        Ok(prefix) => prefix,
+        Err(_) => return Err("Invalid CIDR prefix.".to_string()),
+    };
+
+    //checks the CIDR notation to make sure it is within range
+    if cidr_prefix > 32 {
+        return Err("CIDR prefix must be a number between 0 - 32.".to_string());
+    }
+
+    Ok((ip_address.to_string(), cidr_prefix))
+}
+
+//function to validate the IP address length and octet values,
+pub fn validate_ip_address(ip_address: &str) -> bool {
+    let octets: Vec<&str> = ip_address.split('.').collect();
+    if octets.len() != 4 {
+        return false;
+    }
+
+    //parsing into a u8 rejects anything outside 0 - 255
+    for octet in octets {
+        if octet.parse::<u8>().is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(inputs: &[&str]) -> Vec<Target> {
+        inputs.iter().map(|input| expand(input, None).unwrap()).collect()
+    }
+
+    fn addresses(texts: &[&str]) -> Vec<Ipv4Addr> {
+        texts.iter().map(|text| text.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn expand_pattern_pads_ranges_to_the_width_written() {
+        assert_eq!(expand_pattern("web{08..10}.corp").unwrap(), ["web08.corp", "web09.corp", "web10.corp"]);
+        assert_eq!(expand_pattern("db{3..1}").unwrap(), ["db3", "db2", "db1"]);
+    }
+
+    #[test]
+    fn expand_pattern_multiplies_groups_in_order() {
+        assert_eq!(expand_pattern("{web,db}{1..2}.lan").unwrap(), ["web1.lan", "web2.lan", "db1.lan", "db2.lan"]);
+        assert_eq!(expand_pattern("plain.lan").unwrap(), ["plain.lan"]);
+    }
+
+    #[test]
+    fn expand_pattern_rejects_bad_groups() {
+        assert!(expand_pattern("web{01..03").is_err());
+        assert!(expand_pattern("web01}").is_err());
+        assert!(expand_pattern("web{}").is_err());
+        assert!(expand_pattern("web{single}").is_err());
+        assert!(expand_pattern("web{a..b}").is_err());
+    }
+
+    #[test]
+    fn expand_pattern_caps_the_names() {
+        assert!(expand_pattern("host{0..65535}").is_ok());
+        assert!(expand_pattern("host{0..65536}").is_err());
+        //each group alone is small, together they would be 1000 * 1000 names
+        assert!(expand_pattern("a{1..1000}b{1..1000}").is_err());
+    }
+
+    #[test]
+    fn unique_addresses_drop_overlaps_and_keep_order() {
+        let unique = unique_addresses(&targets(&["10.0.0.4/30", "10.0.0.0/29", "10.0.0.6"]));
+        assert_eq!(unique.len(), 8);
+        assert_eq!(unique.to_vec(), addresses(&["10.0.0.4", "10.0.0.5", "10.0.0.6", "10.0.0.7", "10.0.0.0", "10.0.0.1", "10.0.0.2", "10.0.0.3"]));
+    }
+
+    #[test]
+    fn shard_splits_without_gaps_or_overlap() {
+        let all = unique_addresses(&targets(&["10.0.0.0/30", "10.0.1.0/31", "10.0.2.5"]));
+        for shards in 1..=8 {
+            let mut joined = Vec::new();
+            for shard in 1..=shards {
+                let slice = all.shard(shard, shards);
+                assert!(slice.len().abs_diff(all.len() / shards as usize) <= 1);
+                joined.extend(slice.to_vec());
+            }
+            assert_eq!(joined, all.to_vec(), "{} shards", shards);
+        }
+    }
+
+    #[test]
+    fn shard_of_runs_matches_shard_of_a_list() {
+        let runs = unique_addresses(&targets(&["10.0.0.0/29", "10.0.3.0/30"]));
+        let listed = Addresses::Listed(runs.to_vec());
+        for shard in 1..=3 {
+            assert_eq!(runs.shard(shard, 3).to_vec(), listed.shard(shard, 3).to_vec());
+            //the positions of a sharded run start again from 0
+            assert_eq!(runs.shard(shard, 3).get(0), listed.shard(shard, 3).get(0));
+        }
+    }
+
+    #[test]
+    fn blocks_without_cut_around_the_taken_addresses() {
+        let all = unique_addresses(&targets(&["10.0.0.0/29", "10.0.0.8/29"]));
+        assert_eq!(
+            all.blocks_without(&addresses(&["10.0.0.3", "10.0.0.3", "10.0.0.15", "192.168.0.1"])),
+            vec![
+                (Ipv4Addr::new(10, 0, 0, 0), 31),
+                (Ipv4Addr::new(10, 0, 0, 2), 32),
+                (Ipv4Addr::new(10, 0, 0, 4), 30),
+                (Ipv4Addr::new(10, 0, 0, 8), 30),
+                (Ipv4Addr::new(10, 0, 0, 12), 31),
+                (Ipv4Addr::new(10, 0, 0, 14), 32),
+            ]
+        );
+        assert!(all.blocks_without(&all.to_vec()).is_empty());
+    }
+
+    #[test]
+    fn blocks_without_match_aggregating_a_list() {
+        let mut listed = Addresses::Listed(addresses(&["10.0.0.9", "10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.8"]));
+        let taken = addresses(&["10.0.0.2"]);
+        let free: Vec<Ipv4Addr> = listed.list().iter().filter(|address| !taken.contains(address)).copied().collect();
+        assert_eq!(listed.blocks_without(&taken), subnet::aggregate(&free));
+    }
+
+    #[test]
+    fn validate_ip_cidr_checks_both_parts() {
+        assert_eq!(validate_ip_cidr("10.0.0.0/24").unwrap(), ("10.0.0.0".to_string(), 24));
+        assert!(validate_ip_cidr("10.0.0.0").is_err());
+        assert!(validate_ip_cidr("10.0.0.256/24").is_err());
+        assert!(validate_ip_cidr("10.0.0.0/33").is_err());
+    }
+}
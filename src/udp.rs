@@ -0,0 +1,85 @@
+//UDP probing with payloads real services answer, since an empty datagram rarely gets a reply
+
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use crate::dns;
+use crate::host::{PortResult, PortState};
+use crate::ports;
+
+//how long to wait for a reply before calling the port open|filtered
+const REPLY_TIMEOUT: Duration = Duration::from_millis(1500);
+
+//SNMPv1 GetRequest for sysDescr.0 with the community "public"
+const SNMP_GET_SYSDESCR: [u8; 40] = [
+    0x30, 0x26, 0x02, 0x01, 0x00, 0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', 0xa0, 0x19, 0x02, 0x01, 0x01, 0x02,
+    0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00,
+    0x05, 0x00,
+];
+
+//function to build the payload a service on this port is known to answer
+pub fn payload(port: u16) -> Vec<u8> {
+    match port {
+        //a TXT query for version.bind, any DNS server answers it even if only to refuse
+        53 => dns::build_query(0x4844, "version.bind", 16),
+        //an NTPv3 client request, mode 3
+        123 => {
+            let mut request = vec![0u8; 48];
+            request[0] = 0x1b;
+            request
+        }
+        137 => netbios_status_query(),
+        161 => SNMP_GET_SYSDESCR.to_vec(),
+        //BACnet/IP unicast Who-Is, answered with an I-Am
+        47808 => vec![0x81, 0x0a, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08],
+        _ => Vec::new(),
+    }
+}
+
+//NetBIOS node status request for the wildcard name "*"
+fn netbios_status_query() -> Vec<u8> {
+    let mut query = vec![0x48, 0x44, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    //the name is "*" padded with nulls to 16 bytes, each nibble encoded as a letter from 'A'
+    query.push(0x20);
+    query.extend_from_slice(b"CK");
+    query.extend_from_slice(&[b'A'; 30]);
+    query.push(0x00);
+    query.extend_from_slice(&[0x00, 0x21, 0x00, 0x01]); //NBSTAT, class IN
+    query
+}
+
+//function to probe each UDP port, a reply means open, an ICMP port unreachable means closed
+pub fn scan_udp(address: Ipv4Addr, udp_ports: &[u16]) -> Vec<PortResult> {
+    udp_ports
+        .iter()
+        .map(|&port| PortResult {
+            port,
+            protocol: "udp",
+            state: probe_port(address, port),
+            service: ports::service_name(port).map(str::to_string),
+            info: None,
+        })
+        .collect()
+}
+
+fn probe_port(address: Ipv4Addr, port: u16) -> PortState {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(_) => return PortState::OpenFiltered,
+    };
+    //connecting the socket makes the kernel report ICMP port unreachable as ConnectionRefused
+    if socket.connect(SocketAddr::from((address, port))).is_err() || socket.set_read_timeout(Some(REPLY_TIMEOUT)).is_err() {
+        return PortState::OpenFiltered;
+    }
+    if socket.send(&payload(port)).is_err() {
+        return PortState::OpenFiltered;
+    }
+
+    let mut buf = [0u8; 1500];
+    match socket.recv(&mut buf) {
+        Ok(_) => PortState::Open,
+        Err(error) if error.kind() == ErrorKind::ConnectionRefused => PortState::Closed,
+        Err(_) => PortState::OpenFiltered,
+    }
+}
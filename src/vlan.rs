@@ -0,0 +1,133 @@
+//sweeping VLANs carried on a trunk port (--vlan with --trunk), each through its tagged subinterface,
+//e.g. eth0.30, created with ip when it doesn't exist yet and given the address to probe from, so the
+//kernel routes the probes for the VLAN's subnet out tagged
+//
+//what was created or added is taken away again when the scan ends
+
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::process::Command;
+
+use crate::subnet;
+
+//the longest interface name the kernel takes, IFNAMSIZ less the terminating null
+const MAX_NAME: usize = 15;
+
+//the subinterfaces and addresses set up for the scan, removed again when dropped
+pub struct Vlans {
+    created: Vec<String>,
+    added: Vec<(String, String)>,
+}
+
+//one VLAN ready to sweep
+pub struct Vlan {
+    pub id: u16,
+    pub interface: String,
+    pub created: bool,
+    //the subnet to sweep, e.g. "10.30.0.0/24"
+    pub block: String,
+}
+
+//function to parse a --vlan value, ID or ID:ADDRESS/PREFIX, e.g. 30:10.30.0.250/24
+pub fn parse(value: &str) -> Result<(u16, Option<(Ipv4Addr, u8)>), String> {
+    let (id, address) = value.split_once(':').map_or((value, None), |(id, address)| (id, Some(address)));
+    let id = id
+        .parse::<u16>()
+        .ok()
+        .filter(|id| (1..=4094).contains(id))
+        .ok_or_else(|| format!("--vlan needs a VLAN ID from 1 to 4094, got {}", id))?;
+    let address = match address {
+        Some(address) => {
+            let (ip, prefix) = address
+                .split_once('/')
+                .and_then(|(ip, prefix)| Some((ip.parse::<Ipv4Addr>().ok()?, prefix.parse::<u8>().ok().filter(|prefix| *prefix <= 30)?)))
+                .ok_or_else(|| format!("--vlan {} needs the address to probe from as ADDRESS/PREFIX, e.g. 10.30.0.250/24", id))?;
+            Some((ip, prefix))
+        }
+        None => None,
+    };
+    Ok((id, address))
+}
+
+impl Vlans {
+    //function to make sure every VLAN has its subinterface and an address on it, a dry run only looks
+    pub fn setup(trunk: &str, vlans: &[(u16, Option<(Ipv4Addr, u8)>)], dry_run: bool) -> Result<(Vlans, Vec<Vlan>), String> {
+        if !Path::new("/sys/class/net").join(trunk).exists() {
+            return Err(format!("there is no interface {}", trunk));
+        }
+        let mut setup = Vlans { created: Vec::new(), added: Vec::new() };
+        let mut ready = Vec::new();
+        for (id, address) in vlans {
+            let interface = format!("{}.{}", trunk, id);
+            if interface.len() > MAX_NAME {
+                return Err(format!("{} is too long for an interface name", interface));
+            }
+            let exists = Path::new("/sys/class/net").join(&interface).exists();
+            if !exists && !dry_run {
+                ip(&["link", "add", "link", trunk, "name", &interface, "type", "vlan", "id", &id.to_string()])?;
+                setup.created.push(interface.clone());
+                ip(&["link", "set", "dev", &interface, "up"])?;
+            }
+
+            //the address given, else the one the subinterface already has
+            let existing = if exists { addresses(&interface)? } else { Vec::new() };
+            let (ip_address, prefix) = match address {
+                Some(address) => *address,
+                None => *existing
+                    .first()
+                    .ok_or_else(|| format!("{} has no IPv4 address, give one to probe from with --vlan {}:ADDRESS/PREFIX", interface, id))?,
+            };
+            if !existing.contains(&(ip_address, prefix)) && !dry_run {
+                let cidr = format!("{}/{}", ip_address, prefix);
+                ip(&["addr", "add", &cidr, "dev", &interface])?;
+                if exists {
+                    setup.added.push((interface.clone(), cidr));
+                }
+            }
+            let network = Ipv4Addr::from(u32::from(ip_address) & subnet::mask(prefix));
+            ready.push(Vlan { id: *id, interface, created: !exists, block: format!("{}/{}", network, prefix) });
+        }
+        Ok((setup, ready))
+    }
+}
+
+impl Drop for Vlans {
+    fn drop(&mut self) {
+        for (interface, cidr) in &self.added {
+            if let Err(error) = ip(&["addr", "del", cidr, "dev", interface]) {
+                eprintln!("Cannot remove {} from {}, {}", cidr, interface, error);
+            }
+        }
+        for interface in &self.created {
+            if let Err(error) = ip(&["link", "del", interface]) {
+                eprintln!("Cannot remove {}, {}", interface, error);
+            }
+        }
+    }
+}
+
+fn ip(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("ip").args(args).output().map_err(|error| format!("cannot run ip, {}", error))?;
+    if !output.status.success() {
+        //e.g. "RTNETLINK answers: Operation not permitted"
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+//function to list the IPv4 addresses of an interface, from "2: eth0.30    inet 10.30.0.250/24 brd ..." lines
+fn addresses(interface: &str) -> Result<Vec<(Ipv4Addr, u8)>, String> {
+    let output = Command::new("ip")
+        .args(["-4", "-o", "addr", "show", "dev", interface])
+        .output()
+        .map_err(|error| format!("cannot run ip, {}", error))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip_while(|field| *field != "inet");
+            let (address, prefix) = fields.nth(1)?.split_once('/')?;
+            Some((address.parse().ok()?, prefix.parse().ok()?))
+        })
+        .collect())
+}
@@ -0,0 +1,124 @@
+//waiting for one host to answer (host_disco wait), probing it over and over until it does or the
+//timeout passes, for scripts that reboot a machine and block until it is back
+//
+//with --reboot it times a reboot for patching runs instead: it waits for the host to go down, then
+//for ping and each of the ports (SSH by default) to answer again, and reports how long each took
+//from the moment the host went down, e.g. "reboot took 94s, SSH ready after 121s"
+//
+//the exit status is 0 once it answered (everything came back), 2 when the timeout passed first and 1 for errors
+
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::host::PortState;
+use crate::ports;
+use crate::probe::{self, Outcome};
+use crate::target;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+pub const DEFAULT_REBOOT_PORTS: [u16; 1] = [22];
+//a probe starts this often at most, one that times out takes longer
+const INTERVAL: Duration = Duration::from_secs(1);
+
+//function to probe the host until it answers, returning false when the timeout passed first
+pub fn run(target: &str, timeout: Duration) -> Result<bool, String> {
+    let address = single_address(target)?;
+    println!("Waiting up to {}s for {} to answer.", timeout.as_secs(), address);
+    let started = Instant::now();
+    let mut probes = 0;
+    while started.elapsed() < timeout {
+        let sent = Instant::now();
+        probes += 1;
+        match probe::ping(&address.to_string()) {
+            Outcome::Up { rtt, .. } => {
+                let rtt = rtt.map(|rtt| format!(", {:.2}ms", rtt)).unwrap_or_default();
+                println!("{} answered after {}s, on probe {}{}.", address, started.elapsed().as_secs(), probes, rtt);
+                return Ok(true);
+            }
+            //a machine that is rebooting takes the local network with it at times, keep trying
+            Outcome::Down(_) | Outcome::LocalError(_) => {}
+        }
+        thread::sleep(INTERVAL.saturating_sub(sent.elapsed()));
+    }
+    println!("{} didn't answer within {}s, probes sent: {}.", address, timeout.as_secs(), probes);
+    Ok(false)
+}
+
+//function to time a reboot of the host, returning false when the timeout passed before all of it came back
+pub fn reboot(target: &str, timeout: Duration, ports: &[u16]) -> Result<bool, String> {
+    let address = single_address(target)?;
+    println!("Waiting up to {}s for {} to go down and come back.", timeout.as_secs(), address);
+    let started = Instant::now();
+
+    //the reboot starts with the last answer before the host went quiet, or with the wait when it never answered
+    let mut last_answer = started;
+    loop {
+        if started.elapsed() >= timeout {
+            println!("{} didn't go down within {}s.", address, timeout.as_secs());
+            return Ok(false);
+        }
+        let sent = Instant::now();
+        let pinged = matches!(probe::ping(&address.to_string()), Outcome::Up { .. });
+        if !pinged && !ports::answers(address, ports) {
+            break;
+        }
+        last_answer = Instant::now();
+        thread::sleep(INTERVAL.saturating_sub(sent.elapsed()));
+    }
+    let down_at = last_answer;
+    println!("{} went down {}s into the wait.", address, down_at.duration_since(started).as_secs());
+
+    //each probe type is timed on its own, a port can be ready before ping answers when ICMP is filtered
+    let mut pinged: Option<Duration> = None;
+    let mut ready: Vec<(u16, Option<Duration>)> = ports.iter().map(|port| (*port, None)).collect();
+    while started.elapsed() < timeout {
+        let sent = Instant::now();
+        if pinged.is_none() && matches!(probe::ping(&address.to_string()), Outcome::Up { .. }) {
+            pinged = Some(down_at.elapsed());
+            println!("{} answers ping again, {}s after going down.", address, down_at.elapsed().as_secs());
+        }
+        for (port, at) in ready.iter_mut().filter(|(_, at)| at.is_none()) {
+            if ports::scan_tcp(address, &[*port]).first().is_some_and(|result| result.state == PortState::Open) {
+                *at = Some(down_at.elapsed());
+                println!("{} is ready on {}, {}s after going down.", address, port_label(*port), down_at.elapsed().as_secs());
+            }
+        }
+        if pinged.is_some() && ready.iter().all(|(_, at)| at.is_some()) {
+            break;
+        }
+        thread::sleep(INTERVAL.saturating_sub(sent.elapsed()));
+    }
+
+    let mut report = vec![match pinged {
+        Some(took) => format!("reboot took {}s", took.as_secs()),
+        None => "no ping reply within the timeout".to_string(),
+    }];
+    for (port, at) in &ready {
+        report.push(match at {
+            Some(took) => format!("{} ready after {}s", port_label(*port), took.as_secs()),
+            None => format!("{} not ready within the timeout", port_label(*port)),
+        });
+    }
+    println!("{}: {}.", address, report.join(", "));
+    Ok(pinged.is_some() && ready.iter().all(|(_, at)| at.is_some()))
+}
+
+//function to name a port in the report, by the protocol people know it as
+fn port_label(port: u16) -> String {
+    match port {
+        22 => "SSH".to_string(),
+        3389 => "RDP".to_string(),
+        5985 | 5986 => "WinRM".to_string(),
+        _ => ports::service_name(port).map(|service| format!("{} ({})", service, port)).unwrap_or_else(|| format!("port {}", port)),
+    }
+}
+
+//function to resolve the target to the one address to wait for
+fn single_address(target: &str) -> Result<Ipv4Addr, String> {
+    let expanded = target::expand(target, None)?;
+    match expanded.size() {
+        1 => Ok(expanded.network),
+        size => Err(format!("wait takes a single host, {} is {} addresses", target, size)),
+    }
+}
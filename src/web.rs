@@ -0,0 +1,85 @@
+//identifying web servers with curl, so requests can go through a proxy with the headers a segment expects
+
+use crate::cli::Options;
+use crate::curl;
+use crate::host::{HostResult, PortState};
+
+//the ports the web profile probes, the ones listed here are spoken to over TLS
+pub const HTTP_PORTS: [u16; 5] = [80, 443, 8000, 8080, 8443];
+const HTTPS_PORTS: [u16; 2] = [443, 8443];
+
+//the user agent sent when --user-agent isn't given
+pub const DEFAULT_USER_AGENT: &str = concat!("host_disco/", env!("CARGO_PKG_VERSION"));
+
+//how long one request may take, including the proxy
+const REQUEST_TIMEOUT_SECS: u32 = 5;
+
+//function to fill in the status, server and page title of every open web port of a host
+pub fn identify(host: &mut HostResult, options: &Options) {
+    let address = host.address;
+    for port in host
+        .ports
+        .iter_mut()
+        .filter(|port| port.protocol == "tcp" && port.state == PortState::Open && HTTP_PORTS.contains(&port.port))
+    {
+        let scheme = if HTTPS_PORTS.contains(&port.port) { "https" } else { "http" };
+        if let Some(info) = fetch(&format!("{}://{}:{}/", scheme, address, port.port), options) {
+            port.info = Some(info);
+        }
+    }
+}
+
+//function to request a page and sum up the answer, e.g. "HTTP 200 OK, nginx/1.24.0, title Welcome"
+fn fetch(url: &str, options: &Options) -> Option<String> {
+    //certificates of internal hosts are rarely ones we could verify, the point is what answers
+    let mut curl_config = format!(
+        "url = {}\ninclude\ninsecure\nmax-time = {}\nuser-agent = {}\n",
+        curl::quote(url),
+        REQUEST_TIMEOUT_SECS,
+        curl::quote(options.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+    );
+    if let Some(proxy) = &options.http_proxy {
+        curl_config.push_str(&format!("proxy = {}\n", curl::quote(proxy)));
+    }
+    for header in &options.http_headers {
+        curl_config.push_str(&format!("header = {}\n", curl::quote(header)));
+    }
+    let response = curl::run(&curl_config).ok()?;
+    let response = String::from_utf8_lossy(&response);
+
+    //a proxy tunnel and interim 1xx answers come first, each with its own header block
+    let mut rest = response.as_ref();
+    let (headers, body) = loop {
+        let (headers, body) = rest.split_once("\r\n\r\n").unwrap_or((rest, ""));
+        if body.starts_with("HTTP/") {
+            rest = body;
+            continue;
+        }
+        break (headers, body);
+    };
+
+    let mut lines = headers.lines();
+    let status = lines.next()?.split_once(' ')?.1.trim();
+    let mut parts = vec![format!("HTTP {}", status)];
+    let server = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("server").then(|| value.trim().to_string())
+    });
+    if let Some(server) = server.filter(|server| !server.is_empty()) {
+        parts.push(server);
+    }
+    if let Some(title) = page_title(body) {
+        parts.push(format!("title {}", title));
+    }
+    Some(parts.join(", "))
+}
+
+//function to pull the text of the <title> element out of a page, whitespace collapsed
+fn page_title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = body[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
@@ -0,0 +1,138 @@
+//whois lookups of the public ranges a sweep covers (--whois), one per range rather than one per host,
+//so reports of external-facing sweeps say who owns what was scanned and where to send abuse reports
+//
+//the lookups speak the whois protocol (TCP port 43) directly: IANA is asked which registry holds the
+//range, then the registry, following referrals between registries for ranges that were transferred
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::json;
+
+const ROOT: &str = "whois.iana.org";
+const PORT: u16 = 43;
+const TIMEOUT: Duration = Duration::from_secs(10);
+//IANA, the registry it refers to and one transfer between registries is as deep as it goes
+const MAX_SERVERS: usize = 3;
+
+//who a range belongs to, as far as its registry says
+pub struct Owner {
+    pub range: String,
+    pub netname: Option<String>,
+    pub org: Option<String>,
+    pub abuse: Option<String>,
+}
+
+impl Owner {
+    //function to describe the owner for the report header, e.g. "8.8.8.0/24: netname GOGL, org Google LLC, abuse network-abuse@google.com"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for (name, value) in [("netname", &self.netname), ("org", &self.org), ("abuse", &self.abuse)] {
+            if let Some(value) = value {
+                parts.push(format!("{} {}", name, value));
+            }
+        }
+        if parts.is_empty() {
+            parts.push("no owner details in the registry".to_string());
+        }
+        format!("{}: {}", self.range, parts.join(", "))
+    }
+
+    //function to read an owner back from the object to_json writes, None if it has no range
+    pub fn from_json(value: &json::Value) -> Option<Owner> {
+        let text = |key: &str| value.get(key).and_then(json::Value::as_str).map(str::to_string);
+        Some(Owner { range: text("range")?, netname: text("netname"), org: text("org"), abuse: text("abuse") })
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"range\":{},\"netname\":{},\"org\":{},\"abuse\":{}}}",
+            json::quote(&self.range),
+            json::quote_option(self.netname.as_deref()),
+            json::quote_option(self.org.as_deref()),
+            json::quote_option(self.abuse.as_deref())
+        )
+    }
+}
+
+//function to look up the owner of a range by its network address
+pub fn lookup(range: &str, network: Ipv4Addr) -> Result<Owner, String> {
+    let mut server = ROOT.to_string();
+    let mut asked: Vec<String> = Vec::new();
+    loop {
+        let reply = query(&server, &request(&server, network))?;
+        asked.push(server);
+        match referral(&reply).filter(|next| !asked.contains(next)) {
+            Some(next) if asked.len() < MAX_SERVERS => server = next,
+            _ => return Ok(parse(range, &reply)),
+        }
+    }
+}
+
+//ARIN answers a bare address with a list when several networks hold it, n + asks for the full records
+fn request(server: &str, network: Ipv4Addr) -> String {
+    if server == "whois.arin.net" {
+        format!("n + {}\r\n", network)
+    }
+    else {
+        format!("{}\r\n", network)
+    }
+}
+
+fn query(server: &str, request: &str) -> Result<String, String> {
+    let address = (server, PORT)
+        .to_socket_addrs()
+        .map_err(|error| format!("cannot resolve {}, {}", server, error))?
+        .next()
+        .ok_or_else(|| format!("no address for {}", server))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|error| format!("cannot connect to {}, {}", server, error))?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|error| error.to_string())?;
+    stream.write_all(request.as_bytes()).map_err(|error| format!("cannot send to {}, {}", server, error))?;
+    //the server closes the connection after the answer
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).map_err(|error| format!("no answer from {}, {}", server, error))?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+//function to split a reply into its key: value lines, comments and continuation lines left out
+fn fields(reply: &str) -> impl Iterator<Item = (String, &str)> {
+    reply.lines().filter(|line| !line.starts_with(['%', '#', ' '])).filter_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let value = value.trim();
+        (!value.is_empty()).then_some((key.trim().to_lowercase(), value))
+    })
+}
+
+//function to find the server a reply sends the question on to, IANA's refer: or ARIN's ReferralServer: whois://
+fn referral(reply: &str) -> Option<String> {
+    fields(reply).find_map(|(key, value)| match key.as_str() {
+        "refer" | "whois" => Some(value.to_string()),
+        "referralserver" => value.strip_prefix("whois://").map(|server| server.trim_end_matches('/').split(':').next().unwrap_or("").to_string()),
+        _ => None,
+    })
+}
+
+//function to pick the owner details out of a registry's reply, the registries name them differently and
+//list the wider networks before the narrower ones, so the last of each is kept
+fn parse(range: &str, reply: &str) -> Owner {
+    let mut owner = Owner { range: range.to_string(), netname: None, org: None, abuse: None };
+    for (key, value) in fields(reply) {
+        match key.as_str() {
+            "netname" => owner.netname = Some(value.to_string()),
+            "orgname" | "org-name" | "owner" => owner.org = Some(value.to_string()),
+            "orgabuseemail" | "abuse-mailbox" => owner.abuse = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    //RIPE and AFRINIC give the abuse contact in a comment, e.g. % Abuse contact for '...' is 'abuse@example.net'
+    if owner.abuse.is_none() {
+        owner.abuse = reply
+            .lines()
+            .find_map(|line| line.strip_prefix("% Abuse contact for ")?.split(" is '").nth(1)?.strip_suffix('\''))
+            .filter(|contact| !contact.is_empty())
+            .map(str::to_string);
+    }
+    owner
+}